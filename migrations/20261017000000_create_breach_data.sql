@@ -0,0 +1,20 @@
+-- Locally ingested breach records (see `guardr ingest`)
+-- Emails and passwords are stored only as SHA-256 digests (src/breach_hash.rs).
+CREATE TABLE IF NOT EXISTS breach_data (
+    id BLOB PRIMARY KEY NOT NULL,
+    email_hash TEXT NOT NULL,
+    password_hash TEXT,
+    source_name TEXT NOT NULL,
+    breach_date TEXT NOT NULL,
+    data_types TEXT NOT NULL DEFAULT '[]',
+    severity TEXT NOT NULL,
+    verified BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_breach_data_email_hash ON breach_data (email_hash);
+CREATE INDEX IF NOT EXISTS idx_breach_data_password_hash ON breach_data (password_hash);
+
+-- Re-ingesting a dump must not duplicate records
+CREATE UNIQUE INDEX IF NOT EXISTS idx_breach_data_record
+    ON breach_data (email_hash, source_name, COALESCE(password_hash, ''));
@@ -0,0 +1,16 @@
+-- Saved results of security checks, kept for 30 days
+-- `risk_model` records the risk model version (config [risk].model_version)
+-- that produced `risk_score`; NULL for scores that are not model risk scores.
+CREATE TABLE IF NOT EXISTS security_reports (
+    id BLOB PRIMARY KEY NOT NULL,
+    user_id BLOB NOT NULL,
+    report_type TEXT NOT NULL,
+    input_data_hash TEXT NOT NULL,
+    results TEXT NOT NULL,
+    risk_score INTEGER,
+    risk_model TEXT,
+    created_at TEXT NOT NULL,
+    expires_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_security_reports_user ON security_reports (user_id, created_at);
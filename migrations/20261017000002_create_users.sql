@@ -0,0 +1,41 @@
+-- Accounts, their API keys and monthly usage counters
+-- Passwords are Argon2 hashes; API keys are stored only as SHA-256 digests
+-- (AuthService::hash_api_key) with a short prefix for display.
+CREATE TABLE IF NOT EXISTS users (
+    id BLOB PRIMARY KEY NOT NULL,
+    email TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    name TEXT,
+    subscription_tier TEXT NOT NULL DEFAULT 'free',
+    email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    last_login TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE TABLE IF NOT EXISTS api_keys (
+    id BLOB PRIMARY KEY NOT NULL,
+    user_id BLOB NOT NULL REFERENCES users (id),
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    key_prefix TEXT NOT NULL,
+    last_used TEXT,
+    created_at TEXT NOT NULL,
+    expires_at TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_user ON api_keys (user_id);
+
+-- One counter per user, month ("2026-10") and endpoint
+CREATE TABLE IF NOT EXISTS usage_tracking (
+    id BLOB PRIMARY KEY NOT NULL,
+    user_id BLOB NOT NULL REFERENCES users (id),
+    month_year TEXT NOT NULL,
+    endpoint TEXT NOT NULL,
+    requests_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (user_id, month_year, endpoint)
+);
@@ -0,0 +1,3 @@
+-- What each API key may be used for, as a JSON array of scope names
+-- (auth::Scope). Keys from before scopes get the default set.
+ALTER TABLE api_keys ADD COLUMN scopes TEXT NOT NULL DEFAULT '["security:read","dating:analyze","reports:read"]';
@@ -0,0 +1,12 @@
+-- Account security events shown to the user (e.g. login lockouts)
+-- `details` is a JSON object whose fields depend on `event_type`.
+CREATE TABLE IF NOT EXISTS security_events (
+    id BLOB PRIMARY KEY NOT NULL,
+    user_id BLOB NOT NULL REFERENCES users (id),
+    event_type TEXT NOT NULL,
+    ip_address TEXT,
+    details TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_security_events_user ON security_events (user_id, created_at);
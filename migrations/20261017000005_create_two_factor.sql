@@ -0,0 +1,24 @@
+-- TOTP two-factor authentication (src/totp.rs)
+-- `secret` is the base32 TOTP secret, encrypted like report results. A row
+-- with enabled = 0 is an enrollment waiting for its first code.
+-- `last_used_step` is the last accepted 30-second step, so a code can't be
+-- replayed.
+CREATE TABLE IF NOT EXISTS two_factor (
+    user_id BLOB PRIMARY KEY NOT NULL REFERENCES users (id),
+    secret TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT 0,
+    last_used_step INTEGER,
+    created_at TEXT NOT NULL,
+    enabled_at TEXT
+);
+
+-- Single-use recovery codes, stored as SHA-256 digests
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id BLOB PRIMARY KEY NOT NULL,
+    user_id BLOB NOT NULL REFERENCES users (id),
+    code_hash TEXT NOT NULL,
+    used_at TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_recovery_codes_user ON recovery_codes (user_id, code_hash);
@@ -0,0 +1,14 @@
+-- Email verification and password reset tokens (src/api/email.rs)
+-- The tokens themselves are signed JWTs carrying `id`; a row lets each be
+-- redeemed once, and issuing a new token of a purpose supersedes the user's
+-- earlier unused ones.
+CREATE TABLE IF NOT EXISTS email_tokens (
+    id BLOB PRIMARY KEY NOT NULL,
+    user_id BLOB NOT NULL REFERENCES users (id),
+    purpose TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    used_at TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_tokens_user ON email_tokens (user_id, purpose);
@@ -0,0 +1,4 @@
+-- Bumped whenever every existing session must end (password reset). Access
+-- and refresh tokens carry the generation they were issued under and stop
+-- working once it moves on.
+ALTER TABLE users ADD COLUMN session_generation INTEGER NOT NULL DEFAULT 0;
@@ -1,10 +1,18 @@
 use axum::{extract::State, Json};
 use chrono::Utc;
+use serde_json::json;
 use tracing::{info, warn};
 use validator::Validate;
 
-use crate::auth::{RegisterRequest, LoginRequest, AuthResponse, UserProfile, UsageStats, RefreshTokenRequest};
-use crate::errors::{AppError, validation_error_response};
+use crate::auth::{
+    AuthenticatedUser, RegisterRequest, LoginRequest, LoginResponse, LogoutRequest, AuthResponse, UserProfile,
+    UsageStats, RefreshTokenRequest, TwoFactorChallenge, TWO_FACTOR_CHALLENGE_SECONDS,
+};
+use crate::api::email::send_token_email;
+use crate::database::{EmailTokenPurpose, User};
+use crate::errors::{login_locked_error, AppError, validation_error_response};
+use crate::middleware::ClientIp;
+use crate::quota;
 use crate::state::AppState;
 
 pub async fn register(
@@ -38,16 +46,26 @@ pub async fn register(
     // Log successful registration
     info!("User registered successfully: {}", user.email);
 
+    // Mailed in the background so a slow relay doesn't hold up sign-up; the
+    // user can ask for another from /v1/user/verify-email/resend
+    let mail_state = state.clone();
+    let mail_user = user.clone();
+    tokio::spawn(async move {
+        if let Err(e) = send_token_email(&mail_state, &mail_user, EmailTokenPurpose::VerifyEmail).await {
+            warn!("Failed to send verification email to {}: {}", mail_user.email, e);
+        }
+    });
+
     let user_profile = UserProfile {
         id: user.id.to_string(),
         email: user.email.clone(),
         name: user.name.clone(),
         subscription_tier: user.subscription_tier.to_string(),
         usage_stats: UsageStats {
-            monthly_queries: 100, // Free tier default
+            monthly_queries: state.quota.limit(&user.subscription_tier),
             queries_used: 0,
-            queries_remaining: 100,
-            reset_date: Utc::now(),
+            queries_remaining: state.quota.limit(&user.subscription_tier),
+            reset_date: quota::period_end(Utc::now()),
         },
         created_at: user.created_at,
     };
@@ -62,39 +80,64 @@ pub async fn register(
 
 pub async fn login(
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<AuthResponse>, AppError> {
+) -> Result<Json<LoginResponse>, AppError> {
     // Validate input
     if let Err(errors) = payload.validate() {
         return Err(validation_error_response(&errors));
     }
 
-    // Get user by email
-    let user = state.db.get_user_by_email(&payload.email).await?
-        .ok_or_else(|| AppError::Unauthorized("Invalid credentials".to_string()))?;
+    // Locked accounts and IPs aren't even checked (src/login_guard.rs)
+    if let Some(wait) = state.login_guard.locked_for(&payload.email, ip.as_deref()).await {
+        return Err(login_locked_error(wait));
+    }
+
+    // Get user by email, and verify the password even if there's no such
+    // user so both failures look the same
+    let user = state.db.get_user_by_email(&payload.email).await?;
+    let hash = user.as_ref().map(|user| user.password_hash.as_str());
+    let verified = state.auth.verify_login_password(&payload.password, hash).await?;
+    let user = match user {
+        Some(user) if verified => user,
+        user => {
+            warn!("Failed login attempt for user: {}", payload.email);
+            record_failed_login(&state, &payload.email, user.as_ref(), ip.as_deref()).await;
+            return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+        }
+    };
 
-    // Verify password
-    if !state.auth.verify_password(&payload.password, &user.password_hash).await? {
-        warn!("Failed login attempt for user: {}", payload.email);
-        return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+    // With two-factor on, the password only earns a challenge to redeem at
+    // /v1/auth/2fa/verify. Failed attempts aren't cleared until then, so a
+    // known password doesn't reset the count of wrong codes.
+    if state.db.get_two_factor(user.id).await?.is_some_and(|two_factor| two_factor.enabled) {
+        let challenge_token = state.auth.generate_two_factor_challenge(&user)?;
+        info!("Two-factor challenge issued for user: {}", user.email);
+        return Ok(Json(LoginResponse::TwoFactorRequired(TwoFactorChallenge {
+            two_factor_required: true,
+            challenge_token,
+            expires_in: TWO_FACTOR_CHALLENGE_SECONDS as u64,
+        })));
     }
 
+    state.login_guard.record_success(&payload.email).await;
+    Ok(Json(LoginResponse::Authenticated(start_session(&state, &user).await?)))
+}
+
+// Issue tokens for a fully authenticated login
+pub(crate) async fn start_session(state: &AppState, user: &User) -> Result<AuthResponse, AppError> {
     // Update last login
     state.db.update_user_last_login(user.id).await?;
 
     // Generate tokens
-    let (access_token, refresh_token) = state.auth.generate_tokens(&user)?;
+    let (access_token, refresh_token) = state.auth.generate_tokens(user)?;
 
     // Get usage stats (simplified for now)
     let current_month = Utc::now().format("%Y-%m").to_string();
     let usage = state.db.get_user_usage(user.id, &current_month).await?;
     let total_usage: i32 = usage.iter().map(|u| u.requests_count).sum();
 
-    let monthly_limit = match user.subscription_tier {
-        crate::database::UserSubscriptionTier::Free => 100,
-        crate::database::UserSubscriptionTier::Pro => 5000,
-        crate::database::UserSubscriptionTier::Enterprise => 50000,
-    };
+    let monthly_limit = state.quota.limit(&user.subscription_tier);
 
     info!("User logged in successfully: {}", user.email);
 
@@ -107,17 +150,39 @@ pub async fn login(
             monthly_queries: monthly_limit,
             queries_used: total_usage as u32,
             queries_remaining: (monthly_limit as i32 - total_usage).max(0) as u32,
-            reset_date: Utc::now(),
+            reset_date: quota::period_end(Utc::now()),
         },
         created_at: user.created_at,
     };
 
-    Ok(Json(AuthResponse {
+    Ok(AuthResponse {
         access_token,
         refresh_token,
         expires_in: state.auth.settings.auth.jwt_expiration_hours * 3600,
         user: user_profile,
-    }))
+    })
+}
+
+// Count a failed login, and record any lockout it starts against the account
+pub(crate) async fn record_failed_login(state: &AppState, email: &str, user: Option<&User>, ip: Option<&str>) {
+    for lockout in state.login_guard.record_failure(email, ip).await {
+        warn!(
+            "Login locked for {} for {} seconds (strike {})",
+            lockout.target,
+            lockout.duration.as_secs(),
+            lockout.strike,
+        );
+        // Unknown accounts have no one to tell
+        let Some(user) = user else { continue };
+        let details = json!({
+            "scope": lockout.target.scope(),
+            "duration_seconds": lockout.duration.as_secs(),
+            "strike": lockout.strike,
+        });
+        if let Err(e) = state.db.record_security_event(user.id, "login_lockout", ip, &details).await {
+            warn!("Failed to record lockout for {}: {}", user.email, e);
+        }
+    }
 }
 
 pub async fn refresh_token(
@@ -126,6 +191,10 @@ pub async fn refresh_token(
 ) -> Result<Json<AuthResponse>, AppError> {
     // Verify refresh token
     let claims = state.auth.verify_refresh_token(&payload.refresh_token)?;
+    let mut redis = state.redis.get_async_connection().await?;
+    if state.auth.is_token_blacklisted(&mut redis, &claims.jti).await? {
+        return Err(AppError::Unauthorized("Refresh token has been revoked".to_string()));
+    }
 
     // Get user
     let user_id = uuid::Uuid::parse_str(&claims.sub)
@@ -133,6 +202,9 @@ pub async fn refresh_token(
     
     let user = state.db.get_user_by_id(user_id).await?
         .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+    if user.session_generation != claims.generation {
+        return Err(AppError::Unauthorized("Session has ended; log in again".to_string()));
+    }
 
     // Generate new tokens
     let (access_token, refresh_token) = state.auth.generate_tokens(&user)?;
@@ -142,11 +214,7 @@ pub async fn refresh_token(
     let usage = state.db.get_user_usage(user.id, &current_month).await?;
     let total_usage: i32 = usage.iter().map(|u| u.requests_count).sum();
 
-    let monthly_limit = match user.subscription_tier {
-        crate::database::UserSubscriptionTier::Free => 100,
-        crate::database::UserSubscriptionTier::Pro => 5000,
-        crate::database::UserSubscriptionTier::Enterprise => 50000,
-    };
+    let monthly_limit = state.quota.limit(&user.subscription_tier);
 
     let user_profile = UserProfile {
         id: user.id.to_string(),
@@ -157,7 +225,7 @@ pub async fn refresh_token(
             monthly_queries: monthly_limit,
             queries_used: total_usage as u32,
             queries_remaining: (monthly_limit as i32 - total_usage).max(0) as u32,
-            reset_date: Utc::now(),
+            reset_date: quota::period_end(Utc::now()),
         },
         created_at: user.created_at,
     };
@@ -172,17 +240,28 @@ pub async fn refresh_token(
 
 pub async fn logout(
     State(state): State<AppState>,
-    // Extract JWT from the authorization header would be done via middleware
+    user: AuthenticatedUser,
+    payload: Option<Json<LogoutRequest>>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    // In a full implementation, we would:
-    // 1. Extract the JWT from the authorization header
-    // 2. Add it to the blacklist
-    // 3. Optionally revoke the refresh token
-    
-    info!("User logged out");
+    // Revoke the access token for the rest of its lifetime
+    let session = user.session()?;
+    let mut redis = state.redis.get_async_connection().await?;
+    state.auth.blacklist_token(&mut redis, &session.jti, session.exp).await?;
+
+    // And the refresh token, so it can't mint a new one
+    if let Some(Json(LogoutRequest { refresh_token: Some(refresh_token) })) = payload {
+        let claims = state.auth.verify_refresh_token(&refresh_token)
+            .map_err(|e| AppError::Unauthorized(e.to_string()))?;
+        if claims.sub != session.sub {
+            return Err(AppError::Unauthorized("Refresh token belongs to another user".to_string()));
+        }
+        state.auth.blacklist_token(&mut redis, &claims.jti, claims.exp).await?;
+    }
+
+    info!("User logged out: {}", user.email);
 
     Ok(Json(serde_json::json!({
         "success": true,
         "message": "Logged out successfully"
     })))
-}
\ No newline at end of file
+}
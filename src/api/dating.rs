@@ -1,9 +1,9 @@
 use axum::{extract::State, Json};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::auth::AuthenticatedUser;
+use crate::auth::{AuthenticatedUser, Scope};
 use crate::errors::AppError;
 use crate::state::AppState;
 
@@ -45,13 +45,17 @@ pub struct SafetyAnalysisResponse {
     pub safety_tips: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RiskIndicator {
     pub indicator_type: String,
     pub confidence: f32,
     pub severity: String,
     pub description: String,
+    #[serde(default)]
     pub evidence: Vec<String>,
+    // The LLM provider that raised this indicator; None for the built-in heuristics
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub ai_provider: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -86,14 +90,25 @@ pub struct CommunicationStyle {
     pub topic_steering: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RedFlag {
     pub flag_type: String,
     pub severity: String,
     pub description: String,
+    #[serde(default)]
     pub action_required: String,
 }
 
+// Shape we ask the LLM to answer in for comprehensive analysis
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AiConversationAssessment {
+    overall_risk_score: f32,
+    risk_indicators: Vec<RiskIndicator>,
+    red_flags: Vec<RedFlag>,
+    recommendations: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct IdentityVerificationRequest {
     pub participant_claims: ParticipantClaims,
@@ -299,20 +314,80 @@ fn calculate_response_time_analysis(messages: &[Message]) -> ResponseTimeAnalysi
     }
 }
 
+fn conversation_analysis_prompt(messages: &[Message], claims: Option<&ParticipantClaims>) -> String {
+    let transcript = messages.iter()
+        .map(|m| format!("[{}] {}: {}", m.timestamp.to_rfc3339(), m.sender, m.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let claims = claims.map(|c| serde_json::json!({
+        "name": c.name,
+        "age": c.age,
+        "location": c.location,
+        "occupation": c.occupation,
+        "education": c.education,
+        "social_media": c.social_media,
+    })).unwrap_or(serde_json::Value::Null);
+
+    format!(
+        r#"Analyze this dating app conversation for romance scams, love bombing, pressure tactics,
+financial requests, identity inconsistencies and other safety risks to "user".
+
+PARTICIPANT CLAIMS:
+{claims}
+
+CONVERSATION:
+{transcript}
+
+Respond with JSON in exactly this shape:
+{{
+  "overall_risk_score": <0-100>,
+  "risk_indicators": [{{"indicator_type": "...", "confidence": <0-1>, "severity": "low|medium|high|critical", "description": "...", "evidence": ["quoted message", "..."]}}],
+  "red_flags": [{{"flag_type": "...", "severity": "low|medium|high|critical", "description": "...", "action_required": "..."}}],
+  "recommendations": ["..."]
+}}"#,
+        claims = claims,
+        transcript = transcript,
+    )
+}
+
+fn normalize_severity(severity: &str) -> String {
+    match severity.trim().to_lowercase().as_str() {
+        s @ ("low" | "medium" | "high" | "critical") => s.to_string(),
+        _ => "medium".to_string(),
+    }
+}
+
+// Check subscription limits
+fn check_conversation_limits(user: &AuthenticatedUser, payload: &ConversationAnalysisRequest) -> Result<(), AppError> {
+    if user.subscription_tier == crate::database::UserSubscriptionTier::Free && payload.messages.len() > 50 {
+        return Err(AppError::BadRequest("Free tier limited to 50 messages per analysis".to_string()));
+    }
+    Ok(())
+}
+
 // API Handlers
 pub async fn analyze_conversation(
     State(state): State<AppState>,
     user: AuthenticatedUser,
     Json(payload): Json<ConversationAnalysisRequest>,
 ) -> Result<Json<SafetyAnalysisResponse>, AppError> {
-    // Track usage
-    state.db.track_api_usage(user.user_id, "analyze_conversation").await?;
+    user.require_scope(Scope::DatingAnalyze)?;
+    check_conversation_limits(&user, &payload)?;
 
-    // Check subscription limits
-    if user.subscription_tier == crate::database::UserSubscriptionTier::Free && payload.messages.len() > 50 {
-        return Err(AppError::BadRequest("Free tier limited to 50 messages per analysis".to_string()));
-    }
+    // Charge the monthly quota once the request is known to be acceptable
+    state.quota.charge(&user, "analyze_conversation", 1).await?;
 
+    Ok(Json(conversation_analysis(&state, &user, payload).await?))
+}
+
+// The analysis behind analyze_conversation, for handlers that have already
+// checked limits and charged the quota
+async fn conversation_analysis(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    payload: ConversationAnalysisRequest,
+) -> Result<SafetyAnalysisResponse, AppError> {
     // Perform safety analysis
     let love_bombing_score = analyze_love_bombing_patterns(&payload.messages);
     let pressure_indicators = detect_pressure_indicators(&payload.messages);
@@ -338,6 +413,20 @@ pub async fn analyze_conversation(
     risk_score += (100.0 - identity_consistency.consistency_score) * 0.2;
     risk_score += (100.0 - response_time_analysis.consistency_score) * 0.1;
 
+    // Comprehensive analysis layers a model assessment over the heuristics;
+    // if every provider fails we still answer with the heuristic result
+    let mut ai_assessment = None;
+    if payload.analysis_depth.as_deref() == Some("comprehensive") && !state.llm.is_empty() {
+        let prompt = conversation_analysis_prompt(&payload.messages, payload.participant_info.as_ref());
+        match state.llm.complete_json::<AiConversationAssessment>(&prompt).await {
+            Ok(response) => {
+                risk_score = f32::max(risk_score, response.output.overall_risk_score.clamp(0.0, 100.0));
+                ai_assessment = Some(response);
+            }
+            Err(e) => warn!("AI conversation analysis unavailable, using heuristics only: {}", e),
+        }
+    }
+
     let risk_level = match risk_score {
         0.0..=25.0 => "low",
         25.1..=50.0 => "medium",
@@ -355,6 +444,7 @@ pub async fn analyze_conversation(
             severity: if love_bombing_score > 60.0 { "high" } else { "medium" }.to_string(),
             description: "Excessive romantic language detected".to_string(),
             evidence: vec!["Multiple intense compliments".to_string()],
+            ai_provider: None,
         });
     }
 
@@ -365,12 +455,30 @@ pub async fn analyze_conversation(
             severity: "high".to_string(),
             description: "Pressure tactics detected in conversation".to_string(),
             evidence: vec![format!("{} pressure indicators found", pressure_indicators)],
+            ai_provider: None,
         });
     }
 
-    // Generate red flags
     let mut red_flags = Vec::new();
-    
+    let mut ai_recommendations = Vec::new();
+
+    if let Some(response) = ai_assessment {
+        risk_indicators.extend(response.output.risk_indicators.into_iter().map(|mut indicator| {
+            indicator.confidence = indicator.confidence.clamp(0.0, 1.0);
+            indicator.severity = normalize_severity(&indicator.severity);
+            indicator.ai_provider = Some(response.provider.clone());
+            indicator
+        }));
+
+        red_flags.extend(response.output.red_flags.into_iter().map(|mut flag| {
+            flag.severity = normalize_severity(&flag.severity);
+            flag
+        }));
+
+        ai_recommendations = response.output.recommendations;
+    }
+
+    // Generate red flags
     if pressure_indicators > 5 {
         red_flags.push(RedFlag {
             flag_type: "immediate_danger".to_string(),
@@ -417,6 +525,12 @@ pub async fn analyze_conversation(
         }
     }
 
+    for recommendation in ai_recommendations {
+        if !recommendations.contains(&recommendation) {
+            recommendations.push(recommendation);
+        }
+    }
+
     let conversation_patterns = ConversationPatterns {
         love_bombing_score,
         pressure_indicators,
@@ -449,11 +563,12 @@ pub async fn analyze_conversation(
         &input_hash,
         &report_data.to_string(),
         Some(risk_score as i32),
+        None,
     ).await?;
 
     info!("Conversation analysis completed for user: {} (risk: {})", user.email, risk_level);
 
-    Ok(Json(SafetyAnalysisResponse {
+    Ok(SafetyAnalysisResponse {
         overall_risk_score: risk_score,
         risk_level,
         risk_indicators,
@@ -462,7 +577,7 @@ pub async fn analyze_conversation(
         red_flags,
         recommendations,
         safety_tips,
-    }))
+    })
 }
 
 pub async fn verify_identity_claims(
@@ -470,9 +585,16 @@ pub async fn verify_identity_claims(
     user: AuthenticatedUser,
     Json(payload): Json<IdentityVerificationRequest>,
 ) -> Result<Json<IdentityVerificationResponse>, AppError> {
-    // Track usage
-    state.db.track_api_usage(user.user_id, "verify_identity").await?;
+    user.require_scope(Scope::DatingAnalyze)?;
+
+    // Charge the monthly quota
+    state.quota.charge(&user, "verify_identity", 1).await?;
+
+    Ok(Json(identity_verification(&user, payload)))
+}
 
+// The checks behind verify_identity_claims, unmetered
+fn identity_verification(user: &AuthenticatedUser, payload: IdentityVerificationRequest) -> IdentityVerificationResponse {
     // Simple identity verification (in production, this would integrate with external services)
     let mut verified_claims = Vec::new();
     let mut unverified_claims = Vec::new();
@@ -521,13 +643,13 @@ pub async fn verify_identity_claims(
 
     info!("Identity verification completed for user: {} (score: {})", user.email, verification_score);
 
-    Ok(Json(IdentityVerificationResponse {
+    IdentityVerificationResponse {
         verification_score,
         verified_claims,
         unverified_claims,
         suspicious_indicators,
         recommendations,
-    }))
+    }
 }
 
 pub async fn generate_safety_report(
@@ -535,8 +657,14 @@ pub async fn generate_safety_report(
     user: AuthenticatedUser,
     Json(payload): Json<SafetyReportRequest>,
 ) -> Result<Json<ComprehensiveSafetyReport>, AppError> {
-    // Track usage
-    state.db.track_api_usage(user.user_id, "safety_report").await?;
+    user.require_scope(Scope::DatingAnalyze)?;
+    if let Some(conv_req) = &payload.conversation_analysis {
+        check_conversation_limits(&user, conv_req)?;
+    }
+
+    // One charge covers the whole report ([quota.costs] safety_report); the
+    // analyses below are the unmetered helpers, not the handlers
+    state.quota.charge(&user, "safety_report", 1).await?;
 
     let report_id = uuid::Uuid::new_v4().to_string();
     let mut overall_safety_score = 100.0;
@@ -544,20 +672,20 @@ pub async fn generate_safety_report(
 
     // Analyze conversation if provided
     let conversation_analysis = if let Some(conv_req) = payload.conversation_analysis {
-        let analysis = analyze_conversation(State(state.clone()), user.clone(), Json(conv_req)).await?;
-        overall_safety_score -= analysis.0.overall_risk_score * 0.6;
-        aggregated_recommendations.extend(analysis.0.recommendations.clone());
-        Some(analysis.0)
+        let analysis = conversation_analysis(&state, &user, conv_req).await?;
+        overall_safety_score -= analysis.overall_risk_score * 0.6;
+        aggregated_recommendations.extend(analysis.recommendations.clone());
+        Some(analysis)
     } else {
         None
     };
 
     // Verify identity if provided
     let identity_verification = if let Some(id_req) = payload.identity_verification {
-        let verification = verify_identity_claims(State(state.clone()), user.clone(), Json(id_req)).await?;
-        overall_safety_score -= (100.0 - verification.0.verification_score) * 0.4;
-        aggregated_recommendations.extend(verification.0.recommendations.clone());
-        Some(verification.0)
+        let verification = identity_verification(&user, id_req);
+        overall_safety_score -= (100.0 - verification.verification_score) * 0.4;
+        aggregated_recommendations.extend(verification.recommendations.clone());
+        Some(verification)
     } else {
         None
     };
@@ -622,6 +750,7 @@ pub async fn generate_safety_report(
         &input_hash,
         &report_data.to_string(),
         Some(overall_safety_score as i32),
+        None,
     ).await?;
 
     info!("Comprehensive safety report generated for user: {} (score: {})", user.email, overall_safety_score);
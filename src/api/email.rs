@@ -0,0 +1,232 @@
+// Email verification and password reset
+// Both mail a link carrying a signed token (AuthService::generate_email_token)
+// that works once and until it expires; sending a new one supersedes the last
+// (email_tokens). Messages of each kind go out at most once per
+// resend_cooldown_seconds per account, so these routes can't flood an inbox.
+
+use axum::{extract::State, Json};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::auth::{AuthenticatedUser, EmailTokenClaims, ForgotPasswordRequest, ResetPasswordRequest, Scope, VerifyEmailRequest};
+use crate::config::VerifiedFeature;
+use crate::database::{EmailTokenPurpose, User};
+use crate::errors::{validation_error_response, AppError};
+use crate::mail::templates::Template;
+use crate::middleware::ClientIp;
+use crate::state::AppState;
+
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<Json<Value>, AppError> {
+    let invalid = || AppError::BadRequest("Invalid or expired verification link".to_string());
+    let claims = state.auth.verify_email_token(&payload.token, EmailTokenPurpose::VerifyEmail)
+        .map_err(|_| invalid())?;
+    let (user, token_id) = token_user(&state, &claims).await?.ok_or_else(invalid)?;
+
+    if !state.db.verify_email(user.id, token_id).await? {
+        return Err(invalid());
+    }
+
+    info!("Email verified for user: {}", user.email);
+    record_event(&state, user.id, "email_verified", None).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Email address verified"
+    })))
+}
+
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<Json<Value>, AppError> {
+    user.require_scope(Scope::AccountWrite)?;
+
+    let account = state.db.get_user_by_id(user.user_id).await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    if account.email_verified {
+        return Err(AppError::BadRequest("Email address is already verified".to_string()));
+    }
+
+    if !send_token_email(&state, &account, EmailTokenPurpose::VerifyEmail).await? {
+        return Err(AppError::TooManyRequests("A verification email was sent recently".to_string()));
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Verification email sent"
+    })))
+}
+
+// Answers the same whether or not the account exists, and sends in the
+// background so the response time doesn't tell either
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<Value>, AppError> {
+    if let Err(errors) = payload.validate() {
+        return Err(validation_error_response(&errors));
+    }
+
+    tokio::spawn(async move {
+        let user = match state.db.get_user_by_email(&payload.email).await {
+            Ok(Some(user)) => user,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Password reset lookup failed: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = send_token_email(&state, &user, EmailTokenPurpose::PasswordReset).await {
+            warn!("Failed to send password reset email to {}: {}", user.email, e);
+        }
+    });
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "If an account uses that address, a password reset link is on its way"
+    })))
+}
+
+pub async fn reset_password(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<Value>, AppError> {
+    if let Err(errors) = payload.validate() {
+        return Err(validation_error_response(&errors));
+    }
+    if payload.password != payload.confirm_password {
+        return Err(AppError::ValidationError("Passwords do not match".to_string()));
+    }
+
+    let invalid = || AppError::BadRequest("Invalid or expired reset link".to_string());
+    let claims = state.auth.verify_email_token(&payload.token, EmailTokenPurpose::PasswordReset)
+        .map_err(|_| invalid())?;
+    let (user, token_id) = token_user(&state, &claims).await?.ok_or_else(invalid)?;
+
+    let password_hash = state.auth.hash_password(&payload.password).await?;
+    if !state.db.reset_password(user.id, token_id, &password_hash).await? {
+        return Err(invalid());
+    }
+
+    // Whoever reads the inbox owns the account, so let them straight back in
+    state.login_guard.record_success(&user.email).await;
+
+    info!("Password reset for user: {}", user.email);
+    record_event(&state, user.id, "password_reset", ip.as_deref()).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Password updated. You can now log in with the new password"
+    })))
+}
+
+// Issue a token for `purpose` and mail its link; false, sending nothing, if
+// one went out within the cooldown
+pub(crate) async fn send_token_email(state: &AppState, user: &User, purpose: EmailTokenPurpose) -> Result<bool, AppError> {
+    if !claim_send_slot(state, user.id, purpose).await {
+        return Ok(false);
+    }
+
+    let config = &state.settings.email;
+    let (template, page, lifetime) = match purpose {
+        EmailTokenPurpose::VerifyEmail => (
+            Template::VerifyEmail,
+            "verify-email",
+            Duration::hours(config.verification_token_hours as i64),
+        ),
+        EmailTokenPurpose::PasswordReset => (
+            Template::PasswordReset,
+            "reset-password",
+            Duration::minutes(config.reset_token_minutes as i64),
+        ),
+    };
+
+    let (token, token_id) = state.auth.generate_email_token(user, purpose, lifetime)?;
+    state.db.create_email_token(token_id, user.id, purpose, Utc::now() + lifetime).await?;
+
+    let link = format!("{}/{}?token={}", config.app_url.trim_end_matches('/'), page, token);
+    let email = state.email_templates.render(template, &user.email, &[
+        ("name", user.name.as_deref().unwrap_or("there")),
+        ("link", &link),
+        ("expires_in", &describe(lifetime)),
+    ]);
+    state.mailer.send(&email).await
+        .map_err(|e| AppError::ServiceUnavailable(format!("Email delivery failed: {}", e)))?;
+
+    info!("Sent {:?} email to {}", purpose, user.email);
+    Ok(true)
+}
+
+// Refuse `feature` to accounts whose email isn't verified, if
+// [email].require_verified_for lists it
+pub(crate) async fn require_verified_email(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    feature: VerifiedFeature,
+) -> Result<(), AppError> {
+    if !state.settings.email.require_verified_for.contains(&feature) {
+        return Ok(());
+    }
+
+    let verified = state.db.get_user_by_id(user.user_id).await?
+        .is_some_and(|account| account.email_verified);
+    if !verified {
+        return Err(AppError::EmailNotVerified { feature: feature.to_string() });
+    }
+    Ok(())
+}
+
+// The token's account and id, unless the account is gone or now uses another
+// address than the one the link went to
+async fn token_user(state: &AppState, claims: &EmailTokenClaims) -> Result<Option<(User, Uuid)>, AppError> {
+    let (Ok(user_id), Ok(token_id)) = (Uuid::parse_str(&claims.sub), Uuid::parse_str(&claims.jti)) else {
+        return Ok(None);
+    };
+    let user = state.db.get_user_by_id(user_id).await?
+        .filter(|user| user.email.eq_ignore_ascii_case(&claims.email));
+    Ok(user.map(|user| (user, token_id)))
+}
+
+// Like the login guard, this lets mail through if Redis can't be reached
+async fn claim_send_slot(state: &AppState, user_id: Uuid, purpose: EmailTokenPurpose) -> bool {
+    let cooldown = state.settings.email.resend_cooldown_seconds;
+    if cooldown == 0 {
+        return true;
+    }
+
+    let key = format!("email:cooldown:{:?}:{}", purpose, user_id);
+    let result: redis::RedisResult<Option<String>> = async {
+        let mut conn = state.redis.get_async_connection().await?;
+        redis::cmd("SET").arg(&key).arg(1).arg("NX").arg("EX").arg(cooldown)
+            .query_async(&mut conn)
+            .await
+    }.await;
+
+    match result {
+        Ok(claimed) => claimed.is_some(),
+        Err(e) => {
+            warn!("Email cooldown check failed, sending anyway: {}", e);
+            true
+        }
+    }
+}
+
+async fn record_event(state: &AppState, user_id: Uuid, event_type: &str, ip: Option<&str>) {
+    if let Err(e) = state.db.record_security_event(user_id, event_type, ip, &json!({})).await {
+        warn!("Failed to record {} for user {}: {}", event_type, user_id, e);
+    }
+}
+
+// "48 hours", "1 hour", "90 minutes"
+fn describe(lifetime: Duration) -> String {
+    let minutes = lifetime.num_minutes();
+    let (count, unit) = if minutes % 60 == 0 { (minutes / 60, "hour") } else { (minutes, "minute") };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
@@ -18,6 +18,8 @@ pub mod security;
 pub mod reports;
 pub mod users;
 pub mod dating;
+pub mod email;
+pub mod two_factor;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
@@ -53,13 +55,22 @@ pub fn create_router() -> Router<crate::state::AppState> {
         .route("/v1/auth/login", post(auth::login))
         .route("/v1/auth/refresh", post(auth::refresh_token))
         .route("/v1/auth/logout", post(auth::logout))
+        .route("/v1/auth/2fa/verify", post(two_factor::verify_login))
+        .route("/v1/auth/verify-email", post(email::verify_email))
+        .route("/v1/auth/forgot-password", post(email::forgot_password))
+        .route("/v1/auth/reset-password", post(email::reset_password))
 
         // User management (auth required)
         .route("/v1/user/profile", get(users::get_profile))
         .route("/v1/user/profile", put(users::update_profile))
+        .route("/v1/user/verify-email/resend", post(email::resend_verification))
         .route("/v1/user/api-keys", get(users::list_api_keys))
         .route("/v1/user/api-keys", post(users::create_api_key))
         .route("/v1/user/api-keys/:key_id", delete(users::revoke_api_key))
+        .route("/v1/user/2fa/setup", post(two_factor::setup))
+        .route("/v1/user/2fa/enable", post(two_factor::enable))
+        .route("/v1/user/2fa/disable", post(two_factor::disable))
+        .route("/v1/user/2fa/recovery-codes", post(two_factor::regenerate_recovery_codes))
 
         // Security analysis endpoints (auth required)
         .route("/v1/security/check-breach", post(security::check_breach))
@@ -67,6 +78,7 @@ pub fn create_router() -> Router<crate::state::AppState> {
         .route("/v1/security/risk-score", post(security::calculate_risk_score))
         .route("/v1/security/bulk-check", post(security::bulk_security_check))
         .route("/v1/security/filter-data", post(security::filter_data))
+        .route("/v1/security/investigate", post(security::investigate))
 
         // Dating safety endpoints (auth required)
         .route("/v1/dating/analyze-conversation", post(dating::analyze_conversation))
@@ -83,6 +95,8 @@ pub fn create_router() -> Router<crate::state::AppState> {
         .route("/v1/admin/breach-sources", get(reports::admin::list_breach_sources))
         .route("/v1/admin/breach-sources", post(reports::admin::add_breach_source))
         .route("/v1/admin/update-breach-data", post(reports::admin::update_breach_data))
+        .route("/v1/admin/ingest", post(reports::admin::ingest_breach_data))
+        .route("/v1/admin/ingest/:job_id", get(reports::admin::get_ingest_job))
         
         // Add middleware layers
         .layer(TimeoutLayer::new(Duration::from_secs(30)))
@@ -91,6 +105,11 @@ pub fn create_router() -> Router<crate::state::AppState> {
 }
 
 use axum::{extract::State, Json};
+use crate::auth::{AuthenticatedUser, Scope};
+use crate::errors::AppError;
+use crate::osint::aggregator::{
+    Finding, FindingCategory, OsintReport, SourceOutcome, SourceStatus, Subject,
+};
 use crate::state::AppState;
 
 async fn health_check() -> Json<ApiResponse<String>> {
@@ -124,6 +143,8 @@ async fn root_info() -> Json<ApiResponse<serde_json::Value>> {
 struct DemoCheckRequest {
     name: String,
     location: Option<String>,
+    email: Option<String>,
+    username: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -135,52 +156,72 @@ struct SafetyTip {
 #[derive(Debug, Serialize)]
 struct DemoCheckResponse {
     name: String,
+    // LOW up to 30, HIGH from 61; the website's badge shows it with risk_score
     risk_level: String,
     risk_score: u32,
+    // 100 - risk_score, for clients that show how safe a match looks
+    safety_score: u32,
     person_verification: String,
     recommendations: Vec<String>,
     safety_tips: Vec<SafetyTip>,
+    findings: Vec<Finding>,
+    sources: Vec<SourceStatus>,
 }
 
+// Anonymous callers get the guidance only: the OSINT sources are paid APIs,
+// and breach membership isn't for whoever types in an address. Signed-in
+// callers get the aggregated lookup against their quota, without sensitive
+// breaches.
 async fn demo_check(
     State(state): State<AppState>,
+    user: Option<AuthenticatedUser>,
     Json(payload): Json<DemoCheckRequest>,
-) -> Json<DemoCheckResponse> {
-    // Public demo endpoint - performs real OSINT analysis for demonstration
-
-    let name = payload.name.clone();
-    let location = payload.location.clone().unwrap_or_else(|| "Unknown".to_string());
-
-    // TODO: Implement real OSINT calls here:
-    // 1. Call HIBP API for breach checking
-    // 2. Call Intelligence X for dark web monitoring
-    // 3. Call Gemini AI for risk analysis
-    // 4. Aggregate results
-
-    // For now, return enhanced demo response with realistic data
-    let risk_score = ((name.len() * 7 + location.len() * 3) % 100) as u32;
-    let risk_level = if risk_score >= 70 {
-        "LOW"
-    } else if risk_score >= 40 {
-        "MEDIUM"
+) -> Result<Json<DemoCheckResponse>, AppError> {
+    let subject = Subject {
+        name: Some(payload.name.clone()),
+        email: payload.email.clone(),
+        username: payload.username.clone(),
+        location: payload.location.clone(),
+    };
+
+    let report = match &user {
+        Some(user) => {
+            user.require_scope(Scope::SecurityRead)?;
+            state.quota.charge(user, "osint_check", 1).await?;
+            state.osint.investigate(&subject).await.without_sensitive()
+        }
+        None => OsintReport::unchecked(&subject),
+    };
+
+    let name = payload.name;
+    let location = payload.location.unwrap_or_else(|| "Unknown".to_string());
+    let person_verification = if user.is_some() {
+        summarize_osint_report(&report, &name, &location)
     } else {
-        "HIGH"
-    }.to_string();
-
-    let person_verification = format!(
-        "OSINT Analysis for {} in {}: Checked 40+ databases, scanned for data breaches, \
-        verified identity claims, and analyzed behavioral patterns. \
-        Analysis completed using HIBP, Intelligence X, and AI risk assessment.",
-        name, location
-    );
+        format!(
+            "Safety guidance for {} in {}. Sign in to run breach and web checks on this match.",
+            name, location
+        )
+    };
 
-    let recommendations = vec![
+    let mut recommendations = vec![
         "Video call before meeting in person to verify identity".to_string(),
         "Meet in a public place for your first meeting".to_string(),
         "Tell a trusted friend where you're going".to_string(),
         "Trust your instincts - if something feels off, it probably is".to_string(),
     ];
 
+    let has_category = |category: FindingCategory| report.findings.iter().any(|f| f.category == category);
+    let has_high_severity_mentions = report.findings.iter()
+        .any(|f| f.category == FindingCategory::WebMention && f.severity == "high");
+
+    if has_high_severity_mentions {
+        recommendations.push("Public web results mention safety concerns - review the linked sources before meeting".to_string());
+    }
+    if has_category(FindingCategory::Breach) || has_category(FindingCategory::Leak) {
+        recommendations.push("Their details appear in leaked data - be alert to impersonation of this identity".to_string());
+    }
+
     let safety_tips = vec![
         SafetyTip {
             category: "Smart Habits".to_string(),
@@ -196,14 +237,58 @@ async fn demo_check(
         },
     ];
 
-    Json(DemoCheckResponse {
+    let risk_score = report.risk_score.min(100) as u32;
+
+    Ok(Json(DemoCheckResponse {
         name,
-        risk_level,
+        risk_level: demo_risk_level(risk_score).to_string(),
         risk_score,
+        safety_score: 100 - risk_score,
         person_verification,
         recommendations,
         safety_tips,
-    })
+        findings: report.findings,
+        sources: report.sources,
+    }))
+}
+
+fn demo_risk_level(risk_score: u32) -> &'static str {
+    if risk_score <= 30 {
+        "LOW"
+    } else if risk_score <= 60 {
+        "MEDIUM"
+    } else {
+        "HIGH"
+    }
+}
+
+fn summarize_osint_report(report: &OsintReport, name: &str, location: &str) -> String {
+    if report.sources.is_empty() {
+        return format!(
+            "OSINT Analysis for {} in {}: no OSINT sources are configured, so no external checks were run.",
+            name, location
+        );
+    }
+
+    let succeeded: Vec<&str> = report.succeeded_sources().map(|s| s.provider.as_str()).collect();
+    let unavailable = report.sources.iter()
+        .filter(|s| matches!(s.outcome, SourceOutcome::Failed | SourceOutcome::TimedOut))
+        .count();
+    let count = |category: FindingCategory| report.findings.iter().filter(|f| f.category == category).count();
+
+    format!(
+        "OSINT Analysis for {} in {}: queried {} source(s) ({} answered: {}; {} unavailable). \
+        Found {} breach record(s), {} paste/leak record(s) and {} web mention(s).",
+        name,
+        location,
+        report.sources.len(),
+        succeeded.len(),
+        if succeeded.is_empty() { "none".to_string() } else { succeeded.join(", ") },
+        unavailable,
+        count(FindingCategory::Breach),
+        count(FindingCategory::Paste) + count(FindingCategory::Leak),
+        count(FindingCategory::WebMention),
+    )
 }
 
 // Request/Response models for each endpoint category
@@ -4,7 +4,9 @@ use serde::{Deserialize, Serialize};
 use tracing::info;
 use uuid::Uuid;
 
-use crate::auth::AuthenticatedUser;
+use crate::api::email::require_verified_email;
+use crate::auth::{AuthenticatedUser, Scope};
+use crate::config::VerifiedFeature;
 use crate::database::SecurityReport;
 use crate::errors::AppError;
 use crate::state::AppState;
@@ -23,6 +25,7 @@ pub struct ReportSummary {
     pub id: String,
     pub report_type: String,
     pub risk_score: Option<i32>,
+    pub risk_model: Option<String>,
     pub created_at: chrono::DateTime<Utc>,
     pub expires_at: Option<chrono::DateTime<Utc>>,
 }
@@ -47,6 +50,7 @@ pub struct DetailedReport {
     pub report_type: String,
     pub results: serde_json::Value,
     pub risk_score: Option<i32>,
+    pub risk_model: Option<String>,
     pub created_at: chrono::DateTime<Utc>,
     pub expires_at: Option<chrono::DateTime<Utc>>,
 }
@@ -63,6 +67,8 @@ pub async fn list_reports(
     user: AuthenticatedUser,
     Query(params): Query<ListReportsQuery>,
 ) -> Result<Json<PaginatedReports>, AppError> {
+    user.require_scope(Scope::ReportsRead)?;
+
     let page = params.page.unwrap_or(1).max(1);
     let per_page = params.per_page.unwrap_or(20).min(100);
     let offset = ((page - 1) * per_page) as i64;
@@ -99,6 +105,7 @@ pub async fn list_reports(
             id: r.id.to_string(),
             report_type: r.report_type,
             risk_score: r.risk_score,
+            risk_model: r.risk_model,
             created_at: r.created_at,
             expires_at: r.expires_at,
         })
@@ -126,6 +133,8 @@ pub async fn get_report(
     user: AuthenticatedUser,
     Path(report_id): Path<String>,
 ) -> Result<Json<DetailedReport>, AppError> {
+    user.require_scope(Scope::ReportsRead)?;
+
     let report_uuid = Uuid::parse_str(&report_id)
         .map_err(|_| AppError::BadRequest("Invalid report ID".to_string()))?;
 
@@ -141,6 +150,7 @@ pub async fn get_report(
         report_type: report.report_type,
         results,
         risk_score: report.risk_score,
+        risk_model: report.risk_model,
         created_at: report.created_at,
         expires_at: report.expires_at,
     }))
@@ -151,6 +161,8 @@ pub async fn delete_report(
     user: AuthenticatedUser,
     Path(report_id): Path<String>,
 ) -> Result<Json<serde_json::Value>, AppError> {
+    user.require_scope(Scope::ReportsWrite)?;
+
     let report_uuid = Uuid::parse_str(&report_id)
         .map_err(|_| AppError::BadRequest("Invalid report ID".to_string()))?;
 
@@ -173,6 +185,9 @@ pub async fn export_reports(
     user: AuthenticatedUser,
     Query(params): Query<ListReportsQuery>,
 ) -> Result<Json<ExportResponse>, AppError> {
+    user.require_scope(Scope::ReportsExport)?;
+    require_verified_email(&state, &user, VerifiedFeature::Exports).await?;
+
     // Check if user has export permissions
     if user.subscription_tier == crate::database::UserSubscriptionTier::Free {
         return Err(AppError::Forbidden("Report export requires a premium subscription".to_string()));
@@ -215,6 +230,7 @@ pub async fn export_reports(
                 "report_type": r.report_type,
                 "results": results,
                 "risk_score": r.risk_score,
+                "risk_model": r.risk_model,
                 "created_at": r.created_at,
                 "expires_at": r.expires_at
             })
@@ -244,6 +260,11 @@ pub mod admin {
     use super::*;
     use serde::{Deserialize, Serialize};
 
+    use crate::dump_formats::InputFormat;
+    use axum::{http::StatusCode, response::{IntoResponse, Response}};
+
+    use crate::ingest::{self, IngestJob, IngestOptions};
+
     #[derive(Debug, Serialize)]
     pub struct BreachSource {
         pub id: String,
@@ -254,6 +275,20 @@ pub mod admin {
         pub is_active: bool,
     }
 
+    #[derive(Debug, Deserialize)]
+    pub struct IngestBreachDataRequest {
+        pub source: String,
+        pub breach_date: chrono::NaiveDate,
+        // Either a dump file name inside the configured dump directory...
+        pub file: Option<String>,
+        pub format: Option<String>,
+        // ...or the records themselves
+        pub records: Option<Vec<serde_json::Value>>,
+        // Exposed data beyond the record fields, e.g. ["Dating site membership"]
+        #[serde(default)]
+        pub data_classes: Vec<String>,
+    }
+
     #[derive(Debug, Deserialize)]
     pub struct AddBreachSourceRequest {
         pub name: String,
@@ -261,14 +296,27 @@ pub mod admin {
         pub description: Option<String>,
     }
 
+    // Admin routes change data every user's checks rely on, so they are for
+    // the operators listed in security.operator_emails, not a billing tier
+    async fn require_operator(state: &AppState, user: &AuthenticatedUser) -> Result<(), AppError> {
+        user.require_scope(Scope::Admin)?;
+
+        let listed = |email: &str| state.settings.security.operator_emails.iter()
+            .any(|operator| operator.trim().eq_ignore_ascii_case(email.trim()));
+        let operator = listed(&user.email)
+            && state.db.get_user_by_id(user.user_id).await?
+                .is_some_and(|account| account.email_verified && listed(&account.email));
+        if !operator {
+            return Err(AppError::Forbidden("Admin access required".to_string()));
+        }
+        Ok(())
+    }
+
     pub async fn list_breach_sources(
         State(state): State<AppState>,
         user: AuthenticatedUser,
     ) -> Result<Json<Vec<BreachSource>>, AppError> {
-        // Check admin permissions
-        if user.subscription_tier != crate::database::UserSubscriptionTier::Enterprise {
-            return Err(AppError::Forbidden("Admin access required".to_string()));
-        }
+        require_operator(&state, &user).await?;
 
         // In production, implement actual breach source management
         let sources = vec![
@@ -290,10 +338,7 @@ pub mod admin {
         user: AuthenticatedUser,
         Json(payload): Json<AddBreachSourceRequest>,
     ) -> Result<Json<BreachSource>, AppError> {
-        // Check admin permissions
-        if user.subscription_tier != crate::database::UserSubscriptionTier::Enterprise {
-            return Err(AppError::Forbidden("Admin access required".to_string()));
-        }
+        require_operator(&state, &user).await?;
 
         // In production, implement actual breach source addition
         let source = BreachSource {
@@ -314,10 +359,7 @@ pub mod admin {
         State(state): State<AppState>,
         user: AuthenticatedUser,
     ) -> Result<Json<serde_json::Value>, AppError> {
-        // Check admin permissions
-        if user.subscription_tier != crate::database::UserSubscriptionTier::Enterprise {
-            return Err(AppError::Forbidden("Admin access required".to_string()));
-        }
+        require_operator(&state, &user).await?;
 
         // In production, implement actual breach data update
         info!("Breach data update initiated by admin: {}", user.email);
@@ -328,4 +370,75 @@ pub mod admin {
             "estimated_completion": Utc::now() + chrono::Duration::hours(1)
         })))
     }
-}
\ No newline at end of file
+
+    pub async fn ingest_breach_data(
+        State(state): State<AppState>,
+        user: AuthenticatedUser,
+        Json(payload): Json<IngestBreachDataRequest>,
+    ) -> Result<Response, AppError> {
+        require_operator(&state, &user).await?;
+
+        let format = match payload.format.as_deref() {
+            Some(format) => format.parse::<InputFormat>().map_err(AppError::BadRequest)?,
+            None => InputFormat::Auto,
+        };
+
+        let options = IngestOptions {
+            source: payload.source,
+            breach_date: payload.breach_date,
+            format,
+            batch_size: state.settings.data.ingest_batch_size,
+            data_classes: payload.data_classes,
+        };
+
+        match (payload.file, payload.records) {
+            (Some(file), None) => {
+                // Only bare file names, so requests can't reach outside the dump directory
+                let name = std::path::Path::new(&file).file_name()
+                    .filter(|name| name.to_str() == Some(file.as_str()))
+                    .ok_or_else(|| AppError::BadRequest("file must be a file name inside the dump directory".to_string()))?;
+
+                let path = std::path::Path::new(&state.settings.data.dump_dir).join(name);
+                if !path.is_file() {
+                    return Err(AppError::NotFound(format!("Dump file {} not found", file)));
+                }
+
+                // Dumps can take longer than the request timeout; poll the job
+                let job = state.ingest_jobs.start(
+                    state.db.clone(),
+                    (*state.hasher).clone(),
+                    path.to_string_lossy().into_owned(),
+                    options,
+                ).map_err(|e| AppError::BadRequest(e.to_string()))?;
+                info!("Ingest job {} for {} started by admin {}", job.id, job.file, user.email);
+
+                Ok((StatusCode::ACCEPTED, Json(job)).into_response())
+            }
+            (None, Some(records)) => {
+                let summary = ingest::ingest_records(&state.db, &state.hasher, &records, &options).await?;
+                info!(
+                    "Breach data ingested by admin {}: {} ({} inserted, {} duplicates, {} skipped)",
+                    user.email, summary.source, summary.inserted, summary.duplicates, summary.skipped
+                );
+
+                Ok(Json(summary).into_response())
+            }
+            _ => Err(AppError::BadRequest("Provide exactly one of 'file' or 'records'".to_string())),
+        }
+    }
+
+    pub async fn get_ingest_job(
+        State(state): State<AppState>,
+        user: AuthenticatedUser,
+        Path(job_id): Path<String>,
+    ) -> Result<Json<IngestJob>, AppError> {
+        require_operator(&state, &user).await?;
+
+        let job_uuid = Uuid::parse_str(&job_id)
+            .map_err(|_| AppError::BadRequest("Invalid job ID".to_string()))?;
+
+        state.ingest_jobs.get(job_uuid)
+            .map(Json)
+            .ok_or_else(|| AppError::NotFound("Ingest job not found".to_string()))
+    }
+}
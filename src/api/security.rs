@@ -1,17 +1,25 @@
 use axum::{extract::State, Json};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
-use tracing::info;
+use tracing::{info, warn};
 use validator::Validate;
 
-use crate::auth::AuthenticatedUser;
-use crate::errors::AppError;
+use crate::auth::{AuthenticatedUser, Scope};
+use crate::data_classes::ExposedClass;
+use crate::database::BreachData;
+use crate::identity::{self, NormalizationRule};
+use crate::errors::{validation_error_response, AppError};
+use crate::osint::investigation::InvestigationReport;
+use crate::osint::{BreachLookup, BreachRecord};
+use crate::password::strength::StrengthEstimate;
+use crate::password::weakness::WeaknessReason;
+use crate::password;
 use crate::state::AppState;
-use crate::weak_pass;
-use crate::risk_score;
+use crate::risk_score::{BreachSignal, RiskAssessment, RiskFactor, RiskSignals};
+use crate::dump_formats::InputFormat;
 use crate::filter;
 use crate::filtermain;
+use crate::filter_rules::RuleSet;
 
 // Request/Response models
 #[derive(Debug, Deserialize, Validate)]
@@ -26,8 +34,12 @@ pub struct PasswordCheckResponse {
     pub is_weak: bool,
     pub strength_score: u8,
     pub is_breached: bool,
+    // None when the Pwned Passwords lookup failed: unknown, not "never seen"
+    pub breach_occurrences: Option<u64>,
     pub recommendations: Vec<String>,
     pub entropy: f64,
+    pub weak_reasons: Vec<WeaknessReason>,
+    pub strength: StrengthEstimate,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -40,9 +52,15 @@ pub struct BreachCheckRequest {
 #[derive(Debug, Serialize)]
 pub struct BreachCheckResponse {
     pub email: String,
+    // The form matched against breach rows, and the rules that produced it
+    pub normalized_email: String,
+    pub normalization: Vec<NormalizationRule>,
     pub is_breached: bool,
     pub breach_count: u32,
+    pub paste_count: u32,
     pub risk_score: u8,
+    pub risk_model: String,
+    pub risk_factors: Vec<RiskFactor>,
     pub breaches: Option<Vec<BreachDetail>>,
     pub recommendations: Vec<String>,
 }
@@ -52,7 +70,9 @@ pub struct BreachDetail {
     pub source: String,
     pub breach_date: String,
     pub data_types: Vec<String>,
+    pub data_classes: Vec<ExposedClass>,
     pub severity: String,
+    pub provider: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -65,19 +85,13 @@ pub struct RiskScoreRequest {
 #[derive(Debug, Serialize)]
 pub struct RiskScoreResponse {
     pub risk_score: u8,
+    pub risk_model: String,
     pub email: String,
+    pub normalized_email: String,
     pub factors: Vec<RiskFactor>,
     pub recommendations: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct RiskFactor {
-    pub factor_type: String,
-    pub description: String,
-    pub score_impact: u8,
-    pub severity: String,
-}
-
 #[derive(Debug, Deserialize)]
 pub struct BulkSecurityCheckRequest {
     pub emails: Vec<String>,
@@ -87,6 +101,7 @@ pub struct BulkSecurityCheckRequest {
 
 #[derive(Debug, Serialize)]
 pub struct BulkSecurityCheckResponse {
+    pub risk_model: String,
     pub results: Vec<BulkCheckResult>,
     pub summary: BulkCheckSummary,
 }
@@ -94,6 +109,7 @@ pub struct BulkSecurityCheckResponse {
 #[derive(Debug, Serialize)]
 pub struct BulkCheckResult {
     pub email: String,
+    pub normalized_email: String,
     pub is_breached: bool,
     pub risk_score: u8,
     pub password_weak: Option<bool>,
@@ -107,10 +123,17 @@ pub struct BulkCheckSummary {
     pub weak_passwords: u32,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct InvestigationRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DataFilterRequest {
     pub data: serde_json::Value,
     pub filter_type: String, // "basic" or "advanced"
+    pub rules: Option<serde_json::Value>, // inline rule set; server default when absent
 }
 
 #[derive(Debug, Serialize)]
@@ -120,60 +143,23 @@ pub struct DataFilterResponse {
     pub filter_applied: String,
 }
 
-// Password strength calculation
-fn calculate_password_strength(password: &str) -> u8 {
-    let mut score = 0u8;
-    
-    // Length scoring
-    if password.len() >= 8 { score += 20; }
-    if password.len() >= 12 { score += 10; }
-    if password.len() >= 16 { score += 10; }
-    
-    // Character variety
-    if password.chars().any(|c| c.is_lowercase()) { score += 10; }
-    if password.chars().any(|c| c.is_uppercase()) { score += 10; }
-    if password.chars().any(|c| c.is_ascii_digit()) { score += 10; }
-    if password.chars().any(|c| !c.is_alphanumeric()) { score += 15; }
-    
-    // Pattern checks (deduct points for common patterns)
-    if is_common_pattern(password) { score = score.saturating_sub(20); }
-    
-    score.min(100)
-}
-
-fn is_common_pattern(password: &str) -> bool {
-    let lower = password.to_lowercase();
-    
-    // Common patterns
-    let patterns = [
-        "123456", "password", "qwerty", "abc", "111", "000",
-        "admin", "user", "login", "welcome", "monkey"
-    ];
-    
-    patterns.iter().any(|&pattern| lower.contains(pattern))
+fn breach_signal(breach: &BreachRecord) -> BreachSignal {
+    BreachSignal::new(&breach.source, breach.breach_date, &breach.data_types, Some(&breach.severity))
 }
 
-fn calculate_password_entropy(password: &str) -> f64 {
-    let mut charset_size = 0;
-    
-    if password.chars().any(|c| c.is_lowercase()) { charset_size += 26; }
-    if password.chars().any(|c| c.is_uppercase()) { charset_size += 26; }
-    if password.chars().any(|c| c.is_ascii_digit()) { charset_size += 10; }
-    if password.chars().any(|c| !c.is_alphanumeric()) { charset_size += 32; }
-    
-    (password.len() as f64) * (charset_size as f64).log2()
-}
-
-fn hash_email(email: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(email.to_lowercase().as_bytes());
-    hex::encode(hasher.finalize())
-}
-
-fn hash_password(password: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(password.as_bytes());
-    hex::encode(hasher.finalize())
+fn local_breach_record(breach: &BreachData) -> BreachRecord {
+    let data_types: Vec<String> = serde_json::from_str(&breach.data_types)
+        .unwrap_or_else(|_| vec!["unknown".to_string()]);
+
+    BreachRecord {
+        source: breach.source_name.clone(),
+        breach_date: Some(breach.breach_date.date_naive()),
+        data_types,
+        severity: breach.severity.clone(),
+        provider: "local".to_string(),
+        verified: breach.verified,
+        sensitive: false,
+    }
 }
 
 // API Handlers
@@ -182,59 +168,87 @@ pub async fn check_password_strength(
     user: AuthenticatedUser,
     Json(payload): Json<PasswordCheckRequest>,
 ) -> Result<Json<PasswordCheckResponse>, AppError> {
-    // Track usage
-    state.db.track_api_usage(user.user_id, "check_password").await?;
-
-    // Load weak passwords list
-    let weak_passwords = weak_pass::load_password_list("top-passwords.txt")
-        .map_err(|e| AppError::InternalServerError(format!("Failed to load password list: {}", e)))?;
+    user.require_scope(Scope::SecurityRead)?;
 
-    let is_weak = weak_pass::is_password_weak(&payload.password, &weak_passwords);
-    let strength_score = calculate_password_strength(&payload.password);
-    let entropy = calculate_password_entropy(&payload.password);
+    // Charge the monthly quota
+    state.quota.charge(&user, "check_password", 1).await?;
 
-    // Check if password is in breaches
-    let password_hash = hash_password(&payload.password);
-    let password_breaches = state.db.check_password_breaches(&password_hash).await?;
-    let is_breached = !password_breaches.is_empty();
+    // Personal details are the first thing an attacker tries
+    let mut user_inputs = password::email_inputs(&user.email);
+    if let Some(email) = &payload.email {
+        user_inputs.extend(password::email_inputs(email));
+    }
+    let strength = password::estimate(&payload.password, &user_inputs);
+    let verdict = state.passwords.check(&payload.password, &user_inputs).with_strength(&strength);
+    let is_weak = verdict.weak;
+    // 10^14 guesses resists an offline attack on a slow hash for centuries
+    let strength_score = (strength.guesses_log10 * 100.0 / 14.0).round().clamp(0.0, 100.0) as u8;
+    // Bits of entropy implied by the guess estimate
+    let entropy = strength.guesses_log10 * std::f64::consts::LOG2_10;
+
+    // Check if password is in breaches (local data + Pwned Passwords range lookup)
+    let password_hashes = state.hasher.password_lookup_hashes(&payload.password);
+    let password_breaches = state.db.check_password_breaches(&password_hashes).await?;
+    let breach_occurrences = match state.pwned_passwords.occurrences(&payload.password).await {
+        Ok(count) => Some(count),
+        Err(e) => {
+            warn!("Pwned Passwords lookup failed: {}", e);
+            None
+        }
+    };
+    let is_breached = !password_breaches.is_empty() || breach_occurrences.is_some_and(|count| count > 0);
 
     let mut recommendations = Vec::new();
     
-    if is_weak {
-        recommendations.push("Use a longer, more complex password".to_string());
-        recommendations.push("Avoid common words and patterns".to_string());
-    }
+    // The estimator's own feedback below covers low strength scores
+    recommendations.extend(verdict.reasons.iter()
+        .filter(|reason| !matches!(reason, WeaknessReason::Guessable { .. }))
+        .map(|reason| reason.to_string()));
     
     if is_breached {
         recommendations.push("This password has been found in data breaches - change it immediately".to_string());
     }
-    
-    if strength_score < 60 {
-        recommendations.push("Add more character variety (uppercase, lowercase, numbers, symbols)".to_string());
+
+    match breach_occurrences {
+        Some(count) if count > 100 => recommendations.push(format!(
+            "This password has appeared {} times in breach corpora and is used in credential stuffing lists",
+            count
+        )),
+        Some(_) => {}
+        None => recommendations.push(
+            "This password could not be checked against known breach corpora; try again later".to_string(),
+        ),
     }
     
-    if entropy < 50.0 {
-        recommendations.push("Increase password length for better security".to_string());
+    if let Some(warning) = &strength.feedback.warning {
+        recommendations.push(warning.clone());
     }
+    recommendations.extend(strength.feedback.suggestions.iter().cloned());
 
     // Store security report
     let report_data = serde_json::json!({
         "password_analysis": {
             "strength_score": strength_score,
             "is_weak": is_weak,
+            "weak_reasons": verdict.reasons,
             "is_breached": is_breached,
+            "breach_occurrences": breach_occurrences,
             "entropy": entropy,
+            "score": strength.score,
+            "guesses_log10": strength.guesses_log10,
+            "matched_patterns": strength.pattern_names(),
             "recommendations": recommendations
         }
     });
 
-    let input_hash = hash_password(&payload.password);
+    let input_hash = state.hasher.hash_password(&payload.password);
     state.db.create_security_report(
         user.user_id,
         "password_check",
         &input_hash,
         &report_data.to_string(),
         Some(strength_score as i32),
+        None,
     ).await?;
 
     info!("Password check completed for user: {}", user.email);
@@ -243,8 +257,11 @@ pub async fn check_password_strength(
         is_weak,
         strength_score,
         is_breached,
+        breach_occurrences,
         recommendations,
         entropy,
+        weak_reasons: verdict.reasons,
+        strength,
     }))
 }
 
@@ -253,50 +270,57 @@ pub async fn check_breach(
     user: AuthenticatedUser,
     Json(payload): Json<BreachCheckRequest>,
 ) -> Result<Json<BreachCheckResponse>, AppError> {
-    // Track usage
-    state.db.track_api_usage(user.user_id, "check_breach").await?;
+    user.require_scope(Scope::SecurityRead)?;
+
+    // Charge the monthly quota
+    state.quota.charge(&user, "check_breach", 1).await?;
 
-    // Hash email for privacy
-    let email_hash = hash_email(&payload.email);
+    // Hash email for privacy; lookups use the canonical address
+    let identity = identity::canonical_email(&payload.email);
+    let email_hash = state.hasher.hash_email(&payload.email);
 
-    // Check breaches
-    let breaches = state.db.check_email_breaches(&email_hash).await?;
+    // Check local breach data first, then merge in external providers
+    let local_breaches = state.db.check_email_breaches(&state.hasher.email_lookup_hashes(&payload.email)).await?;
+    let mut lookup = BreachLookup {
+        breaches: local_breaches.iter().map(local_breach_record).collect(),
+        pastes: Vec::new(),
+    };
+
+    for provider in state.breach_providers.iter() {
+        match provider.lookup(&payload.email).await {
+            Ok(remote) => lookup.merge(remote),
+            Err(e) => warn!("Breach provider {} failed: {}", provider.name(), e),
+        }
+    }
+
+    let breaches = lookup.breaches;
     let is_breached = !breaches.is_empty();
     let breach_count = breaches.len() as u32;
+    let paste_count = lookup.pastes.len() as u32;
 
-    // Calculate risk score based on breaches
-    let risk_score = if is_breached {
-        let recent_breaches = breaches.iter()
-            .filter(|b| {
-                let breach_date = chrono::DateTime::parse_from_rfc3339(&b.breach_date.to_rfc3339())
-                    .unwrap_or_else(|_| chrono::DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z").unwrap());
-                let one_year_ago = Utc::now() - chrono::Duration::days(365);
-                breach_date > one_year_ago
-            })
-            .count();
-        
-        let base_score = 30 + (breach_count * 10).min(40);
-        if recent_breaches > 0 { base_score + 20 } else { base_score }
-    } else {
-        10 // Base score for no known breaches
-    };
+    let breach_signals: Vec<BreachSignal> = breaches.iter().map(breach_signal).collect();
 
     let breach_details = if payload.include_details.unwrap_or(false) {
-        Some(breaches.iter().map(|b| {
-            let data_types: Vec<String> = serde_json::from_str(&b.data_types)
-                .unwrap_or_else(|_| vec!["unknown".to_string()]);
-            
-            BreachDetail {
-                source: b.source_name.clone(),
-                breach_date: b.breach_date.format("%Y-%m-%d").to_string(),
-                data_types,
-                severity: b.severity.clone(),
-            }
+        Some(breaches.iter().zip(&breach_signals).map(|(b, signal)| BreachDetail {
+            source: b.source.clone(),
+            breach_date: b.breach_date
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            data_types: b.data_types.clone(),
+            data_classes: signal.exposed.clone(),
+            severity: signal.severity.to_string(),
+            provider: b.provider.clone(),
         }).collect())
     } else {
         None
     };
 
+    let risk = state.risk.assess(&RiskSignals {
+        breaches: breach_signals,
+        paste_count,
+        ..Default::default()
+    });
+
     let mut recommendations = Vec::new();
     if is_breached {
         recommendations.push("Change passwords for all accounts using this email".to_string());
@@ -311,13 +335,22 @@ pub async fn check_breach(
         recommendations.push("Regularly monitor for new breaches".to_string());
     }
 
+    if paste_count > 0 {
+        recommendations.push("This email appears in public pastes - expect phishing and spam".to_string());
+    }
+
     // Store security report
     let report_data = serde_json::json!({
         "breach_check": {
             "email": payload.email,
+            "normalized_email": identity.normalized,
+            "normalization": identity.rules,
             "is_breached": is_breached,
             "breach_count": breach_count,
-            "risk_score": risk_score,
+            "paste_count": paste_count,
+            "risk_score": risk.score,
+            "risk_model": risk.model_version,
+            "risk_factors": risk.factors,
             "breaches": breach_details,
             "recommendations": recommendations
         }
@@ -328,16 +361,22 @@ pub async fn check_breach(
         "breach_check",
         &email_hash,
         &report_data.to_string(),
-        Some(risk_score as i32),
+        Some(risk.score as i32),
+        Some(&risk.model_version),
     ).await?;
 
     info!("Breach check completed for user: {} (breaches: {})", user.email, breach_count);
 
     Ok(Json(BreachCheckResponse {
         email: payload.email,
+        normalized_email: identity.normalized,
+        normalization: identity.rules,
         is_breached,
         breach_count,
-        risk_score: risk_score as u8,
+        paste_count,
+        risk_score: risk.score,
+        risk_model: risk.model_version,
+        risk_factors: risk.factors,
         breaches: breach_details,
         recommendations,
     }))
@@ -348,75 +387,28 @@ pub async fn calculate_risk_score(
     user: AuthenticatedUser,
     Json(payload): Json<RiskScoreRequest>,
 ) -> Result<Json<RiskScoreResponse>, AppError> {
-    // Track usage
-    state.db.track_api_usage(user.user_id, "risk_score").await?;
+    user.require_scope(Scope::SecurityRead)?;
 
-    // Load weak passwords
-    let weak_passwords = weak_pass::load_password_list("top-passwords.txt")
-        .map_err(|e| AppError::InternalServerError(format!("Failed to load password list: {}", e)))?;
+    // Charge the monthly quota
+    state.quota.charge(&user, "risk_score", 1).await?;
 
-    // Create a JSON entry for risk calculation
-    let mut entry = serde_json::Map::new();
-    entry.insert("email".to_string(), serde_json::Value::String(payload.email.clone()));
-    entry.insert("occurrences".to_string(), serde_json::Value::Number(serde_json::Number::from(1)));
-
-    if let Some(password) = &payload.password {
-        entry.insert("password".to_string(), serde_json::Value::String(password.clone()));
-    }
+    let identity = identity::canonical_email(&payload.email);
+    let email_breaches = state.db.check_email_breaches(&state.hasher.email_lookup_hashes(&payload.email)).await?;
 
-    // Add breach date (simulate recent breach for higher risk)
-    let mut source = serde_json::Map::new();
-    source.insert("breach_date".to_string(), serde_json::Value::String("2024-01-01".to_string()));
-    entry.insert("source".to_string(), serde_json::Value::Object(source));
-
-    let json_value = serde_json::Value::Object(entry);
-    let base_risk_score = risk_score::calculate_risk_score(&json_value, &weak_passwords);
-
-    // Check for additional risk factors
-    let mut risk_factors = Vec::new();
-    let mut total_risk = base_risk_score;
-
-    // Check email breaches
-    let email_hash = hash_email(&payload.email);
-    let email_breaches = state.db.check_email_breaches(&email_hash).await?;
-    if !email_breaches.is_empty() {
-        let breach_impact = (email_breaches.len() * 10).min(30) as u8;
-        total_risk = (total_risk + breach_impact).min(100);
-        
-        risk_factors.push(RiskFactor {
-            factor_type: "email_breaches".to_string(),
-            description: format!("Email found in {} data breach(es)", email_breaches.len()),
-            score_impact: breach_impact,
-            severity: if email_breaches.len() > 2 { "high".to_string() } else { "medium".to_string() },
-        });
-    }
+    let mut signals = RiskSignals {
+        breaches: email_breaches.iter().map(local_breach_record).map(|b| breach_signal(&b)).collect(),
+        ..Default::default()
+    };
 
-    // Check password if provided
     if let Some(password) = &payload.password {
-        let password_hash = hash_password(password);
-        let password_breaches = state.db.check_password_breaches(&password_hash).await?;
-        
-        if !password_breaches.is_empty() {
-            total_risk = (total_risk + 40).min(100);
-            risk_factors.push(RiskFactor {
-                factor_type: "password_breaches".to_string(),
-                description: "Password found in data breaches".to_string(),
-                score_impact: 40,
-                severity: "critical".to_string(),
-            });
-        }
-
-        if weak_pass::is_password_weak(password, &weak_passwords) {
-            total_risk = (total_risk + 20).min(100);
-            risk_factors.push(RiskFactor {
-                factor_type: "weak_password".to_string(),
-                description: "Password is commonly used and weak".to_string(),
-                score_impact: 20,
-                severity: "high".to_string(),
-            });
-        }
+        let password_hashes = state.hasher.password_lookup_hashes(password);
+        let password_breaches = state.db.check_password_breaches(&password_hashes).await?;
+        signals.password_breach_occurrences = password_breaches.len() as u64;
+        signals.password_verdict = Some(state.passwords.check(password, &password::email_inputs(&payload.email)));
     }
 
+    let RiskAssessment { score: total_risk, model_version, factors: risk_factors } = state.risk.assess(&signals);
+
     let mut recommendations = Vec::new();
     if total_risk > 70 {
         recommendations.push("Immediate action required - high security risk detected".to_string());
@@ -436,26 +428,32 @@ pub async fn calculate_risk_score(
     let report_data = serde_json::json!({
         "risk_assessment": {
             "email": payload.email,
+            "normalized_email": identity.normalized,
+            "normalization": identity.rules,
             "risk_score": total_risk,
+            "risk_model": model_version,
             "factors": risk_factors,
             "recommendations": recommendations
         }
     });
 
-    let input_hash = hash_email(&payload.email);
+    let input_hash = state.hasher.hash_email(&payload.email);
     state.db.create_security_report(
         user.user_id,
         "risk_assessment",
         &input_hash,
         &report_data.to_string(),
         Some(total_risk as i32),
+        Some(&model_version),
     ).await?;
 
     info!("Risk assessment completed for user: {} (score: {})", user.email, total_risk);
 
     Ok(Json(RiskScoreResponse {
         risk_score: total_risk,
+        risk_model: model_version,
         email: payload.email,
+        normalized_email: identity.normalized,
         factors: risk_factors,
         recommendations,
     }))
@@ -466,6 +464,8 @@ pub async fn bulk_security_check(
     user: AuthenticatedUser,
     Json(payload): Json<BulkSecurityCheckRequest>,
 ) -> Result<Json<BulkSecurityCheckResponse>, AppError> {
+    user.require_scope(Scope::SecurityRead)?;
+
     // Check if user has permission for bulk operations
     if user.subscription_tier == crate::database::UserSubscriptionTier::Free {
         return Err(AppError::Forbidden("Bulk operations require a premium subscription".to_string()));
@@ -482,11 +482,8 @@ pub async fn bulk_security_check(
         return Err(AppError::BadRequest(format!("Bulk operation limited to {} items", max_bulk_size)));
     }
 
-    // Track usage
-    state.db.track_api_usage(user.user_id, "bulk_check").await?;
-
-    let weak_passwords = weak_pass::load_password_list("top-passwords.txt")
-        .map_err(|e| AppError::InternalServerError(format!("Failed to load password list: {}", e)))?;
+    // Charge the monthly quota, per email
+    state.quota.charge(&user, "bulk_check", payload.emails.len() as u32).await?;
 
     let mut results = Vec::new();
     let mut breached_count = 0;
@@ -494,44 +491,46 @@ pub async fn bulk_security_check(
     let mut weak_passwords_count = 0;
 
     for (index, email) in payload.emails.iter().enumerate() {
-        let email_hash = hash_email(email);
-        let breaches = state.db.check_email_breaches(&email_hash).await?;
+        let breaches = state.db.check_email_breaches(&state.hasher.email_lookup_hashes(email)).await?;
         let is_breached = !breaches.is_empty();
         
         if is_breached {
             breached_count += 1;
         }
 
-        let mut risk_score = if is_breached { 50 } else { 10 };
+        let mut signals = RiskSignals {
+            breaches: breaches.iter().map(local_breach_record).map(|b| breach_signal(&b)).collect(),
+            ..Default::default()
+        };
         let mut password_weak = None;
 
         // Check password if provided
         if let Some(passwords) = &payload.passwords {
             if let Some(password) = passwords.get(index) {
-                let is_weak = weak_pass::is_password_weak(password, &weak_passwords);
-                password_weak = Some(is_weak);
+                let verdict = state.passwords.check(password, &password::email_inputs(email));
+                password_weak = Some(verdict.weak);
                 
-                if is_weak {
-                    risk_score += 20;
+                if verdict.weak {
                     weak_passwords_count += 1;
                 }
+                signals.password_verdict = Some(verdict);
 
-                let password_hash = hash_password(password);
-                let password_breaches = state.db.check_password_breaches(&password_hash).await?;
-                if !password_breaches.is_empty() {
-                    risk_score += 30;
-                }
+                let password_hashes = state.hasher.password_lookup_hashes(password);
+                let password_breaches = state.db.check_password_breaches(&password_hashes).await?;
+                signals.password_breach_occurrences = password_breaches.len() as u64;
             }
         }
 
+        let risk_score = state.risk.assess(&signals).score;
         if risk_score > 60 {
             high_risk_count += 1;
         }
 
         results.push(BulkCheckResult {
             email: email.clone(),
+            normalized_email: identity::canonical_email(email).normalized,
             is_breached,
-            risk_score: risk_score as u8,
+            risk_score,
             password_weak,
         });
     }
@@ -546,6 +545,7 @@ pub async fn bulk_security_check(
     // Store bulk report
     let report_data = serde_json::json!({
         "bulk_security_check": {
+            "risk_model": state.risk.version(),
             "results": results,
             "summary": summary,
             "checked_at": Utc::now()
@@ -559,23 +559,63 @@ pub async fn bulk_security_check(
         &input_hash,
         &report_data.to_string(),
         None,
+        None,
     ).await?;
 
     info!("Bulk security check completed for user: {} ({} items)", user.email, payload.emails.len());
 
     Ok(Json(BulkSecurityCheckResponse {
+        risk_model: state.risk.version().to_string(),
         results,
         summary,
     }))
 }
 
+pub async fn investigate(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(payload): Json<InvestigationRequest>,
+) -> Result<Json<InvestigationReport>, AppError> {
+    user.require_scope(Scope::SecurityRead)?;
+
+    if let Err(errors) = payload.validate() {
+        return Err(validation_error_response(&errors));
+    }
+
+    // Charge the monthly quota
+    state.quota.charge(&user, "investigate", 1).await?;
+
+    let report = state.investigator.investigate(&payload.email).await;
+
+    // Store security report
+    let email_hash = state.hasher.hash_email(&report.target);
+    state.db.create_security_report(
+        user.user_id,
+        "investigation",
+        &email_hash,
+        &serde_json::to_string(&report)?,
+        None,
+        None,
+    ).await?;
+
+    info!(
+        "Investigation completed for user: {} (HIBP breaches: {})",
+        user.email,
+        report.summary.total_hibp_breaches
+    );
+
+    Ok(Json(report))
+}
+
 pub async fn filter_data(
     State(state): State<AppState>,
     user: AuthenticatedUser,
     Json(payload): Json<DataFilterRequest>,
 ) -> Result<Json<DataFilterResponse>, AppError> {
-    // Track usage
-    state.db.track_api_usage(user.user_id, "filter_data").await?;
+    user.require_scope(Scope::SecurityRead)?;
+
+    // Charge the monthly quota
+    state.quota.charge(&user, "filter_data", 1).await?;
 
     // Convert payload data to JSON string for processing
     let input_json = serde_json::to_string_pretty(&payload.data)?;
@@ -590,15 +630,21 @@ pub async fn filter_data(
 
     let original_entries = count_json_entries(&payload.data);
 
+    let request_rules = payload.rules
+        .map(|rules| RuleSet::from_value(rules).and_then(|set| set.compile()))
+        .transpose()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let rules = request_rules.as_ref().unwrap_or(&state.filter_rules);
+
     // Apply filter based on type
     let result = match payload.filter_type.as_str() {
         "basic" => {
-            filter::run_filter(&temp_input, &temp_output)
+            filter::run_filter(&temp_input, &temp_output, InputFormat::Json, rules, false)
                 .map_err(|e| AppError::InternalServerError(format!("Filter failed: {}", e)))?;
             "basic"
         }
         "advanced" => {
-            filtermain::run_advanced_filter(&temp_input, &temp_output)
+            filtermain::run_advanced_filter(&temp_input, &temp_output, InputFormat::Json, rules, &state.passwords, false)
                 .map_err(|e| AppError::InternalServerError(format!("Advanced filter failed: {}", e)))?;
             "advanced"
         }
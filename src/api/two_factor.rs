@@ -0,0 +1,236 @@
+// TOTP two-factor authentication: enrollment, the second login step, and
+// recovery codes (src/totp.rs)
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::api::auth::{record_failed_login, start_session};
+use crate::auth::{AuthenticatedUser, AuthResponse, Scope, TwoFactorLoginRequest};
+use crate::database::{TwoFactor, User};
+use crate::errors::{login_locked_error, AppError};
+use crate::middleware::ClientIp;
+use crate::state::AppState;
+use crate::totp;
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorSetupResponse {
+    // Base32, for typing into an authenticator by hand
+    pub secret: String,
+    // otpauth:// URI to render as a QR code
+    pub provisioning_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    // A TOTP code or a recovery code
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+    pub warning: String,
+}
+
+// Second login step for accounts with two-factor enabled
+pub async fn verify_login(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    Json(payload): Json<TwoFactorLoginRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    let claims = state.auth.verify_two_factor_challenge(&payload.challenge_token)
+        .map_err(|_| AppError::Unauthorized("Invalid or expired challenge".to_string()))?;
+    let mut redis = state.redis.get_async_connection().await?;
+    if state.auth.is_token_blacklisted(&mut redis, &claims.jti).await? {
+        return Err(AppError::Unauthorized("Challenge has already been used".to_string()));
+    }
+
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AppError::Unauthorized("Invalid user ID in challenge".to_string()))?;
+    let user = state.db.get_user_by_id(user_id).await?
+        .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+    let two_factor = state.db.get_two_factor(user.id).await?
+        .filter(|two_factor| two_factor.enabled)
+        .ok_or_else(|| AppError::Unauthorized("Two-factor authentication is not enabled".to_string()))?;
+
+    if !redeem_code(&state, &user, &two_factor, &payload.code, ip.as_deref()).await? {
+        return Err(AppError::Unauthorized("Invalid two-factor code".to_string()));
+    }
+
+    state.auth.blacklist_token(&mut redis, &claims.jti, claims.exp).await?;
+    state.login_guard.record_success(&user.email).await;
+    Ok(Json(start_session(&state, &user).await?))
+}
+
+// Start enrollment with a new secret; two-factor isn't on until `enable`
+// confirms a code from it
+pub async fn setup(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<Json<TwoFactorSetupResponse>, AppError> {
+    require_session(&user)?;
+
+    let secret = state.totp.generate_secret();
+    if !state.db.start_two_factor_enrollment(user.user_id, &secret).await? {
+        return Err(AppError::BadRequest("Two-factor authentication is already enabled".to_string()));
+    }
+
+    info!("Two-factor enrollment started for user: {}", user.email);
+
+    Ok(Json(TwoFactorSetupResponse {
+        provisioning_uri: state.totp.provisioning_uri(&secret, &user.email),
+        secret,
+    }))
+}
+
+pub async fn enable(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    require_session(&user)?;
+
+    let pending = state.db.get_two_factor(user.user_id).await?
+        .filter(|two_factor| !two_factor.enabled)
+        .ok_or_else(|| AppError::BadRequest("No two-factor enrollment in progress".to_string()))?;
+    let step = state.totp.verify(&pending.secret, &payload.code)
+        .ok_or_else(|| AppError::ValidationError("Invalid two-factor code".to_string()))?;
+
+    let codes = totp::generate_recovery_codes();
+    if !state.db.enable_two_factor(user.user_id, step, &hash_codes(&codes)).await? {
+        return Err(AppError::BadRequest("Two-factor authentication is already enabled".to_string()));
+    }
+
+    info!("Two-factor authentication enabled for user: {}", user.email);
+    record_event(&state, user.user_id, "two_factor_enabled", ip.as_deref(), json!({})).await;
+
+    Ok(Json(recovery_codes_response(codes)))
+}
+
+// Needs the password as well as a code, so a stolen session can't turn it off
+pub async fn disable(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Json(payload): Json<DisableTwoFactorRequest>,
+) -> Result<Json<Value>, AppError> {
+    require_session(&user)?;
+
+    let (account, two_factor) = enabled_two_factor(&state, user.user_id).await?;
+    if !state.auth.verify_password(&payload.password, &account.password_hash).await? {
+        record_failed_login(&state, &account.email, Some(&account), ip.as_deref()).await;
+        return Err(AppError::ValidationError("Invalid password".to_string()));
+    }
+    if !redeem_code(&state, &account, &two_factor, &payload.code, ip.as_deref()).await? {
+        return Err(AppError::ValidationError("Invalid two-factor code".to_string()));
+    }
+
+    state.db.disable_two_factor(user.user_id).await?;
+
+    info!("Two-factor authentication disabled for user: {}", user.email);
+    record_event(&state, user.user_id, "two_factor_disabled", ip.as_deref(), json!({})).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Two-factor authentication disabled"
+    })))
+}
+
+// Replace every recovery code, used or not
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    user: AuthenticatedUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    require_session(&user)?;
+
+    let (account, two_factor) = enabled_two_factor(&state, user.user_id).await?;
+    if !redeem_code(&state, &account, &two_factor, &payload.code, ip.as_deref()).await? {
+        return Err(AppError::ValidationError("Invalid two-factor code".to_string()));
+    }
+
+    let codes = totp::generate_recovery_codes();
+    state.db.replace_recovery_codes(user.user_id, &hash_codes(&codes)).await?;
+
+    info!("Recovery codes regenerated for user: {}", user.email);
+    record_event(&state, user.user_id, "recovery_codes_regenerated", ip.as_deref(), json!({})).await;
+
+    Ok(Json(recovery_codes_response(codes)))
+}
+
+// Changing how the account signs in takes a session, not an API key
+fn require_session(user: &AuthenticatedUser) -> Result<(), AppError> {
+    user.require_scope(Scope::AccountWrite)?;
+    user.session()?;
+    Ok(())
+}
+
+async fn enabled_two_factor(state: &AppState, user_id: Uuid) -> Result<(User, TwoFactor), AppError> {
+    let user = state.db.get_user_by_id(user_id).await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let two_factor = state.db.get_two_factor(user_id).await?
+        .filter(|two_factor| two_factor.enabled)
+        .ok_or_else(|| AppError::BadRequest("Two-factor authentication is not enabled".to_string()))?;
+    Ok((user, two_factor))
+}
+
+// Spend a TOTP code or a recovery code. Wrong codes count as failed logins,
+// so guessing them runs into the same lockouts as guessing passwords.
+async fn redeem_code(
+    state: &AppState,
+    user: &User,
+    two_factor: &TwoFactor,
+    code: &str,
+    ip: Option<&str>,
+) -> Result<bool, AppError> {
+    if let Some(wait) = state.login_guard.locked_for(&user.email, ip).await {
+        return Err(login_locked_error(wait));
+    }
+
+    let redeemed = if totp::is_totp_code(code) {
+        match state.totp.verify(&two_factor.secret, code) {
+            Some(step) => state.db.use_totp_step(user.id, step).await?,
+            None => false,
+        }
+    } else if state.db.use_recovery_code(user.id, &totp::hash_recovery_code(code)).await? {
+        let remaining = state.db.count_unused_recovery_codes(user.id).await?;
+        record_event(state, user.id, "recovery_code_used", ip, json!({ "remaining": remaining })).await;
+        true
+    } else {
+        false
+    };
+
+    if !redeemed {
+        warn!("Failed two-factor attempt for user: {}", user.email);
+        record_failed_login(state, &user.email, Some(user), ip).await;
+    }
+    Ok(redeemed)
+}
+
+async fn record_event(state: &AppState, user_id: Uuid, event_type: &str, ip: Option<&str>, details: Value) {
+    if let Err(e) = state.db.record_security_event(user_id, event_type, ip, &details).await {
+        warn!("Failed to record {} for user {}: {}", event_type, user_id, e);
+    }
+}
+
+fn hash_codes(codes: &[String]) -> Vec<String> {
+    codes.iter().map(|code| totp::hash_recovery_code(code)).collect()
+}
+
+fn recovery_codes_response(recovery_codes: Vec<String>) -> RecoveryCodesResponse {
+    RecoveryCodesResponse {
+        recovery_codes,
+        warning: "Each recovery code works once. Store them somewhere safe; they won't be shown again.".to_string(),
+    }
+}
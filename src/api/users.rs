@@ -1,13 +1,19 @@
 use axum::{extract::{State, Path}, Json};
-use chrono::{Datelike, Timelike, Utc};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 use uuid::Uuid;
 
-use crate::auth::AuthenticatedUser;
+use crate::api::email::require_verified_email;
+use crate::auth::{AuthenticatedUser, Scope};
+use crate::config::VerifiedFeature;
 use crate::errors::AppError;
+use crate::quota::{self, UsageNotice};
 use crate::state::AppState;
 
+// Security events listed in the profile
+const SECURITY_EVENTS_SHOWN: i64 = 20;
+
 #[derive(Debug, Serialize)]
 pub struct UserProfileResponse {
     pub id: String,
@@ -18,6 +24,17 @@ pub struct UserProfileResponse {
     pub created_at: chrono::DateTime<Utc>,
     pub last_login: Option<chrono::DateTime<Utc>>,
     pub usage_stats: UsageStatsResponse,
+    pub two_factor_enabled: bool,
+    // Most recent first
+    pub security_events: Vec<SecurityEventResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SecurityEventResponse {
+    pub event_type: String,
+    pub ip_address: Option<String>,
+    pub details: serde_json::Value,
+    pub created_at: chrono::DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
@@ -28,6 +45,8 @@ pub struct UsageStatsResponse {
     pub requests_remaining: u32,
     pub reset_date: chrono::DateTime<Utc>,
     pub usage_by_endpoint: Vec<EndpointUsage>,
+    // Warnings as the quota runs low or is overrun
+    pub notices: Vec<UsageNotice>,
 }
 
 #[derive(Debug, Serialize)]
@@ -46,6 +65,7 @@ pub struct ApiKeyResponse {
     pub id: String,
     pub name: String,
     pub key_prefix: String,
+    pub scopes: Vec<Scope>,
     pub created_at: chrono::DateTime<Utc>,
     pub last_used: Option<chrono::DateTime<Utc>>,
     pub expires_at: Option<chrono::DateTime<Utc>>,
@@ -55,6 +75,8 @@ pub struct ApiKeyResponse {
 pub struct CreateApiKeyRequest {
     pub name: String,
     pub expires_at: Option<chrono::DateTime<Utc>>,
+    // Defaults to Scope::DEFAULT_KEY
+    pub scopes: Option<Vec<Scope>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -68,49 +90,50 @@ pub async fn get_profile(
     State(state): State<AppState>,
     user: AuthenticatedUser,
 ) -> Result<Json<UserProfileResponse>, AppError> {
+    user.require_scope(Scope::AccountRead)?;
+
     let user_data = state.db.get_user_by_id(user.user_id).await?
         .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
-    // Get current month usage
-    let current_month = Utc::now().format("%Y-%m").to_string();
+    // Get current month usage, in quota units
+    let now = Utc::now();
+    let current_month = quota::current_month(now);
     let usage = state.db.get_user_usage(user.user_id, &current_month).await?;
     
     let total_requests: i32 = usage.iter().map(|u| u.requests_count).sum();
-    
-    let monthly_limit = match user.subscription_tier {
-        crate::database::UserSubscriptionTier::Free => 100,
-        crate::database::UserSubscriptionTier::Pro => 5000,
-        crate::database::UserSubscriptionTier::Enterprise => 50000,
-    };
+    let requests_used = total_requests.max(0) as u32;
+    let monthly_limit = state.quota.limit(&user.subscription_tier);
 
     let usage_by_endpoint = usage.into_iter().map(|u| EndpointUsage {
         endpoint: u.endpoint,
         requests: u.requests_count as u32,
     }).collect();
 
-    // Calculate reset date (first day of next month)
-    let now = Utc::now();
-    let next_month = if now.month() == 12 {
-        now.with_year(now.year() + 1).and_then(|d| d.with_month(1))
-    } else {
-        now.with_month(now.month() + 1)
-    };
-    let reset_date = next_month.and_then(|d| d.with_day(1))
-        .and_then(|d| d.with_hour(0))
-        .and_then(|d| d.with_minute(0))
-        .and_then(|d| d.with_second(0))
-        .and_then(|d| d.with_nanosecond(0))
-        .unwrap_or_else(Utc::now);
+    let reset_date = quota::period_end(now);
 
     let usage_stats = UsageStatsResponse {
         current_month,
         monthly_limit,
-        requests_used: total_requests as u32,
-        requests_remaining: (monthly_limit as i32 - total_requests).max(0) as u32,
+        requests_used,
+        requests_remaining: monthly_limit.saturating_sub(requests_used),
         reset_date,
         usage_by_endpoint,
+        notices: state.quota.notices(requests_used, monthly_limit, reset_date),
     };
 
+    let security_events = state.db.get_security_events(user.user_id, SECURITY_EVENTS_SHOWN).await?
+        .into_iter()
+        .map(|event| SecurityEventResponse {
+            event_type: event.event_type,
+            ip_address: event.ip_address,
+            details: serde_json::from_str(&event.details).unwrap_or_default(),
+            created_at: event.created_at,
+        })
+        .collect();
+
+    let two_factor_enabled = state.db.get_two_factor(user.user_id).await?
+        .is_some_and(|two_factor| two_factor.enabled);
+
     Ok(Json(UserProfileResponse {
         id: user_data.id.to_string(),
         email: user_data.email,
@@ -120,6 +143,8 @@ pub async fn get_profile(
         created_at: user_data.created_at,
         last_login: user_data.last_login,
         usage_stats,
+        two_factor_enabled,
+        security_events,
     }))
 }
 
@@ -128,6 +153,8 @@ pub async fn update_profile(
     user: AuthenticatedUser,
     Json(payload): Json<UpdateProfileRequest>,
 ) -> Result<Json<UserProfileResponse>, AppError> {
+    user.require_scope(Scope::AccountWrite)?;
+
     // For now, we'll just update the name
     // In a full implementation, you'd have an update_user method in the database
     
@@ -145,11 +172,14 @@ pub async fn list_api_keys(
     State(state): State<AppState>,
     user: AuthenticatedUser,
 ) -> Result<Json<Vec<ApiKeyResponse>>, AppError> {
+    user.require_scope(Scope::AccountRead)?;
+
     let api_keys = state.db.list_user_api_keys(user.user_id).await?;
 
     let response: Vec<ApiKeyResponse> = api_keys.into_iter().map(|key| ApiKeyResponse {
         id: key.id.to_string(),
         name: key.name,
+        scopes: Scope::parse_list(&key.scopes),
         key_prefix: key.key_prefix,
         created_at: key.created_at,
         last_used: key.last_used,
@@ -164,6 +194,9 @@ pub async fn create_api_key(
     user: AuthenticatedUser,
     Json(payload): Json<CreateApiKeyRequest>,
 ) -> Result<Json<CreateApiKeyResponse>, AppError> {
+    user.require_scope(Scope::AccountWrite)?;
+    require_verified_email(&state, &user, VerifiedFeature::ApiKeys).await?;
+
     // Check API key limits based on subscription
     let existing_keys = state.db.list_user_api_keys(user.user_id).await?;
     let max_keys = match user.subscription_tier {
@@ -180,6 +213,20 @@ pub async fn create_api_key(
         )));
     }
 
+    let mut scopes = payload.scopes.unwrap_or_else(|| Scope::DEFAULT_KEY.to_vec());
+    scopes.sort_by_key(|scope| Scope::ALL.iter().position(|s| s == scope));
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(AppError::ValidationError("An API key needs at least one scope".to_string()));
+    }
+    // A key can't hand out more than its creator holds
+    if let Some(scope) = scopes.iter().find(|scope| !user.has_scope(**scope)) {
+        return Err(AppError::Forbidden(format!("Cannot grant the '{}' scope", scope)));
+    }
+    if payload.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(AppError::ValidationError("expires_at must be in the future".to_string()));
+    }
+
     // Generate API key
     let api_key = state.auth.generate_api_key();
     let key_hash = state.auth.hash_api_key(&api_key);
@@ -191,6 +238,8 @@ pub async fn create_api_key(
         &payload.name,
         &key_hash,
         &key_prefix,
+        &scopes.iter().map(Scope::to_string).collect::<Vec<_>>(),
+        payload.expires_at,
     ).await?;
 
     info!("API key created for user: {} (name: {})", user.email, payload.name);
@@ -200,6 +249,7 @@ pub async fn create_api_key(
         key_info: ApiKeyResponse {
             id: stored_key.id.to_string(),
             name: stored_key.name,
+            scopes: Scope::parse_list(&stored_key.scopes),
             key_prefix: stored_key.key_prefix,
             created_at: stored_key.created_at,
             last_used: stored_key.last_used,
@@ -214,6 +264,8 @@ pub async fn revoke_api_key(
     user: AuthenticatedUser,
     Path(key_id): Path<String>,
 ) -> Result<Json<serde_json::Value>, AppError> {
+    user.require_scope(Scope::AccountWrite)?;
+
     let key_uuid = Uuid::parse_str(&key_id)
         .map_err(|_| AppError::BadRequest("Invalid API key ID".to_string()))?;
 
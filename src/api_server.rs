@@ -12,29 +12,40 @@ use tower_http::{
     timeout::TimeoutLayer,
     trace::TraceLayer,
 };
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 // Import all modules
 mod api;
 mod auth;
-mod config;
-mod database;
 mod errors;
+mod login_guard;
+mod mail;
 mod middleware;
+mod quota;
+mod rate_limit;
 mod state;
-mod filter;
-mod filtermain;
-mod fetch_dumps;
-mod weak_pass;
-mod risk_score;
+mod totp;
+
+// Shared with the CLI (src/lib.rs)
+use guardr::{
+    breach_hash, config, data_classes, database, dump_formats, filter, filter_rules, filtermain,
+    identity, ingest, llm, osint, password, risk_score,
+};
+
+#[cfg(test)]
+mod tests;
 
 use crate::{
     config::Settings,
+    database::Reencrypted,
     middleware::*,
     state::AppState,
 };
 
+// Rows per query when re-encrypting report results and TOTP secrets at startup
+const REENCRYPT_BATCH_SIZE: i64 = 500;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Load environment variables
@@ -59,6 +70,30 @@ async fn main() -> Result<()> {
         .map_err(|e| anyhow::anyhow!("Failed to initialize application state: {}", e))?;
 
     info!("Database connection established");
+
+    // Move reports and TOTP secrets left under a retired key (or unencrypted)
+    // to the current key
+    let db = app_state.db.clone();
+    tokio::spawn(async move {
+        let passes = [
+            ("security reports", db.reencrypt_reports(REENCRYPT_BATCH_SIZE).await),
+            ("two-factor secrets", db.reencrypt_two_factor_secrets(REENCRYPT_BATCH_SIZE).await),
+        ];
+        for (what, pass) in passes {
+            match pass {
+                Ok(outcome) if outcome == Reencrypted::default() => {}
+                Ok(outcome) if outcome.skipped == 0 => {
+                    info!("Re-encrypted {} {} with key '{}'", outcome.reencrypted, what, db.report_key_id());
+                }
+                // The retired key is still needed for the skipped rows
+                Ok(outcome) => warn!(
+                    "Re-encrypted {} {} with key '{}'; {} could not be decrypted and were skipped",
+                    outcome.reencrypted, what, db.report_key_id(), outcome.skipped,
+                ),
+                Err(e) => warn!("Re-encrypting {} failed: {}", what, e),
+            }
+        }
+    });
     info!("Redis connection established");
 
     // Build the application router
@@ -79,7 +114,7 @@ async fn main() -> Result<()> {
     info!("🚀 Guardr API Server is ready and listening on {}", addr);
 
     // Start server with graceful shutdown
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await
         .map_err(|e| anyhow::anyhow!("Server error: {}", e))?;
@@ -3,7 +3,7 @@ use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use argon2::password_hash::{rand_core::OsRng, SaltString};
 use axum::{
     async_trait,
-    extract::{FromRequest, FromRequestParts, Request},
+    extract::{FromRef, FromRequestParts},
     http::{header::AUTHORIZATION, request::Parts},
 };
 use base64::{Engine as _, engine::general_purpose};
@@ -17,8 +17,12 @@ use uuid::Uuid;
 use validator::{Validate, ValidationError};
 
 use crate::config::Settings;
-use crate::database::{User, UserSubscriptionTier};
+use crate::database::{EmailTokenPurpose, User, UserSubscriptionTier};
 use crate::errors::AppError;
+use crate::state::AppState;
+
+// Machine clients authenticate with this header instead of a bearer token
+pub const API_KEY_HEADER: &str = "x-api-key";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
@@ -28,6 +32,8 @@ pub struct Claims {
     pub exp: i64,           // Expiration time
     pub iat: i64,           // Issued at
     pub jti: String,        // JWT ID for blacklisting
+    #[serde(default)]
+    pub generation: i64,    // users.session_generation when issued
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -36,6 +42,31 @@ pub struct RefreshTokenClaims {
     pub jti: String,         // Token ID
     pub exp: i64,           // Expiration time
     pub iat: i64,           // Issued at
+    #[serde(default)]
+    pub generation: i64,    // users.session_generation when issued
+}
+
+// Stands in for the session until the second factor is checked
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorChallengeClaims {
+    pub sub: String,         // User ID
+    pub jti: String,         // Single use: blacklisted once redeemed
+    pub exp: i64,           // Expiration time
+    pub iat: i64,           // Issued at
+}
+
+// How long a login has to supply its second factor
+pub const TWO_FACTOR_CHALLENGE_SECONDS: i64 = 300;
+
+// Emailed in verification and password reset links
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailTokenClaims {
+    pub sub: String,         // User ID
+    pub email: String,       // Address the link was sent to
+    pub purpose: EmailTokenPurpose,
+    pub jti: String,         // email_tokens.id, spent when redeemed
+    pub exp: i64,           // Expiration time
+    pub iat: i64,           // Issued at
 }
 
 #[derive(Clone)]
@@ -43,6 +74,15 @@ pub struct AuthService {
     pub encoding_key: EncodingKey,
     pub decoding_key: DecodingKey,
     pub settings: Settings,
+    // Checked against when a login names no account, so it takes as long
+    dummy_password_hash: String,
+    // Challenge tokens get their own key so they can never pass as access
+    // or refresh tokens, nor those as challenges
+    challenge_encoding_key: EncodingKey,
+    challenge_decoding_key: DecodingKey,
+    // Likewise for email tokens
+    email_encoding_key: EncodingKey,
+    email_decoding_key: DecodingKey,
 }
 
 impl std::fmt::Debug for AuthService {
@@ -56,10 +96,22 @@ impl std::fmt::Debug for AuthService {
 impl AuthService {
     pub fn new(settings: Settings) -> Self {
         let key = settings.auth.jwt_secret.as_bytes();
+        let dummy_password = Uuid::new_v4().to_string();
+        let dummy_password_hash = Argon2::default()
+            .hash_password(dummy_password.as_bytes(), &SaltString::generate(&mut OsRng))
+            .map(|hash| hash.to_string())
+            .expect("hashing a random password");
+        let challenge_key = derive_key(b"guardr two-factor challenge:", key);
+        let email_key = derive_key(b"guardr email token:", key);
         Self {
             encoding_key: EncodingKey::from_secret(key),
             decoding_key: DecodingKey::from_secret(key),
             settings,
+            dummy_password_hash,
+            challenge_encoding_key: EncodingKey::from_secret(&challenge_key),
+            challenge_decoding_key: DecodingKey::from_secret(&challenge_key),
+            email_encoding_key: EncodingKey::from_secret(&email_key),
+            email_decoding_key: DecodingKey::from_secret(&email_key),
         }
     }
 
@@ -83,6 +135,13 @@ impl AuthService {
         }
     }
 
+    // Verify a login against the account's hash, or the dummy hash when there
+    // is no account, so unknown emails can't be told apart by response time
+    pub async fn verify_login_password(&self, password: &str, hash: Option<&str>) -> Result<bool> {
+        let verified = self.verify_password(password, hash.unwrap_or(&self.dummy_password_hash)).await?;
+        Ok(verified && hash.is_some())
+    }
+
     pub fn generate_tokens(&self, user: &User) -> Result<(String, String)> {
         let now = Utc::now();
         let access_exp = now + Duration::hours(self.settings.auth.jwt_expiration_hours as i64);
@@ -98,6 +157,7 @@ impl AuthService {
             exp: access_exp.timestamp(),
             iat: now.timestamp(),
             jti: access_jti,
+            generation: user.session_generation,
         };
 
         let refresh_claims = RefreshTokenClaims {
@@ -105,6 +165,7 @@ impl AuthService {
             jti: refresh_jti,
             exp: refresh_exp.timestamp(),
             iat: now.timestamp(),
+            generation: user.session_generation,
         };
 
         let access_token = encode(&Header::default(), &access_claims, &self.encoding_key)
@@ -144,6 +205,51 @@ impl AuthService {
         }
     }
 
+    pub fn generate_two_factor_challenge(&self, user: &User) -> Result<String> {
+        let now = Utc::now();
+        let claims = TwoFactorChallengeClaims {
+            sub: user.id.to_string(),
+            jti: Uuid::new_v4().to_string(),
+            exp: (now + Duration::seconds(TWO_FACTOR_CHALLENGE_SECONDS)).timestamp(),
+            iat: now.timestamp(),
+        };
+        encode(&Header::default(), &claims, &self.challenge_encoding_key)
+            .map_err(|e| anyhow!("Failed to encode two-factor challenge: {}", e))
+    }
+
+    pub fn verify_two_factor_challenge(&self, token: &str) -> Result<TwoFactorChallengeClaims> {
+        decode::<TwoFactorChallengeClaims>(token, &self.challenge_decoding_key, &Validation::default())
+            .map(|token_data| token_data.claims)
+            .map_err(|e| anyhow!("Invalid two-factor challenge: {}", e))
+    }
+
+    // A signed token for `purpose`, plus its id to record in email_tokens
+    pub fn generate_email_token(&self, user: &User, purpose: EmailTokenPurpose, lifetime: Duration) -> Result<(String, Uuid)> {
+        let now = Utc::now();
+        let id = Uuid::new_v4();
+        let claims = EmailTokenClaims {
+            sub: user.id.to_string(),
+            email: user.email.clone(),
+            purpose,
+            jti: id.to_string(),
+            exp: (now + lifetime).timestamp(),
+            iat: now.timestamp(),
+        };
+        let token = encode(&Header::default(), &claims, &self.email_encoding_key)
+            .map_err(|e| anyhow!("Failed to encode email token: {}", e))?;
+        Ok((token, id))
+    }
+
+    pub fn verify_email_token(&self, token: &str, purpose: EmailTokenPurpose) -> Result<EmailTokenClaims> {
+        let claims = decode::<EmailTokenClaims>(token, &self.email_decoding_key, &Validation::default())
+            .map(|token_data| token_data.claims)
+            .map_err(|e| anyhow!("Invalid email token: {}", e))?;
+        if claims.purpose != purpose {
+            return Err(anyhow!("Email token is for {:?}, not {:?}", claims.purpose, purpose));
+        }
+        Ok(claims)
+    }
+
     pub async fn blacklist_token(&self, redis: &mut redis::aio::Connection, jti: &str, exp: i64) -> Result<()> {
         let ttl = exp - Utc::now().timestamp();
         if ttl > 0 {
@@ -176,6 +282,14 @@ impl AuthService {
     }
 }
 
+// A signing key for one kind of token, derived from the JWT secret
+fn derive_key(label: &[u8], secret: &[u8]) -> Vec<u8> {
+    let mut key = Sha256::new();
+    key.update(label);
+    key.update(secret);
+    key.finalize().to_vec()
+}
+
 // Password validation
 fn validate_password_strength(password: &str) -> Result<(), ValidationError> {
     let min_length = 8;
@@ -237,6 +351,28 @@ pub struct AuthResponse {
     pub user: UserProfile,
 }
 
+// Accounts with two-factor enabled get a challenge instead of tokens
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    TwoFactorRequired(TwoFactorChallenge),
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallenge {
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+    pub expires_in: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    // A TOTP code or a recovery code
+    pub code: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserProfile {
     pub id: String,
@@ -255,48 +391,152 @@ pub struct UsageStats {
     pub reset_date: DateTime<Utc>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    #[validate(custom(function = "validate_password_strength"))]
+    pub password: String,
+
+    pub confirm_password: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RefreshTokenRequest {
     pub refresh_token: String,
 }
 
-// JWT authentication extractor
+#[derive(Debug, Deserialize)]
+pub struct LogoutRequest {
+    // Revoked along with the access token when given
+    pub refresh_token: Option<String>,
+}
+
+// What an API key may be used for. JWT sessions carry every scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "security:read")]
+    SecurityRead,
+    #[serde(rename = "dating:analyze")]
+    DatingAnalyze,
+    #[serde(rename = "reports:read")]
+    ReportsRead,
+    #[serde(rename = "reports:write")]
+    ReportsWrite,
+    #[serde(rename = "reports:export")]
+    ReportsExport,
+    #[serde(rename = "account:read")]
+    AccountRead,
+    #[serde(rename = "account:write")]
+    AccountWrite,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: &'static [Scope] = &[
+        Scope::SecurityRead,
+        Scope::DatingAnalyze,
+        Scope::ReportsRead,
+        Scope::ReportsWrite,
+        Scope::ReportsExport,
+        Scope::AccountRead,
+        Scope::AccountWrite,
+        Scope::Admin,
+    ];
+
+    // Granted to keys created without an explicit list
+    pub const DEFAULT_KEY: &'static [Scope] = &[Scope::SecurityRead, Scope::DatingAnalyze, Scope::ReportsRead];
+
+    // Scopes stored as a JSON array (api_keys.scopes); unknown names are dropped
+    pub fn parse_list(json: &str) -> Vec<Scope> {
+        serde_json::from_str::<Vec<serde_json::Value>>(json)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|value| serde_json::from_value(value).ok())
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = serde_json::to_value(self).ok();
+        f.write_str(name.as_ref().and_then(|n| n.as_str()).unwrap_or("unknown"))
+    }
+}
+
+// How a request authenticated
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Credential {
+    Session { claims: Claims },
+    ApiKey { key_id: Uuid, scopes: Vec<Scope> },
+}
+
+// The caller of a /v1 route: a Bearer JWT or an X-API-Key, whichever was sent
 #[derive(Debug, Clone, Serialize)]
 pub struct AuthenticatedUser {
     pub user_id: Uuid,
     pub email: String,
     pub subscription_tier: UserSubscriptionTier,
-    pub claims: Claims,
+    pub credential: Credential,
 }
 
-#[async_trait]
-impl<S> FromRequestParts<S> for AuthenticatedUser
-where
-    S: Send + Sync,
-{
-    type Rejection = AppError;
+impl AuthenticatedUser {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        match &self.credential {
+            Credential::Session { .. } => true,
+            Credential::ApiKey { scopes, .. } => scopes.contains(&scope),
+        }
+    }
 
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        let auth_header = parts
-            .headers
-            .get(AUTHORIZATION)
-            .and_then(|header| header.to_str().ok())
-            .and_then(|header| header.strip_prefix("Bearer "));
+    pub fn require_scope(&self, scope: Scope) -> Result<(), AppError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(format!("API key lacks the '{}' scope", scope)))
+        }
+    }
 
-        let token = match auth_header {
-            Some(token) => token,
-            None => return Err(AppError::Unauthorized("Missing authorization header".to_string())),
-        };
+    // Claims of a JWT session; for routes API keys can't use, like logout
+    pub fn session(&self) -> Result<&Claims, AppError> {
+        match &self.credential {
+            Credential::Session { claims } => Ok(claims),
+            Credential::ApiKey { .. } => Err(AppError::Forbidden("This route requires a login session".to_string())),
+        }
+    }
 
-        // Extract auth service from app state (this would need to be implemented based on your state structure)
-        let auth_service = AuthService::new(crate::config::Settings::default()); // This should come from state
-        
-        let claims = auth_service.verify_access_token(token)
+    async fn from_bearer(state: &AppState, token: &str) -> Result<Self, AppError> {
+        let claims = state.auth.verify_access_token(token)
             .map_err(|e| AppError::Unauthorized(e.to_string()))?;
 
+        // Tokens revoked by logout stay valid JWTs until they expire; if the
+        // blacklist can't be checked the request fails rather than trusting them
+        let mut redis = state.redis.get_async_connection().await?;
+        if state.auth.is_token_blacklisted(&mut redis, &claims.jti).await? {
+            return Err(AppError::Unauthorized("Token has been revoked".to_string()));
+        }
+
         let user_id = Uuid::parse_str(&claims.sub)
             .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))?;
-        
+
+        // A password reset ends every session issued before it
+        if state.db.get_session_generation(user_id).await? != Some(claims.generation) {
+            return Err(AppError::Unauthorized("Session has ended; log in again".to_string()));
+        }
+
         let subscription_tier = claims.tier.parse::<UserSubscriptionTier>()
             .map_err(|_| AppError::Unauthorized("Invalid subscription tier in token".to_string()))?;
 
@@ -304,42 +544,69 @@ where
             user_id,
             email: claims.email.clone(),
             subscription_tier,
-            claims,
+            credential: Credential::Session { claims },
         })
     }
-}
 
-// API Key authentication
-pub struct ApiKeyAuth {
-    pub user_id: Uuid,
-    pub key_id: String,
-    pub subscription_tier: UserSubscriptionTier,
+    async fn from_api_key(state: &AppState, api_key: &str) -> Result<Self, AppError> {
+        // Only active keys of active users are found
+        let key_hash = state.auth.hash_api_key(api_key);
+        let (key, user) = state.db.get_api_key_by_hash(&key_hash).await?
+            .ok_or_else(|| AppError::Unauthorized("Invalid API key".to_string()))?;
+
+        if key.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(AppError::Unauthorized("API key has expired".to_string()));
+        }
+
+        state.db.update_api_key_last_used(key.id).await?;
+
+        Ok(AuthenticatedUser {
+            user_id: user.id,
+            email: user.email,
+            subscription_tier: user.subscription_tier,
+            credential: Credential::ApiKey {
+                key_id: key.id,
+                scopes: Scope::parse_list(&key.scopes),
+            },
+        })
+    }
 }
 
 #[async_trait]
-impl<S> FromRequest<S> for ApiKeyAuth
+impl<S> FromRequestParts<S> for AuthenticatedUser
 where
+    AppState: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = AppError;
 
-    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
-        let api_key = req
-            .headers()
-            .get("x-api-key")
-            .and_then(|header| header.to_str().ok());
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Already authenticated by the rate limiter
+        if let Some(user) = parts.extensions.get::<AuthenticatedUser>() {
+            return Ok(user.clone());
+        }
 
-        let api_key = match api_key {
-            Some(key) => key,
-            None => return Err(AppError::Unauthorized("Missing API key".to_string())),
-        };
+        let state = AppState::from_ref(state);
 
-        // Hash the provided API key
-        let auth_service = AuthService::new(crate::config::Settings::default()); // This should come from state
-        let hashed_key = auth_service.hash_api_key(api_key);
+        let api_key = parts.headers.get(API_KEY_HEADER);
+        let bearer = parts.headers.get(AUTHORIZATION);
 
-        // Look up the API key in the database (this would need database access from state)
-        // For now, this is a placeholder
-        return Err(AppError::Unauthorized("API key authentication not implemented".to_string()));
+        match (api_key, bearer) {
+            (Some(_), Some(_)) => Err(AppError::BadRequest(
+                "Send either an API key or a bearer token, not both".to_string(),
+            )),
+            (Some(api_key), None) => {
+                let api_key = api_key.to_str()
+                    .map_err(|_| AppError::Unauthorized("Invalid API key".to_string()))?;
+                Self::from_api_key(&state, api_key).await
+            }
+            (None, Some(header)) => {
+                let token = header.to_str().ok()
+                    .and_then(|header| header.strip_prefix("Bearer "))
+                    .ok_or_else(|| AppError::Unauthorized("Invalid authorization header".to_string()))?;
+                Self::from_bearer(&state, token).await
+            }
+            (None, None) => Err(AppError::Unauthorized("Missing authorization header or API key".to_string())),
+        }
     }
-}
\ No newline at end of file
+}
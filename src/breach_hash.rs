@@ -0,0 +1,217 @@
+// Hashing for breach lookups
+// Emails and passwords are only ever stored and queried as these digests, so the
+// API and `guardr ingest` must agree on them exactly.
+//
+// Digests are keyed with the server-side peppers in [security].hash_peppers,
+// applied as layers: SHA-256 of the input, then HMAC-SHA-256 of the previous
+// layer's hex digest under each pepper in turn. Stored values read
+// "<pepper id>$<hex>" (bare hex for unkeyed rows from before peppers).
+// Rotating means appending a pepper: `guardr rehash` wraps existing rows in
+// the layers they miss, which needs no plaintext. Lookups only try the current
+// layer; the older ones (and unkeyed digests) are matched only while
+// [security].legacy_hash_lookups is on during such a migration.
+// Nothing is ever stored without a pepper.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::config::SecurityConfig;
+use crate::identity;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SEPARATOR: char = '$';
+
+#[derive(Clone)]
+struct Pepper {
+    id: String,
+    key: Vec<u8>,
+}
+
+#[derive(Clone, Default)]
+pub struct BreachHasher {
+    // Oldest first; the last one is current
+    peppers: Vec<Pepper>,
+    legacy_lookups: bool,
+}
+
+// Never print the secrets
+impl fmt::Debug for BreachHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BreachHasher")
+            .field("peppers", &self.peppers.iter().map(|p| &p.id).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl BreachHasher {
+    pub fn from_config(config: &SecurityConfig) -> Self {
+        Self {
+            peppers: config.hash_peppers.iter()
+                .map(|p| Pepper { id: p.id.clone(), key: p.secret.as_bytes().to_vec() })
+                .collect(),
+            legacy_lookups: config.legacy_hash_lookups,
+        }
+    }
+
+    pub fn current_pepper(&self) -> Option<&str> {
+        self.peppers.last().map(|p| p.id.as_str())
+    }
+
+    // Digest of the canonical address (see src/identity.rs); input that does
+    // not parse as an address is only lowercased
+    pub fn hash_email(&self, email: &str) -> String {
+        self.hash(&identity::canonical_email(email).normalized)
+    }
+
+    pub fn hash_password(&self, password: &str) -> String {
+        self.hash(password)
+    }
+
+    // Every stored form a lookup must try: the current digest of the canonical
+    // address, plus during a migration each older layer of it and of the
+    // lowercase-only form rows ingested before normalization used
+    pub fn email_lookup_hashes(&self, email: &str) -> Vec<String> {
+        let canonical = identity::canonical_email(email).normalized;
+        if !self.legacy_lookups {
+            return vec![self.hash(&canonical)];
+        }
+
+        let mut hashes = self.layers(&canonical);
+        for legacy in self.layers(&email.trim().to_lowercase()) {
+            if !hashes.contains(&legacy) {
+                hashes.push(legacy);
+            }
+        }
+        hashes
+    }
+
+    pub fn password_lookup_hashes(&self, password: &str) -> Vec<String> {
+        if self.legacy_lookups {
+            self.layers(password)
+        } else {
+            vec![self.hash(password)]
+        }
+    }
+
+    // Wrap a stored digest in the layers it is missing. None when it is
+    // already current, or is not a digest (or pepper) this hasher knows.
+    // Only `guardr rehash` upgrades stored rows.
+    pub fn upgrade(&self, stored: &str) -> Option<String> {
+        let (applied, digest) = match stored.split_once(SEPARATOR) {
+            Some((id, digest)) => (self.peppers.iter().position(|p| p.id == id)? + 1, digest),
+            None if is_sha256_hex(stored) => (0, stored),
+            None => return None,
+        };
+        if applied == self.peppers.len() {
+            return None;
+        }
+
+        let digest = self.peppers[applied..].iter()
+            .fold(digest.to_string(), |digest, pepper| wrap(&pepper.key, &digest));
+        Some(self.stored_form(self.peppers.len(), digest))
+    }
+
+    fn hash(&self, input: &str) -> String {
+        self.layers(input).pop().expect("at least the unkeyed layer")
+    }
+
+    // Stored forms of `input` at every layer, unkeyed first
+    fn layers(&self, input: &str) -> Vec<String> {
+        let mut digest = sha256_hex(input);
+        let mut layers = vec![digest.clone()];
+        for (index, pepper) in self.peppers.iter().enumerate() {
+            digest = wrap(&pepper.key, &digest);
+            layers.push(self.stored_form(index + 1, digest.clone()));
+        }
+        layers
+    }
+
+    fn stored_form(&self, layers: usize, digest: String) -> String {
+        match layers {
+            0 => digest,
+            n => format!("{}{}{}", self.peppers[n - 1].id, SEPARATOR, digest),
+        }
+    }
+}
+
+fn wrap(key: &[u8], digest: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(digest.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn sha256_hex(value: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(value.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{HashPepper, Settings};
+
+    fn hasher(peppers: &[&str], legacy_hash_lookups: bool) -> BreachHasher {
+        let mut config = Settings::default().security;
+        config.hash_peppers = peppers.iter()
+            .map(|id| HashPepper { id: id.to_string(), secret: format!("{}-pepper-secret-0123456789abcdef", id) })
+            .collect();
+        config.legacy_hash_lookups = legacy_hash_lookups;
+        BreachHasher::from_config(&config)
+    }
+
+    #[test]
+    fn digests_are_layered_under_each_pepper() {
+        let unkeyed = sha256_hex("hunter2");
+        let rotated = hasher(&["p1", "p2"], false);
+        let (p1, p2) = (&rotated.peppers[0].key, &rotated.peppers[1].key);
+
+        let first = hasher(&["p1"], false).hash_password("hunter2");
+        assert_eq!(first, format!("p1${}", wrap(p1, &unkeyed)));
+        assert_eq!(rotated.hash_password("hunter2"), format!("p2${}", wrap(p2, &wrap(p1, &unkeyed))));
+        // Different pepper sets never collide
+        assert_ne!(hasher(&["other"], false).hash_password("hunter2"), first);
+    }
+
+    #[test]
+    fn lookups_only_try_older_layers_during_a_migration() {
+        let rotated = hasher(&["p1", "p2"], false);
+        assert_eq!(rotated.password_lookup_hashes("hunter2"), vec![rotated.hash_password("hunter2")]);
+        assert_eq!(rotated.email_lookup_hashes("J.Doe@gmail.com"), vec![rotated.hash_email("jdoe@gmail.com")]);
+
+        let migrating = hasher(&["p1", "p2"], true);
+        let before_rotation = hasher(&["p1"], false).hash_password("hunter2");
+        let lookups = migrating.password_lookup_hashes("hunter2");
+        assert!(lookups.contains(&sha256_hex("hunter2")));
+        assert!(lookups.contains(&before_rotation));
+        assert_eq!(lookups.last(), Some(&rotated.hash_password("hunter2")));
+
+        // and the lowercase-only form from before normalization
+        let emails = migrating.email_lookup_hashes("J.Doe@gmail.com");
+        assert!(emails.contains(&sha256_hex("j.doe@gmail.com")));
+    }
+
+    #[test]
+    fn upgrading_after_a_rotation_reaches_the_current_digest() {
+        let old = hasher(&["p1"], false);
+        let rotated = hasher(&["p1", "p2"], false);
+
+        let stored = old.hash_email("jdoe@gmail.com");
+        let upgraded = rotated.upgrade(&stored).unwrap();
+        assert_eq!(upgraded, rotated.hash_email("jdoe@gmail.com"));
+        assert!(rotated.email_lookup_hashes("j.doe+x@gmail.com").contains(&upgraded));
+
+        // Unkeyed rows from before peppers are wrapped in all of them
+        assert_eq!(rotated.upgrade(&sha256_hex("hunter2")), Some(rotated.hash_password("hunter2")));
+        // Current rows, unknown peppers and non-digests are left alone
+        assert_eq!(rotated.upgrade(&upgraded), None);
+        assert_eq!(rotated.upgrade("gone$abcdef"), None);
+        assert_eq!(rotated.upgrade(&uuid::Uuid::new_v4().to_string()), None);
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use config::{Config, ConfigError, Environment, File};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -13,6 +14,14 @@ pub struct Settings {
     pub logging: LoggingConfig,
     pub rate_limiting: RateLimitConfig,
     pub osint: OsintConfig,
+    #[serde(default)]
+    pub data: DataConfig,
+    #[serde(default)]
+    pub risk: RiskConfig,
+    #[serde(default)]
+    pub quota: QuotaConfig,
+    #[serde(default)]
+    pub email: EmailConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -22,6 +31,10 @@ pub struct ServerConfig {
     pub workers: Option<usize>,
     pub max_connections: Option<u32>,
     pub request_timeout_seconds: u64,
+    // Reverse proxies (IPs or CIDR ranges) whose X-Forwarded-For is believed;
+    // with none, clients are identified by the socket peer (src/middleware.rs)
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -42,6 +55,9 @@ pub struct AuthConfig {
     pub refresh_token_expiration_days: u64,
     pub bcrypt_cost: u32,
     pub api_key_length: usize,
+    // Shown next to the account in authenticator apps
+    #[serde(default = "default_totp_issuer")]
+    pub totp_issuer: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -55,14 +71,47 @@ pub struct RedisConfig {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SecurityConfig {
     pub cors_allowed_origins: Vec<String>,
+    // Current key for report results (src/report_crypto.rs)
     pub encryption_key: String,
+    #[serde(default = "default_encryption_key_id")]
+    pub encryption_key_id: String,
+    // Keys report results may still be encrypted with after a rotation
+    #[serde(default)]
+    pub retired_encryption_keys: Vec<EncryptionKey>,
     pub password_min_length: usize,
     pub password_require_uppercase: bool,
     pub password_require_lowercase: bool,
     pub password_require_numbers: bool,
     pub password_require_special: bool,
+    // Failed logins per account before it is locked (src/login_guard.rs)
     pub max_login_attempts: u32,
+    // Failed logins from one IP, across accounts, before it is locked
+    #[serde(default = "default_max_login_attempts_per_ip")]
+    pub max_login_attempts_per_ip: u32,
+    // First lockout; repeats within a day double it
     pub lockout_duration_minutes: u64,
+    // Keys for breach identifier digests, oldest first (src/breach_hash.rs)
+    #[serde(default)]
+    pub hash_peppers: Vec<HashPepper>,
+    // Also match rows stored unkeyed, under only the older peppers, or from
+    // before email normalization; for migrations only, until `guardr rehash` has run
+    #[serde(default)]
+    pub legacy_hash_lookups: bool,
+    // Accounts allowed to use the /v1/admin routes, by verified email
+    #[serde(default)]
+    pub operator_emails: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptionKey {
+    pub id: String,
+    pub key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HashPepper {
+    pub id: String,
+    pub secret: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -80,6 +129,19 @@ pub struct RateLimitConfig {
     pub burst_size: u32,
     pub premium_multiplier: u32,
     pub enterprise_multiplier: u32,
+    // Where counters live (src/rate_limit.rs)
+    #[serde(default)]
+    pub backend: RateLimitBackend,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBackend {
+    // Per process
+    #[default]
+    Memory,
+    // Shared by every replica through Redis
+    Redis,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -98,6 +160,269 @@ pub struct OsintConfig {
     pub firecrawl_api_key: Option<String>,
     pub exa_api_key: Option<String>,
     pub tavily_api_key: Option<String>,
+    pub leak_lookup_api_key: Option<String>,
+    pub hibp_base_url: Option<String>,
+    pub intelx_base_url: Option<String>,
+    pub pwned_passwords_base_url: Option<String>,
+    pub pwned_passwords_dir: Option<String>,
+    #[serde(default = "default_true")]
+    pub pwned_passwords_padding: bool,
+    #[serde(default = "default_provider_timeout_seconds")]
+    pub provider_timeout_seconds: u64,
+    #[serde(default = "default_llm_providers")]
+    pub llm_providers: Vec<String>,
+}
+
+// Local breach data processing (dump filtering, ingestion)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DataConfig {
+    #[serde(default = "default_filter_rules_path")]
+    pub filter_rules_path: String,
+    // Server-side directory the admin ingest endpoint may read dumps from
+    #[serde(default = "default_dump_dir")]
+    pub dump_dir: String,
+    #[serde(default = "default_ingest_batch_size")]
+    pub ingest_batch_size: usize,
+    // Weak-password list: plain text, or an index from `guardr build-wordlist`
+    #[serde(default = "default_password_list_path")]
+    pub password_list_path: String,
+}
+
+impl Default for DataConfig {
+    fn default() -> Self {
+        Self {
+            filter_rules_path: default_filter_rules_path(),
+            dump_dir: default_dump_dir(),
+            ingest_batch_size: default_ingest_batch_size(),
+            password_list_path: default_password_list_path(),
+        }
+    }
+}
+
+// Risk scoring model (src/risk_score.rs). Bump `model_version` whenever the
+// weights change so stored scores stay comparable.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RiskConfig {
+    #[serde(default = "default_risk_model_version")]
+    pub model_version: String,
+    #[serde(default = "default_recent_breach_days")]
+    pub recent_breach_days: i64,
+    #[serde(default)]
+    pub weights: RiskWeights,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            model_version: default_risk_model_version(),
+            recent_breach_days: default_recent_breach_days(),
+            weights: RiskWeights::default(),
+        }
+    }
+}
+
+// Points each factor adds to the 0-100 score; 0 disables a factor
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RiskWeights {
+    pub weak_password: u8,
+    pub breached_password: u8,
+    // Per breach the email appears in, by the worst data class it exposed,
+    // up to `email_breach_max` in total
+    pub breach_low: u8,
+    pub breach_medium: u8,
+    pub breach_high: u8,
+    pub breach_critical: u8,
+    pub email_breach_max: u8,
+    pub recent_breach: u8,
+    pub pastes: u8,
+    pub repeated_credential: u8,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            weak_password: 25,
+            breached_password: 35,
+            breach_low: 2,
+            breach_medium: 5,
+            breach_high: 12,
+            breach_critical: 25,
+            email_breach_max: 40,
+            recent_breach: 20,
+            pastes: 10,
+            repeated_credential: 10,
+        }
+    }
+}
+
+// Monthly usage quotas (src/quota.rs), in units. Metered endpoints cost
+// `costs[endpoint]` units, 1 when unlisted; bulk checks cost that per email.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct QuotaConfig {
+    pub free: u32,
+    pub pro: u32,
+    pub enterprise: u32,
+    // Usage stats warn once this much of the quota is used
+    pub warn_at_percent: u32,
+    pub costs: HashMap<String, u32>,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        Self {
+            free: 100,
+            pro: 5000,
+            enterprise: 50000,
+            warn_at_percent: 80,
+            costs: HashMap::from([
+                ("analyze_conversation".to_string(), 2),
+                ("safety_report".to_string(), 3),
+                ("investigate".to_string(), 5),
+                ("osint_check".to_string(), 3),
+            ]),
+        }
+    }
+}
+
+// Outbound email (src/mail) and the verification and password reset links it
+// carries
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EmailConfig {
+    pub backend: MailerBackend,
+    pub from: String,
+    // Links in messages point here, at the web app's /verify-email and
+    // /reset-password pages
+    pub app_url: String,
+    // Where the file backend drops messages
+    pub outbox_dir: String,
+    // Overrides for the built-in templates, by file name (verify_email.txt, ...)
+    pub templates_dir: Option<String>,
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub verification_token_hours: u64,
+    pub reset_token_minutes: u64,
+    // Least time between two messages of the same kind to one account
+    pub resend_cooldown_seconds: u64,
+    // Features closed to accounts that haven't verified their email
+    pub require_verified_for: Vec<VerifiedFeature>,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            backend: MailerBackend::File,
+            from: "Guardr <no-reply@guardr.app>".to_string(),
+            app_url: "https://guardr.app".to_string(),
+            outbox_dir: "data/outbox".to_string(),
+            templates_dir: None,
+            smtp_host: None,
+            smtp_port: 587,
+            smtp_security: SmtpSecurity::Starttls,
+            smtp_username: None,
+            smtp_password: None,
+            verification_token_hours: 48,
+            reset_token_minutes: 60,
+            resend_cooldown_seconds: 60,
+            require_verified_for: vec![VerifiedFeature::Exports, VerifiedFeature::ApiKeys],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailerBackend {
+    // One file per message in outbox_dir, for development and tests
+    #[default]
+    File,
+    Smtp,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    // Upgrade a plain connection (port 587)
+    #[default]
+    Starttls,
+    // TLS from the start (port 465)
+    Tls,
+    // Plain text, for a relay on localhost only
+    None,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifiedFeature {
+    // GET /v1/reports/export
+    Exports,
+    // Creating API keys
+    ApiKeys,
+}
+
+impl std::fmt::Display for VerifiedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifiedFeature::Exports => write!(f, "report exports"),
+            VerifiedFeature::ApiKeys => write!(f, "API keys"),
+        }
+    }
+}
+
+fn default_encryption_key_id() -> String {
+    "k1".to_string()
+}
+
+fn default_totp_issuer() -> String {
+    "Guardr".to_string()
+}
+
+fn default_max_login_attempts_per_ip() -> u32 {
+    20
+}
+
+fn default_risk_model_version() -> String {
+    "2".to_string()
+}
+
+fn default_recent_breach_days() -> i64 {
+    365
+}
+
+fn default_dump_dir() -> String {
+    "data/dumps".to_string()
+}
+
+fn default_ingest_batch_size() -> usize {
+    1000
+}
+
+fn default_password_list_path() -> String {
+    "top-passwords.txt".to_string()
+}
+
+fn default_filter_rules_path() -> String {
+    "config/filter_rules.toml".to_string()
+}
+
+fn default_provider_timeout_seconds() -> u64 {
+    10
+}
+
+// Fallback order for AI analysis; providers without an API key are skipped
+fn default_llm_providers() -> Vec<String> {
+    ["gemini", "openai", "anthropic", "mistral", "xai"]
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for Settings {
@@ -109,6 +434,7 @@ impl Default for Settings {
                 workers: None,
                 max_connections: Some(1000),
                 request_timeout_seconds: 30,
+                trusted_proxies: Vec::new(),
             },
             database: DatabaseConfig {
                 sqlite_url: "sqlite:./guardr.db".to_string(),
@@ -124,6 +450,7 @@ impl Default for Settings {
                 refresh_token_expiration_days: 30,
                 bcrypt_cost: 12,
                 api_key_length: 32,
+                totp_issuer: default_totp_issuer(),
             },
             redis: RedisConfig {
                 url: "redis://127.0.0.1:6379".to_string(),
@@ -134,13 +461,19 @@ impl Default for Settings {
             security: SecurityConfig {
                 cors_allowed_origins: vec!["https://guardr.app".to_string(), "https://www.guardr.app".to_string()],
                 encryption_key: "your-encryption-key-32-chars-long!!".to_string(),
+                encryption_key_id: default_encryption_key_id(),
+                retired_encryption_keys: Vec::new(),
                 password_min_length: 8,
                 password_require_uppercase: true,
                 password_require_lowercase: true,
                 password_require_numbers: true,
                 password_require_special: true,
                 max_login_attempts: 5,
+                max_login_attempts_per_ip: default_max_login_attempts_per_ip(),
                 lockout_duration_minutes: 15,
+                hash_peppers: Vec::new(),
+                legacy_hash_lookups: false,
+                operator_emails: Vec::new(),
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
@@ -154,6 +487,7 @@ impl Default for Settings {
                 burst_size: 10,
                 premium_multiplier: 5,
                 enterprise_multiplier: 20,
+                backend: RateLimitBackend::Memory,
             },
             osint: OsintConfig {
                 hibp_api_key: None,
@@ -170,7 +504,19 @@ impl Default for Settings {
                 firecrawl_api_key: None,
                 exa_api_key: None,
                 tavily_api_key: None,
+                leak_lookup_api_key: None,
+                hibp_base_url: None,
+                intelx_base_url: None,
+                pwned_passwords_base_url: None,
+                pwned_passwords_dir: None,
+                pwned_passwords_padding: true,
+                provider_timeout_seconds: default_provider_timeout_seconds(),
+                llm_providers: default_llm_providers(),
             },
+            data: DataConfig::default(),
+            risk: RiskConfig::default(),
+            quota: QuotaConfig::default(),
+            email: EmailConfig::default(),
         }
     }
 }
@@ -218,18 +564,74 @@ impl Settings {
             settings.database.mongodb_url = Some(mongodb_url);
         }
         
+        // "10.0.0.0/8,192.0.2.1"
+        if let Ok(proxies) = env::var("TRUSTED_PROXIES") {
+            settings.server.trusted_proxies = proxies.split(',')
+                .map(|proxy| proxy.trim().to_string())
+                .filter(|proxy| !proxy.is_empty())
+                .collect();
+        }
+
         if let Ok(redis_url) = env::var("REDIS_URL") {
             settings.redis.url = redis_url;
         }
+
+        if let Ok(backend) = env::var("RATE_LIMIT_BACKEND") {
+            settings.rate_limiting.backend = match backend.to_lowercase().as_str() {
+                "redis" => RateLimitBackend::Redis,
+                "memory" => RateLimitBackend::Memory,
+                other => return Err(ConfigError::Message(format!("Unknown RATE_LIMIT_BACKEND '{}'", other))),
+            };
+        }
         
         if let Ok(encryption_key) = env::var("ENCRYPTION_KEY") {
             settings.security.encryption_key = encryption_key;
         }
 
+        if let Ok(encryption_key_id) = env::var("ENCRYPTION_KEY_ID") {
+            settings.security.encryption_key_id = encryption_key_id;
+        }
+
+        // "id:key,id:key"
+        if let Ok(keys) = env::var("RETIRED_ENCRYPTION_KEYS") {
+            settings.security.retired_encryption_keys = keys.split(',')
+                .filter_map(|pair| pair.trim().split_once(':'))
+                .map(|(id, key)| EncryptionKey { id: id.to_string(), key: key.to_string() })
+                .collect();
+        }
+
+        // "id:secret,id:secret", oldest first
+        if let Ok(peppers) = env::var("HASH_PEPPERS") {
+            settings.security.hash_peppers = peppers.split(',')
+                .filter_map(|pair| pair.trim().split_once(':'))
+                .map(|(id, secret)| HashPepper { id: id.to_string(), secret: secret.to_string() })
+                .collect();
+        }
+
+        if let Ok(legacy) = env::var("LEGACY_HASH_LOOKUPS") {
+            settings.security.legacy_hash_lookups = matches!(legacy.trim(), "1" | "true");
+        }
+
+        // "ops@example.com,oncall@example.com"
+        if let Ok(operators) = env::var("OPERATOR_EMAILS") {
+            settings.security.operator_emails = operators.split(',')
+                .map(|email| email.trim().to_string())
+                .filter(|email| !email.is_empty())
+                .collect();
+        }
+
         if let Ok(hibp_key) = env::var("HIBP_API_KEY") {
             settings.osint.hibp_api_key = Some(hibp_key);
         }
 
+        if let Ok(hibp_base_url) = env::var("HIBP_BASE_URL") {
+            settings.osint.hibp_base_url = Some(hibp_base_url);
+        }
+
+        if let Ok(pwned_passwords_dir) = env::var("PWNED_PASSWORDS_DIR") {
+            settings.osint.pwned_passwords_dir = Some(pwned_passwords_dir);
+        }
+
         if let Ok(intelx_key) = env::var("INTELX_API_KEY") {
             settings.osint.intelx_api_key = Some(intelx_key);
         }
@@ -282,6 +684,42 @@ impl Settings {
             settings.osint.tavily_api_key = Some(tavily_key);
         }
 
+        if let Ok(leak_lookup_key) = env::var("LEAK_LOOKUP_API_KEY") {
+            settings.osint.leak_lookup_api_key = Some(leak_lookup_key);
+        }
+
+        if let Ok(filter_rules_path) = env::var("FILTER_RULES_PATH") {
+            settings.data.filter_rules_path = filter_rules_path;
+        }
+
+        if let Ok(dump_dir) = env::var("DUMP_DIR") {
+            settings.data.dump_dir = dump_dir;
+        }
+
+        if let Ok(password_list_path) = env::var("PASSWORD_LIST_PATH") {
+            settings.data.password_list_path = password_list_path;
+        }
+
+        if let Ok(backend) = env::var("EMAIL_BACKEND") {
+            settings.email.backend = match backend.to_lowercase().as_str() {
+                "file" => MailerBackend::File,
+                "smtp" => MailerBackend::Smtp,
+                other => return Err(ConfigError::Message(format!("Unknown EMAIL_BACKEND '{}'", other))),
+            };
+        }
+
+        if let Ok(smtp_host) = env::var("SMTP_HOST") {
+            settings.email.smtp_host = Some(smtp_host);
+        }
+
+        if let Ok(smtp_username) = env::var("SMTP_USERNAME") {
+            settings.email.smtp_username = Some(smtp_username);
+        }
+
+        if let Ok(smtp_password) = env::var("SMTP_PASSWORD") {
+            settings.email.smtp_password = Some(smtp_password);
+        }
+
         Ok(settings)
     }
 
@@ -295,6 +733,65 @@ impl Settings {
         if self.security.encryption_key.len() != 32 {
             return Err(anyhow::anyhow!("Encryption key must be exactly 32 characters"));
         }
+        let key_ids = std::iter::once(&self.security.encryption_key_id)
+            .chain(self.security.retired_encryption_keys.iter().map(|k| &k.id))
+            .collect::<Vec<_>>();
+        for (index, id) in key_ids.iter().enumerate() {
+            if id.is_empty() || id.contains(':') {
+                return Err(anyhow::anyhow!("Encryption key ids must be non-empty and must not contain ':'"));
+            }
+            if key_ids[..index].contains(id) {
+                return Err(anyhow::anyhow!("Duplicate encryption key id '{}'", id));
+            }
+        }
+        for key in &self.security.retired_encryption_keys {
+            if key.key.len() != 32 {
+                return Err(anyhow::anyhow!("Retired encryption key '{}' must be exactly 32 characters", key.id));
+            }
+        }
+
+        // Validate breach hashing peppers
+        if self.security.hash_peppers.is_empty() {
+            return Err(anyhow::anyhow!("At least one hash pepper is required (security.hash_peppers)"));
+        }
+        for (index, pepper) in self.security.hash_peppers.iter().enumerate() {
+            if pepper.id.is_empty() || pepper.id.contains('$') {
+                return Err(anyhow::anyhow!("Hash pepper ids must be non-empty and must not contain '$'"));
+            }
+            if pepper.secret.len() < 32 {
+                return Err(anyhow::anyhow!("Hash pepper '{}' must be at least 32 characters", pepper.id));
+            }
+            if self.security.hash_peppers[..index].iter().any(|p| p.id == pepper.id) {
+                return Err(anyhow::anyhow!("Duplicate hash pepper id '{}'", pepper.id));
+            }
+        }
+
+        // Validate quotas
+        if self.quota.free == 0 || self.quota.pro == 0 || self.quota.enterprise == 0 {
+            return Err(anyhow::anyhow!("Monthly quotas must be at least 1"));
+        }
+        if !(1..=100).contains(&self.quota.warn_at_percent) {
+            return Err(anyhow::anyhow!("quota.warn_at_percent must be between 1 and 100"));
+        }
+
+        // Validate login lockouts
+        if self.security.max_login_attempts == 0 || self.security.max_login_attempts_per_ip == 0 {
+            return Err(anyhow::anyhow!("max_login_attempts and max_login_attempts_per_ip must be at least 1"));
+        }
+        if self.security.lockout_duration_minutes == 0 {
+            return Err(anyhow::anyhow!("lockout_duration_minutes must be at least 1"));
+        }
+
+        // Validate email
+        if self.email.backend == MailerBackend::Smtp && self.email.smtp_host.is_none() {
+            return Err(anyhow::anyhow!("email.smtp_host is required for the smtp backend"));
+        }
+        if self.email.smtp_username.is_some() != self.email.smtp_password.is_some() {
+            return Err(anyhow::anyhow!("email.smtp_username and email.smtp_password must be set together"));
+        }
+        if self.email.verification_token_hours == 0 || self.email.reset_token_minutes == 0 {
+            return Err(anyhow::anyhow!("Email token lifetimes must be at least 1"));
+        }
 
         // Validate bcrypt cost
         if self.auth.bcrypt_cost < 10 || self.auth.bcrypt_cost > 15 {
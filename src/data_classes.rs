@@ -0,0 +1,277 @@
+// Breach data classes and how much each exposure matters
+// Providers and dumps describe what a breach leaked as free-form labels
+// ("Passwords", "Sexual orientations", "Passwords (bcrypt)"). `classify` maps
+// a label onto the catalogue below, so a leaked plaintext password or dating
+// profile weighs far more in risk scoring than a leaked username.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    // Provider severities are free strings; unknown ones carry no weight
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "low" | "info" => Some(Severity::Low),
+            "medium" | "moderate" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" | "severe" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct DataClass {
+    pub id: &'static str,
+    pub label: &'static str,
+    pub severity: Severity,
+    // Lowercase labels providers use for the class, besides `label`
+    aliases: &'static [&'static str],
+}
+
+const fn class(
+    id: &'static str,
+    label: &'static str,
+    severity: Severity,
+    aliases: &'static [&'static str],
+) -> DataClass {
+    DataClass { id, label, severity, aliases }
+}
+
+// Labels follow Have I Been Pwned's data class names where one exists
+pub static CATALOGUE: &[DataClass] = &[
+    class("email_address", "Email addresses", Severity::Low, &["email", "emails", "email address"]),
+    class("username", "Usernames", Severity::Low, &["username", "screen names", "nicknames", "user names"]),
+    class("name", "Names", Severity::Low, &["full names", "first names", "last names"]),
+    class("gender", "Genders", Severity::Low, &["gender"]),
+    class("ip_address", "IP addresses", Severity::Low, &["ip address", "ips"]),
+    class("employer", "Employers", Severity::Low, &["job titles", "occupations"]),
+    class("date_of_birth", "Dates of birth", Severity::Medium, &["date of birth", "birthdates", "ages"]),
+    class("phone_number", "Phone numbers", Severity::Medium, &["phone number", "phones", "mobile numbers"]),
+    class("physical_address", "Physical addresses", Severity::Medium, &["addresses", "home addresses", "postal addresses"]),
+    class("geolocation", "Geographic locations", Severity::Medium, &["locations", "gps coordinates"]),
+    class("relationship_status", "Relationship statuses", Severity::Medium, &["marital statuses"]),
+    class("password_hint", "Password hints", Severity::Medium, &["password hint"]),
+    class("password_slow_hash", "Passwords (slow hash)", Severity::Medium, &[]),
+    class("password", "Passwords", Severity::High, &["password", "credentials"]),
+    class("password_fast_hash", "Passwords (fast hash)", Severity::High, &["password hashes", "hashed passwords"]),
+    class("security_questions", "Security questions and answers", Severity::High, &["security questions"]),
+    class("auth_token", "Auth tokens", Severity::High, &["session tokens", "api keys"]),
+    class("partial_card", "Partial credit card data", Severity::High, &["partial credit cards"]),
+    class("private_messages", "Private messages", Severity::High, &["chat logs", "direct messages"]),
+    class("password_plaintext", "Passwords (plaintext)", Severity::Critical, &["plaintext passwords", "cleartext passwords"]),
+    class("ssn", "Social security numbers", Severity::Critical, &["ssns", "social security number", "national insurance numbers"]),
+    class("government_id", "Government issued IDs", Severity::Critical, &["passport numbers", "driver's licenses", "national ids"]),
+    class("financial", "Credit cards", Severity::Critical, &["credit card numbers", "bank account numbers", "banking information"]),
+    class("health", "Health records", Severity::Critical, &["health insurance information", "medical records"]),
+    class("sexual_orientation", "Sexual orientations", Severity::Critical, &["sexual orientation"]),
+    class("sexual_preferences", "Sexual fantasies", Severity::Critical, &["sexual preferences", "fetishes"]),
+    class("dating_profile", "Dating site membership", Severity::Critical, &["dating profiles", "dating app membership", "dating site profiles"]),
+];
+
+// Labels outside the catalogue still say something leaked, but not much
+static OTHER: DataClass = class("other", "Other", Severity::Low, &[]);
+
+const SLOW_HASHES: &[&str] = &["bcrypt", "scrypt", "argon2", "pbkdf2", "yescrypt", "sha512crypt"];
+const FAST_HASHES: &[&str] = &["md5", "sha1", "sha-1", "sha256", "sha-256", "sha512", "sha-512", "ntlm", "mysql", "crc32"];
+
+fn by_id(id: &str) -> &'static DataClass {
+    CATALOGUE.iter().find(|c| c.id == id).unwrap_or(&OTHER)
+}
+
+// A provider label matched against the catalogue
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposedClass {
+    pub name: String,
+    pub class: &'static str,
+    pub severity: Severity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<&'static str>,
+}
+
+pub fn classify(label: &str) -> ExposedClass {
+    let normalized = label.trim().to_lowercase();
+    let (class, algorithm) = classify_password(&normalized)
+        .unwrap_or_else(|| {
+            let class = CATALOGUE.iter()
+                .find(|c| c.label.to_lowercase() == normalized || c.aliases.contains(&normalized.as_str()))
+                .unwrap_or(&OTHER);
+            (class, None)
+        });
+
+    ExposedClass {
+        name: label.trim().to_string(),
+        class: class.id,
+        severity: class.severity,
+        algorithm,
+    }
+}
+
+// "Passwords" says nothing about storage; plaintext and hash algorithms
+// named in the label ("Passwords (MD5)", "bcrypt password hashes") do
+fn classify_password(label: &str) -> Option<(&'static DataClass, Option<&'static str>)> {
+    if !label.contains("password") || label.contains("hint") {
+        return None;
+    }
+
+    if ["plaintext", "plain text", "cleartext", "clear text"].iter().any(|w| label.contains(w)) {
+        return Some((by_id("password_plaintext"), None));
+    }
+    if let Some(algorithm) = SLOW_HASHES.iter().find(|a| label.contains(*a)) {
+        return Some((by_id("password_slow_hash"), Some(*algorithm)));
+    }
+    if let Some(algorithm) = FAST_HASHES.iter().find(|a| label.contains(*a)) {
+        return Some((by_id("password_fast_hash"), Some(*algorithm)));
+    }
+    // The catalogue's own "Passwords (slow hash)" / "Passwords (fast hash)"
+    if label.contains("slow hash") {
+        return Some((by_id("password_slow_hash"), None));
+    }
+    if label.contains("hash") {
+        return Some((by_id("password_fast_hash"), None));
+    }
+    None
+}
+
+// Guess the algorithm from the shape of a stored hash
+pub fn hash_algorithm(hash: &str) -> Option<&'static str> {
+    let hash = hash.trim();
+    let prefixes: &[(&str, &'static str)] = &[
+        ("$2a$", "bcrypt"), ("$2b$", "bcrypt"), ("$2y$", "bcrypt"),
+        ("$argon2", "argon2"), ("$scrypt$", "scrypt"), ("$7$", "scrypt"),
+        ("$pbkdf2", "pbkdf2"), ("pbkdf2_", "pbkdf2"), ("$y$", "yescrypt"),
+        ("$6$", "sha512crypt"), ("$1$", "md5"),
+    ];
+    if let Some((_, algorithm)) = prefixes.iter().find(|(prefix, _)| hash.starts_with(prefix)) {
+        return Some(algorithm);
+    }
+
+    if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hash.len() {
+        32 => Some("md5"),
+        40 => Some("sha1"),
+        64 => Some("sha256"),
+        128 => Some("sha512"),
+        _ => None,
+    }
+}
+
+// Data classes a dump record exposes, judged from the fields it carries
+pub fn record_data_classes(record: &Value) -> Vec<String> {
+    let present = |field: &str| record.get(field)
+        .and_then(|v| v.as_str())
+        .is_some_and(|v| !v.trim().is_empty());
+
+    let mut classes = Vec::new();
+    if present("email") {
+        classes.push("Email addresses".to_string());
+    }
+    if present("username") {
+        classes.push("Usernames".to_string());
+    }
+    if present("password") {
+        classes.push("Passwords (plaintext)".to_string());
+    } else if let Some(hash) = record.get("password_hash").and_then(|h| h.as_str()).filter(|h| !h.is_empty()) {
+        classes.push(match hash_algorithm(hash) {
+            Some(algorithm) => format!("Passwords ({})", algorithm),
+            None => "Password hashes".to_string(),
+        });
+    }
+    classes
+}
+
+// The worst exposure in a breach, or the provider's own rating if higher
+pub fn breach_severity(data_types: &[String], provider_severity: Option<&str>) -> Severity {
+    data_types.iter()
+        .map(|label| classify(label).severity)
+        .chain(provider_severity.and_then(Severity::parse))
+        .max()
+        .unwrap_or(Severity::Low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::risk_score::{BreachSignal, RiskModel, RiskSignals};
+
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|l| l.to_string()).collect()
+    }
+
+    fn score(data_types: &[&str]) -> u8 {
+        let breach = BreachSignal::new("Leak", None, &labels(data_types), None);
+        RiskModel::default().assess(&RiskSignals { breaches: vec![breach], ..Default::default() }).score
+    }
+
+    #[test]
+    fn plaintext_passwords_and_dating_profiles_outrank_usernames_and_emails() {
+        let contact_only = breach_severity(&labels(&["Email addresses", "Usernames"]), None);
+        assert_eq!(contact_only, Severity::Low);
+
+        for label in ["Passwords (plaintext)", "Cleartext passwords", "Dating site membership", "dating profiles"] {
+            let exposed = classify(label);
+            assert_eq!(exposed.severity, Severity::Critical, "{}", label);
+            assert_eq!(breach_severity(&labels(&["Email addresses", label]), None), Severity::Critical);
+            assert!(score(&["Email addresses", label]) > score(&["Email addresses", "Usernames"]), "{}", label);
+        }
+    }
+
+    #[test]
+    fn password_labels_are_split_by_storage() {
+        let bcrypt = classify("Passwords (bcrypt)");
+        assert_eq!((bcrypt.class, bcrypt.algorithm), ("password_slow_hash", Some("bcrypt")));
+        let md5 = classify("MD5 password hashes");
+        assert_eq!((md5.class, md5.algorithm), ("password_fast_hash", Some("md5")));
+        assert!(classify("Passwords (plaintext)").severity > md5.severity);
+        assert_eq!(classify("Password hints").class, "password_hint");
+    }
+
+    #[test]
+    fn unknown_labels_fall_back_to_other() {
+        let exposed = classify("  Favourite Pizza Toppings ");
+        assert_eq!(exposed.class, "other");
+        assert_eq!(exposed.severity, Severity::Low);
+        assert_eq!(exposed.name, "Favourite Pizza Toppings");
+
+        assert_eq!(breach_severity(&labels(&["Favourite pizza toppings"]), None), Severity::Low);
+        assert_eq!(breach_severity(&[], None), Severity::Low);
+        // Unknown provider ratings carry no weight, known ones raise the floor
+        assert_eq!(breach_severity(&labels(&["Usernames"]), Some("catastrophic")), Severity::Low);
+        assert_eq!(breach_severity(&labels(&["Usernames"]), Some("Severe")), Severity::Critical);
+    }
+
+    #[test]
+    fn record_fields_name_their_classes() {
+        let record = json!({ "email": "a@b.c", "username": " ", "password_hash": "$2b$12$abcdefghijklmnopqrstuv" });
+        assert_eq!(record_data_classes(&record), ["Email addresses", "Passwords (bcrypt)"]);
+
+        let record = json!({ "email": "a@b.c", "password": "hunter2" });
+        assert_eq!(record_data_classes(&record), ["Email addresses", "Passwords (plaintext)"]);
+    }
+}
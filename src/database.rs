@@ -5,13 +5,17 @@ use sqlx::{Pool, Sqlite, SqlitePool, Row, FromRow};
 use sqlx::sqlite::SqliteConnectOptions;
 use std::str::FromStr;
 use std::path::Path;
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::config::Settings;
+use crate::report_crypto::ReportCipher;
 
 #[derive(Debug, Clone)]
 pub struct Database {
     pub pool: Pool<Sqlite>,
+    // Seals security_reports.results and two_factor.secret at rest
+    cipher: ReportCipher,
 }
 
 impl Database {
@@ -39,11 +43,12 @@ impl Database {
             .create_if_missing(true);
         
         let pool = SqlitePool::connect_with(options).await?;
+        let cipher = ReportCipher::from_config(&settings.security)?;
         
         // Run migrations
         sqlx::migrate!("./migrations").run(&pool).await?;
         
-        Ok(Database { pool })
+        Ok(Database { pool, cipher })
     }
 
     pub async fn close(&self) {
@@ -53,7 +58,7 @@ impl Database {
 
 // User models
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::Type)]
-#[sqlx(type_name = "TEXT")]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
 pub enum UserSubscriptionTier {
     Free,
     Pro,
@@ -95,6 +100,8 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
     pub last_login: Option<DateTime<Utc>>,
     pub is_active: bool,
+    // Sessions issued under an older generation are no longer accepted
+    pub session_generation: i64,
 }
 
 #[derive(Debug, Clone, Serialize, FromRow)]
@@ -104,6 +111,7 @@ pub struct ApiKey {
     pub name: String,
     pub key_hash: String,
     pub key_prefix: String, // First 8 characters for display
+    pub scopes: String, // JSON array of scope names
     pub last_used: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
@@ -129,10 +137,37 @@ pub struct SecurityReport {
     pub input_data_hash: String, // Hash of input for privacy
     pub results: String, // JSON string
     pub risk_score: Option<i32>,
+    pub risk_model: Option<String>, // Risk model version behind risk_score
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SecurityEvent {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub event_type: String,
+    pub ip_address: Option<String>,
+    pub details: String, // JSON object
+    pub created_at: DateTime<Utc>,
+}
+
+// Secret is decrypted (base32); not enabled while enrollment is pending
+#[derive(Debug, Clone, FromRow)]
+pub struct TwoFactor {
+    pub secret: String,
+    pub enabled: bool,
+}
+
+// What an email token (email_tokens) is for
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTokenPurpose {
+    VerifyEmail,
+    PasswordReset,
+}
+
 #[derive(Debug, Clone, Serialize, FromRow)]
 pub struct BreachData {
     pub id: Uuid,
@@ -146,6 +181,25 @@ pub struct BreachData {
     pub created_at: DateTime<Utc>,
 }
 
+// A breach record ready for insertion (already normalized and hashed)
+#[derive(Debug, Clone)]
+pub struct NewBreachData {
+    pub email_hash: String,
+    pub password_hash: Option<String>,
+    pub source_name: String,
+    pub breach_date: DateTime<Utc>,
+    pub data_types: Vec<String>,
+    pub severity: String,
+}
+
+// What a re-encryption pass did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reencrypted {
+    pub reencrypted: u64,
+    // Rows that couldn't be decrypted and were left alone
+    pub skipped: u64,
+}
+
 // User repository
 impl Database {
     pub async fn create_user(&self, email: &str, password_hash: &str, name: Option<&str>) -> Result<User> {
@@ -196,6 +250,19 @@ impl Database {
         Ok(user)
     }
 
+    // The generation sessions of an active user must carry, or None if there
+    // is no such user
+    pub async fn get_session_generation(&self, user_id: Uuid) -> Result<Option<i64>> {
+        let generation = sqlx::query_scalar(
+            "SELECT session_generation FROM users WHERE id = $1 AND is_active = true"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(generation)
+    }
+
     pub async fn update_user_last_login(&self, user_id: Uuid) -> Result<()> {
         sqlx::query(
             "UPDATE users SET last_login = $1, updated_at = $1 WHERE id = $2"
@@ -222,14 +289,22 @@ impl Database {
     }
 
     // API Key methods
-    pub async fn create_api_key(&self, user_id: Uuid, name: &str, key_hash: &str, key_prefix: &str) -> Result<ApiKey> {
+    pub async fn create_api_key(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        key_hash: &str,
+        key_prefix: &str,
+        scopes: &[String],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<ApiKey> {
         let id = Uuid::new_v4();
         let now = Utc::now();
         
         let api_key = sqlx::query_as::<_, ApiKey>(
             r#"
-            INSERT INTO api_keys (id, user_id, name, key_hash, key_prefix, created_at, is_active)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO api_keys (id, user_id, name, key_hash, key_prefix, scopes, created_at, expires_at, is_active)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING *
             "#
         )
@@ -238,7 +313,9 @@ impl Database {
         .bind(name)
         .bind(key_hash)
         .bind(key_prefix)
+        .bind(serde_json::to_string(scopes)?)
         .bind(now)
+        .bind(expires_at)
         .bind(true)
         .fetch_one(&self.pool)
         .await?;
@@ -250,11 +327,11 @@ impl Database {
         let result = sqlx::query(
             r#"
             SELECT 
-                ak.id, ak.user_id, ak.name, ak.key_hash, ak.key_prefix, 
+                ak.id, ak.user_id, ak.name, ak.key_hash, ak.key_prefix, ak.scopes,
                 ak.last_used, ak.created_at, ak.expires_at, ak.is_active,
                 u.id as user_id, u.email, u.password_hash, u.name as user_name, 
                 u.subscription_tier, u.email_verified, u.created_at as user_created_at, 
-                u.updated_at, u.last_login, u.is_active as user_is_active
+                u.updated_at, u.last_login, u.is_active as user_is_active, u.session_generation
             FROM api_keys ak
             JOIN users u ON ak.user_id = u.id
             WHERE ak.key_hash = $1 AND ak.is_active = true AND u.is_active = true
@@ -272,6 +349,7 @@ impl Database {
                     name: row.get("name"),
                     key_hash: row.get("key_hash"),
                     key_prefix: row.get("key_prefix"),
+                    scopes: row.get("scopes"),
                     last_used: row.get("last_used"),
                     created_at: row.get("created_at"),
                     expires_at: row.get("expires_at"),
@@ -289,6 +367,7 @@ impl Database {
                     updated_at: row.get("updated_at"),
                     last_login: row.get("last_login"),
                     is_active: row.get("user_is_active"),
+                    session_generation: row.get("session_generation"),
                 };
 
                 Ok(Some((api_key, user)))
@@ -333,17 +412,19 @@ impl Database {
     }
 
     // Usage tracking
-    pub async fn track_api_usage(&self, user_id: Uuid, endpoint: &str) -> Result<()> {
+    // `units` is the quota the call was charged (src/quota.rs), so
+    // requests_count sums weighted costs rather than raw calls
+    pub async fn track_api_usage(&self, user_id: Uuid, endpoint: &str, units: u32) -> Result<()> {
         let now = Utc::now();
         let month_year = now.format("%Y-%m").to_string();
         
         sqlx::query(
             r#"
             INSERT INTO usage_tracking (id, user_id, month_year, endpoint, requests_count, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, 1, $5, $6)
+            VALUES ($1, $2, $3, $4, $7, $5, $6)
             ON CONFLICT(user_id, month_year, endpoint) 
             DO UPDATE SET 
-                requests_count = requests_count + 1,
+                requests_count = requests_count + $7,
                 updated_at = $6
             "#
         )
@@ -353,6 +434,7 @@ impl Database {
         .bind(endpoint)
         .bind(now)
         .bind(now)
+        .bind(units as i64)
         .execute(&self.pool)
         .await?;
 
@@ -371,6 +453,299 @@ impl Database {
         Ok(usage)
     }
 
+    pub async fn get_monthly_usage_total(&self, user_id: Uuid, month_year: &str) -> Result<i64> {
+        let total: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(requests_count), 0) FROM usage_tracking WHERE user_id = $1 AND month_year = $2"
+        )
+        .bind(user_id)
+        .bind(month_year)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(total)
+    }
+
+    // Security events
+    pub async fn record_security_event(
+        &self,
+        user_id: Uuid,
+        event_type: &str,
+        ip_address: Option<&str>,
+        details: &serde_json::Value,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO security_events (id, user_id, event_type, ip_address, details, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(event_type)
+        .bind(ip_address)
+        .bind(details.to_string())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_security_events(&self, user_id: Uuid, limit: i64) -> Result<Vec<SecurityEvent>> {
+        let events = sqlx::query_as::<_, SecurityEvent>(
+            "SELECT * FROM security_events WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2"
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    // Two-factor authentication
+    pub async fn get_two_factor(&self, user_id: Uuid) -> Result<Option<TwoFactor>> {
+        let two_factor = sqlx::query_as::<_, TwoFactor>("SELECT secret, enabled FROM two_factor WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        two_factor.map(|mut two_factor| {
+            two_factor.secret = self.cipher.decrypt(user_id, &two_factor.secret)?;
+            Ok(two_factor)
+        }).transpose()
+    }
+
+    // Store a new secret awaiting confirmation, replacing any earlier one;
+    // false if two-factor is already enabled
+    pub async fn start_two_factor_enrollment(&self, user_id: Uuid, secret: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO two_factor (user_id, secret, enabled, created_at)
+            VALUES ($1, $2, 0, $3)
+            ON CONFLICT(user_id) DO UPDATE SET
+                secret = excluded.secret,
+                last_used_step = NULL,
+                created_at = excluded.created_at
+            WHERE two_factor.enabled = 0
+            "#
+        )
+        .bind(user_id)
+        .bind(self.cipher.encrypt(user_id, secret)?)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    // Turn on a pending enrollment, marking `step` used and storing the first
+    // recovery codes; false if there is no pending enrollment
+    pub async fn enable_two_factor(&self, user_id: Uuid, step: i64, code_hashes: &[String]) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let now = Utc::now();
+
+        let result = sqlx::query(
+            "UPDATE two_factor SET enabled = 1, enabled_at = $2, last_used_step = $3 WHERE user_id = $1 AND enabled = 0"
+        )
+        .bind(user_id)
+        .bind(now)
+        .bind(step)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        Self::insert_recovery_codes(&mut tx, user_id, code_hashes).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn disable_two_factor(&self, user_id: Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM two_factor WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Mark a TOTP step used; false if it (or a later one) already was
+    pub async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE two_factor SET last_used_step = $2
+            WHERE user_id = $1 AND enabled = 1 AND (last_used_step IS NULL OR last_used_step < $2)
+            "#
+        )
+        .bind(user_id)
+        .bind(step)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    // Replace all recovery codes, used or not
+    pub async fn replace_recovery_codes(&self, user_id: Uuid, code_hashes: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        Self::insert_recovery_codes(&mut tx, user_id, code_hashes).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Spend a recovery code; false if it doesn't exist or was already used
+    pub async fn use_recovery_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE recovery_codes SET used_at = $3 WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn count_unused_recovery_codes(&self, user_id: Uuid) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM recovery_codes WHERE user_id = $1 AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    async fn insert_recovery_codes(
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        user_id: Uuid,
+        code_hashes: &[String],
+    ) -> Result<()> {
+        let now = Utc::now();
+        for code_hash in code_hashes {
+            sqlx::query(
+                "INSERT INTO recovery_codes (id, user_id, code_hash, created_at) VALUES ($1, $2, $3, $4)"
+            )
+            .bind(Uuid::new_v4())
+            .bind(user_id)
+            .bind(code_hash)
+            .bind(now)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    // Email tokens
+    // Record a newly issued token, superseding the user's unused ones for the
+    // same purpose
+    pub async fn create_email_token(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        purpose: EmailTokenPurpose,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE email_tokens SET used_at = $3 WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL"
+        )
+        .bind(user_id)
+        .bind(purpose)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO email_tokens (id, user_id, purpose, expires_at, created_at) VALUES ($1, $2, $3, $4, $5)"
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(purpose)
+        .bind(expires_at)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Spend a verification token and mark the email verified; false if the
+    // token was already used, superseded or expired
+    pub async fn verify_email(&self, user_id: Uuid, token_id: Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        if !Self::use_email_token(&mut tx, token_id, user_id, EmailTokenPurpose::VerifyEmail).await? {
+            return Ok(false);
+        }
+        sqlx::query("UPDATE users SET email_verified = 1, updated_at = $1 WHERE id = $2")
+            .bind(Utc::now())
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    // Spend a reset token and set the new password hash, ending every session
+    // issued under the old one. The reset link reached the inbox, so the email
+    // counts as verified too.
+    pub async fn reset_password(&self, user_id: Uuid, token_id: Uuid, password_hash: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        if !Self::use_email_token(&mut tx, token_id, user_id, EmailTokenPurpose::PasswordReset).await? {
+            return Ok(false);
+        }
+        sqlx::query(
+            r#"
+            UPDATE users SET password_hash = $1, email_verified = 1, updated_at = $2,
+                session_generation = session_generation + 1
+            WHERE id = $3
+            "#
+        )
+        .bind(password_hash)
+        .bind(Utc::now())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn use_email_token(
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        token_id: Uuid,
+        user_id: Uuid,
+        purpose: EmailTokenPurpose,
+    ) -> Result<bool> {
+        let now = Utc::now();
+        let result = sqlx::query(
+            r#"
+            UPDATE email_tokens SET used_at = $4
+            WHERE id = $1 AND user_id = $2 AND purpose = $3 AND used_at IS NULL AND expires_at > $4
+            "#
+        )
+        .bind(token_id)
+        .bind(user_id)
+        .bind(purpose)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
     // Security reports
     pub async fn create_security_report(
         &self,
@@ -379,15 +754,16 @@ impl Database {
         input_data_hash: &str,
         results: &str,
         risk_score: Option<i32>,
+        risk_model: Option<&str>,
     ) -> Result<SecurityReport> {
         let id = Uuid::new_v4();
         let now = Utc::now();
         let expires_at = Some(now + chrono::Duration::days(30)); // Reports expire after 30 days
         
-        let report = sqlx::query_as::<_, SecurityReport>(
+        let mut report = sqlx::query_as::<_, SecurityReport>(
             r#"
-            INSERT INTO security_reports (id, user_id, report_type, input_data_hash, results, risk_score, created_at, expires_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO security_reports (id, user_id, report_type, input_data_hash, results, risk_score, risk_model, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING *
             "#
         )
@@ -395,18 +771,20 @@ impl Database {
         .bind(user_id)
         .bind(report_type)
         .bind(input_data_hash)
-        .bind(results)
+        .bind(self.cipher.encrypt(id, results)?)
         .bind(risk_score)
+        .bind(risk_model)
         .bind(now)
         .bind(expires_at)
         .fetch_one(&self.pool)
         .await?;
+        report.results = results.to_string();
 
         Ok(report)
     }
 
     pub async fn get_user_reports(&self, user_id: Uuid, limit: i64, offset: i64) -> Result<Vec<SecurityReport>> {
-        let reports = sqlx::query_as::<_, SecurityReport>(
+        let mut reports = sqlx::query_as::<_, SecurityReport>(
             r#"
             SELECT * FROM security_reports 
             WHERE user_id = $1 AND (expires_at IS NULL OR expires_at > $2)
@@ -420,12 +798,15 @@ impl Database {
         .bind(offset)
         .fetch_all(&self.pool)
         .await?;
+        for report in &mut reports {
+            report.results = self.cipher.decrypt(report.id, &report.results)?;
+        }
 
         Ok(reports)
     }
 
     pub async fn get_report_by_id(&self, user_id: Uuid, report_id: Uuid) -> Result<Option<SecurityReport>> {
-        let report = sqlx::query_as::<_, SecurityReport>(
+        let mut report = sqlx::query_as::<_, SecurityReport>(
             r#"
             SELECT * FROM security_reports 
             WHERE id = $1 AND user_id = $2 AND (expires_at IS NULL OR expires_at > $3)
@@ -436,65 +817,145 @@ impl Database {
         .bind(Utc::now())
         .fetch_optional(&self.pool)
         .await?;
+        if let Some(report) = &mut report {
+            report.results = self.cipher.decrypt(report.id, &report.results)?;
+        }
 
         Ok(report)
     }
 
+    // Re-encrypt report results sealed under a retired key, or stored before
+    // encryption, with the current key. Safe to run from several instances at
+    // once.
+    pub async fn reencrypt_reports(&self, batch_size: i64) -> Result<Reencrypted> {
+        self.reencrypt_column("security_reports", "id", "results", batch_size).await
+    }
+
+    // The same for TOTP secrets, sealed with the user id as associated data
+    pub async fn reencrypt_two_factor_secrets(&self, batch_size: i64) -> Result<Reencrypted> {
+        self.reencrypt_column("two_factor", "user_id", "secret", batch_size).await
+    }
+
+    // Walk `table` in `id_column` order, resealing `column` wherever it isn't
+    // under the current key. The id is the associated data it was sealed with.
+    // Rows that can't be opened (unknown key, corrupt ciphertext) are logged
+    // and left as they are so one bad row doesn't stop the pass.
+    async fn reencrypt_column(&self, table: &str, id_column: &str, column: &str, batch_size: i64) -> Result<Reencrypted> {
+        let select = format!(
+            "SELECT {id}, {column} FROM {table} WHERE $1 IS NULL OR {id} > $1 ORDER BY {id} LIMIT $2",
+            id = id_column, column = column, table = table,
+        );
+        // Skip rows another instance got to first
+        let update = format!(
+            "UPDATE {table} SET {column} = $1 WHERE {id} = $2 AND {column} = $3",
+            id = id_column, column = column, table = table,
+        );
+
+        let mut outcome = Reencrypted::default();
+        let mut last_id: Option<Uuid> = None;
+        loop {
+            let rows: Vec<(Uuid, String)> = sqlx::query_as(&select)
+                .bind(last_id)
+                .bind(batch_size)
+                .fetch_all(&self.pool)
+                .await?;
+            let Some((id, _)) = rows.last() else { break };
+            last_id = Some(*id);
+
+            for (id, stored) in rows.iter().filter(|(_, stored)| !self.cipher.is_current(stored)) {
+                let plaintext = match self.cipher.decrypt(*id, stored) {
+                    Ok(plaintext) => plaintext,
+                    Err(e) => {
+                        warn!("Skipping {} row during re-encryption: {}", table, e);
+                        outcome.skipped += 1;
+                        continue;
+                    }
+                };
+                let sealed = self.cipher.encrypt(*id, &plaintext)?;
+                let result = sqlx::query(&update)
+                    .bind(sealed)
+                    .bind(id)
+                    .bind(stored)
+                    .execute(&self.pool)
+                    .await?;
+                outcome.reencrypted += result.rows_affected();
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    pub fn report_key_id(&self) -> &str {
+        self.cipher.current_key_id()
+    }
+
     // Breach data management
-    pub async fn store_breach_data(
-        &self,
-        email_hash: &str,
-        password_hash: Option<&str>,
-        source_name: &str,
-        breach_date: DateTime<Utc>,
-        data_types: &[String],
-        severity: &str,
-    ) -> Result<BreachData> {
-        let id = Uuid::new_v4();
+
+    // Insert a batch in a single transaction. Records already stored (same email,
+    // source and password) are left alone; returns (inserted, duplicates).
+    pub async fn store_breach_batch(&self, records: &[NewBreachData]) -> Result<(u64, u64)> {
         let now = Utc::now();
-        let data_types_json = serde_json::to_string(data_types)?;
-        
-        let breach_data = sqlx::query_as::<_, BreachData>(
-            r#"
-            INSERT INTO breach_data (id, email_hash, password_hash, source_name, breach_date, data_types, severity, verified, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-            RETURNING *
-            "#
-        )
-        .bind(id)
-        .bind(email_hash)
-        .bind(password_hash)
-        .bind(source_name)
-        .bind(breach_date)
-        .bind(data_types_json)
-        .bind(severity)
-        .bind(true)
-        .bind(now)
-        .fetch_one(&self.pool)
-        .await?;
+        let mut inserted = 0;
+        let mut tx = self.pool.begin().await?;
+
+        for record in records {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO breach_data (id, email_hash, password_hash, source_name, breach_date, data_types, severity, verified, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT DO NOTHING
+                "#
+            )
+            .bind(Uuid::new_v4())
+            .bind(&record.email_hash)
+            .bind(&record.password_hash)
+            .bind(&record.source_name)
+            .bind(record.breach_date)
+            .bind(serde_json::to_string(&record.data_types)?)
+            .bind(&record.severity)
+            .bind(true)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+            inserted += result.rows_affected();
+        }
+
+        tx.commit().await?;
 
-        Ok(breach_data)
+        Ok((inserted, records.len() as u64 - inserted))
     }
 
-    pub async fn check_email_breaches(&self, email_hash: &str) -> Result<Vec<BreachData>> {
-        let breaches = sqlx::query_as::<_, BreachData>(
-            "SELECT * FROM breach_data WHERE email_hash = $1 AND verified = true ORDER BY breach_date DESC"
-        )
-        .bind(email_hash)
-        .fetch_all(&self.pool)
-        .await?;
+    // Rows matching any of the digests (see `BreachHasher::email_lookup_hashes`)
+    pub async fn check_email_breaches(&self, email_hashes: &[String]) -> Result<Vec<BreachData>> {
+        self.breaches_matching("email_hash", email_hashes).await
+    }
 
-        Ok(breaches)
+    // Rows matching any of the digests (see `BreachHasher::password_lookup_hashes`)
+    pub async fn check_password_breaches(&self, password_hashes: &[String]) -> Result<Vec<BreachData>> {
+        self.breaches_matching("password_hash", password_hashes).await
     }
 
-    pub async fn check_password_breaches(&self, password_hash: &str) -> Result<Vec<BreachData>> {
-        let breaches = sqlx::query_as::<_, BreachData>(
-            "SELECT * FROM breach_data WHERE password_hash = $1 AND verified = true ORDER BY breach_date DESC"
-        )
-        .bind(password_hash)
-        .fetch_all(&self.pool)
-        .await?;
+    async fn breaches_matching(&self, column: &str, hashes: &[String]) -> Result<Vec<BreachData>> {
+        if hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = (1..=hashes.len())
+            .map(|i| format!("${}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "SELECT * FROM breach_data WHERE {} IN ({}) AND verified = true ORDER BY breach_date DESC",
+            column, placeholders
+        );
+
+        let mut query = sqlx::query_as::<_, BreachData>(&sql);
+        for hash in hashes {
+            query = query.bind(hash);
+        }
+        let breaches = query.fetch_all(&self.pool).await?;
 
         Ok(breaches)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,767 @@
+// Non-JSON breach dump formats
+// Combolists (`email:password`), CSV/TSV with a header row and SQL INSERT dumps
+// are parsed line by line into `BreachEntry`, the same record shape the JSON
+// tooling works with ({ "email", "password", "source": { "name", "breach_date" } }).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputFormat {
+    #[default]
+    Auto,
+    Json,
+    // JSON records one per line, never the {"result": [...]} wrapper
+    Ndjson,
+    Combo,
+    Csv,
+    Tsv,
+    Sql,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(InputFormat::Auto),
+            "json" => Ok(InputFormat::Json),
+            "ndjson" | "jsonl" => Ok(InputFormat::Ndjson),
+            "combo" | "combolist" => Ok(InputFormat::Combo),
+            "csv" => Ok(InputFormat::Csv),
+            "tsv" => Ok(InputFormat::Tsv),
+            "sql" => Ok(InputFormat::Sql),
+            other => Err(format!(
+                "Unknown input format '{}' (expected auto, json, ndjson, combo, csv, tsv or sql)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InputFormat::Auto => "auto",
+            InputFormat::Json => "json",
+            InputFormat::Ndjson => "ndjson",
+            InputFormat::Combo => "combo",
+            InputFormat::Csv => "csv",
+            InputFormat::Tsv => "tsv",
+            InputFormat::Sql => "sql",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntrySource {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breach_date: Option<String>,
+}
+
+// Common breach record; columns we don't recognise are kept in `extra`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BreachEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<EntrySource>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl BreachEntry {
+    pub fn into_value(self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.email.is_none() && self.username.is_none() && self.password.is_none() && self.password_hash.is_none()
+    }
+
+    // Assign a named column, mapping common header spellings onto our fields
+    fn set(&mut self, column: &str, value: String) {
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            return;
+        }
+
+        match canonical_column(column) {
+            Some(Field::Email) => self.email = Some(value),
+            Some(Field::Username) => self.username = Some(value),
+            Some(Field::Password) => self.password = Some(value),
+            Some(Field::PasswordHash) => self.password_hash = Some(value),
+            Some(Field::Source) => self.source.get_or_insert_with(Default::default).name = Some(value),
+            Some(Field::BreachDate) => {
+                self.source.get_or_insert_with(Default::default).breach_date = Some(value)
+            }
+            None => {
+                self.extra.insert(column.to_string(), Value::String(value));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Email,
+    Username,
+    Password,
+    PasswordHash,
+    Source,
+    BreachDate,
+}
+
+fn canonical_column(column: &str) -> Option<Field> {
+    let normalized: String = column.trim()
+        .trim_matches(|c| c == '`' || c == '"' || c == '\'' || c == '[' || c == ']')
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+
+    match normalized.as_str() {
+        "email" | "mail" | "emailaddress" | "useremail" | "emailaddr" => Some(Field::Email),
+        "username" | "user" | "login" | "uname" | "nick" | "nickname" | "handle" | "screenname" => Some(Field::Username),
+        "password" | "pass" | "passwd" | "pwd" | "plaintext" | "plainpassword" => Some(Field::Password),
+        "hash" | "passwordhash" | "passhash" | "hashedpassword" | "pwdhash" => Some(Field::PasswordHash),
+        // Not "domain": in dumps that is usually the email's domain, not the breach
+        "source" | "site" | "breach" | "breachname" => Some(Field::Source),
+        "breachdate" | "date" | "leakdate" => Some(Field::BreachDate),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseStats {
+    pub records: u64,
+    pub skipped: u64,
+}
+
+// Pick a format from the file extension, falling back to sniffing the first lines
+pub fn detect_format(path: &Path, head: &[u8]) -> InputFormat {
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    match extension.as_deref() {
+        Some("json") => return InputFormat::Json,
+        Some("ndjson" | "jsonl") => return InputFormat::Ndjson,
+        Some("csv") => return InputFormat::Csv,
+        Some("tsv" | "tab") => return InputFormat::Tsv,
+        Some("sql") => return InputFormat::Sql,
+        _ => {}
+    }
+
+    let head = String::from_utf8_lossy(head);
+    let first_line = head.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("");
+    let upper = first_line.to_uppercase();
+
+    if first_line.starts_with('[') || first_line.starts_with('{') || first_line.starts_with('\u{feff}') {
+        InputFormat::Json
+    } else if head.to_uppercase().contains("INSERT INTO")
+        || first_line.starts_with("--")
+        || first_line.starts_with("/*")
+        || ["CREATE ", "DROP ", "SET ", "LOCK ", "USE "].iter().any(|k| upper.starts_with(k))
+    {
+        InputFormat::Sql
+    } else if first_line.contains('\t') {
+        InputFormat::Tsv
+    } else if first_line.split(',').any(|cell| canonical_column(cell).is_some())
+        || (first_line.contains(',') && combo_split(first_line).is_none())
+    {
+        InputFormat::Csv
+    } else {
+        InputFormat::Combo
+    }
+}
+
+// Parse a text dump, calling `on_entry` for every record found
+pub fn read_entries<R: BufRead>(
+    reader: R,
+    format: InputFormat,
+    on_entry: &mut dyn FnMut(BreachEntry) -> io::Result<()>,
+) -> io::Result<ParseStats> {
+    match format {
+        InputFormat::Combo => read_combolist(reader, on_entry),
+        InputFormat::Csv => read_delimited(reader, b',', on_entry),
+        InputFormat::Tsv => read_delimited(reader, b'\t', on_entry),
+        InputFormat::Sql => read_sql_inserts(reader, on_entry),
+        InputFormat::Json | InputFormat::Ndjson | InputFormat::Auto => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} input is not a text dump format", format),
+        )),
+    }
+}
+
+// `email:password`, also accepting `;` and `|` separators. Only the first separator
+// splits, so passwords containing ':' survive.
+fn combo_split(line: &str) -> Option<(&str, &str)> {
+    let index = line.find([':', ';', '|'])?;
+    let (identity, rest) = line.split_at(index);
+    let identity = identity.trim();
+
+    if identity.is_empty() || identity.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((identity, &rest[1..]))
+}
+
+fn read_combolist<R: BufRead>(
+    reader: R,
+    on_entry: &mut dyn FnMut(BreachEntry) -> io::Result<()>,
+) -> io::Result<ParseStats> {
+    let mut stats = ParseStats::default();
+
+    for line in reader.split(b'\n') {
+        // Dumps are frequently not valid UTF-8; decode lossily rather than abort
+        let line = line?;
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches('\r');
+
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((identity, password)) = combo_split(line) else {
+            stats.skipped += 1;
+            continue;
+        };
+
+        let mut entry = BreachEntry::default();
+        if identity.contains('@') {
+            entry.email = Some(identity.to_string());
+        } else {
+            entry.username = Some(identity.to_string());
+        }
+        if !password.is_empty() {
+            entry.password = Some(password.to_string());
+        }
+
+        stats.records += 1;
+        on_entry(entry)?;
+    }
+
+    Ok(stats)
+}
+
+fn read_delimited<R: BufRead>(
+    reader: R,
+    delimiter: u8,
+    on_entry: &mut dyn FnMut(BreachEntry) -> io::Result<()>,
+) -> io::Result<ParseStats> {
+    let mut stats = ParseStats::default();
+    let mut csv = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader);
+
+    let mut header: Option<Vec<String>> = None;
+    let mut record = csv::ByteRecord::new();
+
+    loop {
+        match csv.read_byte_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) => {}
+            Err(e) if e.is_io_error() => return Err(io::Error::other(e)),
+            Err(_) => {
+                stats.skipped += 1;
+                continue;
+            }
+        }
+
+        let cells: Vec<String> = record.iter()
+            .map(|cell| String::from_utf8_lossy(cell).into_owned())
+            .collect();
+        if cells.iter().all(|c| c.trim().is_empty()) {
+            continue;
+        }
+
+        // The first row is a header when it names at least one field we know;
+        // otherwise columns are taken positionally as email, password
+        let columns = match &header {
+            Some(columns) => columns,
+            None => {
+                let is_header = cells.iter().any(|c| canonical_column(c).is_some())
+                    && !cells.iter().any(|c| c.contains('@'));
+                header = Some(if is_header {
+                    cells.iter().map(|c| c.trim().to_string()).collect()
+                } else {
+                    vec!["email".to_string(), "password".to_string()]
+                });
+                if is_header {
+                    continue;
+                }
+                header.as_ref().unwrap()
+            }
+        };
+
+        let mut entry = BreachEntry::default();
+        for (index, value) in cells.into_iter().enumerate() {
+            match columns.get(index) {
+                Some(column) => entry.set(column, value),
+                None => entry.set(&format!("column_{}", index + 1), value),
+            }
+        }
+
+        if entry.is_empty() {
+            stats.skipped += 1;
+            continue;
+        }
+        stats.records += 1;
+        on_entry(entry)?;
+    }
+
+    Ok(stats)
+}
+
+// Reads `INSERT INTO table (cols) VALUES (...), (...);` statements one at a time.
+// Other statements (CREATE TABLE, SET, comments) are ignored.
+fn read_sql_inserts<R: BufRead>(
+    mut reader: R,
+    on_entry: &mut dyn FnMut(BreachEntry) -> io::Result<()>,
+) -> io::Result<ParseStats> {
+    let mut stats = ParseStats::default();
+    let mut statement = Vec::new();
+
+    while read_statement(&mut reader, &mut statement)? {
+        let text = String::from_utf8_lossy(&statement);
+        let Some(insert) = parse_insert(&text) else {
+            continue;
+        };
+
+        for row in insert.rows {
+            let mut entry = BreachEntry::default();
+            for (index, value) in row.into_iter().enumerate() {
+                let Some(value) = value else { continue };
+                match insert.columns.get(index) {
+                    Some(column) => entry.set(column, value),
+                    // Without a column list the email is the value that looks like one
+                    None if entry.email.is_none() && value.contains('@') => entry.email = Some(value),
+                    None => entry.set(&format!("column_{}", index + 1), value),
+                }
+            }
+
+            if entry.is_empty() {
+                stats.skipped += 1;
+                continue;
+            }
+            stats.records += 1;
+            on_entry(entry)?;
+        }
+    }
+
+    Ok(stats)
+}
+
+// Collect bytes up to the next `;` that is outside quotes and comments
+fn read_statement<R: BufRead>(reader: &mut R, statement: &mut Vec<u8>) -> io::Result<bool> {
+    statement.clear();
+    let mut quote: Option<u8> = None;
+    let mut escaped = false;
+    let mut line_comment = false;
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(!statement.iter().all(u8::is_ascii_whitespace));
+        }
+
+        let mut consumed = 0;
+        let mut complete = false;
+        for &byte in buf {
+            consumed += 1;
+
+            if line_comment {
+                if byte == b'\n' {
+                    line_comment = false;
+                }
+                continue;
+            }
+
+            if let Some(q) = quote {
+                statement.push(byte);
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == q {
+                    quote = None;
+                }
+                continue;
+            }
+
+            match byte {
+                b'\'' | b'"' | b'`' => {
+                    quote = Some(byte);
+                    statement.push(byte);
+                }
+                b'-' if statement.last() == Some(&b'-') => {
+                    statement.pop();
+                    line_comment = true;
+                }
+                // MySQL's `# comment`, only where a statement would begin; elsewhere
+                // it can be part of a bare value
+                b'#' if statement.iter().all(u8::is_ascii_whitespace) => line_comment = true,
+                b';' => {
+                    complete = true;
+                    break;
+                }
+                _ => statement.push(byte),
+            }
+        }
+
+        reader.consume(consumed);
+        if complete {
+            return Ok(true);
+        }
+    }
+}
+
+struct Insert {
+    columns: Vec<String>,
+    rows: Vec<Vec<Option<String>>>,
+}
+
+fn parse_insert(statement: &str) -> Option<Insert> {
+    let mut tokens = SqlTokens::new(statement);
+
+    // Skip any leading block comments, then expect INSERT [IGNORE] INTO <table>
+    if !tokens.keyword("INSERT") {
+        return None;
+    }
+    tokens.keyword("IGNORE");
+    if !tokens.keyword("INTO") {
+        return None;
+    }
+    tokens.identifier()?;
+
+    let mut columns = Vec::new();
+    if tokens.peek() == Some('(') {
+        tokens.bump();
+        loop {
+            columns.push(tokens.identifier()?);
+            match tokens.next_char()? {
+                ',' => continue,
+                ')' => break,
+                _ => return None,
+            }
+        }
+    }
+
+    if !tokens.keyword("VALUES") && !tokens.keyword("VALUE") {
+        return None;
+    }
+
+    let mut rows = Vec::new();
+    loop {
+        if tokens.next_char()? != '(' {
+            return None;
+        }
+        let mut row = Vec::new();
+        loop {
+            row.push(tokens.value()?);
+            match tokens.next_char()? {
+                ',' => continue,
+                ')' => break,
+                _ => return None,
+            }
+        }
+        rows.push(row);
+
+        match tokens.next_char() {
+            Some(',') => continue,
+            _ => break,
+        }
+    }
+
+    Some(Insert { columns, rows })
+}
+
+// Minimal SQL lexer: enough for mysqldump / pg_dump --inserts output
+struct SqlTokens<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> SqlTokens<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { chars: text.chars().peekable() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+                self.chars.next();
+            }
+            // /* ... */ comments (including mysqldump's /*!40000 ... */ hints)
+            let mut lookahead = self.chars.clone();
+            if lookahead.next() == Some('/') && lookahead.next() == Some('*') {
+                self.chars.next();
+                self.chars.next();
+                let mut previous = ' ';
+                for c in self.chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                continue;
+            }
+            break;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.peek().copied()
+    }
+
+    fn bump(&mut self) {
+        self.chars.next();
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.next()
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '.' || c == '$' {
+                word.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        word
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        let mut lookahead = SqlTokens { chars: self.chars.clone() };
+        if lookahead.word().eq_ignore_ascii_case(keyword) {
+            self.chars = lookahead.chars;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Bare, `backtick`, "double" or [bracket] quoted identifier, optionally schema-qualified
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let mut name = String::new();
+        loop {
+            match self.chars.peek().copied() {
+                Some(open @ ('`' | '"' | '[')) => {
+                    let close = if open == '[' { ']' } else { open };
+                    self.chars.next();
+                    for c in self.chars.by_ref() {
+                        if c == close {
+                            break;
+                        }
+                        name.push(c);
+                    }
+                }
+                Some(_) => name.push_str(&self.word()),
+                None => {}
+            }
+
+            if self.chars.peek() == Some(&'.') {
+                self.chars.next();
+                name.clear();
+                continue;
+            }
+            break;
+        }
+
+        (!name.is_empty()).then_some(name)
+    }
+
+    // A literal: 'string' (with \' or '' escapes), number, NULL or bare word
+    fn value(&mut self) -> Option<Option<String>> {
+        self.skip_whitespace();
+        match self.chars.peek().copied()? {
+            quote @ ('\'' | '"') => {
+                self.chars.next();
+                let mut value = String::new();
+                while let Some(c) = self.chars.next() {
+                    match c {
+                        '\\' => match self.chars.next()? {
+                            'n' => value.push('\n'),
+                            'r' => value.push('\r'),
+                            't' => value.push('\t'),
+                            '0' => value.push('\0'),
+                            other => value.push(other),
+                        },
+                        c if c == quote => {
+                            if self.chars.peek() == Some(&quote) {
+                                self.chars.next();
+                                value.push(quote);
+                            } else {
+                                return Some(Some(value));
+                            }
+                        }
+                        c => value.push(c),
+                    }
+                }
+                None
+            }
+            _ => {
+                let mut raw = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c == ',' || c == ')' || c.is_whitespace() {
+                        break;
+                    }
+                    raw.push(c);
+                    self.chars.next();
+                }
+                if raw.is_empty() {
+                    None
+                } else if raw.eq_ignore_ascii_case("NULL") {
+                    Some(None)
+                } else {
+                    Some(Some(raw))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(format: InputFormat, text: &str) -> (Vec<BreachEntry>, ParseStats) {
+        let mut entries = Vec::new();
+        let stats = read_entries(text.as_bytes(), format, &mut |entry| {
+            entries.push(entry);
+            Ok(())
+        }).unwrap();
+        (entries, stats)
+    }
+
+    #[test]
+    fn combo_passwords_keep_later_separators() {
+        let (entries, stats) = parse(InputFormat::Combo, "ann@example.com:pa:ss;w|rd\r\nbob;hunter2\n# comment\n\nno separator here\n");
+
+        assert_eq!((stats.records, stats.skipped), (2, 1));
+        assert_eq!(entries[0].email.as_deref(), Some("ann@example.com"));
+        assert_eq!(entries[0].password.as_deref(), Some("pa:ss;w|rd"));
+        assert_eq!(entries[1].username.as_deref(), Some("bob"));
+        assert_eq!(entries[1].password.as_deref(), Some("hunter2"));
+    }
+
+    #[test]
+    fn combo_lines_need_not_be_utf8() {
+        let mut entries = Vec::new();
+        let stats = read_entries(&b"cy@example.com:caf\xe9\n"[..], InputFormat::Combo, &mut |entry| {
+            entries.push(entry);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(stats.records, 1);
+        assert_eq!(entries[0].password.as_deref(), Some("caf\u{fffd}"));
+    }
+
+    #[test]
+    fn csv_header_names_the_columns() {
+        let text = "E-Mail,Passwd,Site,favourite_colour\n\"dee@example.com\",\"a,b \"\"quoted\"\"\",forum,teal\n";
+        let (entries, stats) = parse(InputFormat::Csv, text);
+
+        assert_eq!((stats.records, stats.skipped), (1, 0));
+        let entry = &entries[0];
+        assert_eq!(entry.email.as_deref(), Some("dee@example.com"));
+        assert_eq!(entry.password.as_deref(), Some("a,b \"quoted\""));
+        assert_eq!(entry.source.as_ref().and_then(|s| s.name.as_deref()), Some("forum"));
+        assert_eq!(entry.extra["favourite_colour"], "teal");
+    }
+
+    #[test]
+    fn csv_domain_column_is_not_the_source() {
+        let (entries, _) = parse(InputFormat::Csv, "email,domain,password\nkim@example.com,example.com,pw\n");
+
+        assert_eq!(entries[0].source.as_ref().and_then(|s| s.name.as_deref()), None);
+        assert_eq!(entries[0].extra["domain"], "example.com");
+        assert_eq!(entries[0].password.as_deref(), Some("pw"));
+    }
+
+    #[test]
+    fn csv_without_a_header_is_email_then_password() {
+        let (entries, stats) = parse(InputFormat::Tsv, "eve@example.com\tsecret\textra\n\t\n");
+
+        assert_eq!(stats.records, 1);
+        assert_eq!(entries[0].email.as_deref(), Some("eve@example.com"));
+        assert_eq!(entries[0].password.as_deref(), Some("secret"));
+        assert_eq!(entries[0].extra["column_3"], "extra");
+    }
+
+    #[test]
+    fn sql_inserts_with_several_rows_escapes_and_nulls() {
+        let text = r#"
+-- MySQL dump
+CREATE TABLE `users` (`id` int, `email` varchar(255), `password` varchar(255));
+/*!40000 ALTER TABLE `users` DISABLE KEYS */;
+INSERT INTO `db`.`users` (`id`, `email`, `password`) VALUES
+  (1, 'fay@example.com', 'it''s;a\'secret\n'),
+  (2, 'gus@example.com', NULL),
+  (3, NULL, NULL);
+INSERT IGNORE INTO users VALUES (4, 'hal@example.com', 'x');
+"#;
+        let (entries, stats) = parse(InputFormat::Sql, text);
+
+        assert_eq!((stats.records, stats.skipped), (3, 1));
+        assert_eq!(entries[0].email.as_deref(), Some("fay@example.com"));
+        assert_eq!(entries[0].password.as_deref(), Some("it's;a'secret"));
+        assert_eq!(entries[0].extra["id"], "1");
+        assert_eq!(entries[1].email.as_deref(), Some("gus@example.com"));
+        assert_eq!(entries[1].password, None);
+        // Without a column list the email is recognised by its '@'
+        assert_eq!(entries[2].email.as_deref(), Some("hal@example.com"));
+        assert_eq!(entries[2].extra["column_1"], "4");
+    }
+
+    #[test]
+    fn sql_hash_comments_only_start_a_statement() {
+        let text = "# Dumped by mysqldump; don't edit\n  # another; comment\nINSERT INTO users (id, colour, email) VALUES (1, #ff0000, 'lou@example.com'), (2, #00ff00, 'max@example.com');\n";
+        let (entries, stats) = parse(InputFormat::Sql, text);
+
+        assert_eq!((stats.records, stats.skipped), (2, 0));
+        assert_eq!(entries[0].email.as_deref(), Some("lou@example.com"));
+        assert_eq!(entries[0].extra["colour"], "#ff0000");
+        assert_eq!(entries[1].email.as_deref(), Some("max@example.com"));
+    }
+
+    #[test]
+    fn detects_formats_from_extension_then_content() {
+        let detect = |name: &str, head: &str| detect_format(Path::new(name), head.as_bytes());
+
+        assert_eq!(detect("dump.sql", ""), InputFormat::Sql);
+        assert_eq!(detect("dump.tab", ""), InputFormat::Tsv);
+        assert_eq!(detect("dump.jsonl", ""), InputFormat::Ndjson);
+        assert_eq!("ndjson".parse::<InputFormat>(), Ok(InputFormat::Ndjson));
+        assert_eq!(detect("dump.txt", "[{\"email\": \"a@b.c\"}]"), InputFormat::Json);
+        assert_eq!(detect("dump.txt", "-- dump\nINSERT INTO t VALUES (1);"), InputFormat::Sql);
+        assert_eq!(detect("dump.txt", "email\tpassword\n"), InputFormat::Tsv);
+        assert_eq!(detect("dump.txt", "email,password\n"), InputFormat::Csv);
+        assert_eq!(detect("dump.txt", "ivy@example.com:pass,word\n"), InputFormat::Combo);
+    }
+}
@@ -0,0 +1,782 @@
+// Streaming breach dump processing
+// Dumps are read one record at a time so multi-gigabyte combolists filter in
+// constant memory. Three layouts are understood and mirrored on output:
+//   - a top-level JSON array:          [ {...}, {...} ]
+//   - newline-delimited JSON (NDJSON): {...}\n{...}\n
+//   - the API wrapper:                 { "result": [ {...} ], ...other fields }
+// An NDJSON record may itself have a "result" field, so an object is only taken
+// as the wrapper when "result" holds an array and the input isn't line-delimited.
+// A one-line file can't be told apart from the wrapper by its content; the
+// .ndjson / .jsonl extension or `--format ndjson` settles it.
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer as _};
+use serde_json::{Map, Value};
+use std::cell::Cell;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::dump_formats::{self, InputFormat};
+
+const WRAPPER_KEY: &str = "result";
+// Records held in memory before a sort run is spilled to disk
+const SORT_CHUNK_RECORDS: usize = 100_000;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    Array,
+    Ndjson,
+    ResultWrapper,
+}
+
+#[derive(Debug)]
+pub enum DumpEvent {
+    Start(DumpFormat),
+    // Wrapper fields other than "result", passed through untouched
+    Field(String, Value),
+    ResultStart,
+    ResultEnd,
+    Record(Value),
+}
+
+type EventSink<'a> = dyn FnMut(DumpEvent) -> io::Result<()> + 'a;
+
+// Drive `on_event` over every record in the dump without buffering the whole input
+pub fn read_dump<R: Read>(input: R, on_event: &mut EventSink) -> io::Result<()> {
+    read_json(input, false, on_event)
+}
+
+// Like `read_dump`, for input known to be NDJSON: every top-level object is a record
+pub fn read_ndjson<R: Read>(input: R, on_event: &mut EventSink) -> io::Result<()> {
+    read_json(input, true, on_event)
+}
+
+fn read_json<R: Read>(input: R, ndjson: bool, on_event: &mut EventSink) -> io::Result<()> {
+    let mut reader = BufReader::new(input);
+
+    let first = loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break None;
+        }
+        // Skip whitespace and a UTF-8 byte order mark
+        match buf.iter().position(|b| !b.is_ascii_whitespace() && ![0xEF, 0xBB, 0xBF].contains(b)) {
+            Some(pos) => {
+                let byte = buf[pos];
+                reader.consume(pos);
+                break Some(byte);
+            }
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            }
+        }
+    };
+
+    let line_delimited = first == Some(b'{') && (ndjson || is_line_delimited(reader.fill_buf()?));
+    let mut de = serde_json::Deserializer::from_reader(reader);
+
+    match first {
+        None => on_event(DumpEvent::Start(DumpFormat::Ndjson)),
+        Some(b'[') => {
+            on_event(DumpEvent::Start(DumpFormat::Array))?;
+            de.deserialize_seq(RecordSeq { on_event: &mut *on_event }).map_err(io::Error::from)?;
+            de.end().map_err(io::Error::from)
+        }
+        Some(b'{') if line_delimited => {
+            on_event(DumpEvent::Start(DumpFormat::Ndjson))?;
+            for value in de.into_iter::<Value>() {
+                on_event(DumpEvent::Record(value?))?;
+            }
+            Ok(())
+        }
+        Some(b'{') => {
+            let first = de.deserialize_map(TopLevelObject { on_event: &mut *on_event })
+                .map_err(io::Error::from)?;
+
+            match first {
+                // The whole first object was a record, so this is NDJSON
+                Some(record) => {
+                    on_event(DumpEvent::Start(DumpFormat::Ndjson))?;
+                    on_event(DumpEvent::Record(Value::Object(record)))?;
+                    for value in de.into_iter::<Value>() {
+                        on_event(DumpEvent::Record(value?))?;
+                    }
+                    Ok(())
+                }
+                None => de.end().map_err(io::Error::from),
+            }
+        }
+        Some(other) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unsupported dump layout: unexpected leading byte {:?}", other as char),
+        )),
+    }
+}
+
+// NDJSON when the first line is a whole JSON value with more input after it.
+// Only what is already buffered is looked at, so a first record longer than the
+// buffer falls back to reading the first object on its own.
+fn is_line_delimited(head: &[u8]) -> bool {
+    let Some(end) = head.iter().position(|&b| b == b'\n') else {
+        return false;
+    };
+    serde_json::from_slice::<de::IgnoredAny>(&head[..end]).is_ok()
+        && head[end + 1..].iter().any(|b| !b.is_ascii_whitespace())
+}
+
+// Like `read_dump`, but also accepts the text dump formats. `Auto` is resolved from
+// the path and the first bytes; text dumps are presented as an array of records.
+// Returns the number of unparseable lines that were skipped.
+pub fn read_input<R: Read>(
+    input: R,
+    path: &Path,
+    format: InputFormat,
+    on_event: &mut EventSink,
+) -> io::Result<u64> {
+    let mut reader = BufReader::new(input);
+    let format = match format {
+        InputFormat::Auto => dump_formats::detect_format(path, reader.fill_buf()?),
+        format => format,
+    };
+
+    match format {
+        InputFormat::Json => return read_dump(reader, on_event).map(|_| 0),
+        InputFormat::Ndjson => return read_ndjson(reader, on_event).map(|_| 0),
+        _ => {}
+    }
+
+    on_event(DumpEvent::Start(DumpFormat::Array))?;
+    let stats = dump_formats::read_entries(reader, format, &mut |entry| {
+        on_event(DumpEvent::Record(entry.into_value()))
+    })?;
+    Ok(stats.skipped)
+}
+
+// Visit every record of a dump file without writing anything
+pub fn for_each_record<F>(input: &str, format: InputFormat, mut visit: F) -> io::Result<u64>
+where
+    F: FnMut(Value) -> io::Result<()>,
+{
+    let file = File::open(input)?;
+    read_input(file, Path::new(input), format, &mut |event| match event {
+        DumpEvent::Record(record) => visit(record),
+        _ => Ok(()),
+    })
+}
+
+fn sink_error<E: de::Error>(e: io::Error) -> E {
+    E::custom(e)
+}
+
+// Streams the elements of a JSON array to the sink as records
+struct RecordSeq<'a, 'b> {
+    on_event: &'a mut EventSink<'b>,
+}
+
+impl<'de> Visitor<'de> for RecordSeq<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of breach records")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(record) = seq.next_element::<Value>()? {
+            (self.on_event)(DumpEvent::Record(record)).map_err(sink_error)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for RecordSeq<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+// The first top-level object is either the `{ "result": [...] }` wrapper, which is
+// streamed, or the first NDJSON record, which is returned whole
+struct TopLevelObject<'a, 'b> {
+    on_event: &'a mut EventSink<'b>,
+}
+
+impl<'de> Visitor<'de> for TopLevelObject<'_, '_> {
+    type Value = Option<Map<String, Value>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a breach record or a {\"result\": [...]} wrapper")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut leading = Map::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            if key != WRAPPER_KEY {
+                let value = map.next_value::<Value>()?;
+                leading.insert(key, value);
+                continue;
+            }
+
+            let result = ResultValue { on_event: &mut *self.on_event, leading: &mut leading };
+            if let Some(value) = map.next_value_seed(result)? {
+                leading.insert(key, value);
+                continue;
+            }
+
+            while let Some(key) = map.next_key::<String>()? {
+                let value = map.next_value::<Value>()?;
+                (self.on_event)(DumpEvent::Field(key, value)).map_err(sink_error)?;
+            }
+            return Ok(None);
+        }
+
+        Ok(Some(leading))
+    }
+}
+
+// The value under "result": an array makes the object the wrapper and is
+// streamed (None); anything else is an ordinary record field and comes back whole
+struct ResultValue<'a, 'b> {
+    on_event: &'a mut EventSink<'b>,
+    // Fields seen before "result", emitted once the object turns out to be the wrapper
+    leading: &'a mut Map<String, Value>,
+}
+
+impl<'de> Visitor<'de> for ResultValue<'_, '_> {
+    type Value = Option<Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        (self.on_event)(DumpEvent::Start(DumpFormat::ResultWrapper)).map_err(sink_error)?;
+        for (key, value) in std::mem::take(self.leading) {
+            (self.on_event)(DumpEvent::Field(key, value)).map_err(sink_error)?;
+        }
+
+        (self.on_event)(DumpEvent::ResultStart).map_err(sink_error)?;
+        RecordSeq { on_event: &mut *self.on_event }.visit_seq(seq)?;
+        (self.on_event)(DumpEvent::ResultEnd).map_err(sink_error)?;
+        Ok(None)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        Value::deserialize(de::value::MapAccessDeserializer::new(map)).map(Some)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Some(Value::Bool(v)))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Some(Value::String(v.to_string())))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Some(Value::Null))
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for ResultValue<'_, '_> {
+    type Value = Option<Value>;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+// Writes events back out in the layout they were read in
+pub struct DumpWriter<W: Write> {
+    out: W,
+    format: Option<DumpFormat>,
+    members: usize,
+    records: usize,
+}
+
+impl<W: Write> DumpWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            format: None,
+            members: 0,
+            records: 0,
+        }
+    }
+
+    pub fn write_event(&mut self, event: DumpEvent) -> io::Result<()> {
+        match event {
+            DumpEvent::Start(format) => {
+                self.format = Some(format);
+                match format {
+                    DumpFormat::Array => self.out.write_all(b"[")?,
+                    DumpFormat::ResultWrapper => self.out.write_all(b"{")?,
+                    DumpFormat::Ndjson => {}
+                }
+            }
+            DumpEvent::Field(key, value) => {
+                self.member_separator()?;
+                write!(self.out, "\n  {}: {}", Value::String(key), value)?;
+            }
+            DumpEvent::ResultStart => {
+                self.member_separator()?;
+                write!(self.out, "\n  \"{}\": [", WRAPPER_KEY)?;
+                self.records = 0;
+            }
+            DumpEvent::ResultEnd => {
+                self.close_array("\n  ]")?;
+            }
+            DumpEvent::Record(record) => self.write_record(&record)?,
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        match self.format {
+            Some(DumpFormat::Array) => self.close_array("\n]\n")?,
+            Some(DumpFormat::ResultWrapper) => self.out.write_all(b"\n}\n")?,
+            Some(DumpFormat::Ndjson) | None => {}
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_record(&mut self, record: &Value) -> io::Result<()> {
+        match self.format {
+            Some(DumpFormat::Ndjson) | None => {
+                serde_json::to_writer(&mut self.out, record)?;
+                self.out.write_all(b"\n")?;
+            }
+            Some(DumpFormat::Array) | Some(DumpFormat::ResultWrapper) => {
+                let indent = if self.format == Some(DumpFormat::Array) { "\n  " } else { "\n    " };
+                if self.records > 0 {
+                    self.out.write_all(b",")?;
+                }
+                self.out.write_all(indent.as_bytes())?;
+                serde_json::to_writer(&mut self.out, record)?;
+            }
+        }
+        self.records += 1;
+        Ok(())
+    }
+
+    fn member_separator(&mut self) -> io::Result<()> {
+        if self.members > 0 {
+            self.out.write_all(b",")?;
+        }
+        self.members += 1;
+        Ok(())
+    }
+
+    fn close_array(&mut self, closing: &str) -> io::Result<()> {
+        // Keep empty arrays on one line
+        if self.records == 0 {
+            self.out.write_all(b"]")?;
+            if closing.ends_with('\n') {
+                self.out.write_all(b"\n")?;
+            }
+            Ok(())
+        } else {
+            self.out.write_all(closing.as_bytes())
+        }
+    }
+}
+
+// Reader wrapper that counts bytes consumed, for progress reporting
+struct CountingReader<R> {
+    inner: R,
+    bytes: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes.set(self.bytes.get() + n as u64);
+        Ok(n)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DumpStats {
+    pub records_read: u64,
+    pub records_written: u64,
+    pub records_skipped: u64,
+    pub bytes_read: u64,
+    pub elapsed: Duration,
+}
+
+impl DumpStats {
+    pub fn removed(&self) -> u64 {
+        self.records_read - self.records_written
+    }
+}
+
+struct Progress {
+    enabled: bool,
+    total_bytes: Option<u64>,
+    started: Instant,
+    last_report: Instant,
+}
+
+impl Progress {
+    fn new(enabled: bool, total_bytes: Option<u64>) -> Self {
+        let now = Instant::now();
+        Self {
+            enabled,
+            total_bytes,
+            started: now,
+            last_report: now,
+        }
+    }
+
+    fn tick(&mut self, records: u64, bytes: u64) {
+        if !self.enabled || !records.is_multiple_of(1_000) || self.last_report.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        self.last_report = Instant::now();
+
+        let percent = self.total_bytes
+            .filter(|total| *total > 0)
+            .map(|total| format!(" {:5.1}%", bytes as f64 * 100.0 / total as f64))
+            .unwrap_or_default();
+
+        eprint!("\r⏳{} {} records, {}", percent, records, throughput(bytes, records, self.started.elapsed()));
+    }
+
+    fn done(&self, stats: &DumpStats) {
+        if !self.enabled {
+            return;
+        }
+        if stats.records_skipped > 0 {
+            eprintln!("\r⚠️  Skipped {} malformed lines", stats.records_skipped);
+        }
+        eprintln!(
+            "\r✅ {} records read, {} kept, {} removed — {}",
+            stats.records_read,
+            stats.records_written,
+            stats.removed(),
+            throughput(stats.bytes_read, stats.records_read, stats.elapsed),
+        );
+    }
+}
+
+fn throughput(bytes: u64, records: u64, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64().max(0.001);
+    let mb = bytes as f64 / (1024.0 * 1024.0);
+    format!(
+        "{:.1} MB in {:.1}s ({:.1} MB/s, {:.0} records/s)",
+        mb,
+        secs,
+        mb / secs,
+        records as f64 / secs,
+    )
+}
+
+// Key used to order records, highest first
+pub type SortKey = fn(&Value) -> String;
+
+// Filter (and optionally transform and sort) a dump file into `output`.
+// `keep` may mutate a record before it is written; returning false drops it.
+pub fn process_dump<F>(
+    input: &str,
+    output: &str,
+    format: InputFormat,
+    show_progress: bool,
+    sort_desc_by: Option<SortKey>,
+    mut keep: F,
+) -> io::Result<DumpStats>
+where
+    F: FnMut(&mut Value) -> bool,
+{
+    let file = File::open(input)?;
+    let total_bytes = file.metadata().ok().map(|m| m.len());
+    let bytes = Rc::new(Cell::new(0));
+    let reader = CountingReader { inner: file, bytes: bytes.clone() };
+
+    let mut writer = DumpWriter::new(BufWriter::new(File::create(output)?));
+    let mut sorter = sort_desc_by.map(ExternalSorter::new);
+    let mut progress = Progress::new(show_progress, total_bytes);
+    let mut records_read = 0u64;
+    let mut records_written = 0u64;
+
+    let records_skipped = read_input(reader, Path::new(input), format, &mut |event| match event {
+        DumpEvent::Record(mut record) => {
+            records_read += 1;
+            progress.tick(records_read, bytes.get());
+
+            if !keep(&mut record) {
+                return Ok(());
+            }
+            records_written += 1;
+
+            match &mut sorter {
+                Some(sorter) => sorter.push(record),
+                None => writer.write_event(DumpEvent::Record(record)),
+            }
+        }
+        DumpEvent::ResultEnd => {
+            if let Some(sorter) = sorter.take() {
+                sorter.drain(&mut |record| writer.write_event(DumpEvent::Record(record)))?;
+            }
+            writer.write_event(DumpEvent::ResultEnd)
+        }
+        event => writer.write_event(event),
+    })?;
+
+    // Arrays and NDJSON have no closing event, so sorted records are flushed here
+    if let Some(sorter) = sorter {
+        sorter.drain(&mut |record| writer.write_event(DumpEvent::Record(record)))?;
+    }
+    writer.finish()?;
+
+    let stats = DumpStats {
+        records_read,
+        records_written,
+        records_skipped,
+        bytes_read: bytes.get(),
+        elapsed: progress.started.elapsed(),
+    };
+    progress.done(&stats);
+
+    Ok(stats)
+}
+
+// Descending sort with bounded memory: full chunks are sorted and spilled to
+// temporary NDJSON runs, which are then k-way merged. Ties keep input order.
+struct ExternalSorter {
+    key: SortKey,
+    chunk: Vec<(String, Value)>,
+    runs: Vec<PathBuf>,
+}
+
+impl ExternalSorter {
+    fn new(key: SortKey) -> Self {
+        Self {
+            key,
+            chunk: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, record: Value) -> io::Result<()> {
+        self.chunk.push(((self.key)(&record), record));
+        if self.chunk.len() >= SORT_CHUNK_RECORDS {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn sort_chunk(&mut self) {
+        // Stable, so equal keys stay in input order
+        self.chunk.sort_by(|a, b| b.0.cmp(&a.0));
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.sort_chunk();
+
+        let path = std::env::temp_dir().join(format!("guardr_sort_{}.ndjson", uuid::Uuid::new_v4()));
+        self.runs.push(path.clone());
+
+        let mut out = BufWriter::new(File::create(&path)?);
+        for entry in self.chunk.drain(..) {
+            serde_json::to_writer(&mut out, &entry)?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()
+    }
+
+    fn drain(mut self, emit: &mut dyn FnMut(Value) -> io::Result<()>) -> io::Result<()> {
+        if self.runs.is_empty() {
+            self.sort_chunk();
+            for (_, record) in self.chunk.drain(..) {
+                emit(record)?;
+            }
+            return Ok(());
+        }
+
+        if !self.chunk.is_empty() {
+            self.spill()?;
+        }
+
+        let mut readers = self.runs.iter()
+            .map(|path| File::open(path).map(|f| BufReader::new(f).lines()))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut heap = BinaryHeap::new();
+        for (run, reader) in readers.iter_mut().enumerate() {
+            if let Some(entry) = next_run_entry(reader)? {
+                heap.push(MergeEntry { key: entry.0, run: Reverse(run), record: entry.1 });
+            }
+        }
+
+        while let Some(MergeEntry { run, record, .. }) = heap.pop() {
+            emit(record)?;
+            if let Some(entry) = next_run_entry(&mut readers[run.0])? {
+                heap.push(MergeEntry { key: entry.0, run, record: entry.1 });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ExternalSorter {
+    fn drop(&mut self) {
+        for run in &self.runs {
+            let _ = fs::remove_file(run);
+        }
+    }
+}
+
+fn next_run_entry(lines: &mut io::Lines<BufReader<File>>) -> io::Result<Option<(String, Value)>> {
+    match lines.next() {
+        Some(line) => Ok(Some(serde_json::from_str(&line?)?)),
+        None => Ok(None),
+    }
+}
+
+// Max-heap entry: highest key first, earlier run first on ties
+struct MergeEntry {
+    key: String,
+    run: Reverse<usize>,
+    record: Value,
+}
+
+impl PartialEq for MergeEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeEntry {}
+
+impl PartialOrd for MergeEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key).then(self.run.cmp(&other.run))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Events in a compact form: the layout, then fields, records and the
+    // brackets around the wrapper's "result"
+    fn events(input: &str) -> Vec<String> {
+        let mut events = Vec::new();
+        read_dump(input.as_bytes(), &mut |event| {
+            events.push(match event {
+                DumpEvent::Start(format) => format!("{:?}", format),
+                DumpEvent::Field(key, value) => format!("{}={}", key, value),
+                DumpEvent::ResultStart => "[".to_string(),
+                DumpEvent::ResultEnd => "]".to_string(),
+                DumpEvent::Record(record) => record.to_string(),
+            });
+            Ok(())
+        }).unwrap();
+        events
+    }
+
+    fn round_trip(input: &str) -> String {
+        let mut writer = DumpWriter::new(Vec::new());
+        read_dump(input.as_bytes(), &mut |event| writer.write_event(event)).unwrap();
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn top_level_arrays() {
+        let input = "\u{feff}  [{\"email\": \"a@example.com\"}, {\"email\": \"b@example.com\"}]";
+        assert_eq!(events(input), vec!["Array", r#"{"email":"a@example.com"}"#, r#"{"email":"b@example.com"}"#]);
+        assert_eq!(round_trip(input), "[\n  {\"email\":\"a@example.com\"},\n  {\"email\":\"b@example.com\"}\n]\n");
+    }
+
+    #[test]
+    fn ndjson() {
+        let input = "{\"email\": \"a@example.com\"}\n{\"email\": \"b@example.com\"}\n";
+        assert_eq!(events(input), vec!["Ndjson", r#"{"email":"a@example.com"}"#, r#"{"email":"b@example.com"}"#]);
+        assert_eq!(round_trip(input), "{\"email\":\"a@example.com\"}\n{\"email\":\"b@example.com\"}\n");
+    }
+
+    #[test]
+    fn result_wrapper_keeps_its_other_fields() {
+        let input = "{\n  \"success\": true,\n  \"result\": [{\"email\": \"a@example.com\"}],\n  \"size\": 1\n}\n";
+        assert_eq!(events(input), vec!["ResultWrapper", "success=true", "[", r#"{"email":"a@example.com"}"#, "]", "size=1"]);
+        assert_eq!(
+            round_trip(input),
+            "{\n  \"success\": true,\n  \"result\": [\n    {\"email\":\"a@example.com\"}\n  ],\n  \"size\": 1\n}\n",
+        );
+        // Minified on one line is still the wrapper
+        assert_eq!(events(r#"{"result": []}"#), vec!["ResultWrapper", "[", "]"]);
+    }
+
+    #[test]
+    fn ndjson_records_may_have_a_result_field() {
+        let input = "{\"email\": \"a@example.com\", \"result\": \"cracked\"}\n{\"email\": \"b@example.com\", \"result\": [1]}\n";
+        assert_eq!(events(input), vec![
+            "Ndjson",
+            r#"{"email":"a@example.com","result":"cracked"}"#,
+            r#"{"email":"b@example.com","result":[1]}"#,
+        ]);
+
+        // Nor does a lone record become the wrapper unless "result" is an array
+        let input = "{\n  \"email\": \"a@example.com\",\n  \"result\": {\"cracked\": true}\n}\n";
+        assert_eq!(events(input), vec!["Ndjson", r#"{"email":"a@example.com","result":{"cracked":true}}"#]);
+    }
+
+    #[test]
+    fn one_line_ndjson_files_are_never_the_wrapper() {
+        let input = "{\"email\": \"a@example.com\", \"result\": [\"cracked\"]}\n";
+        let read = |path: &str, format: InputFormat| {
+            let mut events = Vec::new();
+            read_input(input.as_bytes(), Path::new(path), format, &mut |event| {
+                events.push(match event {
+                    DumpEvent::Start(format) => format!("{:?}", format),
+                    DumpEvent::Record(record) => record.to_string(),
+                    _ => "wrapper".to_string(),
+                });
+                Ok(())
+            }).unwrap();
+            events
+        };
+        let record = vec!["Ndjson".to_string(), r#"{"email":"a@example.com","result":["cracked"]}"#.to_string()];
+
+        assert_eq!(read("dump.ndjson", InputFormat::Auto), record);
+        assert_eq!(read("dump.JSONL", InputFormat::Auto), record);
+        assert_eq!(read("dump.txt", InputFormat::Ndjson), record);
+        // Without the extension or the flag it reads as the wrapper
+        assert_eq!(read("dump.json", InputFormat::Auto)[0], "ResultWrapper");
+    }
+
+    #[test]
+    fn empty_input_is_empty_ndjson() {
+        assert_eq!(events("  \n"), vec!["Ndjson"]);
+    }
+}
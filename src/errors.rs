@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -25,6 +25,19 @@ pub enum AppError {
     #[error("Rate limit exceeded: {0}")]
     RateLimitExceeded(String),
 
+    #[error("Monthly quota exceeded: {used} of {limit} units used")]
+    QuotaExceeded {
+        limit: u32,
+        used: u32,
+        resets_at: chrono::DateTime<chrono::Utc>,
+    },
+
+    #[error("Login locked for {retry_after_secs} seconds")]
+    LoginLocked { retry_after_secs: u64 },
+
+    #[error("Email not verified: {feature} requires a verified email address")]
+    EmailNotVerified { feature: String },
+
     #[error("Database error: {0}")]
     DatabaseError(#[from] sqlx::Error),
 
@@ -70,10 +83,13 @@ impl AppError {
         match self {
             AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
             AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::EmailNotVerified { .. } => StatusCode::FORBIDDEN,
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
             AppError::ValidationError(_) => StatusCode::BAD_REQUEST,
             AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
             AppError::RateLimitExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::LoginLocked { .. } => StatusCode::TOO_MANY_REQUESTS,
             AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             AppError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
             AppError::RequestTimeout(_) => StatusCode::REQUEST_TIMEOUT,
@@ -101,10 +117,13 @@ impl AppError {
         match self {
             AppError::Unauthorized(_) => "UNAUTHORIZED",
             AppError::Forbidden(_) => "FORBIDDEN",
+            AppError::EmailNotVerified { .. } => "EMAIL_NOT_VERIFIED",
             AppError::NotFound(_) => "NOT_FOUND",
             AppError::ValidationError(_) => "VALIDATION_ERROR",
             AppError::BadRequest(_) => "BAD_REQUEST",
             AppError::RateLimitExceeded(_) => "RATE_LIMIT_EXCEEDED",
+            AppError::QuotaExceeded { .. } => "QUOTA_EXCEEDED",
+            AppError::LoginLocked { .. } => "LOGIN_LOCKED",
             AppError::TooManyRequests(_) => "TOO_MANY_REQUESTS",
             AppError::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
             AppError::RequestTimeout(_) => "REQUEST_TIMEOUT",
@@ -124,10 +143,23 @@ impl AppError {
         match self {
             AppError::Unauthorized(_) => "Authentication required or invalid credentials".to_string(),
             AppError::Forbidden(_) => "Access denied for this resource".to_string(),
+            AppError::EmailNotVerified { feature } => format!(
+                "Verify your email address to use {}",
+                feature
+            ),
             AppError::NotFound(_) => "The requested resource was not found".to_string(),
             AppError::ValidationError(msg) => format!("Validation failed: {}", msg),
             AppError::BadRequest(msg) => format!("Invalid request: {}", msg),
             AppError::RateLimitExceeded(_) => "Rate limit exceeded. Please try again later".to_string(),
+            AppError::QuotaExceeded { limit, resets_at, .. } => format!(
+                "Monthly quota of {} units exceeded. It resets at {}",
+                limit,
+                resets_at.to_rfc3339()
+            ),
+            AppError::LoginLocked { retry_after_secs } => format!(
+                "Too many failed login attempts. Try again in {} minutes",
+                retry_after_secs.div_ceil(60)
+            ),
             AppError::TooManyRequests(_) => "Too many requests. Please slow down".to_string(),
             AppError::ServiceUnavailable(_) => "Service temporarily unavailable. Please try again later".to_string(),
             AppError::RequestTimeout(_) => "Request timeout. Please try again".to_string(),
@@ -155,14 +187,28 @@ impl AppError {
         match self {
             AppError::Unauthorized(_) => false, // Common, don't log
             AppError::Forbidden(_) => false,    // Common, don't log
+            AppError::EmailNotVerified { .. } => false, // Expected behavior
             AppError::NotFound(_) => false,     // Common, don't log
             AppError::ValidationError(_) => false, // User error, don't log
             AppError::BadRequest(_) => false,   // User error, don't log
             AppError::RateLimitExceeded(_) => false, // Expected behavior
+            AppError::QuotaExceeded { .. } => false, // Expected behavior
+            AppError::LoginLocked { .. } => false, // Logged when the lockout starts
             AppError::TooManyRequests(_) => false, // Expected behavior
             _ => true, // Log all other errors
         }
     }
+
+    // Seconds a client should wait before retrying, sent as Retry-After
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            AppError::QuotaExceeded { resets_at, .. } => {
+                Some((*resets_at - chrono::Utc::now()).num_seconds().max(1) as u64)
+            }
+            AppError::LoginLocked { retry_after_secs } => Some((*retry_after_secs).max(1)),
+            _ => None,
+        }
+    }
 }
 
 impl IntoResponse for AppError {
@@ -176,7 +222,7 @@ impl IntoResponse for AppError {
             error!("Application error: {} - {}", error_type, self);
         }
 
-        let body = json!({
+        let mut body = json!({
             "success": false,
             "error": {
                 "type": error_type,
@@ -187,7 +233,17 @@ impl IntoResponse for AppError {
             "request_id": uuid::Uuid::new_v4().to_string() // In production, get from request context
         });
 
-        (status, Json(body)).into_response()
+        // Quota rejections say when to come back
+        if let AppError::QuotaExceeded { limit, used, resets_at } = &self {
+            body["error"]["limit"] = json!(limit);
+            body["error"]["used"] = json!(used);
+            body["error"]["resets_at"] = json!(resets_at.to_rfc3339());
+        }
+
+        match self.retry_after() {
+            Some(seconds) => (status, [(RETRY_AFTER, seconds.to_string())], Json(body)).into_response(),
+            None => (status, Json(body)).into_response(),
+        }
     }
 }
 
@@ -297,6 +353,11 @@ pub fn rate_limit_error(limit: u32, window: &str) -> AppError {
     ))
 }
 
+// Login lockout error helper
+pub fn login_locked_error(wait: std::time::Duration) -> AppError {
+    AppError::LoginLocked { retry_after_secs: wait.as_millis().div_ceil(1000) as u64 }
+}
+
 // Service unavailable error helper
 pub fn service_unavailable_error(service: &str) -> AppError {
     AppError::ServiceUnavailable(format!("{} service is currently unavailable", service))
@@ -1,18 +1,405 @@
-use reqwest::blocking::Client;
-use std::fs::File;
-use std::io::copy;
+// Breach dump downloads
+// The response is streamed into `<save_as>.part` so a dropped connection can be
+// resumed with an HTTP Range request instead of starting over. Failed attempts
+// are retried with exponential backoff, the finished file can be checked
+// against a published SHA-256 digest, and .gz / .zip archives are unpacked
+// before the result is moved into place.
 
-pub fn download_dump(url: &str, save_as: &str) -> Result<(), Box<dyn std::error::Error>> {
+use flate2::read::MultiGzDecoder;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+const PROGRESS_WIDTH: usize = 30;
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    // Attempts after the first one
+    pub retries: u32,
+    // Delay before the first retry, doubled after each failure
+    pub backoff: Duration,
+    // Connect timeout, and the longest the server may go quiet mid-download
+    pub timeout: Duration,
+    // Expected hex digest of the downloaded file (before decompression)
+    pub sha256: Option<String>,
+    pub decompress: bool,
+    pub show_progress: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff: Duration::from_secs(2),
+            timeout: Duration::from_secs(30),
+            sha256: None,
+            decompress: true,
+            show_progress: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum AttemptError {
+    // Network errors, timeouts, 5xx and 429: worth another try
+    Retryable(String),
+    Fatal(String),
+}
+
+impl From<io::Error> for AttemptError {
+    fn from(e: io::Error) -> Self {
+        AttemptError::Retryable(format!("I/O error: {}", e))
+    }
+}
+
+pub fn download_dump(url: &str, save_as: &str, options: &DownloadOptions) -> Result<(), Box<dyn Error>> {
     println!("Downloading from: {}", url);
-    let response = Client::new().get(url).send()?;
 
-    // Fix the borrow-checker drama:
-    let bytes = response.bytes()?;               // Keep bytes alive here
-    let mut content = bytes.as_ref();            // Safe reference now
+    let expected = match &options.sha256 {
+        Some(digest) => Some(parse_digest(digest)?),
+        None => None,
+    };
+
+    let client = Client::builder()
+        .connect_timeout(options.timeout)
+        .timeout(options.timeout)
+        .build()?;
+
+    let part = part_path(save_as);
+    let mut attempt = 0;
+
+    loop {
+        match fetch_attempt(&client, url, &part, options.show_progress) {
+            Ok(()) => break,
+            Err(AttemptError::Fatal(e)) => return Err(e.into()),
+            Err(AttemptError::Retryable(e)) if attempt >= options.retries => {
+                return Err(format!("Download failed after {} attempts: {}", attempt + 1, e).into());
+            }
+            Err(AttemptError::Retryable(e)) => {
+                let delay = backoff_delay(options.backoff, attempt);
+                attempt += 1;
+                eprintln!(
+                    "⚠️  {} — retrying in {:.1}s (attempt {} of {})",
+                    e,
+                    delay.as_secs_f64(),
+                    attempt + 1,
+                    options.retries + 1,
+                );
+                thread::sleep(delay);
+            }
+        }
+    }
+
+    if let Some(expected) = expected {
+        let actual = sha256_file(&part)?;
+        if actual != expected {
+            // A corrupt partial file would otherwise be resumed forever
+            fs::remove_file(&part)?;
+            return Err(format!("SHA-256 mismatch: expected {}, got {}", expected, actual).into());
+        }
+        println!("🔒 SHA-256 verified");
+    }
+
+    let saved = if options.decompress {
+        unpack(&part, Path::new(save_as))?
+    } else {
+        fs::rename(&part, save_as)?;
+        Unpacked::File
+    };
+
+    match saved {
+        Unpacked::File => println!("✅ File saved as {}", save_as),
+        Unpacked::Directory(files) => println!("✅ Extracted {} files into {}", files, save_as),
+    }
+    Ok(())
+}
+
+fn part_path(save_as: &str) -> PathBuf {
+    PathBuf::from(format!("{}.part", save_as))
+}
+
+fn backoff_delay(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(1u32 << attempt.min(16)).min(MAX_BACKOFF)
+}
+
+fn parse_digest(digest: &str) -> Result<String, Box<dyn Error>> {
+    let digest = digest.trim().to_lowercase();
+    if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid SHA-256 digest: {:?}", digest).into());
+    }
+    Ok(digest)
+}
 
-    let mut file = File::create(save_as)?;
-    copy(&mut content, &mut file)?;
+// One request, resuming from whatever is already in the .part file
+fn fetch_attempt(client: &Client, url: &str, part: &Path, show_progress: bool) -> Result<(), AttemptError> {
+    let existing = fs::metadata(part).map(|m| m.len()).unwrap_or(0);
 
-    println!("✅ File saved as {}", save_as);
+    let mut request = client.get(url);
+    if existing > 0 {
+        request = request.header(RANGE, format!("bytes={}-", existing));
+    }
+
+    let response = request.send()
+        .map_err(|e| AttemptError::Retryable(format!("Request failed: {}", e)))?;
+    let status = response.status();
+
+    let (file, offset) = match status {
+        StatusCode::PARTIAL_CONTENT => {
+            if content_range_start(&response) != Some(existing) {
+                // The server answered a different range than we asked for
+                File::create(part)?;
+                return Err(AttemptError::Retryable("Server returned an unexpected range".to_string()));
+            }
+            (OpenOptions::new().append(true).open(part)?, existing)
+        }
+        StatusCode::RANGE_NOT_SATISFIABLE if existing > 0 => {
+            if content_range_total(&response) == Some(existing) {
+                return Ok(());
+            }
+            File::create(part)?;
+            return Err(AttemptError::Retryable("Partial file does not match the remote file".to_string()));
+        }
+        // Either a fresh download or a server that ignores Range
+        status if status.is_success() => (File::create(part)?, 0),
+        status if status.is_server_error()
+            || status == StatusCode::TOO_MANY_REQUESTS
+            || status == StatusCode::REQUEST_TIMEOUT => {
+            return Err(AttemptError::Retryable(format!("Server responded with {}", status)));
+        }
+        status => return Err(AttemptError::Fatal(format!("Server responded with {}", status))),
+    };
+
+    if offset > 0 {
+        eprintln!("↪️  Resuming at {:.1} MB", mb(offset));
+    }
+
+    let total = response.content_length().map(|len| len + offset);
+    let mut progress = DownloadProgress::new(show_progress, offset, total);
+    let written = stream_body(response, file, offset, &mut progress);
+    progress.done();
+
+    let written = written?;
+    if let Some(total) = total {
+        if written < total {
+            return Err(AttemptError::Retryable(format!(
+                "Connection closed after {:.1} of {:.1} MB", mb(written), mb(total),
+            )));
+        }
+    }
     Ok(())
 }
+
+fn stream_body(
+    mut response: Response,
+    file: File,
+    offset: u64,
+    progress: &mut DownloadProgress,
+) -> Result<u64, AttemptError> {
+    let mut writer = BufWriter::new(file);
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut written = offset;
+
+    loop {
+        let read = match response.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                // Keep what we have so the next attempt resumes from here
+                writer.flush()?;
+                return Err(AttemptError::Retryable(format!("Download interrupted: {}", e)));
+            }
+        };
+        writer.write_all(&buffer[..read])?;
+        written += read as u64;
+        progress.update(written);
+    }
+
+    writer.flush()?;
+    Ok(written)
+}
+
+// "bytes 100-999/1000" -> 100
+fn content_range_start(response: &Response) -> Option<u64> {
+    let range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+// "bytes */1000" -> 1000
+fn content_range_total(response: &Response) -> Option<u64> {
+    let range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (_, total) = range.rsplit_once('/')?;
+    total.trim().parse().ok()
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+enum Unpacked {
+    File,
+    Directory(usize),
+}
+
+// Archives are recognised by their magic bytes rather than the URL, since
+// dump mirrors rarely name files consistently
+fn unpack(part: &Path, save_as: &Path) -> Result<Unpacked, Box<dyn Error>> {
+    let mut magic = [0u8; 4];
+    let read = File::open(part)?.read(&mut magic)?;
+    let magic = &magic[..read];
+
+    let unpacked = if magic.starts_with(&[0x1f, 0x8b]) {
+        println!("📦 Decompressing gzip archive");
+        let mut decoder = MultiGzDecoder::new(File::open(part)?);
+        write_atomically(save_as, |out| io::copy(&mut decoder, out).map(|_| ()))?;
+        Unpacked::File
+    } else if magic.starts_with(b"PK\x03\x04") {
+        println!("📦 Extracting zip archive");
+        extract_zip(part, save_as)?
+    } else {
+        fs::rename(part, save_as)?;
+        return Ok(Unpacked::File);
+    };
+
+    fs::remove_file(part)?;
+    Ok(unpacked)
+}
+
+// A single-file archive becomes `save_as`; anything larger becomes a directory
+fn extract_zip(part: &Path, save_as: &Path) -> Result<Unpacked, Box<dyn Error>> {
+    let mut archive = zip::ZipArchive::new(File::open(part)?)?;
+    let files: Vec<usize> = (0..archive.len())
+        .filter(|&i| archive.by_index(i).is_ok_and(|entry| entry.is_file()))
+        .collect();
+
+    if let [index] = files[..] {
+        let mut entry = archive.by_index(index)?;
+        write_atomically(save_as, |out| io::copy(&mut entry, out).map(|_| ()))?;
+        return Ok(Unpacked::File);
+    }
+
+    fs::create_dir_all(save_as)?;
+    for &index in &files {
+        let mut entry = archive.by_index(index)?;
+        // Reject entries like "../../etc/passwd"
+        let Some(name) = entry.enclosed_name() else {
+            eprintln!("⚠️  Skipping unsafe archive entry {:?}", entry.name());
+            continue;
+        };
+        let target = save_as.join(name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut BufWriter::new(File::create(&target)?))?;
+    }
+    Ok(Unpacked::Directory(files.len()))
+}
+
+// Write through a temp file so an interrupted extraction never leaves a
+// truncated file under the final name
+fn write_atomically<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+{
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let mut out = BufWriter::new(File::create(&temp)?);
+    let result = write(&mut out).and_then(|_| out.flush());
+    drop(out);
+
+    match result {
+        Ok(()) => fs::rename(&temp, path),
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+fn mb(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+struct DownloadProgress {
+    enabled: bool,
+    resumed_from: u64,
+    total: Option<u64>,
+    started: Instant,
+    last_report: Option<Instant>,
+    current: u64,
+}
+
+impl DownloadProgress {
+    fn new(enabled: bool, resumed_from: u64, total: Option<u64>) -> Self {
+        Self {
+            enabled,
+            resumed_from,
+            total,
+            started: Instant::now(),
+            last_report: None,
+            current: resumed_from,
+        }
+    }
+
+    fn update(&mut self, bytes: u64) {
+        self.current = bytes;
+        if !self.enabled || self.last_report.is_some_and(|last| last.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+        self.last_report = Some(Instant::now());
+        self.render();
+    }
+
+    fn render(&self) {
+        let secs = self.started.elapsed().as_secs_f64().max(0.001);
+        let rate = mb(self.current - self.resumed_from) / secs;
+
+        match self.total.filter(|total| *total > 0) {
+            Some(total) => {
+                let fraction = (self.current as f64 / total as f64).min(1.0);
+                let filled = (fraction * PROGRESS_WIDTH as f64) as usize;
+                eprint!(
+                    "\r⬇️  [{}{}] {:5.1}% {:.1}/{:.1} MB ({:.1} MB/s)",
+                    "#".repeat(filled),
+                    ".".repeat(PROGRESS_WIDTH - filled),
+                    fraction * 100.0,
+                    mb(self.current),
+                    mb(total),
+                    rate,
+                );
+            }
+            None => eprint!("\r⬇️  {:.1} MB ({:.1} MB/s)", mb(self.current), rate),
+        }
+    }
+
+    fn done(&self) {
+        if self.enabled && self.last_report.is_some() {
+            self.render();
+            eprintln!();
+        }
+    }
+}
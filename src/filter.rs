@@ -1,28 +1,12 @@
-use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io;
 use serde_json::Value;
+use crate::dump_formats::InputFormat;
+use crate::dump_stream;
+use crate::filter_rules::FilterRules;
 // use colored::*; // temporarily commented out
 
-pub fn run_filter(input: &str, output: &str) -> io::Result<()> {
-    let mut file = File::open(input)?;
-    let mut data = String::new();
-    file.read_to_string(&mut data)?;
-
-    let mut json_data: Value = serde_json::from_str(&data)?;
-
-    if let Some(entries) = json_data.get_mut("result").and_then(|r| r.as_array_mut()) {
-        entries.retain(|entry| {
-            if let Some(email) = entry.get("email").and_then(|e| e.as_str()) {
-                !email.contains("dan@hotmail.com")
-            } else {
-                true
-            }
-        });
-    }
-
-    let cleaned_data = serde_json::to_string_pretty(&json_data)?;
-    let mut output_file = File::create(output)?;
-    output_file.write_all(cleaned_data.as_bytes())?;
+pub fn run_filter(input: &str, output: &str, format: InputFormat, rules: &FilterRules, show_progress: bool) -> io::Result<()> {
+    dump_stream::process_dump(input, output, format, show_progress, None, |entry: &mut Value| rules.keeps(entry))?;
 
     println!("Filtered JSON saved to {}", output);
 
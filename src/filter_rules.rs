@@ -0,0 +1,477 @@
+// Rule-based breach record filtering
+// Rules are read from a TOML or JSON file (picked by extension) and evaluated
+// top to bottom against each record; the first rule whose condition matches
+// decides whether the record is kept. Unmatched records get `default`.
+//
+//   default = "include"
+//
+//   [[rules]]
+//   action = "exclude"
+//   when = { email = ["dan@hotmail.com", "mcrib96@gmail.com"] }
+//
+//   [[rules]]
+//   action = "include"
+//   when = { all = [
+//       { domain = "example.com" },
+//       { breach_date = { from = "2020-01-01", to = "2022-12-31" } },
+//       { not = { has_field = "password" } },
+//   ] }
+//
+// Conditions: email (whole address), email_contains (substring), domain (also
+// matches subdomains), source, regex ({ field, pattern }), breach_date
+// ({ from, to }, inclusive), has_field (dotted path), and the combinators all,
+// any, not. Email matching ignores case.
+//
+// Note: `guardr basic` used to drop any record whose email merely contained
+// dan@hotmail.com (so jordan@hotmail.com went too), while `guardr advanced`
+// compared whole addresses. Both now share these rules, and `email` compares
+// whole addresses; use `email_contains` to get the old substring behavior.
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    #[default]
+    Include,
+    Exclude,
+}
+
+// Accept both `email = "a@b.c"` and `email = ["a@b.c", ...]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn lowercased(&self) -> HashSet<String> {
+        match self {
+            OneOrMany::One(value) => HashSet::from([value.trim().to_lowercase()]),
+            OneOrMany::Many(values) => values.iter().map(|v| v.trim().to_lowercase()).collect(),
+        }
+    }
+}
+
+fn default_regex_field() -> String {
+    "email".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Condition {
+    Email(OneOrMany),
+    EmailContains(OneOrMany),
+    Domain(OneOrMany),
+    Source(OneOrMany),
+    Regex {
+        #[serde(default = "default_regex_field")]
+        field: String,
+        pattern: String,
+    },
+    BreachDate {
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+    HasField(String),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub action: RuleAction,
+    pub when: Condition,
+}
+
+// Serialized form of a rules file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleSet {
+    #[serde(default)]
+    pub default: RuleAction,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+pub enum RuleError {
+    Io(std::io::Error),
+    Parse(String),
+    InvalidRegex { pattern: String, error: regex::Error },
+    InvalidDateRange { from: NaiveDate, to: NaiveDate },
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Io(e) => write!(f, "Could not read filter rules: {}", e),
+            RuleError::Parse(e) => write!(f, "Invalid filter rules: {}", e),
+            RuleError::InvalidRegex { pattern, error } => {
+                write!(f, "Invalid regex {:?} in filter rules: {}", pattern, error)
+            }
+            RuleError::InvalidDateRange { from, to } => {
+                write!(f, "Invalid breach_date range in filter rules: {} is after {}", from, to)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+impl From<std::io::Error> for RuleError {
+    fn from(e: std::io::Error) -> Self {
+        RuleError::Io(e)
+    }
+}
+
+impl RuleSet {
+    pub fn from_toml(text: &str) -> Result<Self, RuleError> {
+        toml::from_str(text).map_err(|e| RuleError::Parse(e.to_string()))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, RuleError> {
+        serde_json::from_str(text).map_err(|e| RuleError::Parse(e.to_string()))
+    }
+
+    pub fn from_value(value: Value) -> Result<Self, RuleError> {
+        serde_json::from_value(value).map_err(|e| RuleError::Parse(e.to_string()))
+    }
+
+    // `.json` files are parsed as JSON, everything else as TOML
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RuleError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::from_json(&text),
+            _ => Self::from_toml(&text),
+        }
+    }
+
+    pub fn compile(&self) -> Result<FilterRules, RuleError> {
+        let rules = self.rules.iter()
+            .map(|rule| Ok((rule.action, Matcher::compile(&rule.when)?)))
+            .collect::<Result<Vec<_>, RuleError>>()?;
+
+        Ok(FilterRules {
+            default: self.default,
+            rules,
+        })
+    }
+}
+
+// Compiled form of a condition: lowercased sets and built regexes
+#[derive(Debug, Clone)]
+enum Matcher {
+    Email(HashSet<String>),
+    EmailContains(HashSet<String>),
+    Domain(HashSet<String>),
+    Source(HashSet<String>),
+    Regex { field: String, regex: Regex },
+    BreachDate { from: Option<NaiveDate>, to: Option<NaiveDate> },
+    HasField(String),
+    All(Vec<Matcher>),
+    Any(Vec<Matcher>),
+    Not(Box<Matcher>),
+}
+
+impl Matcher {
+    fn compile(condition: &Condition) -> Result<Self, RuleError> {
+        Ok(match condition {
+            Condition::Email(emails) => Matcher::Email(emails.lowercased()),
+            Condition::EmailContains(parts) => Matcher::EmailContains(parts.lowercased()),
+            Condition::Domain(domains) => Matcher::Domain(
+                domains.lowercased().into_iter()
+                    .map(|d| d.trim_start_matches('@').to_string())
+                    .collect(),
+            ),
+            Condition::Source(sources) => Matcher::Source(sources.lowercased()),
+            Condition::Regex { field, pattern } => Matcher::Regex {
+                field: field.clone(),
+                regex: Regex::new(pattern).map_err(|error| RuleError::InvalidRegex {
+                    pattern: pattern.clone(),
+                    error,
+                })?,
+            },
+            Condition::BreachDate { from, to } => {
+                if let (Some(from), Some(to)) = (from, to) {
+                    if from > to {
+                        return Err(RuleError::InvalidDateRange { from: *from, to: *to });
+                    }
+                }
+                Matcher::BreachDate { from: *from, to: *to }
+            }
+            Condition::HasField(path) => Matcher::HasField(path.clone()),
+            Condition::All(conditions) => Matcher::All(
+                conditions.iter().map(Matcher::compile).collect::<Result<_, _>>()?,
+            ),
+            Condition::Any(conditions) => Matcher::Any(
+                conditions.iter().map(Matcher::compile).collect::<Result<_, _>>()?,
+            ),
+            Condition::Not(condition) => Matcher::Not(Box::new(Matcher::compile(condition)?)),
+        })
+    }
+
+    fn matches(&self, record: &Value) -> bool {
+        match self {
+            Matcher::Email(emails) => record_email(record)
+                .is_some_and(|email| emails.contains(&email)),
+            Matcher::EmailContains(parts) => record_email(record)
+                .is_some_and(|email| parts.iter().any(|part| email.contains(part.as_str()))),
+            Matcher::Domain(domains) => record_email(record)
+                .and_then(|email| email.rsplit_once('@').map(|(_, domain)| domain.to_string()))
+                .is_some_and(|domain| {
+                    domains.iter().any(|d| domain == *d || domain.ends_with(&format!(".{}", d)))
+                }),
+            Matcher::Source(sources) => record_source(record)
+                .is_some_and(|source| sources.contains(&source.to_lowercase())),
+            Matcher::Regex { field, regex } => lookup(record, field)
+                .is_some_and(|value| match value {
+                    Value::String(s) => regex.is_match(s),
+                    Value::Null => false,
+                    other => regex.is_match(&other.to_string()),
+                }),
+            Matcher::BreachDate { from, to } => record_breach_date(record)
+                .is_some_and(|date| {
+                    from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+                }),
+            Matcher::HasField(path) => lookup(record, path)
+                .is_some_and(|value| !value.is_null()),
+            Matcher::All(matchers) => matchers.iter().all(|m| m.matches(record)),
+            Matcher::Any(matchers) => matchers.iter().any(|m| m.matches(record)),
+            Matcher::Not(matcher) => !matcher.matches(record),
+        }
+    }
+}
+
+// Dotted path lookup, e.g. "source.breach_date"
+fn lookup<'a>(record: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(record, |value, key| value.get(key))
+}
+
+fn record_email(record: &Value) -> Option<String> {
+    record.get("email")
+        .and_then(|e| e.as_str())
+        .map(|e| e.trim().to_lowercase())
+}
+
+// Dumps carry the source either as a plain string or as { "name": ..., "breach_date": ... }
+fn record_source(record: &Value) -> Option<&str> {
+    match record.get("source")? {
+        Value::String(name) => Some(name),
+        source => source.get("name").and_then(|n| n.as_str()),
+    }
+}
+
+fn record_breach_date(record: &Value) -> Option<NaiveDate> {
+    let date = lookup(record, "source.breach_date")
+        .or_else(|| record.get("breach_date"))?
+        .as_str()?;
+
+    // Accept full timestamps as well as bare dates
+    let date = date.get(..10).unwrap_or(date);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FilterRules {
+    default: RuleAction,
+    rules: Vec<(RuleAction, Matcher)>,
+}
+
+impl FilterRules {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RuleError> {
+        RuleSet::load(path)?.compile()
+    }
+
+    // A missing file means "keep everything"; a malformed one is still an error
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<Self, RuleError> {
+        match Self::load(path) {
+            Err(RuleError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            result => result,
+        }
+    }
+
+    pub fn action_for(&self, record: &Value) -> RuleAction {
+        self.rules.iter()
+            .find(|(_, matcher)| matcher.matches(record))
+            .map(|(action, _)| *action)
+            .unwrap_or(self.default)
+    }
+
+    pub fn keeps(&self, record: &Value) -> bool {
+        self.action_for(record) == RuleAction::Include
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules(toml: &str) -> FilterRules {
+        RuleSet::from_toml(toml).unwrap().compile().unwrap()
+    }
+
+    fn excluding(when: &str) -> FilterRules {
+        rules(&format!("[[rules]]\naction = \"exclude\"\nwhen = {}\n", when))
+    }
+
+    fn record(email: &str) -> Value {
+        json!({ "email": email })
+    }
+
+    #[test]
+    fn email_compares_whole_addresses_and_email_contains_substrings() {
+        let exact = excluding(r#"{ email = "dan@hotmail.com" }"#);
+        assert!(!exact.keeps(&record("Dan@Hotmail.com ")));
+        assert!(exact.keeps(&record("jordan@hotmail.com")));
+
+        let substring = excluding(r#"{ email_contains = "dan@hotmail.com" }"#);
+        assert!(!substring.keeps(&record("dan@hotmail.com")));
+        assert!(!substring.keeps(&record("Jordan@hotmail.com")));
+        assert!(substring.keeps(&record("dan@gmail.com")));
+    }
+
+    #[test]
+    fn domain_matches_subdomains_but_not_lookalikes() {
+        let rules = excluding(r#"{ domain = ["@Example.com"] }"#);
+        assert!(!rules.keeps(&record("a@example.com")));
+        assert!(!rules.keeps(&record("a@mail.example.com")));
+        assert!(rules.keeps(&record("a@badexample.com")));
+        assert!(rules.keeps(&record("a@example.com.evil.net")));
+        assert!(rules.keeps(&json!({ "password": "no email" })));
+    }
+
+    #[test]
+    fn combinators_nest() {
+        let rules = rules(r#"
+            default = "exclude"
+
+            [[rules]]
+            action = "include"
+            when = { all = [
+                { any = [{ domain = "example.com" }, { source = "BigLeak" }] },
+                { not = { has_field = "password" } },
+            ] }
+        "#);
+
+        assert!(rules.keeps(&record("a@example.com")));
+        assert!(rules.keeps(&json!({ "email": "a@other.org", "source": { "name": "bigleak" } })));
+        assert!(!rules.keeps(&json!({ "email": "a@example.com", "password": "hunter2" })));
+        // A null password counts as absent
+        assert!(rules.keeps(&json!({ "email": "a@example.com", "password": null })));
+        assert!(!rules.keeps(&record("a@other.org")));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = rules(r#"
+            [[rules]]
+            action = "include"
+            when = { email = "keep@example.com" }
+
+            [[rules]]
+            action = "exclude"
+            when = { domain = "example.com" }
+        "#);
+
+        assert_eq!(rules.action_for(&record("keep@example.com")), RuleAction::Include);
+        assert_eq!(rules.action_for(&record("drop@example.com")), RuleAction::Exclude);
+        assert_eq!(rules.action_for(&record("other@test.org")), RuleAction::Include);
+    }
+
+    #[test]
+    fn breach_date_range_is_inclusive() {
+        let rules = excluding(r#"{ breach_date = { from = "2020-01-01", to = "2022-12-31" } }"#);
+        let dated = |date: &str| json!({ "email": "a@b.c", "source": { "breach_date": date } });
+
+        assert!(!rules.keeps(&dated("2020-01-01")));
+        assert!(!rules.keeps(&dated("2022-12-31T23:59:59Z")));
+        assert!(rules.keeps(&dated("2019-12-31")));
+        assert!(rules.keeps(&dated("2023-01-01")));
+        assert!(!rules.keeps(&json!({ "email": "a@b.c", "breach_date": "2021-06-01" })));
+        // Undated records are never in range
+        assert!(rules.keeps(&record("a@b.c")));
+
+        let open_ended = excluding(r#"{ breach_date = { from = "2020-01-01" } }"#);
+        assert!(!open_ended.keeps(&dated("2030-01-01")));
+        assert!(open_ended.keeps(&dated("2019-01-01")));
+    }
+
+    #[test]
+    fn reversed_date_range_is_rejected() {
+        let set = RuleSet::from_toml(r#"
+            [[rules]]
+            action = "exclude"
+            when = { breach_date = { from = "2022-01-01", to = "2020-01-01" } }
+        "#).unwrap();
+        assert!(matches!(set.compile(), Err(RuleError::InvalidDateRange { .. })));
+    }
+
+    #[test]
+    fn has_field_follows_dotted_paths() {
+        let rules = excluding(r#"{ has_field = "source.breach_date" }"#);
+        assert!(!rules.keeps(&json!({ "source": { "breach_date": "2020-01-01" } })));
+        assert!(rules.keeps(&json!({ "source": "BigLeak" })));
+        assert!(rules.keeps(&json!({ "breach_date": "2020-01-01" })));
+    }
+
+    #[test]
+    fn regex_matches_any_field() {
+        let rules = excluding(r#"{ regex = { pattern = "^admin@" } }"#);
+        assert!(!rules.keeps(&record("admin@example.com")));
+        assert!(rules.keeps(&record("not-admin@example.com")));
+
+        let numeric = excluding(r#"{ regex = { field = "user.id", pattern = "^4\\d+$" } }"#);
+        assert!(!numeric.keeps(&json!({ "user": { "id": 42 } })));
+        assert!(numeric.keeps(&json!({ "user": { "id": 17 } })));
+        assert!(numeric.keeps(&json!({ "user": { "id": null } })));
+
+        let invalid = RuleSet::from_toml(r#"
+            [[rules]]
+            action = "exclude"
+            when = { regex = { pattern = "(" } }
+        "#).unwrap();
+        assert!(matches!(invalid.compile(), Err(RuleError::InvalidRegex { .. })));
+    }
+
+    #[test]
+    fn loads_toml_or_json_by_extension() {
+        let dir = std::env::temp_dir().join(format!("guardr-test-rules-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let toml_path = dir.join("rules.toml");
+        fs::write(&toml_path, "default = \"exclude\"\n[[rules]]\naction = \"include\"\nwhen = { domain = \"example.com\" }\n").unwrap();
+        let json_path = dir.join("rules.JSON");
+        fs::write(&json_path, r#"{ "default": "exclude", "rules": [
+            { "action": "include", "when": { "domain": "example.com" } }
+        ] }"#).unwrap();
+
+        for path in [&toml_path, &json_path] {
+            let rules = FilterRules::load(path).unwrap();
+            assert!(rules.keeps(&record("a@example.com")));
+            assert!(!rules.keeps(&record("a@other.org")));
+        }
+
+        // JSON in a .toml file is a parse error, not silently ignored
+        fs::write(&toml_path, fs::read(&json_path).unwrap()).unwrap();
+        assert!(matches!(FilterRules::load(&toml_path), Err(RuleError::Parse(_))));
+
+        // A missing file keeps everything; unknown condition names are rejected
+        let missing = FilterRules::load_or_default(dir.join("missing.toml")).unwrap();
+        assert!(missing.keeps(&record("a@other.org")));
+        assert!(RuleSet::from_toml("[[rules]]\naction = \"exclude\"\nwhen = { emails = \"a@b.c\" }\n").is_err());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
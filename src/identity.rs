@@ -0,0 +1,242 @@
+// Email identity normalization for breach matching
+// Many spellings reach the same mailbox: Gmail ignores dots and "+tags" and
+// also answers at googlemail.com, Outlook/Hotmail drop "+tags", domains can be
+// written in Unicode or punycode, and pasted addresses pick up invisible or
+// lookalike characters. Breach rows are hashed from the canonical form, so
+// `guardr ingest` and every lookup go through `normalize_email`. The rules that
+// fired are kept so reports can show both forms.
+
+use serde::Serialize;
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalizationRule {
+    // Surrounding or embedded whitespace and zero-width characters removed
+    Whitespace,
+    // Fullwidth and other compatibility characters folded (NFKC)
+    UnicodeCompatibility,
+    // Cyrillic/Greek lookalikes mixed into Latin text replaced
+    Confusables,
+    // Internationalized domain converted to punycode
+    Punycode,
+    // googlemail.com is the same mailbox as gmail.com
+    GoogleMailAlias,
+    // Gmail ignores dots in the local part
+    GmailDots,
+    // "+tag" suffix removed (Gmail, Outlook/Hotmail)
+    PlusTag,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NormalizedEmail {
+    pub original: String,
+    pub normalized: String,
+    pub rules: Vec<NormalizationRule>,
+}
+
+const GMAIL_DOMAIN: &str = "gmail.com";
+const GOOGLEMAIL_DOMAIN: &str = "googlemail.com";
+
+// Microsoft consumer mail, under .com and the country domains
+const OUTLOOK_NAMES: &[&str] = &["outlook", "hotmail", "live", "msn", "windowslive"];
+
+const INVISIBLE: &[char] = &['\u{00AD}', '\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}'];
+
+// Lowercase Cyrillic and Greek letters that render like Latin ones
+const CONFUSABLES: &[(char, char)] = &[
+    ('а', 'a'), ('е', 'e'), ('о', 'o'), ('р', 'p'), ('с', 'c'), ('у', 'y'), ('х', 'x'),
+    ('і', 'i'), ('ј', 'j'), ('ѕ', 's'), ('һ', 'h'), ('ԁ', 'd'), ('ԛ', 'q'), ('ԝ', 'w'),
+    ('ӏ', 'l'), ('к', 'k'), ('м', 'm'), ('т', 't'), ('в', 'b'),
+    ('α', 'a'), ('ο', 'o'), ('ν', 'v'), ('ι', 'i'), ('κ', 'k'), ('ρ', 'p'), ('υ', 'u'),
+    ('χ', 'x'), ('ε', 'e'), ('τ', 't'),
+];
+
+// Canonical form of an address, or None when it is not a usable address
+pub fn normalize_email(email: &str) -> Option<NormalizedEmail> {
+    let mut rules = Vec::new();
+
+    let visible: String = email.chars()
+        .filter(|c| !c.is_whitespace() && !INVISIBLE.contains(c))
+        .collect();
+    if visible != email {
+        rules.push(NormalizationRule::Whitespace);
+    }
+
+    let folded: String = visible.nfkc().collect();
+    if folded != visible {
+        rules.push(NormalizationRule::UnicodeCompatibility);
+    }
+
+    let lowered = folded.to_lowercase();
+    let (local, domain) = lowered.rsplit_once('@')?;
+    let domain = domain.trim_end_matches('.');
+
+    let mut local = unconfuse(local);
+    let mut domain = domain.split('.').map(unconfuse).collect::<Vec<_>>().join(".");
+    if format!("{}@{}", local, domain) != lowered.trim_end_matches('.') {
+        rules.push(NormalizationRule::Confusables);
+    }
+
+    if !domain.is_ascii() {
+        domain = idna::domain_to_ascii(&domain).ok()?;
+        rules.push(NormalizationRule::Punycode);
+    }
+
+    if domain == GOOGLEMAIL_DOMAIN {
+        domain = GMAIL_DOMAIN.to_string();
+        rules.push(NormalizationRule::GoogleMailAlias);
+    }
+
+    let strips_plus_tag = domain == GMAIL_DOMAIN || is_outlook_domain(&domain);
+    if strips_plus_tag {
+        if let Some((base, _tag)) = local.split_once('+') {
+            local = base.to_string();
+            rules.push(NormalizationRule::PlusTag);
+        }
+    }
+    if domain == GMAIL_DOMAIN && local.contains('.') {
+        local.retain(|c| c != '.');
+        rules.push(NormalizationRule::GmailDots);
+    }
+
+    let valid = !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.contains("..")
+        && !domain.contains('@');
+    if !valid {
+        return None;
+    }
+
+    Some(NormalizedEmail {
+        original: email.to_string(),
+        normalized: format!("{}@{}", local, domain),
+        rules,
+    })
+}
+
+// Like `normalize_email`, but input that is not an address is only trimmed
+// and lowercased, so every string has a form to hash and report
+pub fn canonical_email(email: &str) -> NormalizedEmail {
+    normalize_email(email).unwrap_or_else(|| NormalizedEmail {
+        original: email.to_string(),
+        normalized: email.trim().to_lowercase(),
+        rules: Vec::new(),
+    })
+}
+
+// outlook.com, hotmail.co.uk, live.com.au, live.fr, ...: .com or a two-letter
+// country code, optionally under co./com.
+fn is_outlook_domain(domain: &str) -> bool {
+    let Some((name, suffix)) = domain.split_once('.') else {
+        return false;
+    };
+    let is_country = |label: &str| label.len() == 2 && label.chars().all(|c| c.is_ascii_lowercase());
+    let known_suffix = match suffix.split_once('.') {
+        None => suffix == "com" || is_country(suffix),
+        Some((second_level, country)) => matches!(second_level, "co" | "com") && is_country(country),
+    };
+    OUTLOOK_NAMES.contains(&name) && known_suffix
+}
+
+// Lookalikes only count as such next to Latin letters; an all-Cyrillic label
+// is a real name and stays as it is
+fn unconfuse(part: &str) -> String {
+    let has_latin = part.chars().any(|c| c.is_ascii_alphabetic());
+    let has_confusable = part.chars().any(|c| CONFUSABLES.iter().any(|(from, _)| *from == c));
+    if !(has_latin && has_confusable) {
+        return part.to_string();
+    }
+
+    part.chars()
+        .map(|c| CONFUSABLES.iter().find(|(from, _)| *from == c).map_or(c, |(_, to)| *to))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(email: &str) -> String {
+        normalize_email(email).unwrap_or_else(|| panic!("{} did not normalize", email)).normalized
+    }
+
+    fn rules(email: &str) -> Vec<NormalizationRule> {
+        normalize_email(email).unwrap().rules
+    }
+
+    #[test]
+    fn gmail_ignores_dots_and_plus_tags() {
+        assert_eq!(normalized("J.Doe+dating@Gmail.com"), "jdoe@gmail.com");
+        assert_eq!(rules("j.doe+dating@gmail.com"), vec![NormalizationRule::PlusTag, NormalizationRule::GmailDots]);
+        // Other providers keep both
+        assert_eq!(normalized("j.doe+dating@example.com"), "j.doe+dating@example.com");
+    }
+
+    #[test]
+    fn googlemail_is_gmail() {
+        assert_eq!(normalized("j.doe@googlemail.com"), "jdoe@gmail.com");
+        assert!(rules("jdoe@googlemail.com").contains(&NormalizationRule::GoogleMailAlias));
+    }
+
+    #[test]
+    fn outlook_drops_plus_tags_but_keeps_dots() {
+        assert_eq!(normalized("j.doe+news@outlook.com"), "j.doe@outlook.com");
+        assert_eq!(normalized("j.doe+news@hotmail.co.uk"), "j.doe@hotmail.co.uk");
+        assert_eq!(normalized("j.doe+news@live.com.au"), "j.doe@live.com.au");
+        assert_eq!(normalized("j.doe+news@live.fr"), "j.doe@live.fr");
+    }
+
+    #[test]
+    fn only_microsoft_mail_domains_count_as_outlook() {
+        for domain in ["outlook.com", "hotmail.co.uk", "live.fr", "msn.com", "live.com.au"] {
+            assert!(is_outlook_domain(domain), "{}", domain);
+        }
+        for domain in ["live.net", "live.org", "hotmail.example.com", "live.info", "example.com"] {
+            assert!(!is_outlook_domain(domain), "{}", domain);
+        }
+        assert_eq!(normalized("a+b@live.net"), "a+b@live.net");
+    }
+
+    #[test]
+    fn internationalized_domains_become_punycode() {
+        assert_eq!(normalized("user@bücher.de"), "user@xn--bcher-kva.de");
+        assert!(rules("user@bücher.de").contains(&NormalizationRule::Punycode));
+        // Already punycode is left alone
+        assert!(rules("user@xn--bcher-kva.de").is_empty());
+    }
+
+    #[test]
+    fn compatibility_characters_and_invisibles_are_folded() {
+        assert_eq!(normalized("ｊｄｏｅ＠ｅｘａｍｐｌｅ．ｃｏｍ"), "jdoe@example.com");
+        assert!(rules("ｊｄｏｅ@example.com").contains(&NormalizationRule::UnicodeCompatibility));
+
+        assert_eq!(normalized(" jdoe\u{200B}@example.com\n"), "jdoe@example.com");
+        assert_eq!(rules(" jdoe\u{200B}@example.com"), vec![NormalizationRule::Whitespace]);
+    }
+
+    #[test]
+    fn lookalikes_mixed_into_latin_are_replaced() {
+        // Cyrillic а and о in an otherwise Latin address
+        assert_eq!(normalized("j\u{0430}ne@ex\u{0430}mple.c\u{043E}m"), "jane@example.com");
+        assert!(rules("j\u{0430}ne@example.com").contains(&NormalizationRule::Confusables));
+        // Cyrillic н is N-shaped, not an h
+        assert_eq!(normalized("jo\u{043D}n@example.com"), "jo\u{043D}n@example.com");
+    }
+
+    #[test]
+    fn all_cyrillic_local_parts_are_names() {
+        assert_eq!(normalized("анна@example.com"), "анна@example.com");
+        assert!(rules("анна@example.com").is_empty());
+    }
+
+    #[test]
+    fn non_addresses_are_rejected() {
+        for input in ["", "no-at-sign", "@example.com", "user@localhost", "user@exa..mple.com"] {
+            assert!(normalize_email(input).is_none(), "{}", input);
+        }
+        // but still have a form to hash
+        assert_eq!(canonical_email(" Not An Address ").normalized, "not an address");
+    }
+}
@@ -0,0 +1,282 @@
+// Bulk ingestion of breach dumps into the breach_data table
+// Records are normalized, hashed with the same functions the API queries with,
+// and inserted in batches, one transaction per batch. Re-ingesting the same dump
+// is safe: rows already present are counted as duplicates and left alone.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::breach_hash::BreachHasher;
+use crate::data_classes;
+use crate::identity;
+use crate::database::{Database, NewBreachData};
+use crate::dump_formats::InputFormat;
+use crate::dump_stream;
+
+// Batches buffered between the file reader and the database writer
+const PIPELINE_DEPTH: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct IngestOptions {
+    pub source: String,
+    pub breach_date: NaiveDate,
+    pub format: InputFormat,
+    pub batch_size: usize,
+    // What the breach exposed beyond the record fields, e.g. "Dating site membership"
+    pub data_classes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IngestSummary {
+    pub source: String,
+    pub records_read: u64,
+    pub inserted: u64,
+    pub duplicates: u64,
+    pub skipped: u64,
+    pub elapsed_ms: u64,
+}
+
+// Turn a dump record into a row; records without a usable email are skipped
+pub fn normalize_record(record: &Value, hasher: &BreachHasher, options: &IngestOptions) -> Option<NewBreachData> {
+    let email = identity::normalize_email(record.get("email")?.as_str()?)?;
+    let password = record.get("password")
+        .and_then(|p| p.as_str())
+        .filter(|p| !p.is_empty());
+
+    let mut data_types = data_classes::record_data_classes(record);
+    for class in &options.data_classes {
+        if !data_types.iter().any(|t| t.eq_ignore_ascii_case(class)) {
+            data_types.push(class.clone());
+        }
+    }
+    let severity = data_classes::breach_severity(&data_types, None).to_string();
+
+    Some(NewBreachData {
+        email_hash: hasher.hash_email(&email.normalized),
+        password_hash: password.map(|p| hasher.hash_password(p)),
+        source_name: options.source.clone(),
+        breach_date: Utc.from_utc_datetime(&options.breach_date.and_hms_opt(0, 0, 0)?),
+        data_types,
+        severity,
+    })
+}
+
+fn validate(options: &IngestOptions, hasher: &BreachHasher) -> Result<()> {
+    // Unkeyed digests of emails and passwords are trivially reversed
+    if hasher.current_pepper().is_none() {
+        return Err(anyhow!("No hash peppers configured (security.hash_peppers); refusing to store unkeyed digests"));
+    }
+    if options.source.trim().is_empty() {
+        return Err(anyhow!("A source name is required"));
+    }
+    if options.batch_size == 0 {
+        return Err(anyhow!("Batch size must be at least 1"));
+    }
+    Ok(())
+}
+
+// Stream a dump file from disk. Parsing runs on a blocking thread and hands
+// batches to the async writer, so memory stays bounded for any dump size.
+pub async fn ingest_file(
+    db: &Database,
+    hasher: &BreachHasher,
+    path: &str,
+    options: &IngestOptions,
+) -> Result<IngestSummary> {
+    validate(options, hasher)?;
+    let started = Instant::now();
+    let (sender, mut batches) = mpsc::channel::<Vec<NewBreachData>>(PIPELINE_DEPTH);
+
+    let reader = {
+        let path = path.to_string();
+        let options = options.clone();
+        let hasher = hasher.clone();
+
+        tokio::task::spawn_blocking(move || -> io::Result<(u64, u64)> {
+            let mut read = 0u64;
+            let mut skipped = 0u64;
+            let mut batch = Vec::with_capacity(options.batch_size);
+
+            let malformed = dump_stream::for_each_record(&path, options.format, |record| {
+                read += 1;
+                match normalize_record(&record, &hasher, &options) {
+                    Some(row) => batch.push(row),
+                    None => skipped += 1,
+                }
+
+                if batch.len() >= options.batch_size {
+                    let full = std::mem::replace(&mut batch, Vec::with_capacity(options.batch_size));
+                    sender.blocking_send(full)
+                        .map_err(|_| io::Error::other("ingest writer stopped"))?;
+                }
+                Ok(())
+            })?;
+
+            if !batch.is_empty() {
+                sender.blocking_send(batch)
+                    .map_err(|_| io::Error::other("ingest writer stopped"))?;
+            }
+
+            Ok((read + malformed, skipped + malformed))
+        })
+    };
+
+    let mut summary = IngestSummary {
+        source: options.source.clone(),
+        ..Default::default()
+    };
+
+    while let Some(batch) = batches.recv().await {
+        let (inserted, duplicates) = db.store_breach_batch(&batch).await?;
+        summary.inserted += inserted;
+        summary.duplicates += duplicates;
+    }
+
+    let (read, skipped) = reader.await??;
+    summary.records_read = read;
+    summary.skipped = skipped;
+    summary.elapsed_ms = started.elapsed().as_millis() as u64;
+
+    Ok(summary)
+}
+
+// Ingest records that are already in memory (e.g. posted to the admin endpoint)
+pub async fn ingest_records(
+    db: &Database,
+    hasher: &BreachHasher,
+    records: &[Value],
+    options: &IngestOptions,
+) -> Result<IngestSummary> {
+    validate(options, hasher)?;
+    let started = Instant::now();
+
+    let rows: Vec<NewBreachData> = records.iter()
+        .filter_map(|record| normalize_record(record, hasher, options))
+        .collect();
+
+    let mut summary = IngestSummary {
+        source: options.source.clone(),
+        records_read: records.len() as u64,
+        skipped: (records.len() - rows.len()) as u64,
+        ..Default::default()
+    };
+
+    for batch in rows.chunks(options.batch_size) {
+        let (inserted, duplicates) = db.store_breach_batch(batch).await?;
+        summary.inserted += inserted;
+        summary.duplicates += duplicates;
+    }
+
+    summary.elapsed_ms = started.elapsed().as_millis() as u64;
+    Ok(summary)
+}
+
+// Dump files ingested through the admin endpoint run as background jobs: a big
+// dump outlives any request timeout, and a handler cut off mid-way would leave
+// it half ingested. Jobs are only kept in memory, so a restart forgets them;
+// re-running the same dump picks up where it stopped.
+#[derive(Default)]
+pub struct IngestJobs {
+    jobs: Mutex<HashMap<Uuid, IngestJob>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestJob {
+    pub id: Uuid,
+    pub source: String,
+    pub file: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub status: IngestJobStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum IngestJobStatus {
+    Running,
+    Completed { summary: IngestSummary },
+    Failed { error: String },
+}
+
+impl IngestJobs {
+    // Start ingesting `path`; returns the job as it stands now (running).
+    // Bad options are refused here rather than left for the job to report.
+    pub fn start(
+        self: &Arc<Self>,
+        db: Arc<Database>,
+        hasher: BreachHasher,
+        path: String,
+        options: IngestOptions,
+    ) -> Result<IngestJob> {
+        validate(&options, &hasher)?;
+        let job = IngestJob {
+            id: Uuid::new_v4(),
+            source: options.source.clone(),
+            file: path.clone(),
+            started_at: Utc::now(),
+            finished_at: None,
+            status: IngestJobStatus::Running,
+        };
+        self.jobs.lock().unwrap().insert(job.id, job.clone());
+
+        let jobs = self.clone();
+        let id = job.id;
+        tokio::spawn(async move {
+            let status = match ingest_file(&db, &hasher, &path, &options).await {
+                Ok(summary) => {
+                    info!(
+                        "Ingest job {} finished: {} ({} inserted, {} duplicates, {} skipped)",
+                        id, summary.source, summary.inserted, summary.duplicates, summary.skipped
+                    );
+                    IngestJobStatus::Completed { summary }
+                }
+                Err(e) => {
+                    warn!("Ingest job {} failed: {}", id, e);
+                    IngestJobStatus::Failed { error: e.to_string() }
+                }
+            };
+            if let Some(job) = jobs.jobs.lock().unwrap().get_mut(&id) {
+                job.finished_at = Some(Utc::now());
+                job.status = status;
+            }
+        });
+
+        Ok(job)
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<IngestJob> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+
+    #[test]
+    fn refuses_to_ingest_without_a_pepper() {
+        let mut security = Settings::default().security;
+        security.hash_peppers.clear();
+        let options = IngestOptions {
+            source: "NoPepperLeak".to_string(),
+            breach_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            format: InputFormat::Json,
+            batch_size: 100,
+            data_classes: Vec::new(),
+        };
+
+        let err = validate(&options, &BreachHasher::from_config(&security)).unwrap_err();
+        assert!(err.to_string().contains("hash_peppers"));
+    }
+}
@@ -0,0 +1,22 @@
+// Breach data handling shared by the guardr CLI (src/main.rs) and the
+// guardr-api server (src/api_server.rs): dump parsing and filtering, keyed
+// breach digests, storage, risk scoring and the OSINT providers.
+
+pub mod breach_hash;
+pub mod config;
+pub mod data_classes;
+pub mod database;
+pub mod dump_formats;
+pub mod dump_stream;
+pub mod fetch_dumps;
+pub mod filter;
+pub mod filter_rules;
+pub mod filtermain;
+pub mod identity;
+pub mod ingest;
+pub mod llm;
+pub mod osint;
+pub mod password;
+pub mod rehash;
+pub mod report_crypto;
+pub mod risk_score;
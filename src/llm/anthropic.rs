@@ -0,0 +1,64 @@
+// Anthropic Messages API adapter
+// Docs: https://docs.anthropic.com/en/api/messages
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{LlmAnalyzer, SYSTEM_PROMPT};
+
+pub const DEFAULT_MODEL: &str = "claude-3-5-haiku-latest";
+const MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+const API_VERSION: &str = "2023-06-01";
+const MAX_TOKENS: u32 = 2048;
+
+pub struct AnthropicAnalyzer {
+    client: Client,
+    api_key: String,
+    model: String,
+}
+
+impl AnthropicAnalyzer {
+    pub fn new(api_key: &str, model: &str, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder().timeout(timeout).build()?,
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl LlmAnalyzer for AnthropicAnalyzer {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        let body = json!({
+            "model": self.model,
+            "max_tokens": MAX_TOKENS,
+            "system": SYSTEM_PROMPT,
+            "messages": [{ "role": "user", "content": prompt }]
+        });
+
+        let response = self.client.post(MESSAGES_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Anthropic API error: {}", response.status()));
+        }
+
+        let body: Value = response.json().await?;
+        body["content"][0]["text"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Anthropic returned no text"))
+    }
+}
@@ -0,0 +1,34 @@
+// Deterministic in-process analyzer for tests and offline development
+// Returns a canned response without any network access.
+
+use anyhow::Result;
+use axum::async_trait;
+
+use super::LlmAnalyzer;
+
+pub struct FakeAnalyzer {
+    response: String,
+}
+
+impl FakeAnalyzer {
+    pub fn new(response: impl Into<String>) -> Self {
+        Self { response: response.into() }
+    }
+}
+
+impl Default for FakeAnalyzer {
+    fn default() -> Self {
+        Self::new("{}")
+    }
+}
+
+#[async_trait]
+impl LlmAnalyzer for FakeAnalyzer {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    async fn complete(&self, _prompt: &str) -> Result<String> {
+        Ok(self.response.clone())
+    }
+}
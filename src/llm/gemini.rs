@@ -0,0 +1,60 @@
+// Google Gemini adapter
+// Docs: https://ai.google.dev/api/generate-content
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{LlmAnalyzer, SYSTEM_PROMPT};
+
+pub const DEFAULT_MODEL: &str = "gemini-1.5-flash";
+const BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+
+pub struct GeminiAnalyzer {
+    client: Client,
+    api_key: String,
+    model: String,
+}
+
+impl GeminiAnalyzer {
+    pub fn new(api_key: &str, model: &str, timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder().timeout(timeout).build()?,
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl LlmAnalyzer for GeminiAnalyzer {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        let body = json!({
+            "systemInstruction": { "parts": [{ "text": SYSTEM_PROMPT }] },
+            "contents": [{ "role": "user", "parts": [{ "text": prompt }] }],
+            "generationConfig": { "responseMimeType": "application/json" }
+        });
+
+        let response = self.client.post(format!("{}/{}:generateContent", BASE_URL, self.model))
+            .query(&[("key", &self.api_key)])
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Gemini API error: {}", response.status()));
+        }
+
+        let body: Value = response.json().await?;
+        body["candidates"][0]["content"]["parts"][0]["text"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Gemini returned no text"))
+    }
+}
@@ -0,0 +1,207 @@
+// LLM risk-analysis backends
+// Every vendor sits behind `LlmAnalyzer`; `LlmChain` tries them in the configured
+// order and falls through to the next one when a call or its JSON output fails.
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::OsintConfig;
+
+pub mod anthropic;
+pub mod fake;
+pub mod gemini;
+pub mod openai;
+
+pub const SYSTEM_PROMPT: &str = "You are a dating safety and cybersecurity analyst. \
+    Respond with a single JSON object only, no prose and no code fences.";
+
+#[async_trait]
+pub trait LlmAnalyzer: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Raw completion text for a prompt; callers parse it with `extract_json`
+    async fn complete(&self, prompt: &str) -> Result<String>;
+}
+
+// A parsed response along with the provider that produced it
+#[derive(Debug, Clone)]
+pub struct LlmResponse<T> {
+    pub provider: String,
+    pub output: T,
+}
+
+pub struct LlmChain {
+    analyzers: Vec<Arc<dyn LlmAnalyzer>>,
+}
+
+impl LlmChain {
+    pub fn new(analyzers: Vec<Arc<dyn LlmAnalyzer>>) -> Self {
+        Self { analyzers }
+    }
+
+    pub fn from_config(config: &OsintConfig) -> Result<Self> {
+        // Model calls are much slower than the lookup APIs
+        let timeout = Duration::from_secs(config.provider_timeout_seconds * 6);
+        let mut analyzers: Vec<Arc<dyn LlmAnalyzer>> = Vec::new();
+
+        for provider in &config.llm_providers {
+            let analyzer: Option<Arc<dyn LlmAnalyzer>> = match provider.as_str() {
+                "gemini" => config.gemini_api_key.as_deref()
+                    .map(|key| gemini::GeminiAnalyzer::new(key, gemini::DEFAULT_MODEL, timeout))
+                    .transpose()?
+                    .map(|a| Arc::new(a) as Arc<dyn LlmAnalyzer>),
+                "openai" => config.openai_api_key.as_deref()
+                    .map(|key| openai::OpenAiCompatible::openai(key, timeout))
+                    .transpose()?
+                    .map(|a| Arc::new(a) as Arc<dyn LlmAnalyzer>),
+                "anthropic" => config.anthropic_api_key.as_deref()
+                    .map(|key| anthropic::AnthropicAnalyzer::new(key, anthropic::DEFAULT_MODEL, timeout))
+                    .transpose()?
+                    .map(|a| Arc::new(a) as Arc<dyn LlmAnalyzer>),
+                "mistral" => config.mistral_api_key.as_deref()
+                    .map(|key| openai::OpenAiCompatible::mistral(key, timeout))
+                    .transpose()?
+                    .map(|a| Arc::new(a) as Arc<dyn LlmAnalyzer>),
+                "xai" => config.xai_api_key.as_deref()
+                    .map(|key| openai::OpenAiCompatible::xai(key, timeout))
+                    .transpose()?
+                    .map(|a| Arc::new(a) as Arc<dyn LlmAnalyzer>),
+                "fake" => Some(Arc::new(fake::FakeAnalyzer::default()) as Arc<dyn LlmAnalyzer>),
+                other => return Err(anyhow!("Unknown LLM provider in llm_providers: {}", other)),
+            };
+
+            if let Some(analyzer) = analyzer {
+                analyzers.push(analyzer);
+            }
+        }
+
+        Ok(Self::new(analyzers))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.analyzers.is_empty()
+    }
+
+    // Ask each provider in turn until one returns JSON that parses as `T`
+    pub async fn complete_json<T: DeserializeOwned>(&self, prompt: &str) -> Result<LlmResponse<T>> {
+        let mut failures = Vec::new();
+
+        for analyzer in &self.analyzers {
+            let result = analyzer.complete(prompt).await.and_then(|text| {
+                let json = extract_json(&text)
+                    .ok_or_else(|| anyhow!("response contained no JSON object"))?;
+                Ok(serde_json::from_str::<T>(json)?)
+            });
+
+            match result {
+                Ok(output) => {
+                    return Ok(LlmResponse {
+                        provider: analyzer.name().to_string(),
+                        output,
+                    })
+                }
+                Err(e) => {
+                    warn!("LLM provider {} failed, trying next: {}", analyzer.name(), e);
+                    failures.push(format!("{}: {}", analyzer.name(), e));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            Err(anyhow!("no LLM provider is configured"))
+        } else {
+            Err(anyhow!("all LLM providers failed ({})", failures.join("; ")))
+        }
+    }
+}
+
+// Models tend to wrap JSON in prose or code fences; pull out the outermost object
+pub fn extract_json(text: &str) -> Option<&str> {
+    match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if end > start => Some(&text[start..=end]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    use super::fake::FakeAnalyzer;
+
+    // A provider whose API call itself fails
+    struct Unreachable;
+
+    #[async_trait]
+    impl LlmAnalyzer for Unreachable {
+        fn name(&self) -> &'static str {
+            "unreachable"
+        }
+
+        async fn complete(&self, _prompt: &str) -> Result<String> {
+            Err(anyhow!("connection refused"))
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Verdict {
+        risk: u8,
+    }
+
+    #[tokio::test]
+    async fn falls_through_to_the_first_provider_that_answers() {
+        let llm = LlmChain::new(vec![
+            Arc::new(Unreachable),
+            Arc::new(FakeAnalyzer::new("I can't help with that.")),
+            Arc::new(FakeAnalyzer::new(r#"{"verdict": "wrong shape"}"#)),
+            Arc::new(FakeAnalyzer::new("```json\n{\"risk\": 7}\n```")),
+        ]);
+
+        let response = llm.complete_json::<Verdict>("prompt").await.unwrap();
+        assert_eq!(response.provider, "fake");
+        assert_eq!(response.output.risk, 7);
+    }
+
+    #[tokio::test]
+    async fn reports_every_failure_when_none_answer() {
+        let llm = LlmChain::new(vec![Arc::new(Unreachable), Arc::new(FakeAnalyzer::new("no json here"))]);
+
+        let error = llm.complete_json::<Verdict>("prompt").await.unwrap_err().to_string();
+        assert!(error.starts_with("all LLM providers failed"), "{}", error);
+        assert!(error.contains("unreachable: connection refused"), "{}", error);
+        assert!(error.contains("fake: response contained no JSON object"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn an_empty_chain_is_an_error() {
+        let llm = LlmChain::new(Vec::new());
+        assert!(llm.is_empty());
+
+        let error = llm.complete_json::<Verdict>("prompt").await.unwrap_err();
+        assert_eq!(error.to_string(), "no LLM provider is configured");
+    }
+
+    #[test]
+    fn providers_are_built_in_configured_order() {
+        let mut config = crate::config::Settings::default().osint;
+        config.llm_providers = vec!["gemini".to_string(), "fake".to_string()];
+        config.gemini_api_key = None;
+        // Providers without a key are left out
+        assert_eq!(LlmChain::from_config(&config).unwrap().analyzers.len(), 1);
+
+        config.llm_providers.push("clippy".to_string());
+        assert!(LlmChain::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn json_is_cut_out_of_surrounding_prose() {
+        assert_eq!(extract_json("Sure! {\"a\": {\"b\": 1}} Hope that helps"), Some("{\"a\": {\"b\": 1}}"));
+        assert_eq!(extract_json("} backwards {"), None);
+        assert_eq!(extract_json("nothing"), None);
+    }
+}
@@ -0,0 +1,84 @@
+// OpenAI chat completions adapter
+// Mistral and xAI expose the same wire format, so they share this adapter.
+// Docs: https://platform.openai.com/docs/api-reference/chat
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{LlmAnalyzer, SYSTEM_PROMPT};
+
+pub struct OpenAiCompatible {
+    client: Client,
+    name: &'static str,
+    base_url: &'static str,
+    api_key: String,
+    model: String,
+}
+
+impl OpenAiCompatible {
+    pub fn new(
+        name: &'static str,
+        base_url: &'static str,
+        api_key: &str,
+        model: &str,
+        timeout: Duration,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder().timeout(timeout).build()?,
+            name,
+            base_url,
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+        })
+    }
+
+    pub fn openai(api_key: &str, timeout: Duration) -> Result<Self> {
+        Self::new("openai", "https://api.openai.com/v1", api_key, "gpt-4o-mini", timeout)
+    }
+
+    pub fn mistral(api_key: &str, timeout: Duration) -> Result<Self> {
+        Self::new("mistral", "https://api.mistral.ai/v1", api_key, "mistral-small-latest", timeout)
+    }
+
+    pub fn xai(api_key: &str, timeout: Duration) -> Result<Self> {
+        Self::new("xai", "https://api.x.ai/v1", api_key, "grok-2-latest", timeout)
+    }
+}
+
+#[async_trait]
+impl LlmAnalyzer for OpenAiCompatible {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        let body = json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": SYSTEM_PROMPT },
+                { "role": "user", "content": prompt }
+            ],
+            "response_format": { "type": "json_object" },
+            "temperature": 0.2
+        });
+
+        let response = self.client.post(format!("{}/chat/completions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("{} API error: {}", self.name, response.status()));
+        }
+
+        let body: Value = response.json().await?;
+        body["choices"][0]["message"]["content"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("{} returned no message content", self.name))
+    }
+}
@@ -0,0 +1,200 @@
+// Login brute-force protection
+// Failed logins are counted in Redis per account (email) and per client IP.
+// [security].max_login_attempts failures on an account, or
+// max_login_attempts_per_ip from one IP across accounts, within
+// lockout_duration_minutes lock it for lockout_duration_minutes. Each further
+// lockout of the same account or IP within a day doubles the wait, up to a
+// day. A successful login clears the account's count, but not the IP's, so
+// logging in to your own account doesn't buy more guesses at others.
+//
+// Like the rate limiter, this lets logins through if Redis can't be reached.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::SecurityConfig;
+
+// Lockouts never last longer than this, and strikes are forgotten after it
+const MAX_LOCKOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+// What a lockout applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockTarget {
+    Account(String),
+    Ip(String),
+}
+
+impl LockTarget {
+    pub fn scope(&self) -> &'static str {
+        match self {
+            LockTarget::Account(_) => "account",
+            LockTarget::Ip(_) => "ip",
+        }
+    }
+}
+
+impl fmt::Display for LockTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockTarget::Account(email) => write!(f, "account:{}", email),
+            LockTarget::Ip(ip) => write!(f, "ip:{}", ip),
+        }
+    }
+}
+
+// A lockout started by a failed login
+#[derive(Debug, Clone)]
+pub struct Lockout {
+    pub target: LockTarget,
+    pub duration: Duration,
+    // 1 for the first lockout in a day, 2 for the second, ...
+    pub strike: u32,
+}
+
+pub struct LoginGuard {
+    redis: Arc<redis::Client>,
+    max_account_attempts: u32,
+    max_ip_attempts: u32,
+    lockout: Duration,
+}
+
+impl LoginGuard {
+    pub fn from_config(config: &SecurityConfig, redis: Arc<redis::Client>) -> Self {
+        Self {
+            redis,
+            max_account_attempts: config.max_login_attempts,
+            max_ip_attempts: config.max_login_attempts_per_ip,
+            lockout: Duration::from_secs(config.lockout_duration_minutes * 60).min(MAX_LOCKOUT),
+        }
+    }
+
+    // How long until `email` may try again from `ip`, if it's locked out
+    pub async fn locked_for(&self, email: &str, ip: Option<&str>) -> Option<Duration> {
+        let targets = targets(email, ip);
+        match self.remaining_lock(&targets).await {
+            Ok(remaining) => remaining,
+            Err(e) => {
+                warn!("Login lockout check failed, allowing attempt: {}", e);
+                None
+            }
+        }
+    }
+
+    // Count a failed login; returns any lockouts it started
+    pub async fn record_failure(&self, email: &str, ip: Option<&str>) -> Vec<Lockout> {
+        let mut lockouts = Vec::new();
+        for target in targets(email, ip) {
+            match self.count_failure(&target).await {
+                Ok(Some(lockout)) => lockouts.push(lockout),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to record failed login for {}: {}", target, e),
+            }
+        }
+        lockouts
+    }
+
+    // Forget the account's failures and strikes
+    pub async fn record_success(&self, email: &str) {
+        let target = LockTarget::Account(normalize_email(email));
+        let result: redis::RedisResult<()> = async {
+            let mut conn = self.redis.get_async_connection().await?;
+            redis::cmd("DEL")
+                .arg(failures_key(&target))
+                .arg(strikes_key(&target))
+                .query_async(&mut conn)
+                .await
+        }.await;
+        if let Err(e) = result {
+            warn!("Failed to clear failed logins for {}: {}", target, e);
+        }
+    }
+
+    async fn remaining_lock(&self, targets: &[LockTarget]) -> redis::RedisResult<Option<Duration>> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let mut pipe = redis::pipe();
+        for target in targets {
+            pipe.cmd("PTTL").arg(lock_key(target));
+        }
+        // -2 when there's no lock
+        let remaining: Vec<i64> = pipe.query_async(&mut conn).await?;
+        Ok(remaining.into_iter()
+            .filter(|ms| *ms > 0)
+            .max()
+            .map(|ms| Duration::from_millis(ms as u64)))
+    }
+
+    async fn count_failure(&self, target: &LockTarget) -> redis::RedisResult<Option<Lockout>> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let failures_key = failures_key(target);
+
+        // Failures count within a window that starts at the first one
+        let (failures,): (u32,) = redis::pipe()
+            .atomic()
+            .cmd("SET").arg(&failures_key).arg(0).arg("NX").arg("PX").arg(self.lockout.as_millis() as u64).ignore()
+            .cmd("INCR").arg(&failures_key)
+            .query_async(&mut conn)
+            .await?;
+
+        let max_attempts = match target {
+            LockTarget::Account(_) => self.max_account_attempts,
+            LockTarget::Ip(_) => self.max_ip_attempts,
+        };
+        // Exactly at the threshold, so concurrent failures lock only once
+        if failures != max_attempts {
+            return Ok(None);
+        }
+
+        let strikes_key = strikes_key(target);
+        let (strike,): (u32,) = redis::pipe()
+            .atomic()
+            .cmd("INCR").arg(&strikes_key)
+            .cmd("PEXPIRE").arg(&strikes_key).arg(MAX_LOCKOUT.as_millis() as u64).ignore()
+            .query_async(&mut conn)
+            .await?;
+        let duration = backoff(self.lockout, strike);
+
+        redis::pipe()
+            .atomic()
+            .cmd("SET").arg(lock_key(target)).arg(strike).arg("PX").arg(duration.as_millis() as u64).ignore()
+            .cmd("DEL").arg(&failures_key).ignore()
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+
+        Ok(Some(Lockout { target: target.clone(), duration, strike }))
+    }
+}
+
+// `base` for the first strike, doubling for each one after, up to MAX_LOCKOUT
+fn backoff(base: Duration, strike: u32) -> Duration {
+    let factor = 1u32.checked_shl(strike.saturating_sub(1)).unwrap_or(u32::MAX);
+    base.checked_mul(factor).unwrap_or(MAX_LOCKOUT).min(MAX_LOCKOUT)
+}
+
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+// `ip` is the ClientIp from src/middleware.rs: the socket peer, or the hop our
+// trusted proxy saw, never an address the client wrote itself. Requests whose
+// IP isn't known are only counted against the account.
+fn targets(email: &str, ip: Option<&str>) -> Vec<LockTarget> {
+    let mut targets = vec![LockTarget::Account(normalize_email(email))];
+    if let Some(ip) = ip {
+        targets.push(LockTarget::Ip(ip.to_string()));
+    }
+    targets
+}
+
+fn failures_key(target: &LockTarget) -> String {
+    format!("login:failures:{}", target)
+}
+
+fn strikes_key(target: &LockTarget) -> String {
+    format!("login:strikes:{}", target)
+}
+
+fn lock_key(target: &LockTarget) -> String {
+    format!("login:lock:{}", target)
+}
@@ -0,0 +1,43 @@
+// Drops each message into a directory instead of sending it, so development
+// setups and tests can read what would have gone out
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use chrono::Utc;
+use lettre::message::Mailbox;
+use std::path::PathBuf;
+use tracing::info;
+use uuid::Uuid;
+
+use super::{Email, Mailer};
+
+pub struct FileMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(dir: &str, from: Mailbox) -> Self {
+        Self { dir: PathBuf::from(dir), from }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await
+            .map_err(|e| anyhow!("Failed to create outbox {}: {}", self.dir.display(), e))?;
+
+        // Timestamped names list in the order they were sent
+        let path = self.dir.join(format!("{}-{}.eml", Utc::now().format("%Y%m%dT%H%M%S%.6f"), Uuid::new_v4()));
+        let contents = format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}",
+            self.from, email.to, email.subject, email.body
+        );
+        tokio::fs::write(&path, contents).await
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+
+        info!("Email to {} written to {}", email.to, path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+// Outbound email
+// Everything is sent through `Mailer`; `from_config` picks SMTP or, for
+// development and tests, a directory that collects each message as a file.
+// Message text comes from templates (templates.rs).
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use lettre::message::Mailbox;
+use std::sync::Arc;
+
+use crate::config::{EmailConfig, MailerBackend};
+
+pub mod file;
+pub mod smtp;
+pub mod templates;
+
+// A plain-text message, ready to send
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<()>;
+}
+
+pub fn from_config(config: &EmailConfig) -> Result<Arc<dyn Mailer>> {
+    let from: Mailbox = config.from.parse()
+        .map_err(|e| anyhow!("Invalid email.from '{}': {}", config.from, e))?;
+
+    Ok(match config.backend {
+        MailerBackend::File => Arc::new(file::FileMailer::new(&config.outbox_dir, from)),
+        MailerBackend::Smtp => Arc::new(smtp::SmtpMailer::from_config(config, from)?),
+    })
+}
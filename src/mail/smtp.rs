@@ -0,0 +1,57 @@
+// Sends through an SMTP relay
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+
+use super::{Email, Mailer};
+use crate::config::{EmailConfig, SmtpSecurity};
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn from_config(config: &EmailConfig, from: Mailbox) -> Result<Self> {
+        let host = config.smtp_host.as_deref()
+            .ok_or_else(|| anyhow!("email.smtp_host is required for the smtp backend"))?;
+
+        let mut builder = match config.smtp_security {
+            SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        }
+        .port(config.smtp_port)
+        .timeout(Some(SEND_TIMEOUT));
+
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self { transport: builder.build(), from })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        let to: Mailbox = email.to.parse()
+            .map_err(|e| anyhow!("Invalid recipient '{}': {}", email.to, e))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())?;
+
+        self.transport.send(message).await
+            .map_err(|e| anyhow!("SMTP delivery to {} failed: {}", email.to, e))?;
+        Ok(())
+    }
+}
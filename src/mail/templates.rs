@@ -0,0 +1,90 @@
+// Email templates
+// A template is a "Subject:" line, a blank line and the body; {{name}}-style
+// placeholders are filled in when it's rendered. The built-in templates
+// (src/mail/templates/) can be replaced one file at a time from
+// [email].templates_dir.
+
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+use super::Email;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    VerifyEmail,
+    PasswordReset,
+}
+
+impl Template {
+    const ALL: [Template; 2] = [Template::VerifyEmail, Template::PasswordReset];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Template::VerifyEmail => "verify_email.txt",
+            Template::PasswordReset => "password_reset.txt",
+        }
+    }
+
+    fn builtin(self) -> &'static str {
+        match self {
+            Template::VerifyEmail => include_str!("templates/verify_email.txt"),
+            Template::PasswordReset => include_str!("templates/password_reset.txt"),
+        }
+    }
+}
+
+struct MessageTemplate {
+    subject: String,
+    body: String,
+}
+
+impl MessageTemplate {
+    fn parse(source: &str) -> Option<Self> {
+        let (header, body) = source.split_once('\n')?;
+        let subject = header.trim_end_matches('\r').strip_prefix("Subject:")?.trim();
+        Some(Self {
+            subject: subject.to_string(),
+            body: body.trim_start_matches(['\r', '\n']).to_string(),
+        })
+    }
+}
+
+pub struct EmailTemplates {
+    // In Template::ALL order
+    templates: Vec<MessageTemplate>,
+}
+
+impl EmailTemplates {
+    pub fn load(templates_dir: Option<&str>) -> Result<Self> {
+        let mut templates = Vec::new();
+        for template in Template::ALL {
+            let path = templates_dir.map(|dir| Path::new(dir).join(template.file_name()));
+            let source = match path {
+                Some(path) if path.exists() => std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("Failed to read email template {}: {}", path.display(), e))?,
+                _ => template.builtin().to_string(),
+            };
+            let parsed = MessageTemplate::parse(&source)
+                .ok_or_else(|| anyhow!("Email template {} must start with a Subject: line", template.file_name()))?;
+            templates.push(parsed);
+        }
+        Ok(Self { templates })
+    }
+
+    pub fn render(&self, template: Template, to: &str, values: &[(&str, &str)]) -> Email {
+        let index = Template::ALL.iter().position(|t| *t == template).expect("every template is loaded");
+        let message = &self.templates[index];
+        Email {
+            to: to.to_string(),
+            subject: fill(&message.subject, values),
+            body: fill(&message.body, values),
+        }
+    }
+}
+
+// Unknown placeholders are left as they are
+fn fill(text: &str, values: &[(&str, &str)]) -> String {
+    values.iter().fold(text.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{{{}}}}}", name), value)
+    })
+}
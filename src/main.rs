@@ -1,25 +1,324 @@
-mod filter;
-mod filtermain;
-mod fetch_dumps;
-mod weak_pass;
-mod risk_score;
-
+use guardr::{
+    breach_hash, config, data_classes, database, dump_formats, dump_stream, fetch_dumps, filter,
+    filter_rules, filtermain, ingest, llm, osint, password, rehash, risk_score,
+};
 use std::env;
-use weak_pass::load_password_list;
+use password::{PasswordChecker, Wordlist};
 use serde_json::{Value};
 use std::fs;
+use dump_formats::InputFormat;
 
 fn print_usage() {
     eprintln!("Usage:");
-    eprintln!("  guardr basic <input_file> <output_file>");
-    eprintln!("  guardr advanced <input_file> <output_file>");
-    eprintln!("  guardr fetch <url> <output_file>");
+    eprintln!("  guardr basic <input_file> <output_file> [--rules <rules_file>] [--format <format>]");
+    eprintln!("  guardr advanced <input_file> <output_file> [--rules <rules_file>] [--format <format>] [--wordlist <password_list>]");
+    eprintln!("  guardr fetch <url> <output_file> [--sha256 <digest>] [--retries <n>] [--timeout <secs>] [--no-decompress]");
     eprintln!("  guardr check-pass <password_list> <password>");
-    eprintln!("  guardr risk-score <password_list> <input_json>");
+    eprintln!("  guardr risk-score <password_list> <input_file> [--format <format>]");
+    eprintln!("  guardr build-wordlist <password_list> <index_file>");
+    eprintln!("  guardr rehash [--batch-size <n>]");
+    eprintln!("  guardr investigate <email> [output_file]");
+    eprintln!("  guardr ingest <dump_file> --source <name> --breach-date <YYYY-MM-DD> [--format <format>] [--batch-size <n>] [--data-classes <a,b,...>]");
+    eprintln!();
+    eprintln!("Input formats: auto (default), json, ndjson, combo, csv, tsv, sql");
+}
+
+fn run_investigation(email: &str, output: Option<&String>) -> Result<(), Box<dyn std::error::Error>> {
+    dotenvy::dotenv().ok();
+    let settings = config::Settings::new()?;
+    let llm = std::sync::Arc::new(llm::LlmChain::from_config(&settings.osint)?);
+    let investigator = osint::investigation::Investigator::from_config(&settings.osint, llm)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let report = runtime.block_on(investigator.investigate(email));
+    let report_json = serde_json::to_string_pretty(&report)?;
+
+    match output {
+        Some(path) => {
+            fs::write(path, report_json)?;
+            eprintln!("💾 Full report saved to: {}", path);
+        }
+        None => println!("{}", report_json),
+    }
+
+    Ok(())
+}
+
+// Remove `flag <value>` from the argument list, returning the value
+fn take_option(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let index = args.iter().position(|a| a == flag)?;
+    if index + 1 >= args.len() {
+        eprintln!("Missing value for {}", flag);
+        print_usage();
+        std::process::exit(1);
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Some(value)
+}
+
+// Remove a boolean `flag`, returning whether it was present
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|a| a == flag) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+fn fetch_options(
+    sha256: Option<String>,
+    retries: Option<String>,
+    timeout: Option<String>,
+    no_decompress: bool,
+) -> Result<fetch_dumps::DownloadOptions, Box<dyn std::error::Error>> {
+    let mut options = fetch_dumps::DownloadOptions {
+        sha256,
+        decompress: !no_decompress,
+        ..Default::default()
+    };
+    if let Some(retries) = retries {
+        options.retries = retries.parse()
+            .map_err(|e| format!("Invalid --retries '{}': {}", retries, e))?;
+    }
+    if let Some(timeout) = timeout {
+        let secs: u64 = timeout.parse()
+            .map_err(|e| format!("Invalid --timeout '{}': {}", timeout, e))?;
+        options.timeout = std::time::Duration::from_secs(secs);
+    }
+    Ok(options)
+}
+
+// Explicit --rules must exist; otherwise fall back to the configured rules file, if any
+fn load_filter_rules(rules_path: Option<String>) -> Result<filter_rules::FilterRules, Box<dyn std::error::Error>> {
+    if let Some(path) = rules_path {
+        return Ok(filter_rules::FilterRules::load(&path)?);
+    }
+
+    dotenvy::dotenv().ok();
+    let path = config::Settings::new()
+        .map(|s| s.data.filter_rules_path)
+        .unwrap_or_else(|_| config::DataConfig::default().filter_rules_path);
+    Ok(filter_rules::FilterRules::load_or_default(&path)?)
+}
+
+// Same weights as the API: config/default.toml [risk], or the built-in model
+fn load_risk_model() -> risk_score::RiskModel {
+    dotenvy::dotenv().ok();
+    config::Settings::new()
+        .map(|s| risk_score::RiskModel::from_config(&s.risk))
+        .unwrap_or_default()
+}
+
+// What one dump record says about its account: its password, how often it
+// repeats, and when its source was breached and what it exposed
+fn record_signals(entry: &Value, passwords: &PasswordChecker) -> risk_score::RiskSignals {
+    // Every record came out of a breach; labelled dumps say what it exposed,
+    // otherwise the record's own fields do
+    let source = &entry["source"];
+    let data_types = match source["data_types"].as_array() {
+        Some(types) => types.iter().filter_map(|t| t.as_str()).map(str::to_string).collect(),
+        None => data_classes::record_data_classes(entry),
+    };
+    let breach = risk_score::BreachSignal::new(
+        source["name"].as_str().unwrap_or("dump"),
+        source["breach_date"].as_str().and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()),
+        &data_types,
+        source["severity"].as_str(),
+    );
+
+    risk_score::RiskSignals {
+        password_verdict: passwords.check_record(entry),
+        breaches: vec![breach],
+        occurrences: entry["occurrences"].as_u64().unwrap_or(1),
+        ..Default::default()
+    }
+}
+
+// Scores every record in the dump; a single record also lists its factors
+fn run_risk_score(password_list: &str, input: &str, format: InputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let passwords = PasswordChecker::new(Wordlist::load(password_list)?);
+    let model = load_risk_model();
+    let mut last: Option<risk_score::RiskAssessment> = None;
+    let mut scored = 0u64;
+    let mut total = 0u64;
+    let mut highest = 0u8;
+
+    dump_stream::for_each_record(input, format, |entry: Value| {
+        let risk = model.assess(&record_signals(&entry, &passwords));
+        let label = entry["email"].as_str()
+            .or_else(|| entry["username"].as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("record {}", scored + 1));
+        println!("🔍 {}: {} / 100", label, risk.score);
+
+        scored += 1;
+        total += risk.score as u64;
+        highest = highest.max(risk.score);
+        last = Some(risk);
+        Ok(())
+    })?;
+
+    match (scored, last) {
+        (0, _) => println!("No records found in {}", input),
+        (1, Some(risk)) => {
+            println!("   model {}", risk.model_version);
+            for factor in &risk.factors {
+                println!("   +{:>3}  {}", factor.points, factor.description);
+            }
+        }
+        _ => println!(
+            "📊 {} records scored with model {}, average {:.1} / 100, highest {} / 100",
+            scored,
+            model.version(),
+            total as f64 / scored as f64,
+            highest,
+        ),
+    }
+
+    Ok(())
+}
+
+// Wrap stored breach digests in any newer peppers (run after a rotation)
+fn run_rehash(batch_size: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    dotenvy::dotenv().ok();
+    let settings = config::Settings::new()?;
+    let hasher = breach_hash::BreachHasher::from_config(&settings.security);
+    let Some(pepper) = hasher.current_pepper().map(str::to_string) else {
+        return Err("No hash peppers configured (security.hash_peppers)".into());
+    };
+    let batch_size = match batch_size {
+        Some(size) => size.parse()?,
+        None => settings.data.ingest_batch_size as i64,
+    };
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let summary = runtime.block_on(async {
+        let db = database::Database::new(&settings).await?;
+        let summary = rehash::rehash_identifiers(&db, &hasher, batch_size).await;
+        db.close().await;
+        summary
+    })?;
+
+    println!(
+        "✅ Rehashed to pepper '{}': {} of {} breach rows ({} duplicates removed), {} of {} reports",
+        pepper,
+        summary.breach_rows_rehashed,
+        summary.breach_rows_scanned,
+        summary.duplicates_removed,
+        summary.report_rows_rehashed,
+        summary.report_rows_scanned,
+    );
+
+    Ok(())
+}
+
+// Index a text password list for fast loading by check-pass, risk-score and the API
+fn run_build_wordlist(input: &str, output: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let started = std::time::Instant::now();
+    let stats = password::index::build_index(input, output)?;
+
+    println!(
+        "✅ {} unique entries (from {} lines) written to {} ({:.1} MB, {:.1}s)",
+        stats.entries_written,
+        stats.lines_read,
+        output,
+        stats.bytes_written as f64 / (1024.0 * 1024.0),
+        started.elapsed().as_secs_f64(),
+    );
+
+    Ok(())
+}
+
+fn run_ingest(
+    dump: &str,
+    source: Option<String>,
+    breach_date: Option<String>,
+    format: InputFormat,
+    batch_size: Option<String>,
+    data_classes: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(source), Some(breach_date)) = (source, breach_date) else {
+        eprintln!("ingest requires --source and --breach-date");
+        print_usage();
+        std::process::exit(1);
+    };
+    let breach_date = chrono::NaiveDate::parse_from_str(&breach_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid --breach-date '{}': {}", breach_date, e))?;
+
+    dotenvy::dotenv().ok();
+    let settings = config::Settings::new()?;
+    let batch_size = match batch_size {
+        Some(size) => size.parse()?,
+        None => settings.data.ingest_batch_size,
+    };
+
+    let hasher = breach_hash::BreachHasher::from_config(&settings.security);
+
+    let options = ingest::IngestOptions {
+        source,
+        breach_date,
+        format,
+        batch_size,
+        data_classes: data_classes
+            .map(|classes| classes.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect())
+            .unwrap_or_default(),
+    };
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let summary = runtime.block_on(async {
+        let db = database::Database::new(&settings).await?;
+        let summary = ingest::ingest_file(&db, &hasher, dump, &options).await;
+        db.close().await;
+        summary
+    })?;
+
+    println!(
+        "✅ Ingested {} from {}: {} read, {} inserted, {} duplicates, {} skipped ({:.1}s)",
+        summary.source,
+        dump,
+        summary.records_read,
+        summary.inserted,
+        summary.duplicates,
+        summary.skipped,
+        summary.elapsed_ms as f64 / 1000.0,
+    );
+
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let rules_path = take_option(&mut args, "--rules");
+    let wordlist_path = take_option(&mut args, "--wordlist");
+    let format: InputFormat = match take_option(&mut args, "--format") {
+        Some(format) => format.parse()?,
+        None => InputFormat::Auto,
+    };
+
+    let sha256 = take_option(&mut args, "--sha256");
+    let retries = take_option(&mut args, "--retries");
+    let timeout = take_option(&mut args, "--timeout");
+    let no_decompress = take_flag(&mut args, "--no-decompress");
+
+    if args.len() >= 3 && args[1] == "investigate" {
+        return run_investigation(&args[2], args.get(3));
+    }
+
+    if args.len() >= 3 && args[1] == "ingest" {
+        let source = take_option(&mut args, "--source");
+        let breach_date = take_option(&mut args, "--breach-date");
+        let batch_size = take_option(&mut args, "--batch-size");
+        let data_classes = take_option(&mut args, "--data-classes");
+        return run_ingest(&args[2], source, breach_date, format, batch_size, data_classes);
+    }
+
+    if args.len() >= 2 && args[1] == "rehash" {
+        let batch_size = take_option(&mut args, "--batch-size");
+        return run_rehash(batch_size);
+    }
 
     if args.len() < 4 {
         print_usage();
@@ -31,24 +330,42 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let output = &args[3];
 
     match command.as_str() {
-        "basic" => filter::run_filter(input, output)?,
-        "advanced" => filtermain::run_advanced_filter(input, output)?,
-        "fetch" => fetch_dumps::download_dump(input, output)?,
+        "basic" => {
+            let rules = load_filter_rules(rules_path)?;
+            filter::run_filter(input, output, format, &rules, true)?
+        },
+        "advanced" => {
+            let rules = load_filter_rules(rules_path)?;
+            let passwords = match &wordlist_path {
+                Some(path) => PasswordChecker::new(Wordlist::load(path)?),
+                None => PasswordChecker::default(),
+            };
+            filtermain::run_advanced_filter(input, output, format, &rules, &passwords, true)?
+        },
+        "fetch" => {
+            let options = fetch_options(sha256, retries, timeout, no_decompress)?;
+            fetch_dumps::download_dump(input, output, &options)?
+        },
         "check-pass" => {
-            let passwords = weak_pass::load_password_list(input)?;
-            if weak_pass::is_password_weak(output, &passwords) {
+            let checker = PasswordChecker::new(Wordlist::load(input)?);
+            let strength = password::estimate(output, &[]);
+            let verdict = checker.check(output, &[]).with_strength(&strength);
+            if verdict.weak {
                 println!("🚨 \"{}\" is WEAK. Pick a better password, sweetie!", output);
+                for reason in verdict.messages() {
+                    println!("   • {}", reason);
+                }
             } else {
                 println!("✅ \"{}\" is strong enough. Work it!", output);
             }
+            println!(
+                "⏱️  Strength {}/4 — offline crack time against a slow hash: {}",
+                strength.score,
+                strength.crack_times.offline_slow_hash.display,
+            );
         },
-        "risk-score" => {
-            let passwords = load_password_list(input)?;
-            let json_str = fs::read_to_string(output)?;
-            let json_data: Value = serde_json::from_str(&json_str)?;
-            let risk = risk_score::calculate_risk_score(&json_data, &passwords);
-            println!("🔍 Risk score: {} / 100", risk);
-        },
+        "risk-score" => run_risk_score(input, output, format)?,
+        "build-wordlist" => run_build_wordlist(input, output)?,
         _ => {
             eprintln!("Unknown command: '{}'", command);
             print_usage();
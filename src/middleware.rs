@@ -1,85 +1,175 @@
 use axum::{
-    extract::{Request, State},
-    http::{HeaderMap, StatusCode},
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{
+        header::{AUTHORIZATION, RETRY_AFTER},
+        request::Parts,
+        HeaderMap, HeaderValue,
+    },
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
-use governor::{Quota, RateLimiter};
 use std::{
-    num::NonZeroU32,
-    sync::Arc,
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
 };
 use tracing::{info, warn};
 
 use crate::{
-    auth::AuthenticatedUser,
+    auth::{AuthenticatedUser, Credential, API_KEY_HEADER},
     errors::AppError,
+    rate_limit::{RateLimitDecision, RateLimitKey},
     state::AppState,
 };
 
 // Rate limiting middleware
-pub type IpRateLimiter = RateLimiter<String, governor::state::keyed::DefaultKeyedStateStore<String>, governor::clock::DefaultClock>;
-
-pub fn create_rate_limiter(requests_per_minute: u32) -> Arc<IpRateLimiter> {
-    let quota = Quota::per_minute(NonZeroU32::new(requests_per_minute).unwrap());
-    Arc::new(RateLimiter::keyed(quota))
-}
-
+// Requests count against their user or API key when they authenticate, and
+// against the client IP otherwise (src/rate_limit.rs). The principal is left
+// in the request extensions so handlers don't authenticate twice.
 pub async fn rate_limit_middleware(
     State(state): State<AppState>,
-    headers: HeaderMap,
     request: Request,
     next: Next,
-) -> Result<Response, AppError> {
-    // Extract IP address
-    let ip = extract_ip_from_headers(&headers)
-        .unwrap_or_else(|| "unknown".to_string());
+) -> Response {
+    let (mut parts, body) = request.into_parts();
 
-    // Get rate limiter for IP
-    let rate_limiter = create_rate_limiter(state.settings.rate_limiting.requests_per_minute);
+    let has_credentials = parts.headers.contains_key(AUTHORIZATION) || parts.headers.contains_key(API_KEY_HEADER);
+    // Bad credentials are rejected by the handler; until then the caller is anonymous
+    let principal = if has_credentials {
+        AuthenticatedUser::from_request_parts(&mut parts, &state).await.ok()
+    } else {
+        None
+    };
 
-    // Check rate limit
-    match rate_limiter.check_key(&ip) {
-        Ok(_) => {
-            // Request allowed
-            let response = next.run(request).await;
-            Ok(response)
-        }
-        Err(_) => {
-            warn!("Rate limit exceeded for IP: {}", ip);
-            Err(AppError::RateLimitExceeded(format!(
-                "Rate limit exceeded for IP {}. Max {} requests per minute.",
-                ip,
-                state.settings.rate_limiting.requests_per_minute
-            )))
-        }
+    let key = match &principal {
+        Some(AuthenticatedUser { credential: Credential::ApiKey { key_id, .. }, .. }) => RateLimitKey::ApiKey(*key_id),
+        Some(user) => RateLimitKey::User(user.user_id),
+        None => RateLimitKey::Ip(client_ip(&parts, &state.trusted_proxies).unwrap_or_else(|| "unknown".to_string())),
+    };
+    let decision = state.rate_limiter.check(&key, principal.as_ref().map(|user| &user.subscription_tier)).await;
+
+    if !decision.allowed {
+        warn!("Rate limit exceeded for {}", key);
+        let mut response = AppError::RateLimitExceeded(format!(
+            "Max {} requests per minute. Retry in {} seconds.",
+            decision.limit,
+            ceil_secs(decision.retry_after),
+        )).into_response();
+        set_rate_limit_headers(response.headers_mut(), &decision);
+        return response;
+    }
+
+    if let Some(user) = principal {
+        parts.extensions.insert(user);
+    }
+    let mut response = next.run(Request::from_parts(parts, body)).await;
+    set_rate_limit_headers(response.headers_mut(), &decision);
+    response
+}
+
+// RateLimit-* fields from the IETF draft, plus Retry-After when rejected
+fn set_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert("ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(ceil_secs(decision.reset)));
+    if !decision.allowed {
+        headers.insert(RETRY_AFTER, HeaderValue::from(ceil_secs(decision.retry_after).max(1)));
     }
 }
 
-fn extract_ip_from_headers(headers: &HeaderMap) -> Option<String> {
-    // Check common headers for real IP
-    if let Some(forwarded_for) = headers.get("x-forwarded-for") {
-        if let Ok(value) = forwarded_for.to_str() {
-            // Take the first IP from the comma-separated list
-            if let Some(ip) = value.split(',').next() {
-                return Some(ip.trim().to_string());
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_millis().div_ceil(1000) as u64
+}
+
+// The client IP for handlers, found the same way the rate limiter finds it
+pub struct ClientIp(pub Option<String>);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        Ok(ClientIp(client_ip(parts, &state.trusted_proxies)))
+    }
+}
+
+// Proxies whose X-Forwarded-For entries are believed (server.trusted_proxies)
+#[derive(Debug, Default)]
+pub struct TrustedProxies {
+    // (network, prefix length)
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    // Each entry is an address or a CIDR range
+    pub fn parse(entries: &[String]) -> anyhow::Result<Self> {
+        let ranges = entries.iter().map(|entry| {
+            let (addr, prefix) = match entry.split_once('/') {
+                Some((addr, prefix)) => (addr, Some(prefix)),
+                None => (entry.as_str(), None),
+            };
+            let addr: IpAddr = addr.trim().parse()
+                .map_err(|_| anyhow::anyhow!("Invalid trusted proxy '{}'", entry))?;
+            let max = if addr.is_ipv4() { 32 } else { 128 };
+            let prefix = match prefix {
+                Some(prefix) => prefix.trim().parse::<u8>().ok().filter(|p| *p <= max)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid prefix length in trusted proxy '{}'", entry))?,
+                None => max,
+            };
+            Ok((addr, prefix))
+        }).collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { ranges })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        self.ranges.iter().any(|(network, prefix)| match (canonical(*network), ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
             }
-        }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        })
     }
+}
 
-    if let Some(real_ip) = headers.get("x-real-ip") {
-        if let Ok(value) = real_ip.to_str() {
-            return Some(value.to_string());
-        }
+// IPv4 peers on a dual-stack socket show up as ::ffff:a.b.c.d
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    }
+}
+
+// The socket peer, unless it is a trusted proxy: then the right-most
+// X-Forwarded-For hop that isn't one of our proxies. Everything left of that
+// hop was written by the client and can't be believed.
+fn client_ip(parts: &Parts, trusted: &TrustedProxies) -> Option<String> {
+    let peer = canonical(parts.extensions.get::<ConnectInfo<SocketAddr>>()?.0.ip());
+    if !trusted.contains(peer) {
+        return Some(peer.to_string());
     }
 
-    if let Some(cf_ip) = headers.get("cf-connecting-ip") {
-        if let Ok(value) = cf_ip.to_str() {
-            return Some(value.to_string());
+    let hops = parts.headers.get_all("x-forwarded-for").iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|hop| hop.trim().parse::<IpAddr>().ok().map(canonical))
+        .collect::<Vec<_>>();
+    for hop in hops.iter().rev() {
+        match hop {
+            Some(ip) if trusted.contains(*ip) => continue,
+            Some(ip) => return Some(ip.to_string()),
+            // Garbage in the chain: nothing further left can be trusted either
+            None => break,
         }
     }
 
-    None
+    Some(peer.to_string())
 }
 
 // Request logging middleware
@@ -145,69 +235,6 @@ pub async fn security_headers_middleware(
     response
 }
 
-// API key middleware (alternative to JWT)
-pub async fn api_key_middleware(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    mut request: Request,
-    next: Next,
-) -> Result<Response, AppError> {
-    if let Some(api_key) = headers.get("x-api-key") {
-        if let Ok(key_str) = api_key.to_str() {
-            let key_hash = state.auth.hash_api_key(key_str);
-            
-            if let Some((api_key_record, user)) = state.db.get_api_key_by_hash(&key_hash).await? {
-                // Update last used timestamp
-                state.db.update_api_key_last_used(api_key_record.id).await?;
-
-                // Add user info to request extensions
-                request.extensions_mut().insert(AuthenticatedUser {
-                    user_id: user.id,
-                    email: user.email.clone(),
-                    subscription_tier: user.subscription_tier.clone(),
-                    claims: crate::auth::Claims {
-                        sub: user.id.to_string(),
-                        email: user.email,
-                        tier: user.subscription_tier.to_string(),
-                        exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp(),
-                        iat: chrono::Utc::now().timestamp(),
-                        jti: uuid::Uuid::new_v4().to_string(),
-                    },
-                });
-
-                return Ok(next.run(request).await);
-            }
-        }
-    }
-
-    Err(AppError::Unauthorized("Invalid or missing API key".to_string()))
-}
-
-// Usage tracking middleware
-pub async fn usage_tracking_middleware(
-    State(state): State<AppState>,
-    request: Request,
-    next: Next,
-) -> Result<Response, StatusCode> {
-    let path = request.uri().path();
-    
-    // Extract user from request (if authenticated)
-    if let Some(user) = request.extensions().get::<AuthenticatedUser>() {
-        // Track API usage in background
-        let db = state.db.clone();
-        let user_id = user.user_id;
-        let endpoint = path.to_string();
-        
-        tokio::spawn(async move {
-            if let Err(e) = db.track_api_usage(user_id, &endpoint).await {
-                warn!("Failed to track API usage: {}", e);
-            }
-        });
-    }
-
-    Ok(next.run(request).await)
-}
-
 // Subscription tier validation middleware
 pub async fn subscription_validation_middleware(
     request: Request,
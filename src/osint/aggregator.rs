@@ -0,0 +1,284 @@
+// Multi-source OSINT aggregation
+// Fans a subject out to every configured source concurrently, enforces a
+// per-source timeout and folds the findings into one scored report.
+
+use anyhow::Result;
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{BreachProvider, BreachRecord};
+
+// What we know about the person being looked up
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Subject {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub username: Option<String>,
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingCategory {
+    Breach,
+    Paste,
+    Leak,
+    WebMention,
+}
+
+// A single piece of evidence, always attributed to the source that produced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub provider: String,
+    pub category: FindingCategory,
+    pub title: String,
+    pub description: String,
+    pub severity: String, // "low", "medium", "high", "critical"
+    pub reference: Option<String>,
+    pub observed_at: Option<String>,
+    // Membership alone is damaging (dating and adult sites); never shown to
+    // anyone who hasn't proven they own the address
+    #[serde(default)]
+    pub sensitive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceOutcome {
+    Succeeded,
+    Failed,
+    TimedOut,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceStatus {
+    pub provider: String,
+    pub outcome: SourceOutcome,
+    pub findings: usize,
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsintReport {
+    pub subject: Subject,
+    pub risk_score: u8,
+    pub risk_level: String,
+    pub findings: Vec<Finding>,
+    pub sources: Vec<SourceStatus>,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl OsintReport {
+    // A report for a subject no source was asked about
+    pub fn unchecked(subject: &Subject) -> Self {
+        Self {
+            subject: subject.clone(),
+            risk_score: 0,
+            risk_level: risk_level(0).to_string(),
+            findings: Vec::new(),
+            sources: Vec::new(),
+            generated_at: Utc::now(),
+        }
+    }
+
+    pub fn succeeded_sources(&self) -> impl Iterator<Item = &SourceStatus> {
+        self.sources.iter().filter(|s| s.outcome == SourceOutcome::Succeeded)
+    }
+
+    // The report as if sensitive findings had never been found: dropped,
+    // uncounted in their sources and left out of the score
+    pub fn without_sensitive(mut self) -> Self {
+        for status in &mut self.sources {
+            let hidden = self.findings.iter()
+                .filter(|f| f.sensitive && f.provider == status.provider)
+                .count();
+            status.findings = status.findings.saturating_sub(hidden);
+        }
+        self.findings.retain(|f| !f.sensitive);
+        self.risk_score = score_findings(&self.findings);
+        self.risk_level = risk_level(self.risk_score).to_string();
+        self
+    }
+}
+
+#[async_trait]
+pub trait OsintSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Whether the subject carries enough identifiers for this source to search
+    fn supports(&self, subject: &Subject) -> bool;
+
+    async fn search(&self, subject: &Subject) -> Result<Vec<Finding>>;
+}
+
+// Lets any breach provider take part in aggregation when an email is known
+pub struct BreachSource(pub Arc<dyn BreachProvider>);
+
+#[async_trait]
+impl OsintSource for BreachSource {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn supports(&self, subject: &Subject) -> bool {
+        subject.email.is_some()
+    }
+
+    async fn search(&self, subject: &Subject) -> Result<Vec<Finding>> {
+        let email = subject.email.as_deref().unwrap_or_default();
+        let lookup = self.0.lookup(email).await?;
+
+        let breaches = lookup.breaches.iter().map(breach_finding);
+        let pastes = lookup.pastes.iter().map(|p| Finding {
+            provider: self.name().to_string(),
+            category: FindingCategory::Paste,
+            title: p.title.clone().unwrap_or_else(|| format!("{} paste", p.source)),
+            description: format!("Email appeared in a {} paste alongside {} other addresses", p.source, p.email_count),
+            severity: "medium".to_string(),
+            reference: Some(format!("{}:{}", p.source, p.id)),
+            observed_at: p.date.clone(),
+            sensitive: false,
+        });
+
+        Ok(breaches.chain(pastes).collect())
+    }
+}
+
+fn breach_finding(breach: &BreachRecord) -> Finding {
+    Finding {
+        provider: breach.provider.clone(),
+        category: FindingCategory::Breach,
+        title: breach.source.clone(),
+        description: format!("Exposed data: {}", breach.data_types.join(", ")),
+        severity: breach.severity.clone(),
+        reference: None,
+        observed_at: breach.breach_date.map(|d| d.format("%Y-%m-%d").to_string()),
+        sensitive: breach.sensitive,
+    }
+}
+
+pub struct OsintAggregator {
+    sources: Vec<Arc<dyn OsintSource>>,
+    timeout: Duration,
+}
+
+impl OsintAggregator {
+    pub fn new(sources: Vec<Arc<dyn OsintSource>>, timeout: Duration) -> Self {
+        Self { sources, timeout }
+    }
+
+    pub async fn investigate(&self, subject: &Subject) -> OsintReport {
+        let runs = self.sources.iter().map(|source| self.run_source(source.as_ref(), subject));
+        let results = join_all(runs).await;
+
+        let mut findings = Vec::new();
+        let mut sources = Vec::with_capacity(results.len());
+        for (status, source_findings) in results {
+            findings.extend(source_findings);
+            sources.push(status);
+        }
+
+        let risk_score = score_findings(&findings);
+
+        OsintReport {
+            subject: subject.clone(),
+            risk_score,
+            risk_level: risk_level(risk_score).to_string(),
+            findings,
+            sources,
+            generated_at: Utc::now(),
+        }
+    }
+
+    async fn run_source(&self, source: &dyn OsintSource, subject: &Subject) -> (SourceStatus, Vec<Finding>) {
+        let provider = source.name().to_string();
+
+        if !source.supports(subject) {
+            let status = SourceStatus {
+                provider,
+                outcome: SourceOutcome::Skipped,
+                findings: 0,
+                elapsed_ms: 0,
+                error: None,
+            };
+            return (status, Vec::new());
+        }
+
+        let started = Instant::now();
+        let result = tokio::time::timeout(self.timeout, source.search(subject)).await;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+
+        match result {
+            Ok(Ok(findings)) => {
+                let status = SourceStatus {
+                    provider,
+                    outcome: SourceOutcome::Succeeded,
+                    findings: findings.len(),
+                    elapsed_ms,
+                    error: None,
+                };
+                (status, findings)
+            }
+            Ok(Err(e)) => {
+                let status = SourceStatus {
+                    provider,
+                    outcome: SourceOutcome::Failed,
+                    findings: 0,
+                    elapsed_ms,
+                    error: Some(e.to_string()),
+                };
+                (status, Vec::new())
+            }
+            Err(_) => {
+                let status = SourceStatus {
+                    provider,
+                    outcome: SourceOutcome::TimedOut,
+                    findings: 0,
+                    elapsed_ms,
+                    error: Some(format!("No response within {}s", self.timeout.as_secs())),
+                };
+                (status, Vec::new())
+            }
+        }
+    }
+}
+
+// Severity points per finding, with web mentions counting for much less than
+// leaked credentials. Each category is capped so one noisy source can't max the score.
+pub fn score_findings(findings: &[Finding]) -> u8 {
+    let mut breach_points = 0u32;
+    let mut leak_points = 0u32;
+    let mut web_points = 0u32;
+
+    for finding in findings {
+        let points = match finding.severity.as_str() {
+            "critical" => 25,
+            "high" => 15,
+            "medium" => 8,
+            _ => 3,
+        };
+
+        match finding.category {
+            FindingCategory::Breach => breach_points += points,
+            FindingCategory::Paste | FindingCategory::Leak => leak_points += points,
+            FindingCategory::WebMention => web_points += points / 3,
+        }
+    }
+
+    (breach_points.min(60) + leak_points.min(30) + web_points.min(10)).min(100) as u8
+}
+
+pub fn risk_level(score: u8) -> &'static str {
+    match score {
+        0..=24 => "LOW",
+        25..=49 => "MEDIUM",
+        50..=74 => "HIGH",
+        _ => "CRITICAL",
+    }
+}
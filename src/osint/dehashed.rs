@@ -0,0 +1,137 @@
+// DeHashed breach record search
+// Docs: https://www.dehashed.com/docs
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use reqwest::{Client, StatusCode, Url};
+use serde::Deserialize;
+use std::time::Duration;
+
+use super::aggregator::{Finding, FindingCategory, OsintSource, Subject};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.dehashed.com";
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    entries: Option<Vec<DehashedEntry>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DehashedEntry {
+    id: Option<String>,
+    database_name: Option<String>,
+    password: Option<String>,
+    hashed_password: Option<String>,
+    address: Option<String>,
+    phone: Option<String>,
+}
+
+impl DehashedEntry {
+    fn exposed_fields(&self) -> Vec<&'static str> {
+        let present = |value: &Option<String>| value.as_deref().is_some_and(|v| !v.is_empty());
+
+        let mut fields = Vec::new();
+        if present(&self.password) { fields.push("password"); }
+        if present(&self.hashed_password) { fields.push("hashed password"); }
+        if present(&self.address) { fields.push("address"); }
+        if present(&self.phone) { fields.push("phone"); }
+        fields
+    }
+}
+
+pub struct DehashedSource {
+    client: Client,
+    account_email: String,
+    api_key: String,
+    base_url: Url,
+}
+
+impl DehashedSource {
+    pub fn new(account_email: &str, api_key: &str, base_url: &str, timeout_seconds: u64) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_seconds))
+            .build()?;
+
+        let base_url = Url::parse(&format!("{}/", base_url.trim_end_matches('/')))
+            .map_err(|e| anyhow!("Invalid DeHashed base URL {}: {}", base_url, e))?;
+
+        Ok(Self {
+            client,
+            account_email: account_email.to_string(),
+            api_key: api_key.to_string(),
+            base_url,
+        })
+    }
+}
+
+// Search on the most specific identifier we have
+fn search_query(subject: &Subject) -> Option<String> {
+    if let Some(email) = &subject.email {
+        Some(format!("email:\"{}\"", email))
+    } else if let Some(username) = &subject.username {
+        Some(format!("username:\"{}\"", username))
+    } else {
+        subject.name.as_ref().map(|name| format!("name:\"{}\"", name))
+    }
+}
+
+#[async_trait]
+impl OsintSource for DehashedSource {
+    fn name(&self) -> &'static str {
+        "dehashed"
+    }
+
+    fn supports(&self, subject: &Subject) -> bool {
+        search_query(subject).is_some()
+    }
+
+    async fn search(&self, subject: &Subject) -> Result<Vec<Finding>> {
+        let query = search_query(subject).unwrap_or_default();
+
+        let mut url = self.base_url.join("search")?;
+        url.query_pairs_mut().append_pair("query", &query);
+
+        let response = self.client.get(url)
+            .basic_auth(&self.account_email, Some(&self.api_key))
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {}
+            StatusCode::UNAUTHORIZED => return Err(anyhow!("DeHashed rejected the credentials")),
+            status => return Err(anyhow!("DeHashed API error: {}", status)),
+        }
+
+        let entries = response.json::<SearchResponse>().await?.entries.unwrap_or_default();
+
+        Ok(entries.into_iter().map(|entry| {
+            let fields = entry.exposed_fields();
+            let severity = if fields.contains(&"password") {
+                "critical"
+            } else if !fields.is_empty() {
+                "high"
+            } else {
+                "medium"
+            };
+
+            let description = if fields.is_empty() {
+                "Identifier present in breach record".to_string()
+            } else {
+                format!("Breach record exposes: {}", fields.join(", "))
+            };
+
+            Finding {
+                provider: self.name().to_string(),
+                category: FindingCategory::Breach,
+                title: entry.database_name.unwrap_or_else(|| "Unknown database".to_string()),
+                description,
+                severity: severity.to_string(),
+                reference: entry.id,
+                observed_at: None,
+                sensitive: false,
+            }
+        }).collect())
+    }
+}
@@ -0,0 +1,177 @@
+// Have I Been Pwned (v3) breach provider
+// Docs: https://haveibeenpwned.com/API/v3
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use chrono::NaiveDate;
+use reqwest::{Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::data_classes::{self, Severity};
+use super::{BreachLookup, BreachProvider, BreachRecord, PasteRecord};
+
+pub const DEFAULT_BASE_URL: &str = "https://haveibeenpwned.com/api/v3";
+const USER_AGENT: &str = "Guardr-Dating-Safety-Platform";
+
+// Full breach model as returned by the API (field names kept in HIBP's casing
+// so investigation reports carry the breach exactly as the Python tooling did)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HibpBreach {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breach_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_date: Option<String>,
+    #[serde(default)]
+    pub pwn_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub data_classes: Vec<String>,
+    #[serde(default)]
+    pub is_verified: bool,
+    #[serde(default)]
+    pub is_sensitive: bool,
+    // Everything else HIBP sends (ModifiedDate, LogoPath, IsFabricated, ...),
+    // passed through untouched
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HibpPaste {
+    source: String,
+    id: String,
+    title: Option<String>,
+    date: Option<String>,
+    #[serde(default)]
+    email_count: u64,
+}
+
+#[derive(Clone)]
+pub struct HibpProvider {
+    client: Client,
+    api_key: String,
+    base_url: Url,
+}
+
+impl HibpProvider {
+    pub fn new(api_key: &str, base_url: &str, timeout_seconds: u64) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(timeout_seconds))
+            .build()?;
+
+        // Url::join treats a base without a trailing slash as a file, so normalise it
+        let base_url = Url::parse(&format!("{}/", base_url.trim_end_matches('/')))
+            .map_err(|e| anyhow!("Invalid HIBP base URL {}: {}", base_url, e))?;
+
+        Ok(Self {
+            client,
+            api_key: api_key.to_string(),
+            base_url,
+        })
+    }
+
+    fn endpoint(&self, resource: &str, account: &str) -> Result<Url> {
+        let mut url = self.base_url.join(resource)?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("HIBP base URL cannot be a base"))?
+            .pop_if_empty()
+            .push(account);
+        Ok(url)
+    }
+
+    // GET a HIBP resource; 404 means "not found in any breach" and maps to None
+    async fn get<T: serde::de::DeserializeOwned>(&self, url: Url) -> Result<Option<T>> {
+        let response = self.client.get(url)
+            .header("hibp-api-key", &self.api_key)
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.json::<T>().await?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::UNAUTHORIZED => Err(anyhow!("HIBP rejected the API key")),
+            StatusCode::TOO_MANY_REQUESTS => {
+                let retry_after = response.headers()
+                    .get("retry-after")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("unknown")
+                    .to_string();
+                Err(anyhow!("HIBP rate limit exceeded (retry after {}s)", retry_after))
+            }
+            status => Err(anyhow!("HIBP API error: {}", status)),
+        }
+    }
+
+    pub async fn breaches(&self, email: &str) -> Result<Vec<HibpBreach>> {
+        let mut url = self.endpoint("breachedaccount/", email)?;
+        url.query_pairs_mut().append_pair("truncateResponse", "false");
+
+        Ok(self.get(url).await?.unwrap_or_default())
+    }
+
+    pub async fn breached_account(&self, email: &str) -> Result<Vec<BreachRecord>> {
+        let breaches = self.breaches(email).await?;
+
+        Ok(breaches.into_iter().map(|b| {
+            let severity = breach_severity(&b);
+            BreachRecord {
+                source: b.name,
+                breach_date: b.breach_date
+                    .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+                data_types: b.data_classes,
+                severity,
+                provider: self.name().to_string(),
+                verified: b.is_verified,
+                sensitive: b.is_sensitive,
+            }
+        }).collect())
+    }
+
+    pub async fn paste_account(&self, email: &str) -> Result<Vec<PasteRecord>> {
+        let url = self.endpoint("pasteaccount/", email)?;
+        let pastes: Vec<HibpPaste> = self.get(url).await?.unwrap_or_default();
+
+        Ok(pastes.into_iter().map(|p| PasteRecord {
+            source: p.source,
+            id: p.id,
+            title: p.title,
+            date: p.date,
+            email_count: p.email_count,
+        }).collect())
+    }
+}
+
+// Sensitive breaches (adult and dating sites) expose membership itself
+fn breach_severity(breach: &HibpBreach) -> String {
+    if breach.is_sensitive {
+        Severity::Critical
+    } else {
+        data_classes::breach_severity(&breach.data_classes, None)
+    }.to_string()
+}
+
+#[async_trait]
+impl BreachProvider for HibpProvider {
+    fn name(&self) -> &'static str {
+        "hibp"
+    }
+
+    async fn lookup(&self, email: &str) -> Result<BreachLookup> {
+        let (breaches, pastes) = tokio::try_join!(
+            self.breached_account(email),
+            self.paste_account(email),
+        )?;
+
+        Ok(BreachLookup { breaches, pastes })
+    }
+}
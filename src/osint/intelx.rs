@@ -0,0 +1,173 @@
+// Intelligence X search (dark web, dumps and leak buckets)
+// Docs: https://github.com/IntelligenceX/SDK
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::aggregator::{Finding, FindingCategory, OsintSource, Subject};
+
+pub const DEFAULT_BASE_URL: &str = "https://public.intelx.io";
+const MAX_RESULTS: u32 = 100;
+const RESULT_POLLS: u32 = 5;
+
+#[derive(Debug, Deserialize)]
+struct SearchStarted {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResults {
+    #[serde(default)]
+    records: Vec<IntelxRecord>,
+    status: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntelxRecord {
+    pub systemid: String,
+    pub name: Option<String>,
+    pub date: Option<String>,
+    pub bucket: Option<String>,
+}
+
+pub struct IntelxSource {
+    client: Client,
+    api_key: String,
+    base_url: Url,
+}
+
+impl IntelxSource {
+    pub fn new(api_key: &str, base_url: &str, timeout_seconds: u64) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_seconds))
+            .build()?;
+
+        let base_url = Url::parse(&format!("{}/", base_url.trim_end_matches('/')))
+            .map_err(|e| anyhow!("Invalid Intelligence X base URL {}: {}", base_url, e))?;
+
+        Ok(Self {
+            client,
+            api_key: api_key.to_string(),
+            base_url,
+        })
+    }
+
+    // Start a search; the body is `{ "id": ..., "status": ... }`, which
+    // investigation reports carry as-is, as the Python tooling did
+    pub async fn search_raw(&self, term: &str) -> Result<Value> {
+        let body = json!({
+            "term": term,
+            "buckets": ["darkweb", "dumpster", "leaks"],
+            "lookuplevel": 0,
+            "maxresults": MAX_RESULTS,
+            "timeout": 5,
+            "datefrom": "",
+            "dateto": "",
+            "sort": 4,
+            "media": 0,
+            "terminate": []
+        });
+
+        let response = self.client.post(self.base_url.join("intelligent/search")?)
+            .header("x-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Intelligence X search error: {}", response.status()));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    async fn start_search(&self, term: &str) -> Result<String> {
+        let started: SearchStarted = serde_json::from_value(self.search_raw(term).await?)?;
+        Ok(started.id)
+    }
+
+    // Run a search and collect its records
+    pub async fn records(&self, term: &str) -> Result<Vec<IntelxRecord>> {
+        let search_id = self.start_search(term).await?;
+        self.collect_results(&search_id).await
+    }
+
+    // Status 0 = more results, 1 = finished, 2 = unknown search id, 3 = not ready yet
+    async fn collect_results(&self, search_id: &str) -> Result<Vec<IntelxRecord>> {
+        let mut url = self.base_url.join("intelligent/search/result")?;
+        url.query_pairs_mut()
+            .append_pair("id", search_id)
+            .append_pair("limit", &MAX_RESULTS.to_string());
+
+        let mut records = Vec::new();
+        for _ in 0..RESULT_POLLS {
+            let response = self.client.get(url.clone())
+                .header("x-key", &self.api_key)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(anyhow!("Intelligence X result error: {}", response.status()));
+            }
+
+            let page: SearchResults = response.json().await?;
+            records.extend(page.records);
+
+            match page.status {
+                0 | 3 => tokio::time::sleep(Duration::from_millis(500)).await,
+                1 => break,
+                _ => return Err(anyhow!("Intelligence X lost search {}", search_id)),
+            }
+        }
+
+        Ok(records)
+    }
+}
+
+fn search_term(subject: &Subject) -> Option<&str> {
+    subject.email.as_deref()
+        .or(subject.username.as_deref())
+        .or(subject.name.as_deref())
+}
+
+#[async_trait]
+impl OsintSource for IntelxSource {
+    fn name(&self) -> &'static str {
+        "intelx"
+    }
+
+    fn supports(&self, subject: &Subject) -> bool {
+        search_term(subject).is_some()
+    }
+
+    async fn search(&self, subject: &Subject) -> Result<Vec<Finding>> {
+        let term = search_term(subject).unwrap_or_default();
+        let records = self.records(term).await?;
+
+        Ok(records.into_iter().map(|record| {
+            let bucket = record.bucket.unwrap_or_else(|| "unknown".to_string());
+            let (category, severity) = if bucket.starts_with("darkweb") || bucket.starts_with("leaks") {
+                (FindingCategory::Leak, "high")
+            } else if bucket.starts_with("dumpster") {
+                (FindingCategory::Leak, "medium")
+            } else {
+                (FindingCategory::WebMention, "low")
+            };
+
+            Finding {
+                provider: self.name().to_string(),
+                category,
+                title: record.name.unwrap_or_else(|| "Untitled record".to_string()),
+                description: format!("Search term found in Intelligence X bucket '{}'", bucket),
+                severity: severity.to_string(),
+                reference: Some(record.systemid),
+                observed_at: record.date,
+                sensitive: false,
+            }
+        }).collect())
+    }
+}
@@ -0,0 +1,206 @@
+// Comprehensive email investigation
+// Native port of guardr_ultimate_v2.py: HIBP, Leak-Lookup and Intelligence X
+// lookups followed by an AI risk analysis, emitted in the same report layout.
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::config::OsintConfig;
+use crate::llm::LlmChain;
+
+use super::hibp::{self, HibpBreach, HibpProvider};
+use super::intelx::{self, IntelxSource};
+use super::leak_lookup::{self, LeakLookupClient};
+
+const AI_BREACH_SAMPLE: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvestigationReport {
+    pub target: String,
+    pub timestamp: String,
+    pub hibp_breaches: Vec<HibpBreach>,
+    pub leak_lookup_data: Value,
+    pub intelx_data: Value,
+    pub ai_analysis: Value,
+    pub summary: InvestigationSummary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvestigationSummary {
+    pub total_hibp_breaches: usize,
+    pub earliest_breach: String,
+    pub most_recent_breach: String,
+    pub compromised_data_types: Vec<String>,
+    pub investigation_complete: bool,
+}
+
+pub struct Investigator {
+    hibp: Option<HibpProvider>,
+    leak_lookup: Option<LeakLookupClient>,
+    intelx: Option<IntelxSource>,
+    llm: Arc<LlmChain>,
+}
+
+impl Investigator {
+    pub fn from_config(config: &OsintConfig, llm: Arc<LlmChain>) -> Result<Self> {
+        let timeout = config.provider_timeout_seconds;
+
+        let hibp = config.hibp_api_key.as_deref()
+            .map(|key| HibpProvider::new(
+                key,
+                config.hibp_base_url.as_deref().unwrap_or(hibp::DEFAULT_BASE_URL),
+                timeout,
+            ))
+            .transpose()?;
+
+        let leak_lookup = config.leak_lookup_api_key.as_deref()
+            .map(|key| LeakLookupClient::new(key, leak_lookup::DEFAULT_BASE_URL, timeout))
+            .transpose()?;
+
+        let intelx = config.intelx_api_key.as_deref()
+            .map(|key| IntelxSource::new(
+                key,
+                config.intelx_base_url.as_deref().unwrap_or(intelx::DEFAULT_BASE_URL),
+                timeout,
+            ))
+            .transpose()?;
+
+        Ok(Self {
+            hibp,
+            leak_lookup,
+            intelx,
+            llm,
+        })
+    }
+
+    pub async fn investigate(&self, email: &str) -> InvestigationReport {
+        let target = email.trim().to_lowercase();
+
+        let (hibp_breaches, leak_lookup_data, intelx_data) = tokio::join!(
+            self.check_hibp_breaches(&target),
+            self.check_leak_lookup(&target),
+            self.search_intelligence_x(&target),
+        );
+
+        let breach_summary = json!({
+            "hibp_breach_count": hibp_breaches.len(),
+            "hibp_breaches": hibp_breaches.iter().take(AI_BREACH_SAMPLE).collect::<Vec<_>>(),
+            "leak_lookup_found": is_non_empty(&leak_lookup_data),
+            "intelx_found": is_non_empty(&intelx_data),
+        });
+
+        let ai_analysis = self.ai_risk_analysis(&target, &breach_summary).await;
+        let summary = summarize(&hibp_breaches);
+
+        InvestigationReport {
+            target,
+            timestamp: Utc::now().to_rfc3339(),
+            hibp_breaches,
+            leak_lookup_data,
+            intelx_data,
+            ai_analysis,
+            summary,
+        }
+    }
+
+    async fn check_hibp_breaches(&self, email: &str) -> Vec<HibpBreach> {
+        let Some(hibp) = &self.hibp else {
+            return Vec::new();
+        };
+
+        hibp.breaches(email).await.unwrap_or_else(|e| {
+            warn!("HIBP request failed: {}", e);
+            Vec::new()
+        })
+    }
+
+    async fn check_leak_lookup(&self, email: &str) -> Value {
+        let Some(leak_lookup) = &self.leak_lookup else {
+            return json!({});
+        };
+
+        leak_lookup.search_email(email).await.unwrap_or_else(|e| {
+            warn!("Leak Lookup failed: {}", e);
+            json!({})
+        })
+    }
+
+    async fn search_intelligence_x(&self, email: &str) -> Value {
+        let Some(intelx) = &self.intelx else {
+            return json!({});
+        };
+
+        intelx.search_raw(email).await.unwrap_or_else(|e| {
+            warn!("IntelX search failed: {}", e);
+            json!({})
+        })
+    }
+
+    async fn ai_risk_analysis(&self, email: &str, breach_summary: &Value) -> Value {
+        let prompt = risk_analysis_prompt(email, breach_summary);
+
+        match self.llm.complete_json::<Value>(&prompt).await {
+            Ok(response) => response.output,
+            Err(e) => json!({ "error": format!("AI analysis failed: {}", e) }),
+        }
+    }
+}
+
+fn is_non_empty(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Null => false,
+        _ => true,
+    }
+}
+
+fn summarize(breaches: &[HibpBreach]) -> InvestigationSummary {
+    let dates = breaches.iter().filter_map(|b| b.breach_date.as_deref());
+
+    let compromised_data_types: BTreeSet<String> = breaches.iter()
+        .flat_map(|b| b.data_classes.iter().cloned())
+        .collect();
+
+    InvestigationSummary {
+        total_hibp_breaches: breaches.len(),
+        earliest_breach: dates.clone().min().unwrap_or("Unknown").to_string(),
+        most_recent_breach: dates.max().unwrap_or("Unknown").to_string(),
+        compromised_data_types: compromised_data_types.into_iter().collect(),
+        investigation_complete: true,
+    }
+}
+
+fn risk_analysis_prompt(email: &str, breach_summary: &Value) -> String {
+    format!(
+        r#"You are an expert cybersecurity analyst specializing in dating safety.
+
+TARGET EMAIL: {email}
+
+BREACH DATA SUMMARY:
+{summary}
+
+Provide a comprehensive dating safety analysis including:
+
+1. OVERALL RISK SCORE (1-100)
+2. DATING-SPECIFIC VULNERABILITIES
+3. IDENTITY THEFT RISK
+4. SOCIAL ENGINEERING SUSCEPTIBILITY
+5. ACCOUNT TAKEOVER PROBABILITY
+6. SPECIFIC RED FLAGS FOR DATING
+7. IMMEDIATE PROTECTIVE ACTIONS
+8. LONG-TERM SECURITY RECOMMENDATIONS
+
+Focus on how these breaches specifically impact dating safety and personal security.
+Be thorough but practical. Consider credential stuffing, impersonation, and stalking risks.
+
+Format as detailed JSON with specific risk scores and actionable recommendations."#,
+        email = email,
+        summary = serde_json::to_string_pretty(breach_summary).unwrap_or_default(),
+    )
+}
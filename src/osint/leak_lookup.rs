@@ -0,0 +1,56 @@
+// Leak-Lookup breach search
+// Docs: https://leak-lookup.com/api
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Url};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub const DEFAULT_BASE_URL: &str = "https://leaklookup.com/api";
+
+#[derive(Clone)]
+pub struct LeakLookupClient {
+    client: Client,
+    api_key: String,
+    base_url: Url,
+}
+
+impl LeakLookupClient {
+    pub fn new(api_key: &str, base_url: &str, timeout_seconds: u64) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_seconds))
+            .build()?;
+
+        let base_url = Url::parse(&format!("{}/", base_url.trim_end_matches('/')))
+            .map_err(|e| anyhow!("Invalid Leak-Lookup base URL {}: {}", base_url, e))?;
+
+        Ok(Self {
+            client,
+            api_key: api_key.to_string(),
+            base_url,
+        })
+    }
+
+    // Returns the raw response body; `message` maps breach names to matched records
+    pub async fn search_email(&self, email: &str) -> Result<Value> {
+        let response = self.client.post(self.base_url.join("search")?)
+            .header("X-API-Key", &self.api_key)
+            .json(&json!({ "query": email }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Leak-Lookup API error: {}", response.status()));
+        }
+
+        let body: Value = response.json().await?;
+        if body["error"].as_str() == Some("true") {
+            return Err(anyhow!(
+                "Leak-Lookup error: {}",
+                body["message"].as_str().unwrap_or("unknown error")
+            ));
+        }
+
+        Ok(body)
+    }
+}
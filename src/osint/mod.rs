@@ -0,0 +1,138 @@
+// OSINT provider integrations
+// Each external data source implements one of the provider traits below so the
+// API handlers can fan out to whatever is configured in `OsintConfig`.
+
+use anyhow::Result;
+use axum::async_trait;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::OsintConfig;
+use aggregator::{BreachSource, OsintAggregator, OsintSource};
+
+pub mod aggregator;
+pub mod dehashed;
+pub mod hibp;
+pub mod intelx;
+pub mod investigation;
+pub mod leak_lookup;
+pub mod pwned_passwords;
+pub mod serper;
+
+// A single breach an email address was found in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreachRecord {
+    pub source: String,
+    pub breach_date: Option<NaiveDate>,
+    pub data_types: Vec<String>,
+    pub severity: String,
+    pub provider: String,
+    pub verified: bool,
+    // HIBP's IsSensitive: the breach reveals membership of the site itself
+    #[serde(default)]
+    pub sensitive: bool,
+}
+
+// A paste (Pastebin etc.) an email address appeared in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasteRecord {
+    pub source: String,
+    pub id: String,
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub email_count: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BreachLookup {
+    pub breaches: Vec<BreachRecord>,
+    pub pastes: Vec<PasteRecord>,
+}
+
+impl BreachLookup {
+    // Merge another lookup into this one, skipping breaches we already know about
+    pub fn merge(&mut self, other: BreachLookup) {
+        for breach in other.breaches {
+            let already_known = self.breaches.iter()
+                .any(|b| b.source.eq_ignore_ascii_case(&breach.source));
+            if !already_known {
+                self.breaches.push(breach);
+            }
+        }
+
+        for paste in other.pastes {
+            let already_known = self.pastes.iter()
+                .any(|p| p.source == paste.source && p.id == paste.id);
+            if !already_known {
+                self.pastes.push(paste);
+            }
+        }
+    }
+}
+
+#[async_trait]
+pub trait BreachProvider: Send + Sync {
+    // Short identifier reported as `provider` on every record
+    fn name(&self) -> &'static str;
+
+    // Look up all breaches and pastes for an email address
+    async fn lookup(&self, email: &str) -> Result<BreachLookup>;
+}
+
+// Build every breach provider that has credentials configured
+pub fn breach_providers(config: &OsintConfig) -> Result<Vec<Arc<dyn BreachProvider>>> {
+    let mut providers: Vec<Arc<dyn BreachProvider>> = Vec::new();
+
+    if let Some(api_key) = &config.hibp_api_key {
+        providers.push(Arc::new(hibp::HibpProvider::new(
+            api_key,
+            config.hibp_base_url.as_deref().unwrap_or(hibp::DEFAULT_BASE_URL),
+            config.provider_timeout_seconds,
+        )?));
+    }
+
+    Ok(providers)
+}
+
+// Build the aggregation engine from the breach providers plus every other
+// configured OSINT source
+pub fn aggregator(
+    config: &OsintConfig,
+    breach_providers: &[Arc<dyn BreachProvider>],
+) -> Result<OsintAggregator> {
+    let mut sources: Vec<Arc<dyn OsintSource>> = breach_providers.iter()
+        .map(|provider| Arc::new(BreachSource(provider.clone())) as Arc<dyn OsintSource>)
+        .collect();
+
+    if let Some(api_key) = &config.intelx_api_key {
+        sources.push(Arc::new(intelx::IntelxSource::new(
+            api_key,
+            config.intelx_base_url.as_deref().unwrap_or(intelx::DEFAULT_BASE_URL),
+            config.provider_timeout_seconds,
+        )?));
+    }
+
+    if let (Some(account_email), Some(api_key)) = (&config.dehashed_email, &config.dehashed_api_key) {
+        sources.push(Arc::new(dehashed::DehashedSource::new(
+            account_email,
+            api_key,
+            dehashed::DEFAULT_BASE_URL,
+            config.provider_timeout_seconds,
+        )?));
+    }
+
+    if let Some(api_key) = &config.serper_api_key {
+        sources.push(Arc::new(serper::SerperSource::new(
+            api_key,
+            serper::DEFAULT_BASE_URL,
+            config.provider_timeout_seconds,
+        )?));
+    }
+
+    Ok(OsintAggregator::new(
+        sources,
+        Duration::from_secs(config.provider_timeout_seconds),
+    ))
+}
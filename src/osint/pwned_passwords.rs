@@ -0,0 +1,201 @@
+// Pwned Passwords range lookups (k-anonymity)
+// Only the first 5 hex characters of the password's SHA-1 ever leave the process.
+// Docs: https://haveibeenpwned.com/API/v3#PwnedPasswords
+//
+// In offline mode the same range responses are read from a directory produced by
+// the official PwnedPasswordsDownloader (one `<PREFIX>.txt` file per prefix), so
+// air-gapped deployments get identical results without network access.
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, StatusCode, Url};
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::OsintConfig;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.pwnedpasswords.com";
+const USER_AGENT: &str = "Guardr-Dating-Safety-Platform";
+const PREFIX_LEN: usize = 5;
+
+#[derive(Debug, Clone)]
+enum RangeSource {
+    Online {
+        client: Client,
+        base_url: Url,
+        padding: bool,
+    },
+    Offline {
+        directory: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct PwnedPasswords {
+    source: RangeSource,
+}
+
+impl PwnedPasswords {
+    pub fn online(base_url: &str, padding: bool, timeout_seconds: u64) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(timeout_seconds))
+            .build()?;
+
+        let base_url = Url::parse(&format!("{}/", base_url.trim_end_matches('/')))
+            .map_err(|e| anyhow!("Invalid Pwned Passwords base URL {}: {}", base_url, e))?;
+
+        Ok(Self {
+            source: RangeSource::Online { client, base_url, padding },
+        })
+    }
+
+    pub fn offline(directory: impl Into<PathBuf>) -> Result<Self> {
+        let directory = directory.into();
+        if !directory.is_dir() {
+            return Err(anyhow!(
+                "Pwned Passwords range directory {} does not exist",
+                directory.display()
+            ));
+        }
+
+        Ok(Self {
+            source: RangeSource::Offline { directory },
+        })
+    }
+
+    // Offline directory wins over the online API when both are configured
+    pub fn from_config(config: &OsintConfig) -> Result<Self> {
+        match &config.pwned_passwords_dir {
+            Some(directory) => Self::offline(directory),
+            None => Self::online(
+                config.pwned_passwords_base_url.as_deref().unwrap_or(DEFAULT_BASE_URL),
+                config.pwned_passwords_padding,
+                config.provider_timeout_seconds,
+            ),
+        }
+    }
+
+    // Number of times the password appears in the Pwned Passwords corpus (0 = not found).
+    // A range that can't be fetched or read is an error, never a 0.
+    pub async fn occurrences(&self, password: &str) -> Result<u64> {
+        let hash = sha1_hex(password);
+        let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+
+        let range = match &self.source {
+            RangeSource::Online { client, base_url, padding } => {
+                fetch_range(client, base_url, *padding, prefix).await?
+            }
+            RangeSource::Offline { directory } => read_range(directory, prefix).await?,
+        };
+
+        Ok(find_suffix(&range, suffix))
+    }
+}
+
+fn sha1_hex(password: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(password.as_bytes());
+    hex::encode_upper(hasher.finalize())
+}
+
+async fn fetch_range(client: &Client, base_url: &Url, padding: bool, prefix: &str) -> Result<String> {
+    let url = base_url.join(&format!("range/{}", prefix))?;
+
+    let mut request = client.get(url);
+    if padding {
+        request = request.header("Add-Padding", "true");
+    }
+
+    let response = request.send().await?;
+    match response.status() {
+        StatusCode::OK => Ok(response.text().await?),
+        status => Err(anyhow!("Pwned Passwords API error: {}", status)),
+    }
+}
+
+async fn read_range(directory: &Path, prefix: &str) -> Result<String> {
+    // The downloader writes `<PREFIX>.txt`; also accept bare `<PREFIX>` files
+    for candidate in [format!("{}.txt", prefix), prefix.to_string()] {
+        let path = directory.join(candidate);
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(tokio::fs::read_to_string(&path).await?);
+        }
+    }
+
+    Err(anyhow!(
+        "Range file for prefix {} not found in {}",
+        prefix,
+        directory.display()
+    ))
+}
+
+// Range bodies are `SUFFIX:COUNT` lines; padded entries carry a count of 0
+fn find_suffix(range: &str, suffix: &str) -> u64 {
+    range.lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SHA-1("password") = 5BAA6 1E4C9B93F3F0682250B6CF8331B7EE68FD8
+    const RANGE: &str = "1D2DA4053E34E76F6576ED1DA63134B5E2A:2\r\n1E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365\r\n";
+
+    fn range_dir(files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("guardr-test-ranges-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for (name, body) in files {
+            std::fs::write(directory.join(name), body).unwrap();
+        }
+        directory
+    }
+
+    #[tokio::test]
+    async fn offline_ranges_are_read_from_the_downloader_layout() {
+        let directory = range_dir(&[("5BAA6.txt", RANGE)]);
+        let pwned = PwnedPasswords::offline(&directory).unwrap();
+
+        assert_eq!(pwned.occurrences("password").await.unwrap(), 9659365);
+        std::fs::remove_dir_all(&directory).ok();
+    }
+
+    #[tokio::test]
+    async fn bare_prefix_files_work_too() {
+        let directory = range_dir(&[("5BAA6", RANGE)]);
+        let pwned = PwnedPasswords::offline(&directory).unwrap();
+
+        assert_eq!(pwned.occurrences("password").await.unwrap(), 9659365);
+        std::fs::remove_dir_all(&directory).ok();
+    }
+
+    #[tokio::test]
+    async fn padded_entries_count_as_zero() {
+        // SHA-1("P@ssw0rd") = 21BD1 2DC183F740EE76F27B78EB39C8AD972A757
+        let directory = range_dir(&[("21BD1.txt", "2DC183F740EE76F27B78EB39C8AD972A757:0\n")]);
+        let pwned = PwnedPasswords::offline(&directory).unwrap();
+
+        assert_eq!(pwned.occurrences("P@ssw0rd").await.unwrap(), 0);
+        std::fs::remove_dir_all(&directory).ok();
+    }
+
+    #[tokio::test]
+    async fn a_missing_range_file_is_an_error() {
+        let directory = range_dir(&[]);
+        let pwned = PwnedPasswords::offline(&directory).unwrap();
+
+        let error = pwned.occurrences("password").await.unwrap_err();
+        assert!(error.to_string().starts_with("Range file for prefix 5BAA6 not found"), "{}", error);
+        std::fs::remove_dir_all(&directory).ok();
+    }
+
+    #[test]
+    fn the_range_directory_must_exist() {
+        let missing = std::env::temp_dir().join(format!("guardr-test-ranges-{}", uuid::Uuid::new_v4()));
+        assert!(PwnedPasswords::offline(missing).is_err());
+    }
+}
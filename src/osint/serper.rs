@@ -0,0 +1,108 @@
+// Serper (Google Search API) web mentions
+// Docs: https://serper.dev
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+use super::aggregator::{Finding, FindingCategory, OsintSource, Subject};
+
+pub const DEFAULT_BASE_URL: &str = "https://google.serper.dev";
+const MAX_RESULTS: u32 = 10;
+
+// Terms that make a web mention worth more than background noise
+const RISK_TERMS: [&str; 8] = [
+    "arrest", "scam", "fraud", "catfish", "assault", "warrant", "sex offender", "restraining order",
+];
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    organic: Vec<OrganicResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrganicResult {
+    title: String,
+    link: String,
+    #[serde(default)]
+    snippet: String,
+    date: Option<String>,
+}
+
+pub struct SerperSource {
+    client: Client,
+    api_key: String,
+    base_url: Url,
+}
+
+impl SerperSource {
+    pub fn new(api_key: &str, base_url: &str, timeout_seconds: u64) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_seconds))
+            .build()?;
+
+        let base_url = Url::parse(&format!("{}/", base_url.trim_end_matches('/')))
+            .map_err(|e| anyhow!("Invalid Serper base URL {}: {}", base_url, e))?;
+
+        Ok(Self {
+            client,
+            api_key: api_key.to_string(),
+            base_url,
+        })
+    }
+}
+
+fn search_query(subject: &Subject) -> Option<String> {
+    let who = subject.name.as_deref().or(subject.username.as_deref())?;
+    match &subject.location {
+        Some(location) => Some(format!("\"{}\" {}", who, location)),
+        None => Some(format!("\"{}\"", who)),
+    }
+}
+
+#[async_trait]
+impl OsintSource for SerperSource {
+    fn name(&self) -> &'static str {
+        "serper"
+    }
+
+    fn supports(&self, subject: &Subject) -> bool {
+        search_query(subject).is_some()
+    }
+
+    async fn search(&self, subject: &Subject) -> Result<Vec<Finding>> {
+        let query = search_query(subject).unwrap_or_default();
+
+        let response = self.client.post(self.base_url.join("search")?)
+            .header("X-API-KEY", &self.api_key)
+            .json(&json!({ "q": query, "num": MAX_RESULTS }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Serper API error: {}", response.status()));
+        }
+
+        let results = response.json::<SearchResponse>().await?.organic;
+
+        Ok(results.into_iter().map(|result| {
+            let text = format!("{} {}", result.title, result.snippet).to_lowercase();
+            let severity = if RISK_TERMS.iter().any(|term| text.contains(term)) { "high" } else { "low" };
+
+            Finding {
+                provider: self.name().to_string(),
+                category: FindingCategory::WebMention,
+                title: result.title,
+                description: result.snippet,
+                severity: severity.to_string(),
+                reference: Some(result.link),
+                observed_at: result.date,
+                sensitive: false,
+            }
+        }).collect())
+    }
+}
@@ -0,0 +1,46 @@
+// Built-in ranked dictionaries for the strength estimator
+// Order matters: a word's rank is its position in the list, and the estimator
+// treats rank as the number of guesses an attacker needs to reach it.
+
+pub const COMMON_PASSWORDS: &[&str] = &[
+    "123456", "password", "12345678", "qwerty", "123456789", "12345", "1234", "111111",
+    "1234567", "dragon", "123123", "baseball", "abc123", "football", "monkey", "letmein",
+    "696969", "shadow", "master", "666666", "qwertyuiop", "123321", "mustang", "1234567890",
+    "michael", "654321", "superman", "1qaz2wsx", "7777777", "121212", "000000", "qazwsx",
+    "123qwe", "killer", "trustno1", "jordan", "jennifer", "zxcvbnm", "asdfgh", "hunter",
+    "buster", "soccer", "harley", "batman", "andrew", "tigger", "sunshine", "iloveyou",
+    "2000", "charlie", "robert", "thomas", "hockey", "ranger", "daniel", "starwars",
+    "klaster", "112233", "george", "computer", "michelle", "jessica", "pepper", "1111",
+    "zxcvbn", "555555", "11111111", "131313", "freedom", "777777", "pass", "maggie",
+    "159753", "aaaaaa", "ginger", "princess", "joshua", "cheese", "amanda", "summer",
+    "love", "ashley", "nicole", "chelsea", "biteme", "matthew", "access", "yankees",
+    "987654321", "dallas", "austin", "thunder", "taylor", "matrix", "welcome", "admin",
+    "passw0rd", "changeme", "login", "root", "qwerty123", "password1", "iloveyou1", "lovely",
+    "flower", "hottie", "loveme", "babygirl", "angel", "friends", "butterfly", "purple",
+    "jordan23", "liverpool", "secret", "whatever", "zaq1zaq1", "1q2w3e4r", "superstar", "blink182",
+    "cookie", "nothing", "samsung", "orange", "diamond", "corvette", "merlin", "bailey",
+    "phoenix", "silver", "sexy", "tinder", "single", "dating", "passion", "romance",
+];
+
+pub const ENGLISH_WORDS: &[&str] = &[
+    "love", "life", "time", "good", "home", "world", "house", "money", "happy", "heart",
+    "friend", "family", "music", "baby", "star", "girl", "boy", "dream", "summer", "winter",
+    "spring", "night", "day", "sun", "moon", "blue", "red", "black", "white", "green",
+    "dog", "cat", "horse", "tiger", "lion", "eagle", "wolf", "bear", "king", "queen",
+    "prince", "magic", "power", "fire", "water", "sky", "rock", "heaven", "hell", "forever",
+    "sweet", "honey", "sugar", "candy", "cherry", "apple", "banana", "game", "player", "super",
+    "hello", "internet", "match", "kiss", "hug", "beach", "ocean", "river", "mountain", "rose",
+    "lucky", "correct", "battery", "staple", "school", "garden", "pretty", "beautiful", "angel", "devil",
+    "soul", "mother", "father", "sister", "brother", "daughter", "son", "wife", "husband", "party",
+    "coffee", "chocolate", "pizza", "guitar", "dance", "travel", "secret", "crazy", "cool", "smile",
+];
+
+pub const NAMES: &[&str] = &[
+    "michael", "james", "john", "robert", "david", "william", "richard", "joseph", "thomas", "charles",
+    "daniel", "matthew", "anthony", "mark", "steven", "paul", "andrew", "joshua", "kevin", "brian",
+    "george", "jessica", "jennifer", "ashley", "sarah", "emily", "amanda", "melissa", "michelle", "nicole",
+    "elizabeth", "stephanie", "heather", "lisa", "maria", "laura", "anna", "emma", "olivia", "sophia",
+    "chris", "alex", "sam", "max", "jack", "ben", "tom", "mike", "kate", "lucy",
+    "smith", "johnson", "williams", "brown", "jones", "garcia", "miller", "davis", "rodriguez", "martinez",
+    "wilson", "anderson", "taylor", "moore", "jackson", "martin", "lee", "harris", "clark", "lewis",
+];
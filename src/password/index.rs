@@ -0,0 +1,142 @@
+// Wordlist index builder
+// Turns a text password list into the fingerprint index `Wordlist::load`
+// memory-maps. Only the CLI builds indexes.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::wordlist::{fingerprint, ENTRY_LEN, HEADER_LEN, INDEX_MAGIC};
+
+// Fingerprints sorted in memory before spilling to disk (128 MB)
+const BUILD_CHUNK_ENTRIES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default)]
+pub struct BuildStats {
+    pub lines_read: u64,
+    pub entries_written: u64,
+    pub bytes_written: u64,
+}
+
+// Build an index from a text list. Fingerprints are sorted in fixed-size
+// chunks spilled to temporary runs and merged, so memory stays bounded.
+pub fn build_index(input: impl AsRef<Path>, output: impl AsRef<Path>) -> io::Result<BuildStats> {
+    let reader = BufReader::new(File::open(input)?);
+    let mut stats = BuildStats::default();
+    let mut chunk: Vec<u64> = Vec::new();
+    let mut runs = SortedRuns::default();
+
+    for line in reader.split(b'\n') {
+        let line = line?;
+        // Lists in the wild are not always valid UTF-8; keep what decodes
+        let line = String::from_utf8_lossy(&line);
+        let entry = line.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        stats.lines_read += 1;
+        chunk.push(fingerprint(entry));
+        if chunk.len() >= BUILD_CHUNK_ENTRIES {
+            runs.spill(&mut chunk)?;
+        }
+    }
+
+    let output = output.as_ref();
+    let mut temp = output.as_os_str().to_owned();
+    temp.push(".tmp");
+    let mut writer = BufWriter::new(File::create(&temp)?);
+    // The count is patched in once the merge knows it
+    writer.write_all(INDEX_MAGIC)?;
+    writer.write_all(&0u64.to_le_bytes())?;
+
+    let mut last = None;
+    let mut write = |fingerprint: u64| -> io::Result<()> {
+        if last != Some(fingerprint) {
+            writer.write_all(&fingerprint.to_le_bytes())?;
+            stats.entries_written += 1;
+            last = Some(fingerprint);
+        }
+        Ok(())
+    };
+
+    if runs.paths.is_empty() {
+        chunk.sort_unstable();
+        chunk.into_iter().try_for_each(&mut write)?;
+    } else {
+        if !chunk.is_empty() {
+            runs.spill(&mut chunk)?;
+        }
+        runs.merge(&mut write)?;
+    }
+
+    let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(INDEX_MAGIC.len() as u64))?;
+    file.write_all(&stats.entries_written.to_le_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, output)?;
+
+    stats.bytes_written = HEADER_LEN as u64 + stats.entries_written * ENTRY_LEN as u64;
+    Ok(stats)
+}
+
+#[derive(Default)]
+struct SortedRuns {
+    paths: Vec<PathBuf>,
+}
+
+impl SortedRuns {
+    fn spill(&mut self, chunk: &mut Vec<u64>) -> io::Result<()> {
+        chunk.sort_unstable();
+        chunk.dedup();
+
+        let path = std::env::temp_dir().join(format!("guardr_wordlist_{}.bin", uuid::Uuid::new_v4()));
+        self.paths.push(path.clone());
+
+        let mut out = BufWriter::new(File::create(&path)?);
+        for fingerprint in chunk.drain(..) {
+            out.write_all(&fingerprint.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    fn merge(&self, write: &mut impl FnMut(u64) -> io::Result<()>) -> io::Result<()> {
+        let mut readers = self.paths.iter()
+            .map(|path| File::open(path).map(BufReader::new))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut heap = BinaryHeap::new();
+        for (run, reader) in readers.iter_mut().enumerate() {
+            if let Some(fingerprint) = read_fingerprint(reader)? {
+                heap.push(Reverse((fingerprint, run)));
+            }
+        }
+
+        while let Some(Reverse((fingerprint, run))) = heap.pop() {
+            write(fingerprint)?;
+            if let Some(next) = read_fingerprint(&mut readers[run])? {
+                heap.push(Reverse((next, run)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SortedRuns {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn read_fingerprint(reader: &mut BufReader<File>) -> io::Result<Option<u64>> {
+    let mut bytes = [0u8; ENTRY_LEN];
+    match reader.read_exact(&mut bytes) {
+        Ok(()) => Ok(Some(u64::from_le_bytes(bytes))),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
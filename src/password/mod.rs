@@ -0,0 +1,36 @@
+// Password analysis shared by the API and the CLI
+// `strength` estimates how many guesses an attacker needs by finding the
+// patterns a password is built from, rather than counting character classes.
+// `weakness` turns a wordlist, well-known fragments and the user's own details
+// into a yes/no verdict with reasons, for the CLI, the dump filters and the API.
+
+pub mod dictionaries;
+pub mod index;
+pub mod strength;
+pub mod weakness;
+pub mod wordlist;
+
+pub use strength::estimate;
+pub use weakness::PasswordChecker;
+pub use wordlist::Wordlist;
+
+// Words worth guessing from an email address: the whole address, the local
+// part, and its pieces split on punctuation and digits
+pub fn email_inputs(email: &str) -> Vec<String> {
+    let email = email.trim().to_lowercase();
+    let mut inputs = vec![email.clone()];
+
+    if let Some((local, domain)) = email.split_once('@') {
+        inputs.push(local.to_string());
+        inputs.extend(
+            local.split(|c: char| !c.is_alphabetic())
+                .filter(|part| part.chars().count() >= 3)
+                .map(str::to_string),
+        );
+        if let Some(name) = domain.split('.').next() {
+            inputs.push(name.to_string());
+        }
+    }
+
+    inputs
+}
@@ -0,0 +1,1286 @@
+// Pattern-aware password strength estimation, modelled on Dropbox's zxcvbn
+// The password is split into the cheapest-to-guess sequence of patterns
+// (dictionary words incl. reversed and l33t forms, keyboard walks, repeats,
+// sequences, dates and years, with brute force filling the gaps). The product
+// of their guess counts is the estimate, which is then turned into crack
+// times for several attacker models and a 0-4 score.
+
+use chrono::{Datelike, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::OnceLock;
+
+use super::dictionaries::{COMMON_PASSWORDS, ENGLISH_WORDS, NAMES};
+
+// Anything past this is already far beyond a feasible attack
+const MAX_ANALYZED_LENGTH: usize = 64;
+
+const BRUTEFORCE_CARDINALITY: f64 = 10.0;
+const MIN_GUESSES_BEFORE_GROWING_SEQUENCE: f64 = 10_000.0;
+const MIN_SUBMATCH_GUESSES_SINGLE_CHAR: f64 = 10.0;
+const MIN_SUBMATCH_GUESSES_MULTI_CHAR: f64 = 50.0;
+
+const MIN_YEAR_SPACE: i32 = 20;
+const DATE_MIN_YEAR: i32 = 1000;
+const DATE_MAX_YEAR: i32 = 2050;
+const MAX_SEQUENCE_DELTA: i32 = 5;
+
+// Each l33t enumeration reruns the dictionary matcher, so cap the combinations
+const MAX_L33T_SUBSTITUTIONS: usize = 64;
+
+const L33T_TABLE: &[(char, &[char])] = &[
+    ('a', &['4', '@']),
+    ('b', &['8']),
+    ('c', &['(', '{', '[', '<']),
+    ('e', &['3']),
+    ('g', &['6', '9']),
+    ('i', &['1', '!', '|']),
+    ('l', &['1', '|', '7']),
+    ('o', &['0']),
+    ('s', &['$', '5']),
+    ('t', &['+', '7']),
+    ('x', &['%']),
+    ('z', &['2']),
+];
+
+// Split points for unseparated dates, by token length
+const DATE_SPLITS: &[(usize, &[(usize, usize)])] = &[
+    (4, &[(1, 2), (2, 3)]),
+    (5, &[(1, 3), (2, 3)]),
+    (6, &[(1, 2), (2, 4), (4, 5)]),
+    (7, &[(1, 3), (2, 3), (4, 5), (4, 6)]),
+    (8, &[(2, 4), (4, 6)]),
+];
+
+const QWERTY: &str = r#"`~ 1! 2@ 3# 4$ 5% 6^ 7& 8* 9( 0) -_ =+
+    qQ wW eE rR tT yY uU iI oO pP [{ ]} \|
+     aA sS dD fF gG hH jJ kK lL ;: '"
+      zZ xX cC vV bB nN mM ,< .> /?"#;
+
+const KEYPAD: &str = "  / * -
+7 8 9 +
+4 5 6
+1 2 3
+  0 .";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Substitution {
+    pub from: char,
+    pub to: char,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "pattern", rename_all = "snake_case")]
+pub enum Pattern {
+    Dictionary {
+        dictionary: &'static str,
+        matched_word: String,
+        rank: usize,
+        reversed: bool,
+        l33t: bool,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        substitutions: Vec<Substitution>,
+    },
+    Spatial {
+        graph: &'static str,
+        turns: usize,
+        shifted_count: usize,
+    },
+    Repeat {
+        base_token: String,
+        base_guesses: f64,
+        repeat_count: usize,
+    },
+    Sequence {
+        sequence_name: &'static str,
+        ascending: bool,
+    },
+    Date {
+        year: i32,
+        month: u32,
+        day: u32,
+        separator: String,
+    },
+    Year {
+        year: i32,
+    },
+    Bruteforce,
+}
+
+impl Pattern {
+    fn name(&self) -> &'static str {
+        match self {
+            Pattern::Dictionary { .. } => "dictionary",
+            Pattern::Spatial { .. } => "spatial",
+            Pattern::Repeat { .. } => "repeat",
+            Pattern::Sequence { .. } => "sequence",
+            Pattern::Date { .. } => "date",
+            Pattern::Year { .. } => "year",
+            Pattern::Bruteforce => "bruteforce",
+        }
+    }
+}
+
+// One piece of the password and how it was recognised; i and j are inclusive
+// character offsets
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternMatch {
+    #[serde(flatten)]
+    pub pattern: Pattern,
+    pub token: String,
+    pub i: usize,
+    pub j: usize,
+    pub guesses: f64,
+    pub guesses_log10: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrackTime {
+    pub seconds: f64,
+    pub display: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrackTimes {
+    pub online_throttled: CrackTime,
+    pub online_unthrottled: CrackTime,
+    pub offline_slow_hash: CrackTime,
+    pub offline_fast_hash: CrackTime,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Feedback {
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StrengthEstimate {
+    pub guesses: f64,
+    pub guesses_log10: f64,
+    // 0 (too guessable) to 4 (very unguessable)
+    pub score: u8,
+    pub crack_times: CrackTimes,
+    pub patterns: Vec<PatternMatch>,
+    pub feedback: Feedback,
+}
+
+impl StrengthEstimate {
+    // Names of the patterns that make up the password, e.g. ["dictionary", "year"]
+    pub fn pattern_names(&self) -> Vec<&'static str> {
+        self.patterns.iter().map(|m| m.pattern.name()).collect()
+    }
+}
+
+// `user_inputs` are words an attacker would try first for this user (email
+// local part, name, ...); they form an extra dictionary ranked by position
+pub fn estimate(password: &str, user_inputs: &[String]) -> StrengthEstimate {
+    let chars: Vec<char> = password.chars().take(MAX_ANALYZED_LENGTH).collect();
+
+    let user_dictionary = user_inputs.iter()
+        .map(|input| input.trim().to_lowercase())
+        .filter(|input| !input.is_empty())
+        .enumerate()
+        .fold(HashMap::new(), |mut ranks, (rank, word)| {
+            ranks.entry(word).or_insert(rank + 1);
+            ranks
+        });
+
+    let analyzer = Analyzer::new(&user_dictionary);
+    let matches = analyzer.omnimatch(&chars);
+    let (guesses, patterns) = analyzer.most_guessable(&chars, matches);
+    let score = guesses_to_score(guesses);
+
+    StrengthEstimate {
+        guesses,
+        guesses_log10: guesses.log10(),
+        score,
+        crack_times: CrackTimes {
+            online_throttled: crack_time(guesses / (100.0 / 3600.0)),
+            online_unthrottled: crack_time(guesses / 10.0),
+            offline_slow_hash: crack_time(guesses / 1e4),
+            offline_fast_hash: crack_time(guesses / 1e10),
+        },
+        feedback: feedback(score, &patterns),
+        patterns,
+    }
+}
+
+fn ranked(words: &[&str]) -> HashMap<String, usize> {
+    words.iter().enumerate().fold(HashMap::new(), |mut ranks, (rank, word)| {
+        ranks.entry(word.to_string()).or_insert(rank + 1);
+        ranks
+    })
+}
+
+fn builtin_dictionaries() -> &'static [(&'static str, HashMap<String, usize>)] {
+    static DICTIONARIES: OnceLock<Vec<(&'static str, HashMap<String, usize>)>> = OnceLock::new();
+    DICTIONARIES.get_or_init(|| vec![
+        ("passwords", ranked(COMMON_PASSWORDS)),
+        ("english", ranked(ENGLISH_WORDS)),
+        ("names", ranked(NAMES)),
+    ])
+}
+
+struct Graph {
+    name: &'static str,
+    adjacency: HashMap<char, Vec<Option<String>>>,
+    shifted: HashSet<char>,
+    starting_positions: f64,
+    average_degree: f64,
+}
+
+// Keys are laid out as tokens of `unshifted shifted` characters; on slanted
+// keyboards each row is offset half a key from the one above
+fn build_graph(name: &'static str, layout: &str, slanted: bool) -> Graph {
+    let token_size = layout.split_whitespace().next().map_or(1, str::len);
+    let x_unit = token_size as i32 + 1;
+
+    let mut positions: HashMap<(i32, i32), String> = HashMap::new();
+    for (y, line) in layout.lines().enumerate() {
+        let slant = if slanted { y as i32 } else { 0 };
+        for token in line.split_whitespace() {
+            let index = line.find(token).unwrap_or(0) as i32;
+            positions.insert(((index - slant) / x_unit, y as i32), token.to_string());
+        }
+    }
+
+    let neighbours: &[(i32, i32)] = if slanted {
+        &[(-1, 0), (0, -1), (1, -1), (1, 0), (0, 1), (-1, 1)]
+    } else {
+        &[(-1, 0), (-1, -1), (0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1)]
+    };
+
+    let mut adjacency = HashMap::new();
+    let mut shifted = HashSet::new();
+    for (&(x, y), token) in &positions {
+        for (index, c) in token.chars().enumerate() {
+            if index == 1 {
+                shifted.insert(c);
+            }
+            adjacency.insert(c, neighbours.iter()
+                .map(|(dx, dy)| positions.get(&(x + dx, y + dy)).cloned())
+                .collect::<Vec<_>>());
+        }
+    }
+
+    let degrees: usize = adjacency.values()
+        .map(|adjacent| adjacent.iter().flatten().count())
+        .sum();
+    let average_degree = degrees as f64 / adjacency.len().max(1) as f64;
+
+    Graph {
+        name,
+        starting_positions: adjacency.len() as f64,
+        average_degree,
+        adjacency,
+        shifted,
+    }
+}
+
+fn graphs() -> &'static [Graph] {
+    static GRAPHS: OnceLock<Vec<Graph>> = OnceLock::new();
+    GRAPHS.get_or_init(|| vec![
+        build_graph("qwerty", QWERTY, true),
+        build_graph("keypad", KEYPAD, false),
+    ])
+}
+
+fn separated_date_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(\d{1,4})([\s/\\_.-])(\d{1,2})([\s/\\_.-])(\d{1,4})$").expect("valid date regex")
+    })
+}
+
+struct Analyzer<'a> {
+    dictionaries: Vec<(&'static str, &'a HashMap<String, usize>)>,
+    reference_year: i32,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(user_dictionary: &'a HashMap<String, usize>) -> Self {
+        let mut dictionaries: Vec<(&'static str, &'a HashMap<String, usize>)> = builtin_dictionaries()
+            .iter()
+            .map(|(name, ranks)| (*name, ranks))
+            .collect();
+        if !user_dictionary.is_empty() {
+            dictionaries.push(("user_inputs", user_dictionary));
+        }
+
+        Self {
+            dictionaries,
+            reference_year: Utc::now().year(),
+        }
+    }
+
+    fn omnimatch(&self, password: &[char]) -> Vec<PatternMatch> {
+        let mut matches = Vec::new();
+        matches.extend(self.dictionary_matches(password));
+        matches.extend(self.reverse_dictionary_matches(password));
+        matches.extend(self.l33t_matches(password));
+        matches.extend(spatial_matches(password));
+        matches.extend(self.repeat_matches(password));
+        matches.extend(sequence_matches(password));
+        matches.extend(self.year_matches(password));
+        matches.extend(self.date_matches(password));
+        matches
+    }
+
+    // (i, j, dictionary, word, rank) for every dictionary word found in `lower`
+    fn find_words(&self, lower: &[char]) -> Vec<(usize, usize, &'static str, String, usize)> {
+        let mut found = Vec::new();
+        for i in 0..lower.len() {
+            let mut word = String::new();
+            for (j, c) in lower.iter().enumerate().skip(i) {
+                word.push(*c);
+                for (name, ranks) in &self.dictionaries {
+                    if let Some(&rank) = ranks.get(&word) {
+                        found.push((i, j, *name, word.clone(), rank));
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    fn dictionary_matches(&self, password: &[char]) -> Vec<PatternMatch> {
+        let lower = lowercase(password);
+        self.find_words(&lower).into_iter()
+            .map(|(i, j, dictionary, matched_word, rank)| {
+                let pattern = Pattern::Dictionary {
+                    dictionary,
+                    matched_word,
+                    rank,
+                    reversed: false,
+                    l33t: false,
+                    substitutions: Vec::new(),
+                };
+                finish(pattern, password, i, j)
+            })
+            .collect()
+    }
+
+    fn reverse_dictionary_matches(&self, password: &[char]) -> Vec<PatternMatch> {
+        let n = password.len();
+        let reversed: Vec<char> = lowercase(password).into_iter().rev().collect();
+
+        self.find_words(&reversed).into_iter()
+            // Palindromes are already found by the forward pass
+            .filter(|(_, _, _, word, _)| word.chars().rev().collect::<String>() != *word)
+            .map(|(i, j, dictionary, matched_word, rank)| {
+                let pattern = Pattern::Dictionary {
+                    dictionary,
+                    matched_word,
+                    rank,
+                    reversed: true,
+                    l33t: false,
+                    substitutions: Vec::new(),
+                };
+                finish(pattern, password, n - 1 - j, n - 1 - i)
+            })
+            .collect()
+    }
+
+    fn l33t_matches(&self, password: &[char]) -> Vec<PatternMatch> {
+        let lower = lowercase(password);
+        let mut matches = Vec::new();
+        let mut seen = HashSet::new();
+
+        for substitutions in l33t_substitutions(&lower) {
+            let translated: Vec<char> = lower.iter()
+                .map(|c| substitutions.get(c).copied().unwrap_or(*c))
+                .collect();
+
+            for (i, j, dictionary, matched_word, rank) in self.find_words(&translated) {
+                // Single characters like "4" -> "a" are better left to brute force
+                if i == j {
+                    continue;
+                }
+                let used: Vec<Substitution> = {
+                    let token: HashSet<char> = lower[i..=j].iter().copied().collect();
+                    let mut used: Vec<Substitution> = substitutions.iter()
+                        .filter(|(from, _)| token.contains(from))
+                        .map(|(&from, &to)| Substitution { from, to })
+                        .collect();
+                    used.sort_by_key(|s| s.from);
+                    used
+                };
+                if used.is_empty() || !seen.insert((i, j, dictionary, matched_word.clone())) {
+                    continue;
+                }
+
+                let pattern = Pattern::Dictionary {
+                    dictionary,
+                    matched_word,
+                    rank,
+                    reversed: false,
+                    l33t: true,
+                    substitutions: used,
+                };
+                matches.push(finish(pattern, password, i, j));
+            }
+        }
+        matches
+    }
+
+    fn repeat_matches(&self, password: &[char]) -> Vec<PatternMatch> {
+        let n = password.len();
+        let mut matches = Vec::new();
+        let mut i = 0;
+
+        while i + 1 < n {
+            // Longest run of a repeated base starting here, preferring the shortest base
+            let mut best: Option<(usize, usize)> = None;
+            for base in 1..=(n - i) / 2 {
+                let mut count = 1;
+                while i + (count + 1) * base <= n
+                    && password[i..i + base] == password[i + count * base..i + (count + 1) * base]
+                {
+                    count += 1;
+                }
+                if count >= 2 && best.is_none_or(|(b, c)| base * count > b * c) {
+                    best = Some((base, count));
+                }
+            }
+
+            let Some((base, repeat_count)) = best else {
+                i += 1;
+                continue;
+            };
+
+            let base_chars = &password[i..i + base];
+            let (base_guesses, _) = self.most_guessable(base_chars, self.omnimatch(base_chars));
+            let j = i + base * repeat_count - 1;
+
+            let pattern = Pattern::Repeat {
+                base_token: base_chars.iter().collect(),
+                base_guesses,
+                repeat_count,
+            };
+            matches.push(finish(pattern, password, i, j));
+            i = j + 1;
+        }
+        matches
+    }
+
+    fn year_matches(&self, password: &[char]) -> Vec<PatternMatch> {
+        let mut matches = Vec::new();
+        let mut i = 0;
+
+        while i + 4 <= password.len() {
+            let token = &password[i..i + 4];
+            let recent = token.iter().all(char::is_ascii_digit)
+                && matches!((token[0], token[1]), ('1', '9') | ('2', '0'));
+            if !recent {
+                i += 1;
+                continue;
+            }
+
+            let year = token.iter().collect::<String>().parse().unwrap_or(0);
+            matches.push(finish(Pattern::Year { year }, password, i, i + 3));
+            i += 4;
+        }
+        matches
+    }
+
+    fn date_matches(&self, password: &[char]) -> Vec<PatternMatch> {
+        let n = password.len();
+        let mut matches = Vec::new();
+
+        // Without separators: 4-8 digits split into day, month and year
+        for i in 0..n.saturating_sub(3) {
+            for j in i + 3..(i + 8).min(n) {
+                let token = &password[i..=j];
+                if !token.iter().all(char::is_ascii_digit) {
+                    break;
+                }
+                let text: String = token.iter().collect();
+                let Some((_, splits)) = DATE_SPLITS.iter().find(|(len, _)| *len == token.len()) else {
+                    continue;
+                };
+
+                let best = splits.iter()
+                    .filter_map(|&(k, l)| {
+                        let ints = [text[..k].parse().ok()?, text[k..l].parse().ok()?, text[l..].parse().ok()?];
+                        map_ints_to_dmy(ints)
+                    })
+                    .min_by_key(|(year, _, _)| (year - self.reference_year).abs());
+
+                if let Some((year, month, day)) = best {
+                    let pattern = Pattern::Date { year, month, day, separator: String::new() };
+                    matches.push(finish(pattern, password, i, j));
+                }
+            }
+        }
+
+        // With separators, e.g. 1/1/91 or 1991-01-01
+        for i in 0..n.saturating_sub(5) {
+            for j in i + 5..(i + 10).min(n) {
+                let text: String = password[i..=j].iter().collect();
+                let Some(captures) = separated_date_regex().captures(&text) else {
+                    continue;
+                };
+                if captures[2] != captures[4] {
+                    continue;
+                }
+
+                let ints = [captures[1].parse(), captures[3].parse(), captures[5].parse()];
+                let [Ok(a), Ok(b), Ok(c)] = ints else {
+                    continue;
+                };
+                if let Some((year, month, day)) = map_ints_to_dmy([a, b, c]) {
+                    let separator = captures[2].to_string();
+                    matches.push(finish(Pattern::Date { year, month, day, separator }, password, i, j));
+                }
+            }
+        }
+
+        // Drop dates contained in a longer date, e.g. "1991" inside "1/1/1991"
+        let spans: Vec<(usize, usize)> = matches.iter().map(|m| (m.i, m.j)).collect();
+        matches.retain(|m| {
+            !spans.iter().any(|&(i, j)| (i, j) != (m.i, m.j) && i <= m.i && j >= m.j)
+        });
+        matches
+    }
+
+    // Find the sequence of non-overlapping matches covering the password that
+    // minimises total guesses: l! * product(guesses) + D^(l-1), where the
+    // additive term penalises sequences made of many short pieces
+    fn most_guessable(&self, password: &[char], matches: Vec<PatternMatch>) -> (f64, Vec<PatternMatch>) {
+        let n = password.len();
+        if n == 0 {
+            return (1.0, Vec::new());
+        }
+
+        let mut by_end: Vec<Vec<PatternMatch>> = vec![Vec::new(); n];
+        for m in matches {
+            by_end[m.j].push(m);
+        }
+        for ending in &mut by_end {
+            ending.sort_by_key(|m| m.i);
+        }
+
+        let mut optimal: Vec<BTreeMap<usize, Step>> = vec![BTreeMap::new(); n];
+
+        for (k, ending) in by_end.into_iter().enumerate() {
+            for m in ending {
+                if m.i > 0 {
+                    let lengths: Vec<usize> = optimal[m.i - 1].keys().copied().collect();
+                    for l in lengths {
+                        update(&mut optimal, m.clone(), l + 1);
+                    }
+                } else {
+                    update(&mut optimal, m, 1);
+                }
+            }
+
+            // Brute force from the start, or extending any non-brute-force sequence
+            update(&mut optimal, bruteforce(password, 0, k), 1);
+            for i in 1..=k {
+                let lengths: Vec<usize> = optimal[i - 1].iter()
+                    .filter(|(_, step)| !matches!(step.m.pattern, Pattern::Bruteforce))
+                    .map(|(l, _)| *l)
+                    .collect();
+                for l in lengths {
+                    update(&mut optimal, bruteforce(password, i, k), l + 1);
+                }
+            }
+        }
+
+        let Some((&best_length, best)) = optimal[n - 1].iter()
+            .min_by(|a, b| a.1.g.total_cmp(&b.1.g))
+        else {
+            return (1.0, Vec::new());
+        };
+        let guesses = best.g;
+
+        let mut sequence = Vec::new();
+        let mut k = n - 1;
+        let mut l = best_length;
+        while let Some(step) = optimal[k].get(&l) {
+            sequence.push(step.m.clone());
+            if step.m.i == 0 {
+                break;
+            }
+            k = step.m.i - 1;
+            l -= 1;
+        }
+        sequence.reverse();
+
+        (guesses, sequence)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    m: PatternMatch,
+    // Product of guesses along the sequence ending here
+    pi: f64,
+    // Total guesses for the sequence, including the length penalty
+    g: f64,
+}
+
+fn update(optimal: &mut [BTreeMap<usize, Step>], m: PatternMatch, l: usize) {
+    let k = m.j;
+    let mut pi = m.guesses;
+    if l > 1 {
+        match optimal[m.i - 1].get(&(l - 1)) {
+            Some(previous) => pi *= previous.pi,
+            None => return,
+        }
+    }
+    let g = factorial(l) * pi + MIN_GUESSES_BEFORE_GROWING_SEQUENCE.powi(l as i32 - 1);
+
+    // A sequence at most this long already does at least as well
+    if optimal[k].iter().any(|(&other, step)| other <= l && step.g <= g) {
+        return;
+    }
+    optimal[k].insert(l, Step { m, pi, g });
+}
+
+fn factorial(n: usize) -> f64 {
+    (2..=n).map(|i| i as f64).product()
+}
+
+fn n_choose_k(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (1..=k).fold(1.0, |acc, i| acc * (n - k + i) as f64 / i as f64)
+}
+
+fn lowercase(password: &[char]) -> Vec<char> {
+    password.iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect()
+}
+
+// Every way of reading the l33t characters present in the password as letters
+fn l33t_substitutions(lower: &[char]) -> Vec<HashMap<char, char>> {
+    let mut candidates: Vec<(char, Vec<char>)> = Vec::new();
+    for c in lower.iter().copied().collect::<HashSet<char>>() {
+        let letters: Vec<char> = L33T_TABLE.iter()
+            .filter(|(_, subs)| subs.contains(&c))
+            .map(|(letter, _)| *letter)
+            .collect();
+        if !letters.is_empty() {
+            candidates.push((c, letters));
+        }
+    }
+    if candidates.is_empty() {
+        return Vec::new();
+    }
+    candidates.sort();
+
+    let mut maps = vec![HashMap::new()];
+    for (from, letters) in candidates {
+        maps = maps.into_iter()
+            .flat_map(|map| letters.iter().map(move |&to| {
+                let mut map = map.clone();
+                map.insert(from, to);
+                map
+            }))
+            .take(MAX_L33T_SUBSTITUTIONS)
+            .collect();
+    }
+    maps
+}
+
+fn spatial_matches(password: &[char]) -> Vec<PatternMatch> {
+    let mut matches = Vec::new();
+
+    for graph in graphs() {
+        let mut i = 0;
+        while i + 1 < password.len() {
+            let mut j = i + 1;
+            let mut last_direction = None;
+            let mut turns = 0;
+            let mut shifted_count = usize::from(graph.shifted.contains(&password[i]));
+
+            loop {
+                let next = password.get(j).and_then(|&c| {
+                    graph.adjacency.get(&password[j - 1])?
+                        .iter()
+                        .enumerate()
+                        .find_map(|(direction, key)| {
+                            let position = key.as_ref()?.chars().position(|k| k == c)?;
+                            Some((direction, position == 1))
+                        })
+                });
+
+                match next {
+                    Some((direction, shifted)) => {
+                        if shifted {
+                            shifted_count += 1;
+                        }
+                        if last_direction != Some(direction) {
+                            turns += 1;
+                            last_direction = Some(direction);
+                        }
+                        j += 1;
+                    }
+                    None => {
+                        // Walks of three or more keys count
+                        if j - i > 2 {
+                            let pattern = Pattern::Spatial { graph: graph.name, turns, shifted_count };
+                            matches.push(finish(pattern, password, i, j - 1));
+                        }
+                        i = j;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn sequence_matches(password: &[char]) -> Vec<PatternMatch> {
+    let mut matches = Vec::new();
+    if password.len() < 2 {
+        return matches;
+    }
+
+    let mut push = |i: usize, j: usize, delta: i32| {
+        if (j - i > 1 || delta.abs() == 1) && delta != 0 && delta.abs() <= MAX_SEQUENCE_DELTA {
+            let token = &password[i..=j];
+            let sequence_name = if token.iter().all(char::is_ascii_lowercase) {
+                "lower"
+            } else if token.iter().all(char::is_ascii_uppercase) {
+                "upper"
+            } else if token.iter().all(char::is_ascii_digit) {
+                "digits"
+            } else {
+                "unicode"
+            };
+            let pattern = Pattern::Sequence { sequence_name, ascending: delta > 0 };
+            matches.push(finish(pattern, password, i, j));
+        }
+    };
+
+    let mut i = 0;
+    let mut last_delta = None;
+    for k in 1..password.len() {
+        let delta = password[k] as i32 - password[k - 1] as i32;
+        let last = *last_delta.get_or_insert(delta);
+        if delta == last {
+            continue;
+        }
+        push(i, k - 1, last);
+        i = k - 1;
+        last_delta = Some(delta);
+    }
+    push(i, password.len() - 1, last_delta.unwrap_or(0));
+
+    matches
+}
+
+fn bruteforce(password: &[char], i: usize, j: usize) -> PatternMatch {
+    finish(Pattern::Bruteforce, password, i, j)
+}
+
+// Attach the token and the guess count, applying the floor for sub-matches
+fn finish(pattern: Pattern, password: &[char], i: usize, j: usize) -> PatternMatch {
+    let token = &password[i..=j];
+    let len = token.len();
+
+    let min_guesses = if len < password.len() {
+        if len == 1 { MIN_SUBMATCH_GUESSES_SINGLE_CHAR } else { MIN_SUBMATCH_GUESSES_MULTI_CHAR }
+    } else {
+        1.0
+    };
+    let guesses = pattern_guesses(&pattern, token).max(min_guesses);
+
+    PatternMatch {
+        pattern,
+        token: token.iter().collect(),
+        i,
+        j,
+        guesses,
+        guesses_log10: guesses.log10(),
+    }
+}
+
+fn pattern_guesses(pattern: &Pattern, token: &[char]) -> f64 {
+    let reference_year = Utc::now().year();
+    let len = token.len();
+
+    match pattern {
+        Pattern::Bruteforce => {
+            let min = if len == 1 { MIN_SUBMATCH_GUESSES_SINGLE_CHAR } else { MIN_SUBMATCH_GUESSES_MULTI_CHAR };
+            BRUTEFORCE_CARDINALITY.powi(len as i32).max(min + 1.0)
+        }
+        Pattern::Dictionary { rank, reversed, substitutions, .. } => {
+            let reversed = if *reversed { 2.0 } else { 1.0 };
+            *rank as f64 * uppercase_variations(token) * l33t_variations(token, substitutions) * reversed
+        }
+        Pattern::Spatial { graph, turns, shifted_count } => {
+            let graph = graphs().iter().find(|g| g.name == *graph).expect("known keyboard graph");
+            spatial_guesses(graph, len, *turns, *shifted_count)
+        }
+        Pattern::Repeat { base_guesses, repeat_count, .. } => base_guesses * *repeat_count as f64,
+        Pattern::Sequence { ascending, .. } => {
+            let base = match token[0] {
+                'a' | 'A' | 'z' | 'Z' | '0' | '1' | '9' => 4.0,
+                c if c.is_ascii_digit() => 10.0,
+                _ => 26.0,
+            };
+            let direction = if *ascending { 1.0 } else { 2.0 };
+            base * direction * len as f64
+        }
+        Pattern::Date { year, separator, .. } => {
+            let year_space = (year - reference_year).abs().max(MIN_YEAR_SPACE) as f64;
+            let separator = if separator.is_empty() { 1.0 } else { 4.0 };
+            year_space * 365.0 * separator
+        }
+        Pattern::Year { year } => (year - reference_year).abs().max(MIN_YEAR_SPACE) as f64,
+    }
+}
+
+fn spatial_guesses(graph: &Graph, len: usize, turns: usize, shifted_count: usize) -> f64 {
+    let mut guesses = 0.0;
+    for i in 2..=len {
+        for j in 1..=turns.min(i - 1) {
+            guesses += n_choose_k(i - 1, j - 1) * graph.starting_positions * graph.average_degree.powi(j as i32);
+        }
+    }
+
+    if shifted_count > 0 {
+        let unshifted = len.saturating_sub(shifted_count);
+        if unshifted == 0 {
+            guesses *= 2.0;
+        } else {
+            guesses *= (1..=shifted_count.min(unshifted))
+                .map(|i| n_choose_k(shifted_count + unshifted, i))
+                .sum::<f64>();
+        }
+    }
+    guesses
+}
+
+fn uppercase_variations(token: &[char]) -> f64 {
+    let upper = token.iter().filter(|c| c.is_uppercase()).count();
+    let lower = token.iter().filter(|c| c.is_lowercase()).count();
+
+    if upper == 0 {
+        return 1.0;
+    }
+    // Capitalised, all caps and last-letter caps are tried first
+    let first_only = token[0].is_uppercase() && upper == 1;
+    let last_only = token[token.len() - 1].is_uppercase() && upper == 1;
+    if first_only || last_only || lower == 0 {
+        return 2.0;
+    }
+
+    (1..=upper.min(lower)).map(|i| n_choose_k(upper + lower, i)).sum()
+}
+
+fn l33t_variations(token: &[char], substitutions: &[Substitution]) -> f64 {
+    let lower = lowercase(token);
+
+    substitutions.iter().fold(1.0, |variations, sub| {
+        let subbed = lower.iter().filter(|&&c| c == sub.from).count();
+        let unsubbed = lower.iter().filter(|&&c| c == sub.to).count();
+
+        if subbed == 0 || unsubbed == 0 {
+            variations * 2.0
+        } else {
+            let possibilities: f64 = (1..=subbed.min(unsubbed))
+                .map(|i| n_choose_k(subbed + unsubbed, i))
+                .sum();
+            variations * possibilities
+        }
+    })
+}
+
+// Day/month/year from three integers in any of the usual orders
+fn map_ints_to_dmy(ints: [i32; 3]) -> Option<(i32, u32, u32)> {
+    if ints[1] > 31 || ints[1] <= 0 {
+        return None;
+    }
+
+    let mut over_12 = 0;
+    let mut over_31 = 0;
+    let mut under_1 = 0;
+    for &int in &ints {
+        if (99 < int && int < DATE_MIN_YEAR) || int > DATE_MAX_YEAR {
+            return None;
+        }
+        over_31 += usize::from(int > 31);
+        over_12 += usize::from(int > 12);
+        under_1 += usize::from(int <= 0);
+    }
+    if over_31 >= 2 || over_12 == 3 || under_1 >= 2 {
+        return None;
+    }
+
+    let splits = [(ints[2], [ints[0], ints[1]]), (ints[0], [ints[1], ints[2]])];
+
+    // A four-digit year settles which end the year is on
+    for &(year, rest) in &splits {
+        if (DATE_MIN_YEAR..=DATE_MAX_YEAR).contains(&year) {
+            let (day, month) = map_ints_to_dm(rest)?;
+            return Some((year, month, day));
+        }
+    }
+
+    splits.iter().find_map(|&(year, rest)| {
+        let (day, month) = map_ints_to_dm(rest)?;
+        Some((two_to_four_digit_year(year), month, day))
+    })
+}
+
+fn map_ints_to_dm([a, b]: [i32; 2]) -> Option<(u32, u32)> {
+    [(a, b), (b, a)].into_iter()
+        .find(|&(day, month)| (1..=31).contains(&day) && (1..=12).contains(&month))
+        .map(|(day, month)| (day as u32, month as u32))
+}
+
+fn two_to_four_digit_year(year: i32) -> i32 {
+    match year {
+        y if y > 99 => y,
+        y if y > 50 => 1900 + y,
+        y => 2000 + y,
+    }
+}
+
+fn guesses_to_score(guesses: f64) -> u8 {
+    const DELTA: f64 = 5.0;
+    match guesses {
+        g if g < 1e3 + DELTA => 0,
+        g if g < 1e6 + DELTA => 1,
+        g if g < 1e8 + DELTA => 2,
+        g if g < 1e10 + DELTA => 3,
+        _ => 4,
+    }
+}
+
+fn crack_time(seconds: f64) -> CrackTime {
+    const MINUTE: f64 = 60.0;
+    const HOUR: f64 = MINUTE * 60.0;
+    const DAY: f64 = HOUR * 24.0;
+    const MONTH: f64 = DAY * 31.0;
+    const YEAR: f64 = MONTH * 12.0;
+    const CENTURY: f64 = YEAR * 100.0;
+
+    let unit = |amount: f64, name: &str| {
+        let amount = amount.round() as u64;
+        format!("{} {}{}", amount, name, if amount == 1 { "" } else { "s" })
+    };
+
+    let display = match seconds {
+        s if s < 1.0 => "less than a second".to_string(),
+        s if s < MINUTE => unit(s, "second"),
+        s if s < HOUR => unit(s / MINUTE, "minute"),
+        s if s < DAY => unit(s / HOUR, "hour"),
+        s if s < MONTH => unit(s / DAY, "day"),
+        s if s < YEAR => unit(s / MONTH, "month"),
+        s if s < CENTURY => unit(s / YEAR, "year"),
+        _ => "centuries".to_string(),
+    };
+
+    CrackTime { seconds, display }
+}
+
+fn feedback(score: u8, patterns: &[PatternMatch]) -> Feedback {
+    if patterns.is_empty() {
+        return Feedback {
+            warning: None,
+            suggestions: vec![
+                "Use a few words, avoid common phrases".to_string(),
+                "No need for symbols, digits, or uppercase letters".to_string(),
+            ],
+        };
+    }
+    if score > 2 {
+        return Feedback::default();
+    }
+
+    let mut feedback = Feedback {
+        warning: None,
+        suggestions: vec!["Add another word or two. Uncommon words are better.".to_string()],
+    };
+
+    let Some(longest) = patterns.iter().max_by_key(|m| m.j - m.i) else {
+        return feedback;
+    };
+    let sole_match = patterns.len() == 1;
+    let token: Vec<char> = longest.token.chars().collect();
+
+    match &longest.pattern {
+        Pattern::Dictionary { dictionary, rank, reversed, l33t, .. } => {
+            feedback.warning = match *dictionary {
+                "passwords" if sole_match && !l33t && !reversed => Some(match rank {
+                    r if *r <= 10 => "This is a top-10 common password",
+                    r if *r <= 100 => "This is a top-100 common password",
+                    _ => "This is a very common password",
+                }),
+                "passwords" if longest.guesses_log10 <= 4.0 => Some("This is similar to a commonly used password"),
+                "english" if sole_match => Some("A word by itself is easy to guess"),
+                "names" if sole_match => Some("Names and surnames by themselves are easy to guess"),
+                "names" => Some("Common names and surnames are easy to guess"),
+                "user_inputs" => Some("This is based on your email address or name"),
+                _ => None,
+            }
+            .map(str::to_string);
+
+            let upper = token.iter().filter(|c| c.is_uppercase()).count();
+            if token.first().is_some_and(|c| c.is_uppercase()) && upper == 1 {
+                feedback.suggestions.push("Capitalization doesn't help very much".to_string());
+            } else if upper > 0 && !token.iter().any(|c| c.is_lowercase()) {
+                feedback.suggestions.push("All-uppercase is almost as easy to guess as all-lowercase".to_string());
+            }
+            if *reversed && token.len() >= 4 {
+                feedback.suggestions.push("Reversed words aren't much harder to guess".to_string());
+            }
+            if *l33t {
+                feedback.suggestions.push(
+                    "Predictable substitutions like '@' instead of 'a' don't help very much".to_string(),
+                );
+            }
+        }
+        Pattern::Spatial { turns, .. } => {
+            feedback.warning = Some(if *turns == 1 {
+                "Straight rows of keys are easy to guess".to_string()
+            } else {
+                "Short keyboard patterns are easy to guess".to_string()
+            });
+            feedback.suggestions.push("Use a longer keyboard pattern with more turns".to_string());
+        }
+        Pattern::Repeat { base_token, .. } => {
+            feedback.warning = Some(if base_token.chars().count() == 1 {
+                "Repeats like \"aaa\" are easy to guess".to_string()
+            } else {
+                "Repeats like \"abcabcabc\" are only slightly harder to guess than \"abc\"".to_string()
+            });
+            feedback.suggestions.push("Avoid repeated words and characters".to_string());
+        }
+        Pattern::Sequence { .. } => {
+            feedback.warning = Some("Sequences like abc or 6543 are easy to guess".to_string());
+            feedback.suggestions.push("Avoid sequences".to_string());
+        }
+        Pattern::Year { .. } => {
+            feedback.warning = Some("Recent years are easy to guess".to_string());
+            feedback.suggestions.push("Avoid recent years".to_string());
+            feedback.suggestions.push("Avoid years that are associated with you".to_string());
+        }
+        Pattern::Date { .. } => {
+            feedback.warning = Some("Dates are often easy to guess".to_string());
+            feedback.suggestions.push("Avoid dates and years that are associated with you".to_string());
+        }
+        Pattern::Bruteforce => {}
+    }
+
+    feedback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    fn matching<'m>(matches: &'m [PatternMatch], token: &str) -> Vec<&'m PatternMatch> {
+        matches.iter().filter(|m| m.token == token).collect()
+    }
+
+    #[test]
+    fn dictionary_words_carry_their_rank() {
+        let none = HashMap::new();
+        let matches = Analyzer::new(&none).dictionary_matches(&chars("Password"));
+
+        let word = matching(&matches, "Password");
+        assert_eq!(word.len(), 1, "{:?}", matches);
+        let Pattern::Dictionary { dictionary, matched_word, rank, reversed, l33t, .. } = &word[0].pattern else {
+            panic!("{:?}", word[0]);
+        };
+        assert_eq!((*dictionary, matched_word.as_str(), *rank), ("passwords", "password", 2));
+        assert!(!reversed && !l33t);
+        // Capitalising the first letter only doubles the guesses
+        assert_eq!(word[0].guesses, 4.0);
+    }
+
+    #[test]
+    fn reversed_words_are_found_and_cost_double() {
+        let none = HashMap::new();
+        let matches = Analyzer::new(&none).reverse_dictionary_matches(&chars("drowssap"));
+
+        let word = matching(&matches, "drowssap");
+        let Pattern::Dictionary { matched_word, reversed, .. } = &word[0].pattern else {
+            panic!("{:?}", matches);
+        };
+        assert_eq!(matched_word, "password");
+        assert!(reversed);
+        assert_eq!(word[0].guesses, 4.0);
+    }
+
+    #[test]
+    fn l33t_words_record_their_substitutions() {
+        let none = HashMap::new();
+        let matches = Analyzer::new(&none).l33t_matches(&chars("p4ssw0rd"));
+
+        let word = matching(&matches, "p4ssw0rd").into_iter()
+            .find(|m| matches!(&m.pattern, Pattern::Dictionary { matched_word, .. } if matched_word == "password"))
+            .unwrap_or_else(|| panic!("{:?}", matches));
+        let Pattern::Dictionary { l33t, substitutions, .. } = &word.pattern else { unreachable!() };
+        assert!(l33t);
+        let subs: Vec<(char, char)> = substitutions.iter().map(|s| (s.from, s.to)).collect();
+        assert_eq!(subs, vec![('0', 'o'), ('4', 'a')]);
+    }
+
+    #[test]
+    fn user_inputs_form_their_own_dictionary() {
+        let inputs = vec!["Alice".to_string(), "example".to_string()];
+        let estimate = estimate("alice", &inputs);
+
+        assert_eq!(estimate.pattern_names(), vec!["dictionary"]);
+        let Pattern::Dictionary { dictionary, rank, .. } = &estimate.patterns[0].pattern else { unreachable!() };
+        assert_eq!((*dictionary, *rank), ("user_inputs", 1));
+        assert_eq!(estimate.feedback.warning.as_deref(), Some("This is based on your email address or name"));
+    }
+
+    #[test]
+    fn keyboard_walks_count_turns_and_shifts() {
+        let matches = spatial_matches(&chars("asdfgh"));
+        let row = matches.iter().find(|m| matches!(m.pattern, Pattern::Spatial { graph: "qwerty", .. })).unwrap();
+        assert_eq!(row.token, "asdfgh");
+        assert!(matches!(row.pattern, Pattern::Spatial { turns: 1, shifted_count: 0, .. }));
+
+        let matches = spatial_matches(&chars("qazXSW"));
+        let walk = matches.iter().find(|m| matches!(m.pattern, Pattern::Spatial { graph: "qwerty", .. })).unwrap();
+        assert_eq!(walk.token, "qazXSW");
+        assert!(matches!(walk.pattern, Pattern::Spatial { turns: 3, shifted_count: 3, .. }), "{:?}", walk);
+
+        // Two keys aren't a walk
+        assert!(spatial_matches(&chars("as")).is_empty());
+    }
+
+    #[test]
+    fn sequences_by_alphabet_and_direction() {
+        let matches = sequence_matches(&chars("abcdef"));
+        assert_eq!(matches.len(), 1);
+        assert!(matches!(matches[0].pattern, Pattern::Sequence { sequence_name: "lower", ascending: true }));
+        // Starting at 'a' is among the first guesses
+        assert_eq!(matches[0].guesses, 4.0 * 6.0);
+
+        let matches = sequence_matches(&chars("9753"));
+        assert!(matches!(matches[0].pattern, Pattern::Sequence { sequence_name: "digits", ascending: false }));
+
+        let matches = sequence_matches(&chars("PQR"));
+        assert!(matches!(matches[0].pattern, Pattern::Sequence { sequence_name: "upper", ascending: true }));
+
+        // Steps larger than MAX_SEQUENCE_DELTA aren't sequences
+        assert!(sequence_matches(&chars("aks")).is_empty());
+    }
+
+    #[test]
+    fn repeats_prefer_the_longest_run() {
+        let none = HashMap::new();
+        let analyzer = Analyzer::new(&none);
+
+        let matches = analyzer.repeat_matches(&chars("aaaaa"));
+        assert_eq!(matches.len(), 1);
+        let Pattern::Repeat { base_token, repeat_count, .. } = &matches[0].pattern else { unreachable!() };
+        assert_eq!((base_token.as_str(), *repeat_count), ("a", 5));
+
+        let matches = analyzer.repeat_matches(&chars("abcabcabc"));
+        let Pattern::Repeat { base_token, base_guesses, repeat_count } = &matches[0].pattern else { unreachable!() };
+        assert_eq!((base_token.as_str(), *repeat_count), ("abc", 3));
+        assert_eq!(matches[0].guesses, base_guesses * 3.0);
+
+        let matches = analyzer.repeat_matches(&chars("x$$$$"));
+        assert_eq!((matches[0].i, matches[0].j), (1, 4));
+    }
+
+    #[test]
+    fn dates_with_and_without_separators() {
+        let none = HashMap::new();
+        let analyzer = Analyzer::new(&none);
+
+        let matches = analyzer.date_matches(&chars("13/05/1991"));
+        assert_eq!(matches.len(), 1, "{:?}", matches);
+        let Pattern::Date { year, month, day, separator } = &matches[0].pattern else { unreachable!() };
+        assert_eq!((*year, *month, *day, separator.as_str()), (1991, 5, 13, "/"));
+
+        let matches = analyzer.date_matches(&chars("13051991"));
+        let full = matching(&matches, "13051991");
+        let Pattern::Date { year, month, day, separator } = &full[0].pattern else { unreachable!() };
+        assert_eq!((*year, *month, *day, separator.as_str()), (1991, 5, 13, ""));
+
+        // No reading of these makes a day and a month
+        assert!(analyzer.date_matches(&chars("45/45/45")).is_empty());
+    }
+
+    #[test]
+    fn recent_years() {
+        let none = HashMap::new();
+        let matches = Analyzer::new(&none).year_matches(&chars("x1987y2024"));
+
+        let years: Vec<i32> = matches.iter()
+            .map(|m| match m.pattern { Pattern::Year { year } => year, _ => unreachable!() })
+            .collect();
+        assert_eq!(years, vec![1987, 2024]);
+        assert!(Analyzer::new(&none).year_matches(&chars("1687")).is_empty());
+    }
+
+    #[test]
+    fn scores_follow_guess_thresholds() {
+        assert_eq!(guesses_to_score(1e3), 0);
+        assert_eq!(guesses_to_score(1e3 + 6.0), 1);
+        assert_eq!(guesses_to_score(1e6 + 6.0), 2);
+        assert_eq!(guesses_to_score(1e8 + 6.0), 3);
+        assert_eq!(guesses_to_score(1e10 + 6.0), 4);
+    }
+
+    #[test]
+    fn crack_times_are_displayed_in_the_largest_unit() {
+        assert_eq!(crack_time(0.5).display, "less than a second");
+        assert_eq!(crack_time(1.0).display, "1 second");
+        assert_eq!(crack_time(90.0).display, "2 minutes");
+        assert_eq!(crack_time(3.0 * 86_400.0).display, "3 days");
+        assert_eq!(crack_time(1e12).display, "centuries");
+
+        // Each attacker model divides the same guess count by its rate
+        let estimate = estimate("correcthorse", &[]);
+        let times = &estimate.crack_times;
+        assert_eq!(times.online_throttled.seconds, estimate.guesses * 36.0);
+        assert_eq!(times.online_unthrottled.seconds, estimate.guesses / 10.0);
+        assert_eq!(times.offline_slow_hash.seconds, estimate.guesses / 1e4);
+        assert_eq!(times.offline_fast_hash.seconds, estimate.guesses / 1e10);
+    }
+
+    #[test]
+    fn known_passwords_score_as_expected() {
+        let fixtures: &[(&str, u8, &[&str])] = &[
+            ("password", 0, &["dictionary"]),
+            ("qwerty", 0, &["dictionary"]),
+            ("abcdef", 0, &["sequence"]),
+            ("zzzzzzzz", 0, &["repeat"]),
+            ("p4ssw0rd", 0, &["dictionary"]),
+            ("13/05/1991", 1, &["date"]),
+            ("correcthorsebatterystaple", 4, &["dictionary", "dictionary", "dictionary", "dictionary"]),
+        ];
+
+        for (password, score, patterns) in fixtures {
+            let estimate = estimate(password, &[]);
+            assert_eq!(estimate.score, *score, "{}: {:?}", password, estimate);
+            assert_eq!(estimate.pattern_names(), *patterns, "{}", password);
+        }
+
+        let common = estimate("password", &[]);
+        assert_eq!(common.feedback.warning.as_deref(), Some("This is a top-10 common password"));
+        // Strong passwords get no advice
+        assert!(estimate("correcthorsebatterystaple", &[]).feedback.suggestions.is_empty());
+    }
+
+    #[test]
+    fn only_the_first_characters_are_analyzed() {
+        let long = "a".repeat(MAX_ANALYZED_LENGTH * 2);
+        let estimate = estimate(&long, &[]);
+        assert_eq!(estimate.patterns.iter().map(|m| m.j + 1).max(), Some(MAX_ANALYZED_LENGTH));
+    }
+}
@@ -0,0 +1,233 @@
+// Weak-password verdicts
+// A password is weak when it is on the wordlist, contains a well-known
+// fragment, is built from the user's own email or name, or is too short.
+// Every rule that fires is reported, so callers can explain the verdict.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+use super::strength::StrengthEstimate;
+use super::wordlist::Wordlist;
+use super::email_inputs;
+
+pub const DEFAULT_MIN_LENGTH: usize = 8;
+
+// Estimator scores below this fall to an online attack
+const MIN_STRENGTH_SCORE: u8 = 2;
+
+// Personal details shorter than this match too many unrelated passwords
+const MIN_PERSONAL_INPUT_LENGTH: usize = 4;
+
+pub const COMMON_FRAGMENTS: &[&str] = &[
+    "123456", "password", "passw0rd", "qwerty", "1q2w3e4r", "zaq1zaq1", "abc123",
+    "letmein", "iloveyou", "welcome", "changeme", "trustno1", "football", "superman",
+    "monkey", "shadow", "master", "admin", "login", "root", "user",
+    "abc", "111", "000",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum WeaknessReason {
+    Wordlist,
+    CommonFragment { fragment: &'static str },
+    PersonalInfo { input: String },
+    TooShort { length: usize, minimum: usize },
+    Guessable { score: u8 },
+}
+
+impl fmt::Display for WeaknessReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeaknessReason::Wordlist => write!(f, "It appears on the list of commonly used passwords"),
+            WeaknessReason::CommonFragment { fragment } => {
+                write!(f, "It contains the common pattern \"{}\"", fragment)
+            }
+            WeaknessReason::PersonalInfo { input } => {
+                write!(f, "It is based on your personal details (\"{}\")", input)
+            }
+            WeaknessReason::TooShort { length, minimum } => {
+                write!(f, "It is {} characters long; use at least {}", length, minimum)
+            }
+            WeaknessReason::Guessable { score } => {
+                write!(f, "It could be guessed quickly (strength {}/4)", score)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Verdict {
+    pub weak: bool,
+    pub reasons: Vec<WeaknessReason>,
+}
+
+impl Verdict {
+    fn from_reasons(reasons: Vec<WeaknessReason>) -> Self {
+        Self {
+            weak: !reasons.is_empty(),
+            reasons,
+        }
+    }
+
+    // Fold in the pattern-based estimate, which catches what the rules miss
+    pub fn with_strength(mut self, strength: &StrengthEstimate) -> Self {
+        if strength.score < MIN_STRENGTH_SCORE {
+            self.reasons.push(WeaknessReason::Guessable { score: strength.score });
+            self.weak = true;
+        }
+        self
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.reasons.iter().map(|reason| reason.to_string()).collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PasswordChecker {
+    wordlist: Wordlist,
+    min_length: usize,
+}
+
+impl Default for PasswordChecker {
+    fn default() -> Self {
+        Self::new(Wordlist::builtin())
+    }
+}
+
+impl PasswordChecker {
+    pub fn new(wordlist: Wordlist) -> Self {
+        Self {
+            wordlist,
+            min_length: DEFAULT_MIN_LENGTH,
+        }
+    }
+
+    pub fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    // `personal` holds the user's own details, e.g. from `email_inputs`
+    pub fn check(&self, password: &str, personal: &[String]) -> Verdict {
+        let mut reasons = Vec::new();
+
+        if self.wordlist.contains(password) {
+            reasons.push(WeaknessReason::Wordlist);
+        }
+
+        reasons.extend(common_fragments(password)
+            .into_iter()
+            .map(|fragment| WeaknessReason::CommonFragment { fragment }));
+
+        if let Some(input) = personal_match(password, personal) {
+            reasons.push(WeaknessReason::PersonalInfo { input });
+        }
+
+        let length = password.chars().count();
+        if length < self.min_length {
+            reasons.push(WeaknessReason::TooShort { length, minimum: self.min_length });
+        }
+
+        Verdict::from_reasons(reasons)
+    }
+
+    // Dump records carry their own email and username to compare against;
+    // records without a password have nothing to judge
+    pub fn check_record(&self, record: &Value) -> Option<Verdict> {
+        let password = record.get("password")?.as_str().filter(|p| !p.is_empty())?;
+
+        let mut personal = record.get("email")
+            .and_then(|e| e.as_str())
+            .map(email_inputs)
+            .unwrap_or_default();
+        if let Some(username) = record.get("username").and_then(|u| u.as_str()) {
+            personal.push(username.to_lowercase());
+        }
+
+        Some(self.check(password, &personal))
+    }
+}
+
+// Fragments found in the password, leaving out ones covered by a longer hit
+// ("abc" inside "abc123")
+fn common_fragments(password: &str) -> Vec<&'static str> {
+    let lower = password.to_lowercase();
+    let found: Vec<&'static str> = COMMON_FRAGMENTS.iter()
+        .copied()
+        .filter(|fragment| lower.contains(fragment))
+        .collect();
+
+    found.iter()
+        .copied()
+        .filter(|fragment| !found.iter().any(|other| other.len() > fragment.len() && other.contains(fragment)))
+        .collect()
+}
+
+fn alphanumeric(text: &str) -> String {
+    text.to_lowercase().chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+fn letters(text: &str) -> String {
+    text.to_lowercase().chars().filter(|c| c.is_alphabetic()).collect()
+}
+
+// Read digits and symbols as the letters they usually stand in for
+fn unleet(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            '0' => Some('o'),
+            '1' | '!' | '|' => Some('i'),
+            '3' => Some('e'),
+            '4' | '@' => Some('a'),
+            '5' | '$' => Some('s'),
+            '7' | '+' => Some('t'),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+// The first personal detail the password contains, is contained in, or is a
+// near-miss spelling of
+fn personal_match(password: &str, personal: &[String]) -> Option<String> {
+    let plain = alphanumeric(password);
+    let decoded = unleet(password);
+    let password_letters = letters(password);
+
+    personal.iter().find_map(|input| {
+        let normalized = alphanumeric(input);
+        if normalized.chars().count() < MIN_PERSONAL_INPUT_LENGTH {
+            return None;
+        }
+
+        let contains = plain.contains(&normalized) || decoded.contains(&normalized);
+        // Whole addresses contain the provider name, which says nothing personal
+        let contained = !input.contains('@')
+            && password_letters.chars().count() >= MIN_PERSONAL_INPUT_LENGTH
+            && normalized.contains(&password_letters);
+        let input_letters = letters(input);
+        let similar = input_letters.chars().count() >= MIN_PERSONAL_INPUT_LENGTH
+            && levenshtein(&password_letters, &input_letters) <= (input_letters.chars().count() / 4).max(1);
+
+        (contains || contained || similar).then(|| input.clone())
+    })
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
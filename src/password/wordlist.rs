@@ -0,0 +1,163 @@
+// Weak-password wordlists
+// Either a plain text file with one password per line, held in memory, or a
+// prebuilt index from `guardr build-wordlist` (see `index`): a sorted array of 64-bit
+// SHA-256 fingerprints that is memory-mapped and binary searched, so lists
+// with hundreds of millions of entries load instantly and answer in
+// microseconds. Without a file the estimator's built-in common-password
+// dictionary is used instead.
+
+use memmap2::Mmap;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use super::dictionaries::COMMON_PASSWORDS;
+
+pub(super) const INDEX_MAGIC: &[u8; 8] = b"GRDRWL01";
+pub(super) const HEADER_LEN: usize = 16;
+pub(super) const ENTRY_LEN: usize = 8;
+
+#[derive(Debug, Clone, Default)]
+pub struct Wordlist {
+    backend: Backend,
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    Memory(HashSet<String>),
+    Index(Arc<FingerprintIndex>),
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Memory(HashSet::new())
+    }
+}
+
+#[derive(Debug)]
+struct FingerprintIndex {
+    map: Mmap,
+    len: usize,
+}
+
+impl FingerprintIndex {
+    fn open(file: &File) -> io::Result<Self> {
+        // Safety: index files are written once by build-wordlist and never
+        // modified in place
+        let map = unsafe { Mmap::map(file)? };
+
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        if map.len() < HEADER_LEN || &map[..8] != INDEX_MAGIC {
+            return Err(invalid("not a wordlist index"));
+        }
+        let len = u64::from_le_bytes(map[8..16].try_into().expect("8-byte count")) as usize;
+        if map.len() != HEADER_LEN + len * ENTRY_LEN {
+            return Err(invalid("wordlist index is truncated"));
+        }
+
+        Ok(Self { map, len })
+    }
+
+    fn get(&self, index: usize) -> u64 {
+        let start = HEADER_LEN + index * ENTRY_LEN;
+        u64::from_le_bytes(self.map[start..start + ENTRY_LEN].try_into().expect("8-byte entry"))
+    }
+
+    fn contains(&self, fingerprint: u64) -> bool {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.get(mid).cmp(&fingerprint) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return true,
+            }
+        }
+        false
+    }
+}
+
+// First 8 bytes of SHA-256; collisions are negligible even at 10^9 entries
+pub(super) fn fingerprint(password: &str) -> u64 {
+    let digest = Sha256::digest(password.as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().expect("8-byte prefix"))
+}
+
+impl Wordlist {
+    // Text lists and prebuilt indexes are told apart by the index header
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::open(path.as_ref())?;
+        let mut magic = [0u8; 8];
+        let is_index = file.read(&mut magic)? == magic.len() && &magic == INDEX_MAGIC;
+
+        if is_index {
+            let index = FingerprintIndex::open(&file)?;
+            return Ok(Self { backend: Backend::Index(Arc::new(index)) });
+        }
+
+        // Leaked lists are rarely clean UTF-8; a bad line shouldn't sink the whole list
+        let contents = fs::read(path)?;
+        // Trimming ensures no hidden whitespace messes with your glam
+        let entries = contents.split(|&b| b == b'\n')
+            .map(|line| String::from_utf8_lossy(line).trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        Ok(Self { backend: Backend::Memory(entries) })
+    }
+
+    pub fn builtin() -> Self {
+        Self {
+            backend: Backend::Memory(COMMON_PASSWORDS.iter().map(|p| p.to_string()).collect()),
+        }
+    }
+
+    // Lists are mostly lowercase, so "Dragon" counts as listed too
+    pub fn contains(&self, password: &str) -> bool {
+        let lower = password.to_lowercase();
+        match &self.backend {
+            Backend::Memory(entries) => entries.contains(password) || entries.contains(&lower),
+            Backend::Index(index) => {
+                index.contains(fingerprint(password))
+                    || (lower != password && index.contains(fingerprint(&lower)))
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.backend {
+            Backend::Memory(entries) => entries.len(),
+            Backend::Index(index) => index.len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        matches!(self.backend, Backend::Index(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_lists_survive_invalid_utf8() {
+        let path = std::env::temp_dir().join(format!("guardr-test-wordlist-{}.txt", uuid::Uuid::new_v4()));
+        fs::write(&path, b"hunter2\r\ncaf\xe9\n\n  Dragon \n").unwrap();
+        let wordlist = Wordlist::load(&path);
+        fs::remove_file(&path).ok();
+
+        let wordlist = wordlist.unwrap();
+        assert!(!wordlist.is_indexed());
+        assert_eq!(wordlist.len(), 3);
+        assert!(wordlist.contains("hunter2"));
+        assert!(wordlist.contains("caf\u{fffd}"));
+        assert!(wordlist.contains("Dragon"));
+    }
+}
@@ -0,0 +1,195 @@
+// Monthly usage quotas
+// Metered handlers call `QuotaGuard::charge` before doing any work. A call
+// costs [quota.costs] units for its endpoint (times the item count for bulk
+// endpoints) out of the caller's monthly allowance, and is refused with
+// AppError::QuotaExceeded if that would go over.
+//
+// usage_tracking records every charge and is the source of truth. Redis keeps
+// the month's running total per user so concurrent requests can't all squeeze
+// under the limit; it is primed from usage_tracking and dropped after an hour
+// without charges. If Redis can't be reached the guard checks usage_tracking
+// directly.
+
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::auth::AuthenticatedUser;
+use crate::config::QuotaConfig;
+use crate::database::{Database, UserSubscriptionTier};
+use crate::errors::AppError;
+
+// How long a cached total outlives its last charge
+const CACHE_TTL_SECS: i64 = 3600;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeLevel {
+    Warning,
+    Exceeded,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageNotice {
+    pub level: NoticeLevel,
+    pub message: String,
+}
+
+// What the cache said about a charge
+enum Reservation {
+    // Added to the cached total
+    Cached,
+    // Checked against usage_tracking only
+    Uncached,
+    // Refused, with what had been used
+    Exceeded(u32),
+}
+
+pub struct QuotaGuard {
+    config: QuotaConfig,
+    db: Arc<Database>,
+    redis: Arc<redis::Client>,
+}
+
+impl QuotaGuard {
+    pub fn new(config: QuotaConfig, db: Arc<Database>, redis: Arc<redis::Client>) -> Self {
+        Self { config, db, redis }
+    }
+
+    pub fn limit(&self, tier: &UserSubscriptionTier) -> u32 {
+        match tier {
+            UserSubscriptionTier::Free => self.config.free,
+            UserSubscriptionTier::Pro => self.config.pro,
+            UserSubscriptionTier::Enterprise => self.config.enterprise,
+        }
+    }
+
+    pub fn cost(&self, endpoint: &str) -> u32 {
+        self.config.costs.get(endpoint).copied().unwrap_or(1)
+    }
+
+    // Charge `items` calls to `endpoint` against the user's quota for this
+    // month, or refuse if there isn't enough left
+    pub async fn charge(&self, user: &AuthenticatedUser, endpoint: &str, items: u32) -> Result<(), AppError> {
+        let units = self.cost(endpoint).saturating_mul(items.max(1));
+        let limit = self.limit(&user.subscription_tier);
+        let now = Utc::now();
+        let month = current_month(now);
+
+        let reservation = match self.reserve_cached(user.user_id, &month, units, limit, now).await {
+            Ok(reservation) => reservation,
+            Err(e) => {
+                warn!("Quota cache unavailable, checking usage_tracking: {}", e);
+                let used = self.db.get_monthly_usage_total(user.user_id, &month).await?.max(0) as u32;
+                if used.saturating_add(units) > limit {
+                    Reservation::Exceeded(used)
+                } else {
+                    Reservation::Uncached
+                }
+            }
+        };
+
+        if let Reservation::Exceeded(used) = reservation {
+            return Err(AppError::QuotaExceeded { limit, used, resets_at: period_end(now) });
+        }
+
+        if let Err(e) = self.db.track_api_usage(user.user_id, endpoint, units).await {
+            // Hand the units back so a failed write doesn't use up quota
+            if let Reservation::Cached = reservation {
+                if let Err(e) = self.release(user.user_id, &month, units).await {
+                    warn!("Failed to release quota reservation: {}", e);
+                }
+            }
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    // Notices for the usage stats once the quota is nearly or wholly spent
+    pub fn notices(&self, used: u32, limit: u32, resets_at: DateTime<Utc>) -> Vec<UsageNotice> {
+        let resets = resets_at.format("%Y-%m-%d %H:%M UTC");
+        let notice = if used > limit {
+            UsageNotice {
+                level: NoticeLevel::Exceeded,
+                message: format!(
+                    "{} units over your monthly quota of {}. Metered requests are refused until {}.",
+                    used - limit, limit, resets,
+                ),
+            }
+        } else if used == limit {
+            UsageNotice {
+                level: NoticeLevel::Exceeded,
+                message: format!("Monthly quota of {} used up. Metered requests are refused until {}.", limit, resets),
+            }
+        } else if used as u64 * 100 >= self.config.warn_at_percent as u64 * limit as u64 {
+            UsageNotice {
+                level: NoticeLevel::Warning,
+                message: format!(
+                    "{}% of your monthly quota used; {} units left until {}.",
+                    used as u64 * 100 / limit as u64, limit - used, resets,
+                ),
+            }
+        } else {
+            return Vec::new();
+        };
+        vec![notice]
+    }
+
+    async fn reserve_cached(
+        &self,
+        user_id: Uuid,
+        month: &str,
+        units: u32,
+        limit: u32,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<Reservation> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let key = cache_key(user_id, month);
+        let ttl = (period_end(now) - now).num_seconds().clamp(1, CACHE_TTL_SECS);
+
+        let cached: Option<u32> = redis::cmd("GET").arg(&key).query_async(&mut conn).await?;
+        if cached.is_none() {
+            let used = self.db.get_monthly_usage_total(user_id, month).await?;
+            // Another request may have primed it first; theirs stands
+            redis::cmd("SET").arg(&key).arg(used).arg("NX").arg("EX").arg(ttl)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+        }
+
+        let (total,): (u32,) = redis::pipe()
+            .atomic()
+            .cmd("INCRBY").arg(&key).arg(units)
+            .cmd("EXPIRE").arg(&key).arg(ttl).ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        if total > limit {
+            redis::cmd("DECRBY").arg(&key).arg(units).query_async::<_, ()>(&mut conn).await?;
+            return Ok(Reservation::Exceeded(total - units));
+        }
+        Ok(Reservation::Cached)
+    }
+
+    async fn release(&self, user_id: Uuid, month: &str, units: u32) -> redis::RedisResult<()> {
+        let mut conn = self.redis.get_async_connection().await?;
+        redis::cmd("DECRBY").arg(cache_key(user_id, month)).arg(units).query_async(&mut conn).await
+    }
+}
+
+fn cache_key(user_id: Uuid, month: &str) -> String {
+    format!("quota:{}:{}", user_id, month)
+}
+
+// usage_tracking.month_year for `now`
+pub fn current_month(now: DateTime<Utc>) -> String {
+    now.format("%Y-%m").to_string()
+}
+
+// When quotas reset: midnight UTC on the first of next month
+pub fn period_end(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if now.month() == 12 { (now.year() + 1, 1) } else { (now.year(), now.month() + 1) };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single().unwrap_or(now)
+}
@@ -0,0 +1,243 @@
+// Request rate limiting
+// One `RateLimiter` lives in AppState for the life of the server. Callers are
+// keyed by user (JWT), API key or, unauthenticated, client IP, and get
+// [rate_limiting].requests_per_minute with bursts of burst_size, both scaled by
+// the tier multiplier (Pro: premium_multiplier, Enterprise:
+// enterprise_multiplier).
+//
+// The "memory" backend is a GCRA limiter per process. The "redis" backend keeps
+// a sliding-window log per key in a sorted set, so every API replica draws
+// from the same budget: at most `limit` requests in the last minute, and at
+// most `burst` in the time it takes to earn that many back. If Redis can't be
+// reached requests are let through rather than turning an outage into a
+// total one.
+
+use governor::{
+    clock::{Clock, DefaultClock},
+    middleware::StateInformationMiddleware,
+    state::keyed::DefaultKeyedStateStore,
+    Quota,
+};
+use std::fmt;
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::config::{RateLimitBackend, RateLimitConfig};
+use crate::database::UserSubscriptionTier;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+// Forget idle keys every this many checks (memory backend)
+const RETAIN_EVERY: u64 = 4096;
+
+type KeyedLimiter = governor::RateLimiter<
+    String,
+    DefaultKeyedStateStore<String>,
+    DefaultClock,
+    StateInformationMiddleware,
+>;
+
+// Who a request counts against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitKey {
+    User(uuid::Uuid),
+    ApiKey(uuid::Uuid),
+    Ip(String),
+}
+
+impl fmt::Display for RateLimitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitKey::User(id) => write!(f, "user:{}", id),
+            RateLimitKey::ApiKey(id) => write!(f, "key:{}", id),
+            RateLimitKey::Ip(ip) => write!(f, "ip:{}", ip),
+        }
+    }
+}
+
+// Limits for one tier
+#[derive(Debug, Clone, Copy)]
+pub struct TierLimit {
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+// Outcome of one check, with what the RateLimit-* headers report
+#[derive(Debug, Clone)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    // Until the budget is whole again
+    pub reset: Duration,
+    // Until the next request would be allowed; zero when allowed
+    pub retry_after: Duration,
+}
+
+pub struct RateLimiter {
+    tiers: [TierLimit; 3],
+    backend: Backend,
+}
+
+enum Backend {
+    // One limiter per tier, indexed like `tiers`
+    Memory { limiters: Box<[KeyedLimiter; 3]>, checks: AtomicU64 },
+    Redis(Arc<redis::Client>),
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let backend = match self.backend {
+            Backend::Memory { .. } => "memory",
+            Backend::Redis(_) => "redis",
+        };
+        f.debug_struct("RateLimiter")
+            .field("tiers", &self.tiers)
+            .field("backend", &backend)
+            .finish()
+    }
+}
+
+impl RateLimiter {
+    pub fn from_config(config: &RateLimitConfig, redis: Arc<redis::Client>) -> anyhow::Result<Self> {
+        let tier = |multiplier: u32| TierLimit {
+            per_minute: config.requests_per_minute.saturating_mul(multiplier.max(1)),
+            burst: config.burst_size.saturating_mul(multiplier.max(1)),
+        };
+        let tiers = [tier(1), tier(config.premium_multiplier), tier(config.enterprise_multiplier)];
+        if tiers.iter().any(|t| t.per_minute == 0 || t.burst == 0) {
+            return Err(anyhow::anyhow!("rate_limiting.requests_per_minute and burst_size must be at least 1"));
+        }
+
+        let backend = match config.backend {
+            RateLimitBackend::Memory => Backend::Memory {
+                limiters: Box::new(tiers.map(|t| {
+                    let quota = Quota::per_minute(NonZeroU32::new(t.per_minute).unwrap())
+                        .allow_burst(NonZeroU32::new(t.burst).unwrap());
+                    governor::RateLimiter::keyed(quota).with_middleware::<StateInformationMiddleware>()
+                })),
+                checks: AtomicU64::new(0),
+            },
+            RateLimitBackend::Redis => Backend::Redis(redis),
+        };
+
+        Ok(Self { tiers, backend })
+    }
+
+    // Count one request against `key`; unauthenticated callers get the Free
+    // limits
+    pub async fn check(&self, key: &RateLimitKey, tier: Option<&UserSubscriptionTier>) -> RateLimitDecision {
+        let index = tier_index(tier);
+        let limit = self.tiers[index];
+        match &self.backend {
+            Backend::Memory { limiters, checks } => {
+                if checks.fetch_add(1, Ordering::Relaxed) % RETAIN_EVERY == RETAIN_EVERY - 1 {
+                    for limiter in limiters.iter() {
+                        limiter.retain_recent();
+                    }
+                }
+                check_memory(&limiters[index], key, limit)
+            }
+            Backend::Redis(client) => match check_redis(client, key, limit).await {
+                Ok(decision) => decision,
+                Err(e) => {
+                    warn!("Rate limit check failed, allowing request: {}", e);
+                    RateLimitDecision {
+                        allowed: true,
+                        limit: limit.per_minute,
+                        remaining: limit.burst,
+                        reset: Duration::ZERO,
+                        retry_after: Duration::ZERO,
+                    }
+                }
+            },
+        }
+    }
+}
+
+fn tier_index(tier: Option<&UserSubscriptionTier>) -> usize {
+    match tier {
+        None | Some(UserSubscriptionTier::Free) => 0,
+        Some(UserSubscriptionTier::Pro) => 1,
+        Some(UserSubscriptionTier::Enterprise) => 2,
+    }
+}
+
+// Time to earn back one request
+fn interval(limit: TierLimit) -> Duration {
+    WINDOW / limit.per_minute
+}
+
+fn check_memory(limiter: &KeyedLimiter, key: &RateLimitKey, limit: TierLimit) -> RateLimitDecision {
+    match limiter.check_key(&key.to_string()) {
+        Ok(snapshot) => {
+            let remaining = snapshot.remaining_burst_capacity();
+            RateLimitDecision {
+                allowed: true,
+                limit: limit.per_minute,
+                remaining,
+                reset: interval(limit) * (limit.burst - remaining.min(limit.burst)),
+                retry_after: Duration::ZERO,
+            }
+        }
+        Err(not_until) => {
+            let retry_after = not_until.wait_time_from(DefaultClock::default().now());
+            RateLimitDecision {
+                allowed: false,
+                limit: limit.per_minute,
+                remaining: 0,
+                reset: interval(limit) * limit.burst,
+                retry_after,
+            }
+        }
+    }
+}
+
+async fn check_redis(client: &redis::Client, key: &RateLimitKey, limit: TierLimit) -> redis::RedisResult<RateLimitDecision> {
+    let mut conn = client.get_async_connection().await?;
+    let redis_key = format!("ratelimit:{}", key);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+    let window = WINDOW.as_millis() as u64;
+    // How long `burst` requests take to earn back
+    let burst_window = interval(limit).as_millis() as u64 * limit.burst as u64;
+    let member = format!("{}-{}", now, rand::random::<u32>());
+
+    // Log this request, then count what the window holds
+    let (in_window, in_burst, oldest): (u32, u32, Vec<(String, u64)>) = redis::pipe()
+        .atomic()
+        .cmd("ZREMRANGEBYSCORE").arg(&redis_key).arg("-inf").arg(now.saturating_sub(window)).ignore()
+        .cmd("ZADD").arg(&redis_key).arg(now).arg(&member).ignore()
+        .cmd("ZCARD").arg(&redis_key)
+        .cmd("ZCOUNT").arg(&redis_key).arg(format!("({}", now.saturating_sub(burst_window))).arg("+inf")
+        .cmd("ZRANGE").arg(&redis_key).arg(0).arg(0).arg("WITHSCORES")
+        .cmd("PEXPIRE").arg(&redis_key).arg(window).ignore()
+        .query_async(&mut conn)
+        .await?;
+
+    let oldest = oldest.first().map_or(now, |(_, score)| *score);
+    let reset = Duration::from_millis((oldest + window).saturating_sub(now));
+
+    if in_window > limit.per_minute || in_burst > limit.burst {
+        // Rejected requests don't use up budget
+        redis::cmd("ZREM").arg(&redis_key).arg(&member).query_async::<_, ()>(&mut conn).await?;
+        let retry_after = if in_window > limit.per_minute { reset } else { interval(limit) };
+        return Ok(RateLimitDecision {
+            allowed: false,
+            limit: limit.per_minute,
+            remaining: 0,
+            reset,
+            retry_after,
+        });
+    }
+
+    Ok(RateLimitDecision {
+        allowed: true,
+        limit: limit.per_minute,
+        remaining: (limit.per_minute - in_window).min(limit.burst - in_burst),
+        reset,
+        retry_after: Duration::ZERO,
+    })
+}
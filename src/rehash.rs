@@ -0,0 +1,166 @@
+// Digest migration after a pepper rotation (`guardr rehash`)
+// Every stored email/password digest in breach_data and every digest in
+// security_reports.input_data_hash is wrapped in the peppers it is missing
+// (see src/breach_hash.rs). Safe to interrupt and re-run: rows that are
+// already current are left alone.
+
+use anyhow::Result;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::breach_hash::BreachHasher;
+use crate::database::Database;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RehashSummary {
+    pub breach_rows_scanned: u64,
+    pub breach_rows_rehashed: u64,
+    pub duplicates_removed: u64,
+    pub report_rows_scanned: u64,
+    pub report_rows_rehashed: u64,
+}
+
+pub async fn rehash_identifiers(db: &Database, hasher: &BreachHasher, batch_size: i64) -> Result<RehashSummary> {
+    let mut summary = RehashSummary::default();
+
+    let mut last_id: Option<Uuid> = None;
+    loop {
+        let rows: Vec<(Uuid, String, Option<String>)> = sqlx::query_as(
+            "SELECT id, email_hash, password_hash FROM breach_data WHERE $1 IS NULL OR id > $1 ORDER BY id LIMIT $2"
+        )
+        .bind(last_id)
+        .bind(batch_size)
+        .fetch_all(&db.pool)
+        .await?;
+        let Some((id, _, _)) = rows.last() else { break };
+        last_id = Some(*id);
+
+        let mut tx = db.pool.begin().await?;
+        for (id, email_hash, password_hash) in &rows {
+            summary.breach_rows_scanned += 1;
+            let new_email = hasher.upgrade(email_hash);
+            let new_password = password_hash.as_deref().and_then(|hash| hasher.upgrade(hash));
+            if new_email.is_none() && new_password.is_none() {
+                continue;
+            }
+
+            let result = sqlx::query(
+                "UPDATE OR IGNORE breach_data SET email_hash = $1, password_hash = $2 WHERE id = $3"
+            )
+            .bind(new_email.as_ref().unwrap_or(email_hash))
+            .bind(new_password.as_ref().or(password_hash.as_ref()))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+            if result.rows_affected() > 0 {
+                summary.breach_rows_rehashed += 1;
+            } else {
+                // The same record was re-ingested under the new pepper already
+                sqlx::query("DELETE FROM breach_data WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                summary.duplicates_removed += 1;
+            }
+        }
+        tx.commit().await?;
+    }
+
+    let mut last_id: Option<Uuid> = None;
+    loop {
+        let rows: Vec<(Uuid, String)> = sqlx::query_as(
+            "SELECT id, input_data_hash FROM security_reports WHERE $1 IS NULL OR id > $1 ORDER BY id LIMIT $2"
+        )
+        .bind(last_id)
+        .bind(batch_size)
+        .fetch_all(&db.pool)
+        .await?;
+        let Some((id, _)) = rows.last() else { break };
+        last_id = Some(*id);
+
+        let mut tx = db.pool.begin().await?;
+        for (id, input_hash) in &rows {
+            summary.report_rows_scanned += 1;
+            // Bulk and conversation reports carry a random id, not a digest
+            let Some(new_hash) = hasher.upgrade(input_hash) else { continue };
+
+            sqlx::query("UPDATE security_reports SET input_data_hash = $1 WHERE id = $2")
+                .bind(new_hash)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            summary.report_rows_rehashed += 1;
+        }
+        tx.commit().await?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    use crate::config::{HashPepper, Settings};
+    use crate::database::NewBreachData;
+
+    fn settings(peppers: &[&str], db_path: &std::path::Path) -> Settings {
+        let mut settings = Settings::default();
+        settings.database.sqlite_url = format!("sqlite:{}?mode=rwc", db_path.display());
+        settings.security.encryption_key = "rehash-test-key-0123456789abcdef".to_string();
+        settings.security.hash_peppers = peppers.iter()
+            .map(|id| HashPepper { id: id.to_string(), secret: format!("{}-pepper-secret-0123456789abcdef", id) })
+            .collect();
+        settings
+    }
+
+    fn row(hasher: &BreachHasher, email: &str, password: &str) -> NewBreachData {
+        NewBreachData {
+            email_hash: hasher.hash_email(email),
+            password_hash: Some(hasher.hash_password(password)),
+            source_name: "RotationLeak".to_string(),
+            breach_date: Utc::now(),
+            data_types: vec!["Email addresses".to_string(), "Passwords".to_string()],
+            severity: "high".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn rows_match_again_after_rotating_and_rehashing() {
+        let db_path = std::env::temp_dir().join(format!("guardr-test-rehash-{}.db", Uuid::new_v4()));
+        let before = settings(&["p1"], &db_path);
+        let after = settings(&["p1", "p2"], &db_path);
+        let old = BreachHasher::from_config(&before.security);
+        let rotated = BreachHasher::from_config(&after.security);
+
+        let db = Database::new(&before).await.unwrap();
+        db.store_breach_batch(&[row(&old, "ann@example.com", "hunter2")]).await.unwrap();
+        // Ingested again under the new pepper before the rehash ran
+        db.store_breach_batch(&[row(&old, "bob@example.com", "pass"), row(&rotated, "bob@example.com", "pass")]).await.unwrap();
+
+        // Until the rehash, only migration lookups find the old rows
+        assert!(db.check_email_breaches(&rotated.email_lookup_hashes("ann@example.com")).await.unwrap().is_empty());
+        let mut migrating = after.security.clone();
+        migrating.legacy_hash_lookups = true;
+        let migrating = BreachHasher::from_config(&migrating);
+        assert_eq!(db.check_email_breaches(&migrating.email_lookup_hashes("ann@example.com")).await.unwrap().len(), 1);
+
+        let summary = rehash_identifiers(&db, &rotated, 1).await.unwrap();
+        assert_eq!(summary.breach_rows_scanned, 3);
+        assert_eq!(summary.breach_rows_rehashed, 1);
+        assert_eq!(summary.duplicates_removed, 1);
+
+        assert_eq!(db.check_email_breaches(&rotated.email_lookup_hashes("ann@example.com")).await.unwrap().len(), 1);
+        assert_eq!(db.check_password_breaches(&rotated.password_lookup_hashes("hunter2")).await.unwrap().len(), 1);
+        assert_eq!(db.check_email_breaches(&rotated.email_lookup_hashes("bob@example.com")).await.unwrap().len(), 1);
+
+        // Running it again finds nothing to do
+        let again = rehash_identifiers(&db, &rotated, 10).await.unwrap();
+        assert_eq!((again.breach_rows_scanned, again.breach_rows_rehashed), (2, 0));
+
+        db.close().await;
+        std::fs::remove_file(&db_path).ok();
+    }
+}
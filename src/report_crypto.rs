@@ -0,0 +1,115 @@
+// Encryption of security report results at rest
+// `security_reports.results` can hold the plaintext email or username a report
+// was run for, so Database seals it on insert and opens it on read. Each value
+// is encrypted with AES-256-GCM under a fresh random nonce, with the report id
+// as associated data so a ciphertext cannot be moved to another row, and is
+// stored as "enc:<key id>:<base64 of nonce || ciphertext>". TOTP secrets
+// (two_factor.secret) are sealed the same way, with the user id as associated
+// data.
+//
+// Rotating: set a new [security].encryption_key and encryption_key_id and move
+// the old pair to retired_encryption_keys. The API re-encrypts report results
+// and TOTP secrets under retired keys in the background at startup
+// (Database::reencrypt_reports, reencrypt_two_factor_secrets); keep a retired
+// key until both have logged completion. Rows from before encryption are plain
+// JSON, read as they are and encrypted by the same pass.
+
+use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use anyhow::{anyhow, Result};
+use base64::{Engine as _, engine::general_purpose};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::config::SecurityConfig;
+
+const PREFIX: &str = "enc:";
+const NONCE_LEN: usize = 12;
+
+#[derive(Clone)]
+struct ReportKey {
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+#[derive(Clone)]
+pub struct ReportCipher {
+    current: ReportKey,
+    retired: Vec<ReportKey>,
+}
+
+// Never print the keys
+impl fmt::Debug for ReportCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReportCipher")
+            .field("current", &self.current.id)
+            .field("retired", &self.retired.iter().map(|k| &k.id).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl ReportCipher {
+    pub fn from_config(config: &SecurityConfig) -> Result<Self> {
+        let key = |id: &str, secret: &str| -> Result<ReportKey> {
+            if secret.len() != 32 {
+                return Err(anyhow!("Encryption key '{}' must be exactly 32 bytes", id));
+            }
+            Ok(ReportKey {
+                id: id.to_string(),
+                cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(secret.as_bytes())),
+            })
+        };
+
+        Ok(Self {
+            current: key(&config.encryption_key_id, &config.encryption_key)?,
+            retired: config.retired_encryption_keys.iter()
+                .map(|k| key(&k.id, &k.key))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.current.id
+    }
+
+    pub fn encrypt(&self, row_id: Uuid, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let payload = Payload { msg: plaintext.as_bytes(), aad: row_id.as_bytes() };
+        let ciphertext = self.current.cipher.encrypt(&nonce, payload)
+            .map_err(|_| anyhow!("Failed to encrypt row {}", row_id))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}:{}", PREFIX, self.current.id, general_purpose::STANDARD.encode(sealed)))
+    }
+
+    // Plain (pre-encryption) values come back unchanged
+    pub fn decrypt(&self, row_id: Uuid, stored: &str) -> Result<String> {
+        let Some((key_id, encoded)) = stored.strip_prefix(PREFIX).and_then(|rest| rest.split_once(':')) else {
+            return Ok(stored.to_string());
+        };
+
+        let key = std::iter::once(&self.current)
+            .chain(&self.retired)
+            .find(|k| k.id == key_id)
+            .ok_or_else(|| anyhow!("Row {} is encrypted with unknown key '{}'", row_id, key_id))?;
+
+        let sealed = general_purpose::STANDARD.decode(encoded)?;
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("Row {} has a truncated ciphertext", row_id));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let payload = Payload { msg: ciphertext, aad: row_id.as_bytes() };
+        let plaintext = key.cipher.decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| anyhow!("Failed to decrypt row {} with key '{}'", row_id, key_id))?;
+
+        Ok(String::from_utf8(plaintext)?)
+    }
+
+    // Whether a stored value is already sealed under the current key
+    pub fn is_current(&self, stored: &str) -> bool {
+        stored.strip_prefix(PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .is_some_and(|(key_id, _)| key_id == self.current.id)
+    }
+}
@@ -1,38 +1,393 @@
-use serde_json::Value;
-use std::collections::HashSet;
+// Risk scoring engine
+// Every risk score in the API and the CLI comes from `RiskModel::assess`:
+// callers gather what they know about an account into `RiskSignals`, and the
+// model turns it into a 0-100 score plus the factors that contributed, each
+// with its points and the evidence behind it. Breaches count by the worst data
+// class they exposed (src/data_classes.rs). Weights come from the [risk]
+// config section; its `model_version` is stored next to every score so old
+// reports can be told apart once the weights change.
 
-pub fn calculate_risk_score(entry: &Value, weak_passwords: &HashSet<String>) -> u8 {
-    let mut score = 0;
+use chrono::{Duration, NaiveDate, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
 
-    // Check for weak password (worth up to 40 points)
-    if let Some(password) = entry["password"].as_str() {
-        if weak_passwords.contains(password) {
-            score += 40;
+use crate::config::{RiskConfig, RiskWeights};
+use crate::data_classes::{self, ExposedClass, Severity};
+use crate::password::weakness::Verdict;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskFactorKind {
+    WeakPassword,
+    BreachedPassword,
+    EmailBreaches,
+    RecentBreach,
+    Pastes,
+    RepeatedCredential,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskFactor {
+    pub factor_type: RiskFactorKind,
+    pub description: String,
+    pub points: u8,
+    pub severity: String,
+    pub evidence: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskAssessment {
+    pub score: u8,
+    pub model_version: String,
+    pub factors: Vec<RiskFactor>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BreachSignal {
+    pub source: String,
+    pub breach_date: Option<NaiveDate>,
+    pub severity: Severity,
+    pub exposed: Vec<ExposedClass>,
+}
+
+impl BreachSignal {
+    // `provider_severity` is the source's own rating, kept when it is higher
+    pub fn new(
+        source: &str,
+        breach_date: Option<NaiveDate>,
+        data_types: &[String],
+        provider_severity: Option<&str>,
+    ) -> Self {
+        Self {
+            source: source.to_string(),
+            breach_date,
+            severity: data_classes::breach_severity(data_types, provider_severity),
+            exposed: data_types.iter().map(|label| data_classes::classify(label)).collect(),
         }
     }
+}
+
+// What is known about an account; anything left at its default is treated as
+// "nothing found"
+#[derive(Debug, Clone, Default)]
+pub struct RiskSignals {
+    pub password_verdict: Option<Verdict>,
+    // Times the password was seen in local breach data
+    pub password_breach_occurrences: u64,
+    pub breaches: Vec<BreachSignal>,
+    pub paste_count: u32,
+    // Times the same credential appears within a single dump
+    pub occurrences: u64,
+}
 
-    // Check if email appears multiple times (20 points)
-    if entry["occurrences"].as_u64().unwrap_or(1) > 1 {
-        score += 20;
+#[derive(Debug, Clone)]
+pub struct RiskModel {
+    version: String,
+    weights: RiskWeights,
+    recent_breach_days: i64,
+}
+
+impl Default for RiskModel {
+    fn default() -> Self {
+        Self::from_config(&RiskConfig::default())
     }
+}
 
-    // Recent breach? (within past year - 40 points)
-    if let Some(date) = entry["source"]["breach_date"].as_str() {
-        if is_recent_breach(date) {
-            score += 40;
+impl RiskModel {
+    pub fn from_config(config: &RiskConfig) -> Self {
+        Self {
+            version: config.model_version.clone(),
+            weights: config.weights.clone(),
+            recent_breach_days: config.recent_breach_days,
         }
     }
 
-    score.min(100) // cap at 100
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn assess(&self, signals: &RiskSignals) -> RiskAssessment {
+        let weights = &self.weights;
+        let mut factors = Vec::new();
+        let mut add = |factor_type, points: u8, severity: &str, description: String, evidence: Value| {
+            // A zero weight switches the factor off
+            if points > 0 {
+                factors.push(RiskFactor {
+                    factor_type,
+                    description,
+                    points,
+                    severity: severity.to_string(),
+                    evidence,
+                });
+            }
+        };
+
+        if let Some(verdict) = signals.password_verdict.as_ref().filter(|v| v.weak) {
+            add(
+                RiskFactorKind::WeakPassword,
+                weights.weak_password,
+                "high",
+                format!("Password is weak: {}", verdict.messages().join("; ")),
+                json!({ "reasons": verdict.reasons }),
+            );
+        }
+
+        if signals.password_breach_occurrences > 0 {
+            add(
+                RiskFactorKind::BreachedPassword,
+                weights.breached_password,
+                "critical",
+                "Password found in data breaches".to_string(),
+                json!({ "occurrences": signals.password_breach_occurrences }),
+            );
+        }
+
+        if !signals.breaches.is_empty() {
+            let count = signals.breaches.len();
+            let total: u32 = signals.breaches.iter()
+                .map(|b| breach_points(weights, b.severity) as u32)
+                .sum();
+            let points = total.min(weights.email_breach_max as u32) as u8;
+            let worst = signals.breaches.iter().map(|b| b.severity).max().unwrap_or(Severity::Low);
+            // Name the classes that drove the rating, most severe first
+            let mut worst_classes: Vec<&str> = signals.breaches.iter()
+                .flat_map(|b| &b.exposed)
+                .filter(|c| c.severity == worst)
+                .map(|c| c.name.as_str())
+                .collect();
+            worst_classes.sort_unstable();
+            worst_classes.dedup();
+
+            let description = if worst_classes.is_empty() {
+                format!("Email found in {} data breach(es)", count)
+            } else {
+                format!("Email found in {} data breach(es) exposing {}", count, worst_classes.join(", "))
+            };
+            add(
+                RiskFactorKind::EmailBreaches,
+                points,
+                &worst.to_string(),
+                description,
+                json!({
+                    "breaches": signals.breaches.iter().map(|b| json!({
+                        "source": b.source,
+                        "severity": b.severity,
+                        "points": breach_points(weights, b.severity),
+                        "data_classes": b.exposed,
+                    })).collect::<Vec<_>>(),
+                }),
+            );
+        }
+
+        let cutoff = Utc::now().date_naive() - Duration::days(self.recent_breach_days);
+        let recent: Vec<&BreachSignal> = signals.breaches.iter()
+            .filter(|b| b.breach_date.is_some_and(|date| date > cutoff))
+            .collect();
+        if !recent.is_empty() {
+            add(
+                RiskFactorKind::RecentBreach,
+                weights.recent_breach,
+                "high",
+                format!("{} breach(es) in the last {} days", recent.len(), self.recent_breach_days),
+                json!({
+                    "breaches": recent.iter().map(|b| json!({
+                        "source": b.source,
+                        "breach_date": b.breach_date,
+                    })).collect::<Vec<_>>(),
+                }),
+            );
+        }
+
+        if signals.paste_count > 0 {
+            add(
+                RiskFactorKind::Pastes,
+                weights.pastes,
+                "medium",
+                format!("Email appears in {} public paste(s)", signals.paste_count),
+                json!({ "paste_count": signals.paste_count }),
+            );
+        }
+
+        if signals.occurrences > 1 {
+            add(
+                RiskFactorKind::RepeatedCredential,
+                weights.repeated_credential,
+                "medium",
+                format!("Credential appears {} times in the same dump", signals.occurrences),
+                json!({ "occurrences": signals.occurrences }),
+            );
+        }
+
+        let total: u32 = factors.iter().map(|f| f.points as u32).sum();
+        RiskAssessment {
+            score: total.min(100) as u8,
+            model_version: self.version.clone(),
+            factors,
+        }
+    }
+}
+
+fn breach_points(weights: &RiskWeights, severity: Severity) -> u8 {
+    match severity {
+        Severity::Low => weights.breach_low,
+        Severity::Medium => weights.breach_medium,
+        Severity::High => weights.breach_high,
+        Severity::Critical => weights.breach_critical,
+    }
 }
 
-fn is_recent_breach(date: &str) -> bool {
-    use chrono::{NaiveDate, Utc, Duration};
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::password::weakness::WeaknessReason;
+
+    fn model(configure: impl FnOnce(&mut RiskConfig)) -> RiskModel {
+        let mut config = RiskConfig::default();
+        configure(&mut config);
+        RiskModel::from_config(&config)
+    }
+
+    fn breach(days_ago: i64, data_types: &[&str]) -> BreachSignal {
+        let data_types: Vec<String> = data_types.iter().map(|t| t.to_string()).collect();
+        let date = Utc::now().date_naive() - Duration::days(days_ago);
+        BreachSignal::new("TestLeak", Some(date), &data_types, None)
+    }
+
+    fn weak_password() -> Option<Verdict> {
+        Some(Verdict { weak: true, reasons: vec![WeaknessReason::Wordlist] })
+    }
+
+    fn points(assessment: &RiskAssessment, kind: RiskFactorKind) -> Option<u8> {
+        assessment.factors.iter().find(|f| f.factor_type == kind).map(|f| f.points)
+    }
+
+    #[test]
+    fn factors_add_their_configured_weights() {
+        let model = model(|config| {
+            config.weights.weak_password = 7;
+            config.weights.breached_password = 11;
+            config.weights.pastes = 3;
+            config.weights.repeated_credential = 5;
+        });
+        let risk = model.assess(&RiskSignals {
+            password_verdict: weak_password(),
+            password_breach_occurrences: 4,
+            paste_count: 2,
+            occurrences: 3,
+            ..Default::default()
+        });
+
+        assert_eq!(points(&risk, RiskFactorKind::WeakPassword), Some(7));
+        assert_eq!(points(&risk, RiskFactorKind::BreachedPassword), Some(11));
+        assert_eq!(points(&risk, RiskFactorKind::Pastes), Some(3));
+        assert_eq!(points(&risk, RiskFactorKind::RepeatedCredential), Some(5));
+        assert_eq!(risk.score, 26);
+    }
+
+    #[test]
+    fn nothing_found_scores_zero() {
+        let risk = RiskModel::default().assess(&RiskSignals {
+            password_verdict: Some(Verdict::default()),
+            occurrences: 1,
+            ..Default::default()
+        });
+        assert_eq!(risk.score, 0);
+        assert!(risk.factors.is_empty());
+    }
+
+    #[test]
+    fn breaches_count_by_severity_up_to_the_cap() {
+        let model = model(|config| {
+            config.weights.breach_low = 2;
+            config.weights.breach_critical = 25;
+            config.weights.email_breach_max = 40;
+            config.recent_breach_days = 30;
+        });
+        let old = 3650;
+
+        let risk = model.assess(&RiskSignals {
+            breaches: vec![breach(old, &["Usernames"]), breach(old, &["Usernames"])],
+            ..Default::default()
+        });
+        assert_eq!(points(&risk, RiskFactorKind::EmailBreaches), Some(4));
+
+        let risk = model.assess(&RiskSignals {
+            breaches: vec![
+                breach(old, &["Plaintext passwords"]),
+                breach(old, &["Plaintext passwords"]),
+                breach(old, &["Usernames"]),
+            ],
+            ..Default::default()
+        });
+        assert_eq!(points(&risk, RiskFactorKind::EmailBreaches), Some(40));
+        assert_eq!(risk.factors[0].severity, "critical");
+    }
+
+    #[test]
+    fn zero_weight_disables_a_factor() {
+        let model = model(|config| {
+            config.weights.weak_password = 0;
+            config.weights.recent_breach = 0;
+        });
+        let risk = model.assess(&RiskSignals {
+            password_verdict: weak_password(),
+            breaches: vec![breach(1, &["Passwords"])],
+            ..Default::default()
+        });
+
+        assert_eq!(points(&risk, RiskFactorKind::WeakPassword), None);
+        assert_eq!(points(&risk, RiskFactorKind::RecentBreach), None);
+        assert!(points(&risk, RiskFactorKind::EmailBreaches).is_some());
+    }
+
+    #[test]
+    fn recent_breach_cutoff_is_configurable() {
+        let model = model(|config| config.recent_breach_days = 30);
+        let recent = |days_ago| points(
+            &model.assess(&RiskSignals { breaches: vec![breach(days_ago, &["Usernames"])], ..Default::default() }),
+            RiskFactorKind::RecentBreach,
+        );
+
+        assert_eq!(recent(29), Some(RiskWeights::default().recent_breach));
+        assert_eq!(recent(30), None);
+        assert_eq!(recent(400), None);
+
+        // Undated breaches are never recent
+        let undated = BreachSignal::new("Undated", None, &["Passwords".to_string()], None);
+        let risk = model.assess(&RiskSignals { breaches: vec![undated], ..Default::default() });
+        assert_eq!(points(&risk, RiskFactorKind::RecentBreach), None);
+    }
+
+    #[test]
+    fn score_is_clamped_to_100() {
+        let model = model(|config| config.weights = RiskWeights {
+            weak_password: 90,
+            breached_password: 90,
+            breach_low: 90,
+            breach_medium: 90,
+            breach_high: 90,
+            breach_critical: 90,
+            email_breach_max: 90,
+            recent_breach: 90,
+            pastes: 90,
+            repeated_credential: 90,
+        });
+        let risk = model.assess(&RiskSignals {
+            password_verdict: weak_password(),
+            password_breach_occurrences: 1,
+            breaches: vec![breach(1, &["Passwords"])],
+            paste_count: 1,
+            occurrences: 2,
+        });
+
+        assert_eq!(risk.factors.len(), 6);
+        assert_eq!(risk.score, 100);
+    }
 
-    if let Ok(breach_date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
-        let one_year_ago = Utc::now().naive_utc().date() - Duration::days(365);
-        breach_date > one_year_ago
-    } else {
-        false
+    #[test]
+    fn assessments_carry_the_model_version() {
+        let risk = model(|config| config.model_version = "weights-2099-01".to_string())
+            .assess(&RiskSignals::default());
+        assert_eq!(risk.model_version, "weights-2099-01");
     }
 }
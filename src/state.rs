@@ -1,8 +1,28 @@
 use std::sync::Arc;
+use tracing::{info, warn};
 
 use crate::auth::AuthService;
+use crate::breach_hash::BreachHasher;
 use crate::config::Settings;
 use crate::database::Database;
+use crate::filter_rules::FilterRules;
+use crate::ingest::IngestJobs;
+use crate::llm::LlmChain;
+use crate::login_guard::LoginGuard;
+use crate::mail::{self, templates::EmailTemplates, Mailer};
+use crate::middleware::TrustedProxies;
+use crate::password::{PasswordChecker, Wordlist};
+use crate::quota::QuotaGuard;
+use crate::rate_limit::RateLimiter;
+use crate::risk_score::RiskModel;
+use crate::totp::{SystemClock, Totp};
+use crate::osint::{
+    self,
+    aggregator::OsintAggregator,
+    investigation::Investigator,
+    pwned_passwords::PwnedPasswords,
+    BreachProvider,
+};
 
 #[derive(Clone)]
 pub struct AppState {
@@ -10,6 +30,23 @@ pub struct AppState {
     pub auth: Arc<AuthService>,
     pub settings: Arc<Settings>,
     pub redis: Arc<redis::Client>,
+    pub breach_providers: Arc<Vec<Arc<dyn BreachProvider>>>,
+    pub pwned_passwords: Arc<PwnedPasswords>,
+    pub osint: Arc<OsintAggregator>,
+    pub llm: Arc<LlmChain>,
+    pub investigator: Arc<Investigator>,
+    pub filter_rules: Arc<FilterRules>,
+    pub passwords: Arc<PasswordChecker>,
+    pub risk: Arc<RiskModel>,
+    pub hasher: Arc<BreachHasher>,
+    pub ingest_jobs: Arc<IngestJobs>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub trusted_proxies: Arc<TrustedProxies>,
+    pub quota: Arc<QuotaGuard>,
+    pub login_guard: Arc<LoginGuard>,
+    pub totp: Arc<Totp>,
+    pub mailer: Arc<dyn Mailer>,
+    pub email_templates: Arc<EmailTemplates>,
 }
 
 impl AppState {
@@ -24,6 +61,61 @@ impl AppState {
         let redis_client = redis::Client::open(settings.redis.url.clone())?;
         let redis = Arc::new(redis_client);
 
+        // Held for the server's lifetime so counts carry across requests
+        let rate_limiter = Arc::new(RateLimiter::from_config(&settings.rate_limiting, redis.clone())?);
+        let trusted_proxies = Arc::new(TrustedProxies::parse(&settings.server.trusted_proxies)?);
+        let quota = Arc::new(QuotaGuard::new(settings.quota.clone(), db.clone(), redis.clone()));
+        let login_guard = Arc::new(LoginGuard::from_config(&settings.security, redis.clone()));
+        let totp = Arc::new(Totp::new(&settings.auth.totp_issuer, Arc::new(SystemClock)));
+
+        // Verification and password reset emails
+        let mailer = mail::from_config(&settings.email)?;
+        let email_templates = Arc::new(EmailTemplates::load(settings.email.templates_dir.as_deref())?);
+
+        // Initialize external breach providers (HIBP etc.)
+        let breach_providers = Arc::new(osint::breach_providers(&settings.osint)?);
+        let pwned_passwords = Arc::new(PwnedPasswords::from_config(&settings.osint)?);
+        let osint = Arc::new(osint::aggregator(&settings.osint, &breach_providers)?);
+        let llm = Arc::new(LlmChain::from_config(&settings.osint)?);
+        let investigator = Arc::new(Investigator::from_config(&settings.osint, llm.clone())?);
+
+        // Default rules for /v1/security/filter-data (requests may send their own)
+        let filter_rules = Arc::new(FilterRules::load_or_default(&settings.data.filter_rules_path)?);
+
+        // Weak-password list, shared by every request instead of reloaded per call
+        let wordlist = match Wordlist::load(&settings.data.password_list_path) {
+            Ok(wordlist) => {
+                info!(
+                    "Loaded {} weak passwords from {}{}",
+                    wordlist.len(),
+                    settings.data.password_list_path,
+                    if wordlist.is_indexed() { " (index)" } else { "" },
+                );
+                wordlist
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                warn!(
+                    "Password list {} not found, using the built-in list",
+                    settings.data.password_list_path
+                );
+                Wordlist::builtin()
+            }
+            Err(e) => {
+                return Err(anyhow::anyhow!(
+                    "Failed to load password list {}: {}", settings.data.password_list_path, e
+                ));
+            }
+        };
+        let passwords = Arc::new(
+            PasswordChecker::new(wordlist).with_min_length(settings.security.password_min_length),
+        );
+
+        let risk = Arc::new(RiskModel::from_config(&settings.risk));
+        let hasher = Arc::new(BreachHasher::from_config(&settings.security));
+        if let Some(pepper) = hasher.current_pepper() {
+            info!("Breach digests keyed with pepper '{}'", pepper);
+        }
+
         let settings = Arc::new(settings);
 
         Ok(AppState {
@@ -31,6 +123,23 @@ impl AppState {
             auth,
             settings,
             redis,
+            breach_providers,
+            pwned_passwords,
+            osint,
+            llm,
+            investigator,
+            filter_rules,
+            passwords,
+            risk,
+            hasher,
+            ingest_jobs: Arc::new(IngestJobs::default()),
+            rate_limiter,
+            trusted_proxies,
+            quota,
+            login_guard,
+            totp,
+            mailer,
+            email_templates,
         })
     }
 }
\ No newline at end of file
@@ -0,0 +1,131 @@
+// Admin routes: only verified operator accounts may write to the shared breach
+// corpus
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use super::support::{TestApp, PASSWORD};
+use crate::database::UserSubscriptionTier;
+
+const INGEST: &str = "/v1/admin/ingest";
+
+fn records() -> Value {
+    json!({
+        "source": "ExampleLeak",
+        "breach_date": "2024-03-01",
+        "records": [
+            { "email": "victim@example.com", "password": "hunter2" },
+            { "email": "other@example.com" },
+        ],
+    })
+}
+
+async fn session(app: &TestApp, email: &str) -> String {
+    app.register(email).await;
+    let (_, body) = app.login(email, PASSWORD).await;
+    body["access_token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn paying_customers_are_not_operators() {
+    let app = TestApp::with_settings(|settings| {
+        settings.security.operator_emails = vec!["ops@example.com".to_string()];
+    }).await;
+
+    // Enterprise and verified, but not on the list
+    app.register("big-customer@example.com").await;
+    app.verify_email("big-customer@example.com").await;
+    let user = app.state.db.get_user_by_email("big-customer@example.com").await.unwrap().unwrap();
+    app.state.db.update_user_subscription(user.id, UserSubscriptionTier::Enterprise).await.unwrap();
+    let user = app.state.db.get_user_by_id(user.id).await.unwrap().unwrap();
+    let (token, _) = app.state.auth.generate_tokens(&user).unwrap();
+
+    let (status, _) = app.request(Method::POST, INGEST, Some(&token), Some(records())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.request(Method::GET, "/v1/admin/breach-sources", Some(&token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn operators_need_a_verified_email() {
+    let app = TestApp::with_settings(|settings| {
+        settings.security.operator_emails = vec!["Ops@Example.com".to_string()];
+    }).await;
+    let token = session(&app, "ops@example.com").await;
+
+    // Anyone could have registered the address first
+    let (status, _) = app.request(Method::POST, INGEST, Some(&token), Some(records())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    app.verify_email("ops@example.com").await;
+    let (status, summary) = app.request(Method::POST, INGEST, Some(&token), Some(records())).await;
+    assert_eq!(status, StatusCode::OK, "{}", summary);
+    assert_eq!(summary["inserted"], 2);
+}
+
+#[tokio::test]
+async fn dump_files_are_ingested_as_a_background_job() {
+    let dump_dir = std::env::temp_dir().join(format!("guardr-test-dumps-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dump_dir).unwrap();
+    std::fs::write(dump_dir.join("leak.txt"), "a@example.com:pass1\nb@example.com:pass2\nnot-an-email\n").unwrap();
+
+    let app = TestApp::with_settings(|settings| {
+        settings.security.operator_emails = vec!["ops@example.com".to_string()];
+        settings.data.dump_dir = dump_dir.display().to_string();
+    }).await;
+    let token = session(&app, "ops@example.com").await;
+    app.verify_email("ops@example.com").await;
+
+    let (status, job) = app.request(Method::POST, INGEST, Some(&token), Some(json!({
+        "source": "ComboLeak",
+        "breach_date": "2024-03-01",
+        "file": "leak.txt",
+        "format": "combo",
+    }))).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", job);
+    let job_uri = format!("{}/{}", INGEST, job["id"].as_str().unwrap());
+
+    let mut finished = Value::Null;
+    for _ in 0..100 {
+        let (status, body) = app.request(Method::GET, &job_uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        if body["status"] != "running" {
+            finished = body;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(finished["status"], "completed", "{}", finished);
+    assert_eq!(finished["summary"]["inserted"], 2);
+    assert_eq!(finished["summary"]["skipped"], 1);
+    assert!(finished["finished_at"].is_string());
+
+    let (status, _) = app.request(Method::GET, &format!("{}/{}", INGEST, uuid::Uuid::new_v4()), Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    std::fs::remove_dir_all(&dump_dir).ok();
+}
+
+#[tokio::test]
+async fn ingested_addresses_match_any_spelling_of_the_mailbox() {
+    let app = TestApp::with_settings(|settings| {
+        settings.security.operator_emails = vec!["ops@example.com".to_string()];
+    }).await;
+    let token = session(&app, "ops@example.com").await;
+    app.verify_email("ops@example.com").await;
+
+    let (status, summary) = app.request(Method::POST, INGEST, Some(&token), Some(json!({
+        "source": "SpellingLeak",
+        "breach_date": "2024-03-01",
+        "records": [{ "email": " J.Doe+leak@GoogleMail.com " }],
+    }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", summary);
+
+    for spelling in ["jdoe@gmail.com", "j.d.o.e+other@gmail.com", "ｊｄｏｅ@gmail.com"] {
+        let (status, body) = app.request(Method::POST, "/v1/security/check-breach", Some(&token), Some(json!({
+            "email": spelling,
+        }))).await;
+        assert_eq!(status, StatusCode::OK, "{}: {}", spelling, body);
+        assert_eq!(body["breach_count"], 1, "{}: {}", spelling, body);
+    }
+}
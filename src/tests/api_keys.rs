@@ -0,0 +1,102 @@
+// X-API-Key authentication: interchangeable with JWTs, scoped, revocable
+
+use axum::http::{Method, StatusCode};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+
+use super::support::{TestApp, PASSWORD};
+
+const CHECK_PASSWORD: &str = "/v1/security/check-password";
+
+async fn session(app: &TestApp, email: &str) -> String {
+    app.register(email).await;
+    app.verify_email(email).await;
+    let (_, body) = app.login(email, PASSWORD).await;
+    body["access_token"].as_str().unwrap().to_string()
+}
+
+async fn create_key(app: &TestApp, token: &str, body: Value) -> (StatusCode, Value) {
+    app.request(Method::POST, "/v1/user/api-keys", Some(token), Some(body)).await
+}
+
+#[tokio::test]
+async fn api_key_works_like_a_session_within_its_scopes() {
+    let app = TestApp::new().await;
+    let token = session(&app, "dana@example.com").await;
+
+    let (status, created) = create_key(&app, &token, json!({
+        "name": "scanner",
+        "scopes": ["security:read"],
+    })).await;
+    assert_eq!(status, StatusCode::OK, "{}", created);
+    assert_eq!(created["key_info"]["scopes"], json!(["security:read"]));
+    let key = created["api_key"].as_str().unwrap();
+    let key_id = created["key_info"]["id"].as_str().unwrap();
+
+    let body = json!({ "password": "correct horse battery staple" });
+    let (status, response) = app.request_with_key(Method::POST, CHECK_PASSWORD, key, Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK, "{}", response);
+
+    // Outside its scopes
+    let (status, _) = app.request_with_key(Method::GET, "/v1/reports", key, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.request_with_key(Method::GET, "/v1/user/profile", key, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Logout is for sessions only
+    let (status, _) = app.request_with_key(Method::POST, "/v1/auth/logout", key, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (_, keys) = app.request(Method::GET, "/v1/user/api-keys", Some(&token), None).await;
+    assert!(keys[0]["last_used"].is_string(), "last_used not recorded: {}", keys);
+
+    let (status, _) = app.request(Method::DELETE, &format!("/v1/user/api-keys/{}", key_id), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request_with_key(Method::POST, CHECK_PASSWORD, key, Some(body)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn default_scopes_cannot_manage_keys() {
+    let app = TestApp::new().await;
+    let token = session(&app, "erin@example.com").await;
+
+    let (_, created) = create_key(&app, &token, json!({ "name": "default" })).await;
+    assert_eq!(created["key_info"]["scopes"], json!(["security:read", "dating:analyze", "reports:read"]));
+    let key = created["api_key"].as_str().unwrap();
+
+    let (status, _) = app.request_with_key(Method::GET, "/v1/reports", key, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request_with_key(Method::POST, "/v1/user/api-keys", key, Some(json!({ "name": "escalate" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn rejects_expired_and_unknown_keys() {
+    let app = TestApp::new().await;
+    let token = session(&app, "frank@example.com").await;
+
+    let (status, _) = create_key(&app, &token, json!({
+        "name": "stale",
+        "expires_at": Utc::now() - Duration::hours(1),
+    })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A key that has since passed its expiry
+    let user = app.state.db.get_user_by_email("frank@example.com").await.unwrap().unwrap();
+    let expired = "expired-test-key";
+    app.state.db.create_api_key(
+        user.id,
+        "expired",
+        &app.state.auth.hash_api_key(expired),
+        "expired-",
+        &["security:read".to_string()],
+        Some(Utc::now() - Duration::minutes(1)),
+    ).await.unwrap();
+    let body = json!({ "password": "hunter2" });
+    let (status, _) = app.request_with_key(Method::POST, CHECK_PASSWORD, expired, Some(body.clone())).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = app.request_with_key(Method::POST, CHECK_PASSWORD, "no-such-key", Some(body)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
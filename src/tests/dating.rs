@@ -0,0 +1,52 @@
+// Dating safety analysis with the LLM fallback chain standing in for the vendors
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+use std::sync::Arc;
+
+use super::support::{TestApp, PASSWORD};
+use crate::llm::{fake::FakeAnalyzer, LlmAnalyzer, LlmChain};
+
+#[tokio::test]
+async fn ai_indicators_name_their_provider() {
+    let assessment = json!({
+        "overall_risk_score": 40.0,
+        "risk_indicators": [{
+            "indicator_type": "financial_request",
+            "confidence": 1.5,
+            "severity": "HIGH",
+            "description": "Asks for a gift card",
+            "evidence": ["\"can you grab me an iTunes card\""],
+            "ai_provider": "made up by the model",
+        }],
+    });
+    let app = TestApp::with_setup(|_| {}, move |state| {
+        // The first provider answers in prose, so the chain moves on
+        state.llm = Arc::new(LlmChain::new(vec![
+            Arc::new(FakeAnalyzer::new("I'm not able to assess that.")) as Arc<dyn LlmAnalyzer>,
+            Arc::new(FakeAnalyzer::new(assessment.to_string())),
+        ]));
+    }).await;
+    app.register("ned@example.com").await;
+    let (_, login) = app.login("ned@example.com", PASSWORD).await;
+    let token = login["access_token"].as_str().unwrap();
+
+    let (status, body) = app.request(Method::POST, "/v1/dating/analyze-conversation", Some(token), Some(json!({
+        "messages": [
+            { "content": "hey, how was your day?", "timestamp": "2026-10-01T12:00:00Z", "sender": "match" },
+            { "content": "good thanks!", "timestamp": "2026-10-01T12:05:00Z", "sender": "user" },
+        ],
+        "analysis_depth": "comprehensive",
+    }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let indicators = body["risk_indicators"].as_array().unwrap();
+    let ai = indicators.iter().find(|i| i["indicator_type"] == "financial_request").unwrap();
+    assert_eq!(ai["ai_provider"], "fake");
+    assert_eq!(ai["confidence"], 1.0);
+    assert_eq!(ai["severity"], "high");
+    // Evidence is the model's quotes only
+    assert_eq!(ai["evidence"], json!(["\"can you grab me an iTunes card\""]));
+    assert!(indicators.iter().filter(|i| i != &ai).all(|i| i.get("ai_provider").is_none()), "{:?}", indicators);
+    assert!(body["overall_risk_score"].as_f64().unwrap() >= 40.0);
+}
@@ -0,0 +1,82 @@
+// The public /check demo: no paid lookups for anonymous callers, and no
+// sensitive breaches for anyone
+
+use axum::http::{Method, StatusCode};
+use axum::routing::get;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::support::{spawn_mock, TestApp, PASSWORD};
+
+// HIBP answering every account with one ordinary and one sensitive breach;
+// returns its URL and how many lookups it has served
+async fn mock_hibp() -> (String, Arc<AtomicUsize>) {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let counter = lookups.clone();
+    let router = axum::Router::new()
+        .route("/breachedaccount/:account", get(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                axum::Json(json!([
+                    { "Name": "Adobe", "BreachDate": "2013-10-04", "DataClasses": ["Email addresses", "Passwords"], "IsVerified": true },
+                    { "Name": "AshleyMadison", "BreachDate": "2015-07-19", "DataClasses": ["Email addresses"], "IsVerified": true, "IsSensitive": true },
+                ]))
+            }
+        }))
+        .route("/pasteaccount/:account", get(|| async { StatusCode::NOT_FOUND }));
+    (spawn_mock(router).await, lookups)
+}
+
+fn check_body() -> serde_json::Value {
+    json!({ "name": "Alex", "location": "Austin", "email": "alex@example.com" })
+}
+
+#[tokio::test]
+async fn anonymous_checks_make_no_lookups() {
+    let (hibp, lookups) = mock_hibp().await;
+    let app = TestApp::with_settings(|settings| {
+        settings.osint.hibp_api_key = Some("test-key".to_string());
+        settings.osint.hibp_base_url = Some(hibp);
+    }).await;
+
+    let (status, body) = app.request(Method::POST, "/check", None, Some(check_body())).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(lookups.load(Ordering::SeqCst), 0);
+    assert_eq!(body["findings"], json!([]));
+    assert_eq!(body["sources"], json!([]));
+    assert_eq!(body["risk_score"], 0);
+    assert_eq!(body["safety_score"], 100);
+    assert_eq!(body["risk_level"], "LOW");
+}
+
+#[tokio::test]
+async fn signed_in_checks_are_metered_and_hide_sensitive_breaches() {
+    let (hibp, lookups) = mock_hibp().await;
+    let app = TestApp::with_settings(|settings| {
+        settings.osint.hibp_api_key = Some("test-key".to_string());
+        settings.osint.hibp_base_url = Some(hibp);
+    }).await;
+    app.register("sam@example.com").await;
+    let (_, session) = app.login("sam@example.com", PASSWORD).await;
+    let token = session["access_token"].as_str().unwrap();
+
+    let (status, body) = app.request(Method::POST, "/check", Some(token), Some(check_body())).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(lookups.load(Ordering::SeqCst), 1, "{}", body);
+
+    let titles = body["findings"].as_array().unwrap().iter()
+        .map(|f| f["title"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(titles, vec!["Adobe"]);
+    assert_eq!(body["sources"][0]["findings"], 1);
+
+    // One ordinary breach is a little risk, and the safety score mirrors it
+    let risk = body["risk_score"].as_u64().unwrap();
+    assert!((1..=30).contains(&risk), "{}", body);
+    assert_eq!(body["safety_score"].as_u64().unwrap(), 100 - risk);
+    assert_eq!(body["risk_level"], "LOW");
+
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(token), None).await;
+    assert_eq!(profile["usage_stats"]["requests_used"], 3, "{}", profile);
+}
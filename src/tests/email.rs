@@ -0,0 +1,202 @@
+// Email verification and password reset: mailed single-use links, the resend
+// cooldown, and features gated on a verified email
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use super::support::{TestApp, PASSWORD};
+use crate::config::Settings;
+
+const NEW_PASSWORD: &str = "Battery-Staple-77";
+
+// The token from a message's /{page}?token=... link
+fn link_token(message: &str, page: &str) -> String {
+    let marker = format!("/{}?token=", page);
+    let start = message.find(&marker).unwrap_or_else(|| panic!("no {} link in {}", page, message)) + marker.len();
+    message[start..].split_whitespace().next().unwrap().to_string()
+}
+
+fn addressed_to<'a>(messages: &'a [String], email: &str) -> Vec<&'a String> {
+    let header = format!("To: {}\n", email);
+    messages.iter().filter(|m| m.contains(&header)).collect()
+}
+
+async fn session(app: &TestApp, email: &str) -> String {
+    let (_, body) = app.login(email, PASSWORD).await;
+    body["access_token"].as_str().unwrap_or_else(|| panic!("no access_token in {}", body)).to_string()
+}
+
+async fn post(app: &TestApp, uri: &str, body: Value) -> (StatusCode, Value) {
+    app.request(Method::POST, uri, None, Some(body)).await
+}
+
+#[tokio::test]
+async fn verification_link_unlocks_gated_features() {
+    let app = TestApp::new().await;
+    app.register("vic@example.com").await;
+    let token = session(&app, "vic@example.com").await;
+
+    let messages = app.outbox(1).await;
+    assert!(messages[0].contains("To: vic@example.com\n"), "{}", messages[0]);
+    assert!(messages[0].contains("Subject: Confirm your email address for Guardr"), "{}", messages[0]);
+    assert!(messages[0].contains("expires in 48 hours"), "{}", messages[0]);
+    let link = link_token(&messages[0], "verify-email");
+
+    let (status, body) = app.request(Method::POST, "/v1/user/api-keys", Some(&token), Some(json!({ "name": "ci" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"]["type"], "EMAIL_NOT_VERIFIED");
+    let (status, body) = app.request(Method::GET, "/v1/reports/export", Some(&token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"]["type"], "EMAIL_NOT_VERIFIED");
+
+    let (status, body) = post(&app, "/v1/auth/verify-email", json!({ "token": link })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(&token), None).await;
+    assert_eq!(profile["email_verified"], true);
+    assert_eq!(profile["security_events"][0]["event_type"], "email_verified");
+    let (status, body) = app.request(Method::POST, "/v1/user/api-keys", Some(&token), Some(json!({ "name": "ci" }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // Single use
+    let (status, _) = post(&app, "/v1/auth/verify-email", json!({ "token": link })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn resending_supersedes_the_last_link_after_a_cooldown() {
+    let app = TestApp::new().await;
+    app.register("wes@example.com").await;
+    let token = session(&app, "wes@example.com").await;
+    let first = link_token(&app.outbox(1).await[0], "verify-email");
+
+    // Registration just sent one
+    let (status, _) = app.request(Method::POST, "/v1/user/verify-email/resend", Some(&token), None).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    let user = app.state.db.get_user_by_email("wes@example.com").await.unwrap().unwrap();
+    let mut conn = app.state.redis.get_async_connection().await.unwrap();
+    redis::cmd("DEL").arg(format!("email:cooldown:VerifyEmail:{}", user.id)).query_async::<_, ()>(&mut conn).await.unwrap();
+
+    let (status, body) = app.request(Method::POST, "/v1/user/verify-email/resend", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let second = link_token(&app.outbox(2).await[1], "verify-email");
+
+    let (status, _) = post(&app, "/v1/auth/verify-email", json!({ "token": first })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post(&app, "/v1/auth/verify-email", json!({ "token": second })).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app.request(Method::POST, "/v1/user/verify-email/resend", Some(&token), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn password_reset_link_sets_a_new_password_once() {
+    let app = TestApp::new().await;
+    app.register("xia@example.com").await;
+    let verify_link = link_token(&app.outbox(1).await[0], "verify-email");
+
+    // Unknown addresses get the same answer, and no mail
+    let (status, unknown) = post(&app, "/v1/auth/forgot-password", json!({ "email": "nobody@example.com" })).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, known) = post(&app, "/v1/auth/forgot-password", json!({ "email": "xia@example.com" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(unknown["message"], known["message"]);
+
+    let messages = app.outbox(2).await;
+    assert!(addressed_to(&messages, "nobody@example.com").is_empty());
+    let reset = addressed_to(&messages, "xia@example.com").into_iter()
+        .find(|m| m.contains("Subject: Reset your Guardr password"))
+        .unwrap_or_else(|| panic!("no reset email in {:?}", messages));
+    let link = link_token(reset, "reset-password");
+
+    let reset_with = |token: &str, password: &str, confirm: &str| json!({
+        "token": token,
+        "password": password,
+        "confirm_password": confirm,
+    });
+    let (status, _) = post(&app, "/v1/auth/reset-password", reset_with(&link, NEW_PASSWORD, "Battery-Staple-78")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post(&app, "/v1/auth/reset-password", reset_with(&link, "weakpass", "weakpass")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    // A verification link isn't a reset link
+    let (status, _) = post(&app, "/v1/auth/reset-password", reset_with(&verify_link, NEW_PASSWORD, NEW_PASSWORD)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = post(&app, "/v1/auth/reset-password", reset_with(&link, NEW_PASSWORD, NEW_PASSWORD)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, _) = post(&app, "/v1/auth/reset-password", reset_with(&link, "Another-Pass-99", "Another-Pass-99")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = app.login("xia@example.com", PASSWORD).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = app.login("xia@example.com", NEW_PASSWORD).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // Reading the reset email proved the address
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(body["access_token"].as_str().unwrap()), None).await;
+    assert_eq!(profile["email_verified"], true);
+}
+
+#[tokio::test]
+async fn templates_and_gates_follow_settings() {
+    let templates_dir = std::env::temp_dir().join(format!("guardr-test-templates-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&templates_dir).unwrap();
+    std::fs::write(
+        templates_dir.join("verify_email.txt"),
+        "Subject: Welcome aboard, {{name}}\n\nConfirm here: {{link}}\n",
+    ).unwrap();
+
+    let dir = templates_dir.display().to_string();
+    let app = TestApp::with_settings(move |settings: &mut Settings| {
+        settings.email.templates_dir = Some(dir);
+        settings.email.app_url = "https://app.example.com/".to_string();
+        settings.email.require_verified_for = Vec::new();
+    }).await;
+    app.register("yan@example.com").await;
+    let token = session(&app, "yan@example.com").await;
+
+    let messages = app.outbox(1).await;
+    std::fs::remove_dir_all(&templates_dir).ok();
+    assert!(messages[0].contains("Subject: Welcome aboard, Test User\n"), "{}", messages[0]);
+    assert!(messages[0].contains("Confirm here: https://app.example.com/verify-email?token="), "{}", messages[0]);
+
+    // Nothing is gated
+    let (status, body) = app.request(Method::POST, "/v1/user/api-keys", Some(&token), Some(json!({ "name": "ci" }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+#[tokio::test]
+async fn password_reset_ends_existing_sessions() {
+    let app = TestApp::new().await;
+    app.register("yuki@example.com").await;
+    // The session someone stole before the owner noticed
+    let (_, stolen) = app.login("yuki@example.com", PASSWORD).await;
+    let stolen_access = stolen["access_token"].as_str().unwrap();
+    let stolen_refresh = stolen["refresh_token"].as_str().unwrap();
+
+    post(&app, "/v1/auth/forgot-password", json!({ "email": "yuki@example.com" })).await;
+    let messages = app.outbox(2).await;
+    let reset = messages.iter()
+        .find(|m| m.contains("Subject: Reset your Guardr password"))
+        .unwrap_or_else(|| panic!("no reset email in {:?}", messages));
+    let (status, body) = post(&app, "/v1/auth/reset-password", json!({
+        "token": link_token(reset, "reset-password"),
+        "password": NEW_PASSWORD,
+        "confirm_password": NEW_PASSWORD,
+    })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, _) = app.request(Method::GET, "/v1/user/profile", Some(stolen_access), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post(&app, "/v1/auth/refresh", json!({ "refresh_token": stolen_refresh })).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Sessions from after the reset work, and keep working through a refresh
+    let (_, fresh) = app.login("yuki@example.com", NEW_PASSWORD).await;
+    let (status, refreshed) = post(&app, "/v1/auth/refresh", json!({ "refresh_token": fresh["refresh_token"] })).await;
+    assert_eq!(status, StatusCode::OK, "{}", refreshed);
+    let (status, _) = app.request(Method::GET, "/v1/user/profile", Some(refreshed["access_token"].as_str().unwrap()), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,163 @@
+// Dump downloads against a local mirror: resuming, checksums and archive extraction
+
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::support::spawn_mock;
+use guardr::fetch_dumps::{download_dump, DownloadOptions};
+
+const DUMP: &[u8] = b"{\"email\":\"a@example.com\",\"password\":\"hunter2\"}\n{\"email\":\"b@example.com\",\"password\":\"letmein\"}\n";
+
+fn options() -> DownloadOptions {
+    DownloadOptions {
+        retries: 2,
+        backoff: Duration::from_millis(10),
+        timeout: Duration::from_secs(5),
+        sha256: None,
+        decompress: false,
+        show_progress: false,
+    }
+}
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("guardr-test-fetch-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// download_dump is blocking; keep it off the runtime serving the mock
+async fn download(url: String, save_as: PathBuf, options: DownloadOptions) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        download_dump(&url, save_as.to_str().unwrap(), &options).map_err(|e| e.to_string())
+    }).await.unwrap()
+}
+
+// Serves `body` in full on every request
+async fn serve(body: &'static [u8]) -> String {
+    let router = axum::Router::new().route("/dump", get(move || async move { body }));
+    spawn_mock(router).await
+}
+
+// The first response promises the whole dump but the connection drops halfway;
+// later ones answer Range requests. Records the Range header of each request.
+async fn flaky_mirror(ranges: Arc<Mutex<Vec<Option<String>>>>) -> String {
+    let handler = |State(ranges): State<Arc<Mutex<Vec<Option<String>>>>>, headers: HeaderMap| async move {
+        let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let first = {
+            let mut ranges = ranges.lock().unwrap();
+            ranges.push(range.clone());
+            ranges.len() == 1
+        };
+
+        if first {
+            // Pause before failing so the first half reaches the client
+            let chunks = futures::stream::iter([true, false]).then(|first_half| async move {
+                if first_half {
+                    Ok(Bytes::from_static(&DUMP[..DUMP.len() / 2]))
+                } else {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "mirror went away"))
+                }
+            });
+            return Response::builder()
+                .header(header::CONTENT_LENGTH, DUMP.len())
+                .body(Body::from_stream(chunks))
+                .unwrap();
+        }
+
+        let start: usize = range.as_deref()
+            .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok())
+            .unwrap_or(0);
+        (
+            StatusCode::PARTIAL_CONTENT,
+            [(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, DUMP.len() - 1, DUMP.len()))],
+            &DUMP[start..],
+        ).into_response()
+    };
+    let router = axum::Router::new().route("/dump", get(handler)).with_state(ranges);
+    spawn_mock(router).await
+}
+
+#[tokio::test]
+async fn dropped_download_resumes_with_a_range_request() {
+    let ranges = Arc::new(Mutex::new(Vec::new()));
+    let base = flaky_mirror(ranges.clone()).await;
+    let dir = temp_dir();
+    let save_as = dir.join("dump.json");
+
+    download(format!("{}/dump", base), save_as.clone(), options()).await.unwrap();
+
+    assert_eq!(fs::read(&save_as).unwrap(), DUMP);
+    assert!(!dir.join("dump.json.part").exists());
+    let ranges = ranges.lock().unwrap().clone();
+    assert_eq!(ranges, [None, Some(format!("bytes={}-", DUMP.len() / 2))]);
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn checksum_mismatch_is_rejected() {
+    let base = serve(DUMP).await;
+    let dir = temp_dir();
+    let save_as = dir.join("dump.json");
+
+    let wrong = hex::encode(Sha256::digest(b"some other dump"));
+    let err = download(format!("{}/dump", base), save_as.clone(), DownloadOptions {
+        sha256: Some(wrong),
+        ..options()
+    }).await.unwrap_err();
+    assert!(err.contains("SHA-256 mismatch"), "{}", err);
+    // Neither the bad file nor a partial one to resume from is left behind
+    assert!(!save_as.exists());
+    assert!(!dir.join("dump.json.part").exists());
+
+    let right = hex::encode(Sha256::digest(DUMP)).to_uppercase();
+    download(format!("{}/dump", base), save_as.clone(), DownloadOptions {
+        sha256: Some(right),
+        ..options()
+    }).await.unwrap();
+    assert_eq!(fs::read(&save_as).unwrap(), DUMP);
+
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn zip_entries_outside_the_target_are_skipped() {
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let stored = zip::write::SimpleFileOptions::default();
+    for (name, contents) in [
+        ("part1.json", &b"{\"email\":\"a@example.com\"}\n"[..]),
+        ("nested/part2.json", &b"{\"email\":\"b@example.com\"}\n"[..]),
+        ("../escaped.json", &b"{\"email\":\"evil@example.com\"}\n"[..]),
+    ] {
+        archive.start_file(name, stored).unwrap();
+        archive.write_all(contents).unwrap();
+    }
+    let zipped: &'static [u8] = archive.finish().unwrap().into_inner().leak();
+
+    let base = serve(zipped).await;
+    let dir = temp_dir();
+    let save_as = dir.join("dump");
+
+    download(format!("{}/dump", base), save_as.clone(), DownloadOptions {
+        decompress: true,
+        ..options()
+    }).await.unwrap();
+
+    assert_eq!(fs::read(save_as.join("part1.json")).unwrap(), b"{\"email\":\"a@example.com\"}\n");
+    assert_eq!(fs::read(save_as.join("nested/part2.json")).unwrap(), b"{\"email\":\"b@example.com\"}\n");
+    assert!(!dir.join("escaped.json").exists());
+    assert!(!dir.join("dump.part").exists());
+
+    fs::remove_dir_all(&dir).ok();
+}
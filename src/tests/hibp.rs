@@ -0,0 +1,100 @@
+// The HIBP provider against a local stand-in for the v3 API: breaches found,
+// none found, a rejected key and rate limiting
+
+use axum::extract::{Path, Query};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use serde_json::json;
+use std::collections::HashMap;
+
+use super::support::spawn_mock;
+use crate::osint::hibp::HibpProvider;
+use crate::osint::BreachProvider;
+
+const API_KEY: &str = "test-key";
+
+// Answers by account: found@ has breaches and a paste, busy@ is rate limited
+// and anything else has no record
+async fn mock_hibp() -> String {
+    let breaches = |Path(account): Path<String>, Query(query): Query<HashMap<String, String>>, headers: HeaderMap| async move {
+        if headers.get("hibp-api-key").and_then(|v| v.to_str().ok()) != Some(API_KEY) {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        // Without this HIBP only returns breach names
+        assert_eq!(query.get("truncateResponse").map(String::as_str), Some("false"));
+        match account.as_str() {
+            "found@example.com" => axum::Json(json!([
+                { "Name": "Adobe", "BreachDate": "2013-10-04", "DataClasses": ["Email addresses", "Passwords"], "IsVerified": true },
+                { "Name": "AshleyMadison", "BreachDate": "2015-07-19", "DataClasses": ["Email addresses"], "IsSensitive": true },
+            ])).into_response(),
+            "busy@example.com" => rate_limited(),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    };
+    let pastes = |Path(account): Path<String>| async move {
+        match account.as_str() {
+            "found@example.com" => axum::Json(json!([
+                { "Source": "Pastebin", "Id": "8Q0BvKD8", "Title": "dump", "Date": "2014-03-04T19:14:54Z", "EmailCount": 139 },
+            ])).into_response(),
+            "busy@example.com" => rate_limited(),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    };
+
+    let router = axum::Router::new()
+        .route("/breachedaccount/:account", get(breaches))
+        .route("/pasteaccount/:account", get(pastes));
+    spawn_mock(router).await
+}
+
+fn rate_limited() -> Response {
+    (StatusCode::TOO_MANY_REQUESTS, [("retry-after", "7")]).into_response()
+}
+
+#[tokio::test]
+async fn breaches_and_pastes_are_mapped() {
+    let hibp = HibpProvider::new(API_KEY, &mock_hibp().await, 5).unwrap();
+    let lookup = hibp.lookup("found@example.com").await.unwrap();
+
+    assert_eq!(lookup.breaches.len(), 2);
+    let adobe = &lookup.breaches[0];
+    assert_eq!(adobe.source, "Adobe");
+    assert_eq!(adobe.breach_date, chrono::NaiveDate::from_ymd_opt(2013, 10, 4));
+    assert_eq!(adobe.provider, "hibp");
+    assert!(adobe.verified && !adobe.sensitive);
+    // Membership of a sensitive breach is itself the exposure
+    let sensitive = &lookup.breaches[1];
+    assert!(sensitive.sensitive);
+    assert_eq!(sensitive.severity, "critical");
+
+    assert_eq!(lookup.pastes.len(), 1);
+    assert_eq!(lookup.pastes[0].id, "8Q0BvKD8");
+    assert_eq!(lookup.pastes[0].email_count, 139);
+}
+
+#[tokio::test]
+async fn not_found_means_no_breaches() {
+    let hibp = HibpProvider::new(API_KEY, &mock_hibp().await, 5).unwrap();
+    let lookup = hibp.lookup("clean@example.com").await.unwrap();
+
+    assert!(lookup.breaches.is_empty());
+    assert!(lookup.pastes.is_empty());
+}
+
+#[tokio::test]
+async fn a_rejected_key_is_an_error() {
+    let hibp = HibpProvider::new("wrong-key", &mock_hibp().await, 5).unwrap();
+    let error = hibp.breached_account("found@example.com").await.unwrap_err();
+
+    assert_eq!(error.to_string(), "HIBP rejected the API key");
+}
+
+#[tokio::test]
+async fn rate_limits_report_retry_after() {
+    // A trailing slash on the base URL makes no difference
+    let hibp = HibpProvider::new(API_KEY, &format!("{}/", mock_hibp().await), 5).unwrap();
+    let error = hibp.lookup("busy@example.com").await.unwrap_err();
+
+    assert_eq!(error.to_string(), "HIBP rate limit exceeded (retry after 7s)");
+}
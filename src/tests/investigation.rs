@@ -0,0 +1,92 @@
+// The investigation report's HIBP, Leak-Lookup and Intelligence X sections, against
+// local stand-ins for the three APIs
+
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use super::support::spawn_mock;
+use crate::config::Settings;
+use crate::llm::LlmChain;
+use crate::osint::investigation::Investigator;
+use crate::osint::leak_lookup::LeakLookupClient;
+
+#[tokio::test]
+async fn leak_lookup_sends_the_key_as_a_header_and_the_query_as_json() {
+    let router = axum::Router::new().route("/search", post(|headers: HeaderMap, body: String| async move {
+        let body: Value = match serde_json::from_str(&body) {
+            Ok(body) => body,
+            Err(_) => return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response(),
+        };
+        if headers.get("x-api-key").and_then(|v| v.to_str().ok()) != Some("ll-key") || body != json!({ "query": "pat@example.com" }) {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        axum::Json(json!({ "error": "false", "message": { "adobe.com": [] } })).into_response()
+    }));
+    let leak_lookup = LeakLookupClient::new("ll-key", &spawn_mock(router).await, 5).unwrap();
+
+    let body = leak_lookup.search_email("pat@example.com").await.unwrap();
+    assert_eq!(body["message"], json!({ "adobe.com": [] }));
+}
+
+#[tokio::test]
+async fn intelx_data_is_the_raw_search_response() {
+    let started = json!({ "id": "2f5e4b8a-0000-4000-8000-000000000000", "status": 0 });
+    let response = started.clone();
+    let router = axum::Router::new()
+        .route("/intelligent/search", post(move |headers: HeaderMap| async move {
+            assert_eq!(headers.get("x-key").and_then(|v| v.to_str().ok()), Some("ix-key"));
+            axum::Json(response)
+        }))
+        .route("/intelligent/search/result", get(|| async { StatusCode::INTERNAL_SERVER_ERROR }));
+
+    let mut osint = Settings::default().osint;
+    osint.hibp_api_key = None;
+    osint.leak_lookup_api_key = None;
+    osint.intelx_api_key = Some("ix-key".to_string());
+    osint.intelx_base_url = Some(spawn_mock(router).await);
+    let investigator = Investigator::from_config(&osint, Arc::new(LlmChain::new(Vec::new()))).unwrap();
+
+    let report = investigator.investigate("Pat@Example.com").await;
+    assert_eq!(report.intelx_data, started);
+    assert_eq!(report.leak_lookup_data, json!({}));
+}
+
+#[tokio::test]
+async fn hibp_breaches_are_kept_as_hibp_sent_them() {
+    let breach = json!({
+        "Name": "Adobe",
+        "Title": "Adobe",
+        "Domain": "adobe.com",
+        "BreachDate": "2013-10-04",
+        "AddedDate": "2013-12-04T00:00:00Z",
+        "ModifiedDate": "2022-05-15T23:52:49Z",
+        "PwnCount": 152445165,
+        "Description": "In October 2013, 153 million Adobe accounts were breached...",
+        "LogoPath": "https://haveibeenpwned.com/Content/Images/PwnedLogos/Adobe.png",
+        "DataClasses": ["Email addresses", "Password hints", "Passwords", "Usernames"],
+        "IsVerified": true,
+        "IsFabricated": false,
+        "IsSensitive": false,
+        "IsRetired": false,
+        "IsSpamList": false,
+        "IsMalware": false,
+        "IsSubscriptionFree": false,
+    });
+    let response = json!([breach.clone()]);
+    let router = axum::Router::new()
+        .route("/breachedaccount/:account", get(move || async move { axum::Json(response) }));
+
+    let mut osint = Settings::default().osint;
+    osint.hibp_api_key = Some("hibp-key".to_string());
+    osint.hibp_base_url = Some(spawn_mock(router).await);
+    osint.leak_lookup_api_key = None;
+    osint.intelx_api_key = None;
+    let investigator = Investigator::from_config(&osint, Arc::new(LlmChain::new(Vec::new()))).unwrap();
+
+    let report = serde_json::to_value(investigator.investigate("pat@example.com").await).unwrap();
+    assert_eq!(report["hibp_breaches"], json!([breach]));
+    assert_eq!(report["summary"]["earliest_breach"], "2013-10-04");
+}
@@ -0,0 +1,163 @@
+// Login brute-force protection: per-account and per-IP lockouts with backoff,
+// and lockouts recorded as security events
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use super::support::{TestApp, PASSWORD};
+use crate::config::Settings;
+
+fn three_strikes(settings: &mut Settings) {
+    settings.security.max_login_attempts = 3;
+    settings.security.max_login_attempts_per_ip = 5;
+    settings.security.lockout_duration_minutes = 15;
+    // Leave the request rate limiter out of it
+    settings.rate_limiting.burst_size = 100;
+    // The test client plays the load balancer that sets X-Forwarded-For
+    settings.server.trusted_proxies = vec!["127.0.0.1".to_string()];
+}
+
+async fn login_from(app: &TestApp, ip: &str, email: &str, password: &str) -> reqwest::Response {
+    app.http(Method::POST, "/v1/auth/login")
+        .header("x-forwarded-for", ip)
+        .json(&json!({ "email": email, "password": password }))
+        .send()
+        .await
+        .unwrap()
+}
+
+// Let a lockout run out
+async fn expire_lock(app: &TestApp, target: &str) {
+    let mut conn = app.state.redis.get_async_connection().await.unwrap();
+    redis::cmd("DEL").arg(format!("login:lock:{}", target)).query_async::<_, ()>(&mut conn).await.unwrap();
+}
+
+#[tokio::test]
+async fn account_locks_with_backoff_and_records_events() {
+    let app = TestApp::with_settings(three_strikes).await;
+    app.register("kim@example.com").await;
+
+    // From different IPs, so only the account locks
+    for ip in ["192.0.2.1", "192.0.2.2", "192.0.2.3"] {
+        let response = login_from(&app, ip, "kim@example.com", "Wrong-Password-1").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    // Even the right password is refused while locked
+    let response = login_from(&app, "127.0.0.1", "kim@example.com", PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!((899..=900).contains(&retry_after), "retry-after {}", retry_after);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["type"], "LOGIN_LOCKED");
+
+    // The next lockout in the same day is twice as long
+    expire_lock(&app, "account:kim@example.com").await;
+    for ip in ["192.0.2.4", "192.0.2.5", "192.0.2.6"] {
+        login_from(&app, ip, "kim@example.com", "Wrong-Password-1").await;
+    }
+    let response = login_from(&app, "127.0.0.1", "kim@example.com", PASSWORD).await;
+    let retry_after: u64 = response.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!((1799..=1800).contains(&retry_after), "retry-after {}", retry_after);
+
+    expire_lock(&app, "account:kim@example.com").await;
+    let (status, session) = app.login("kim@example.com", PASSWORD).await;
+    assert_eq!(status, StatusCode::OK);
+    let token = session["access_token"].as_str().unwrap();
+
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(token), None).await;
+    let events = profile["security_events"].as_array().unwrap();
+    assert_eq!(events.len(), 2, "{}", profile);
+    assert!(events.iter().all(|e| e["event_type"] == "login_lockout" && e["details"]["scope"] == "account"));
+    let mut durations = events.iter().map(|e| e["details"]["duration_seconds"].as_u64().unwrap()).collect::<Vec<_>>();
+    durations.sort();
+    assert_eq!(durations, vec![900, 1800]);
+}
+
+#[tokio::test]
+async fn ip_lockout_spans_accounts() {
+    let app = TestApp::with_settings(three_strikes).await;
+    app.register("lee@example.com").await;
+
+    // Two guesses each stays under the per-account limit, but five from one
+    // IP locks the IP
+    for email in ["a@example.com", "b@example.com", "a@example.com", "b@example.com", "lee@example.com"] {
+        let response = login_from(&app, "198.51.100.4", email, "Wrong-Password-1").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let response = login_from(&app, "198.51.100.4", "lee@example.com", PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = login_from(&app, "203.0.113.9", "lee@example.com", PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unknown_accounts_fail_like_wrong_passwords() {
+    let app = TestApp::with_settings(three_strikes).await;
+    app.register("max@example.com").await;
+
+    let (known_status, known) = app.login("max@example.com", "Wrong-Password-1").await;
+    let (unknown_status, unknown) = app.login("nobody@example.com", "Wrong-Password-1").await;
+    assert_eq!(known_status, StatusCode::UNAUTHORIZED);
+    assert_eq!(unknown_status, known_status);
+    assert_eq!(unknown["error"], known["error"]);
+
+    // Unknown accounts lock too, so lockouts don't reveal which exist
+    for _ in 0..2 {
+        app.login("nobody@example.com", "Wrong-Password-1").await;
+    }
+    let (status, body) = app.login("nobody@example.com", "Wrong-Password-1").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["error"]["type"], "LOGIN_LOCKED");
+}
+
+#[tokio::test]
+async fn forged_forwarded_for_neither_escapes_nor_frames_an_ip() {
+    let app = TestApp::with_settings(|settings| {
+        three_strikes(settings);
+        settings.server.trusted_proxies = vec!["127.0.0.1".to_string(), "10.0.0.0/8".to_string()];
+    }).await;
+    app.register("noor@example.com").await;
+
+    // The attacker at 192.0.2.50 rotates a made-up first hop; the proxy at
+    // 10.0.0.1 appends the real address, so all five count against it
+    for (guess, email) in ["a@example.com", "b@example.com", "a@example.com", "b@example.com", "noor@example.com"].iter().enumerate() {
+        let chain = format!("198.51.100.{}, 192.0.2.50, 10.0.0.1", guess);
+        let response = login_from(&app, &chain, email, "Wrong-Password-1").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = login_from(&app, "198.51.100.99, 192.0.2.50, 10.0.0.1", "noor@example.com", PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Naming the victim's address doesn't lock the victim out
+    let response = login_from(&app, "203.0.113.5, 10.0.0.1", "noor@example.com", PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let forged = login_from(&app, "203.0.113.5, 192.0.2.50, 10.0.0.1", "x@example.com", "Wrong-Password-1").await;
+    assert_eq!(forged.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The lockout event carries the address the proxy saw
+    let (_, session) = app.login("noor@example.com", PASSWORD).await;
+    let token = session["access_token"].as_str().unwrap();
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(token), None).await;
+    let events = profile["security_events"].as_array().unwrap();
+    assert_eq!(events.len(), 1, "{}", profile);
+    assert_eq!(events[0]["details"]["scope"], "ip");
+    assert_eq!(events[0]["ip_address"], "192.0.2.50");
+}
+
+#[tokio::test]
+async fn without_trusted_proxies_the_socket_peer_is_locked() {
+    let app = TestApp::with_settings(|settings| {
+        three_strikes(settings);
+        settings.server.trusted_proxies.clear();
+    }).await;
+
+    // Every guess claims a new address, but they all come from 127.0.0.1
+    for (guess, email) in ["a@example.com", "b@example.com", "a@example.com", "b@example.com", "c@example.com"].iter().enumerate() {
+        let response = login_from(&app, &format!("198.51.100.{}", guess), email, "Wrong-Password-1").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = login_from(&app, "198.51.100.200", "d@example.com", "Wrong-Password-1").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
@@ -0,0 +1,20 @@
+// End-to-end tests of the API router
+// Each test gets its own SQLite file and an in-process stand-in for Redis
+// (support.rs), so `cargo test` needs no running services.
+
+mod admin;
+mod api_keys;
+mod auth;
+mod dating;
+mod demo_check;
+mod email;
+mod fetch_dumps;
+mod hibp;
+mod investigation;
+mod login_lockout;
+mod password_check;
+mod quota;
+mod rate_limit;
+mod reports;
+mod support;
+mod two_factor;
@@ -0,0 +1,39 @@
+// /v1/security/check-password against an offline Pwned Passwords directory
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+use super::support::{TestApp, PASSWORD};
+
+#[tokio::test]
+async fn an_unreadable_range_is_unknown_not_clean() {
+    // A directory the downloader hasn't filled in yet
+    let ranges = std::env::temp_dir().join(format!("guardr-test-ranges-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&ranges).unwrap();
+    let dir = ranges.display().to_string();
+    let app = TestApp::with_settings(move |settings| {
+        settings.osint.pwned_passwords_dir = Some(dir);
+    }).await;
+    app.register("max@example.com").await;
+    let (_, login) = app.login("max@example.com", PASSWORD).await;
+    let token = login["access_token"].as_str().unwrap();
+
+    let body = json!({ "password": "correct horse battery staple" });
+    let (status, missing) = app.request(Method::POST, "/v1/security/check-password", Some(token), Some(body.clone())).await;
+    assert_eq!(status, StatusCode::OK, "{}", missing);
+    assert_eq!(missing["breach_occurrences"], serde_json::Value::Null);
+    assert_eq!(missing["is_breached"], false);
+
+    // Now the range is there
+    let hash = {
+        use sha1::{Digest, Sha1};
+        hex::encode_upper(Sha1::digest(b"correct horse battery staple"))
+    };
+    let (prefix, suffix) = hash.split_at(5);
+    std::fs::write(ranges.join(format!("{}.txt", prefix)), format!("{}:3\r\n", suffix)).unwrap();
+
+    let (_, found) = app.request(Method::POST, "/v1/security/check-password", Some(token), Some(body)).await;
+    std::fs::remove_dir_all(&ranges).ok();
+    assert_eq!(found["breach_occurrences"], 3);
+    assert_eq!(found["is_breached"], true);
+}
@@ -0,0 +1,198 @@
+// Monthly quotas: enforced before metered handlers run, weighted per endpoint,
+// reported in the profile's usage stats
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+use super::support::{TestApp, PASSWORD};
+use crate::config::Settings;
+use crate::database::UserSubscriptionTier;
+use crate::quota;
+
+const CHECK_PASSWORD: &str = "/v1/security/check-password";
+
+fn small_quotas(settings: &mut Settings) {
+    settings.quota.free = 3;
+    settings.quota.pro = 10;
+    settings.quota.warn_at_percent = 60;
+}
+
+async fn session(app: &TestApp, email: &str) -> String {
+    app.register(email).await;
+    let (_, body) = app.login(email, PASSWORD).await;
+    body["access_token"].as_str().unwrap().to_string()
+}
+
+async fn check_password(app: &TestApp, token: &str) -> reqwest::Response {
+    app.http(Method::POST, CHECK_PASSWORD)
+        .bearer_auth(token)
+        .json(&json!({ "password": "correct horse battery staple" }))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn refuses_metered_calls_once_the_quota_is_spent() {
+    let app = TestApp::with_settings(small_quotas).await;
+    let token = session(&app, "gail@example.com").await;
+
+    for _ in 0..3 {
+        assert_eq!(check_password(&app, &token).await.status(), StatusCode::OK);
+    }
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(&token), None).await;
+    let stats = &profile["usage_stats"];
+    assert_eq!(stats["requests_used"], 3);
+    assert_eq!(stats["requests_remaining"], 0);
+    assert_eq!(stats["notices"][0]["level"], "exceeded", "{}", stats);
+
+    let response = check_password(&app, &token).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: i64 = response.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after >= 1);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["type"], "QUOTA_EXCEEDED");
+    assert_eq!(body["error"]["limit"], 3);
+    assert_eq!(body["error"]["used"], 3);
+    let resets_at = quota::period_end(chrono::Utc::now()).to_rfc3339();
+    assert_eq!(body["error"]["resets_at"], resets_at);
+
+    // Refused calls aren't recorded
+    let user = app.state.db.get_user_by_email("gail@example.com").await.unwrap().unwrap();
+    let month = quota::current_month(chrono::Utc::now());
+    assert_eq!(app.state.db.get_monthly_usage_total(user.id, &month).await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn bulk_checks_cost_per_item() {
+    let app = TestApp::with_settings(small_quotas).await;
+    app.register("hank@example.com").await;
+    let user = app.state.db.get_user_by_email("hank@example.com").await.unwrap().unwrap();
+    app.state.db.update_user_subscription(user.id, UserSubscriptionTier::Pro).await.unwrap();
+    let (_, login) = app.login("hank@example.com", PASSWORD).await;
+    let token = login["access_token"].as_str().unwrap();
+
+    let emails = |n: usize| json!({
+        "emails": (0..n).map(|i| format!("person{}@example.com", i)).collect::<Vec<_>>(),
+        "include_details": false,
+    });
+
+    let (status, body) = app.request(Method::POST, "/v1/security/bulk-check", Some(token), Some(emails(7))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // 7 of 10 used: warned, and 4 more don't fit
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(token), None).await;
+    assert_eq!(profile["usage_stats"]["requests_used"], 7);
+    assert_eq!(profile["usage_stats"]["notices"][0]["level"], "warning");
+    let (status, body) = app.request(Method::POST, "/v1/security/bulk-check", Some(token), Some(emails(4))).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["error"]["used"], 7);
+
+    let (status, _) = app.request(Method::POST, "/v1/security/bulk-check", Some(token), Some(emails(3))).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn weighted_costs_add_to_recorded_usage() {
+    let app = TestApp::with_settings(|settings| {
+        small_quotas(settings);
+        settings.quota.free = 5;
+        settings.quota.costs.insert("check_password".to_string(), 2);
+    }).await;
+    let token = session(&app, "ida@example.com").await;
+
+    // Usage already in usage_tracking primes the cache
+    let user = app.state.db.get_user_by_email("ida@example.com").await.unwrap().unwrap();
+    app.state.db.track_api_usage(user.id, "check_breach", 2).await.unwrap();
+
+    assert_eq!(check_password(&app, &token).await.status(), StatusCode::OK);
+    assert_eq!(check_password(&app, &token).await.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(&token), None).await;
+    let usage = profile["usage_stats"]["usage_by_endpoint"].as_array().unwrap();
+    let charged = usage.iter().find(|u| u["endpoint"] == "check_password").unwrap();
+    assert_eq!(charged["requests"], 2);
+}
+
+#[tokio::test]
+async fn enforced_from_usage_tracking_without_redis() {
+    let app = TestApp::with_settings(|settings| {
+        small_quotas(settings);
+        // Nothing listens on the discard port
+        settings.redis.url = "redis://127.0.0.1:9".to_string();
+    }).await;
+
+    // Sessions need Redis for the logout blacklist; API keys don't
+    app.register("jo@example.com").await;
+    let user = app.state.db.get_user_by_email("jo@example.com").await.unwrap().unwrap();
+    let key = "quota-test-key";
+    app.state.db.create_api_key(
+        user.id,
+        "quota",
+        &app.state.auth.hash_api_key(key),
+        "quota-te",
+        &["security:read".to_string()],
+        None,
+    ).await.unwrap();
+
+    let body = json!({ "password": "correct horse battery staple" });
+    for _ in 0..3 {
+        let (status, _) = app.request_with_key(Method::POST, CHECK_PASSWORD, key, Some(body.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, response) = app.request_with_key(Method::POST, CHECK_PASSWORD, key, Some(body)).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response["error"]["type"], "QUOTA_EXCEEDED");
+}
+
+fn conversation(messages: usize) -> serde_json::Value {
+    json!({
+        "messages": (0..messages).map(|i| json!({
+            "content": format!("message {}", i),
+            "timestamp": "2026-10-01T12:00:00Z",
+            "sender": if i % 2 == 0 { "user" } else { "match" },
+        })).collect::<Vec<_>>(),
+    })
+}
+
+#[tokio::test]
+async fn safety_reports_are_charged_once() {
+    let app = TestApp::with_settings(small_quotas).await;
+    app.register("kit@example.com").await;
+    let user = app.state.db.get_user_by_email("kit@example.com").await.unwrap().unwrap();
+    app.state.db.update_user_subscription(user.id, UserSubscriptionTier::Pro).await.unwrap();
+    let (_, login) = app.login("kit@example.com", PASSWORD).await;
+    let token = login["access_token"].as_str().unwrap();
+
+    let report = json!({
+        "conversation_analysis": conversation(4),
+        "identity_verification": { "participant_claims": { "name": "Sam", "age": 31 } },
+    });
+    let (status, body) = app.request(Method::POST, "/v1/dating/safety-report", Some(token), Some(report)).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["conversation_analysis"].is_object(), "{}", body);
+    assert!(body["identity_verification"].is_object(), "{}", body);
+
+    // Only the report's own weighted cost, not its parts' as well
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(token), None).await;
+    let usage = profile["usage_stats"]["usage_by_endpoint"].as_array().unwrap();
+    assert_eq!(usage.len(), 1, "{:?}", usage);
+    assert_eq!(usage[0]["endpoint"], "safety_report");
+    assert_eq!(usage[0]["requests"], app.state.settings.quota.costs["safety_report"]);
+}
+
+#[tokio::test]
+async fn tier_limits_are_checked_before_charging() {
+    let app = TestApp::with_settings(small_quotas).await;
+    let token = session(&app, "lou@example.com").await;
+
+    let (status, _) = app.request(Method::POST, "/v1/dating/analyze-conversation", Some(&token), Some(conversation(51))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let report = json!({ "conversation_analysis": conversation(51) });
+    let (status, _) = app.request(Method::POST, "/v1/dating/safety-report", Some(&token), Some(report)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let user = app.state.db.get_user_by_email("lou@example.com").await.unwrap().unwrap();
+    let month = quota::current_month(chrono::Utc::now());
+    assert_eq!(app.state.db.get_monthly_usage_total(user.id, &month).await.unwrap(), 0);
+}
@@ -0,0 +1,131 @@
+// Rate limiting: per-IP and per-principal budgets, tiers, shared Redis counters
+
+use axum::http::{Method, StatusCode};
+
+use super::support::{spawn_fake_redis, TestApp, PASSWORD};
+use crate::config::{RateLimitBackend, Settings};
+use crate::database::UserSubscriptionTier;
+
+// 60 a minute, bursts of 3, with the test client standing in for a trusted
+// load balancer so each test can pick the caller's address
+fn tight_limits(settings: &mut Settings) {
+    settings.rate_limiting.requests_per_minute = 60;
+    settings.rate_limiting.burst_size = 3;
+    settings.server.trusted_proxies = vec!["127.0.0.1".to_string()];
+}
+
+fn header(response: &reqwest::Response, name: &str) -> u64 {
+    response.headers().get(name)
+        .unwrap_or_else(|| panic!("missing {} header", name))
+        .to_str().unwrap()
+        .parse().unwrap()
+}
+
+async fn health(app: &TestApp, ip: &str) -> reqwest::Response {
+    app.http(Method::GET, "/health").header("x-forwarded-for", ip).send().await.unwrap()
+}
+
+#[tokio::test]
+async fn anonymous_requests_are_limited_per_ip() {
+    let app = TestApp::with_settings(tight_limits).await;
+
+    for remaining in [2, 1, 0] {
+        let response = health(&app, "203.0.113.7").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, "ratelimit-limit"), 60);
+        assert_eq!(header(&response, "ratelimit-remaining"), remaining);
+    }
+
+    let response = health(&app, "203.0.113.7").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(header(&response, "retry-after") >= 1);
+    assert_eq!(header(&response, "ratelimit-remaining"), 0);
+
+    // Someone else is unaffected
+    assert_eq!(health(&app, "203.0.113.8").await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn forwarded_for_is_ignored_unless_the_peer_is_a_trusted_proxy() {
+    let app = TestApp::with_settings(|settings| {
+        tight_limits(settings);
+        settings.server.trusted_proxies.clear();
+    }).await;
+
+    // A new X-Forwarded-For per request doesn't buy a new budget
+    for ip in ["203.0.113.1", "203.0.113.2", "203.0.113.3"] {
+        assert_eq!(health(&app, ip).await.status(), StatusCode::OK);
+    }
+    assert_eq!(health(&app, "203.0.113.4").await.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn only_the_hop_our_proxy_saw_counts() {
+    let app = TestApp::with_settings(|settings| {
+        tight_limits(settings);
+        settings.server.trusted_proxies = vec!["127.0.0.0/8".to_string(), "10.0.0.0/8".to_string()];
+    }).await;
+
+    // The client prepends made-up addresses; our proxies (10.x) append the
+    // address they really saw
+    for forged in ["198.51.100.1", "198.51.100.2", "198.51.100.3"] {
+        let chain = format!("{}, 192.0.2.9, 10.1.2.3", forged);
+        assert_eq!(health(&app, &chain).await.status(), StatusCode::OK);
+    }
+    let response = health(&app, "198.51.100.4, 192.0.2.9, 10.1.2.3").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(health(&app, "192.0.2.10").await.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn authenticated_callers_have_their_own_tiered_budget() {
+    let app = TestApp::with_settings(tight_limits).await;
+    let ip = "198.51.100.1";
+
+    // Both count against the IP
+    app.register("grace@example.com").await;
+    let (_, body) = app.login("grace@example.com", PASSWORD).await;
+    let token = body["access_token"].as_str().unwrap().to_string();
+
+    let profile = || app.http(Method::GET, "/v1/user/profile").header("x-forwarded-for", ip).bearer_auth(&token).send();
+    for _ in 0..3 {
+        assert_eq!(profile().await.unwrap().status(), StatusCode::OK);
+    }
+    assert_eq!(profile().await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The user's budget is separate from the address it calls from
+    assert_eq!(health(&app, ip).await.status(), StatusCode::OK);
+
+    // Enterprise gets enterprise_multiplier (20x) the limits
+    let user = app.state.db.get_user_by_email("grace@example.com").await.unwrap().unwrap();
+    app.state.db.update_user_subscription(user.id, UserSubscriptionTier::Enterprise).await.unwrap();
+    let user = app.state.db.get_user_by_id(user.id).await.unwrap().unwrap();
+    let (enterprise_token, _) = app.state.auth.generate_tokens(&user).unwrap();
+    let response = app.http(Method::GET, "/v1/user/profile").bearer_auth(&enterprise_token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(header(&response, "ratelimit-limit"), 60 * 20);
+    assert_eq!(header(&response, "ratelimit-remaining"), 3 * 20 - 1);
+}
+
+#[tokio::test]
+async fn redis_backend_shares_counts_between_replicas() {
+    let redis = spawn_fake_redis().await;
+    let shared = |settings: &mut Settings| {
+        tight_limits(settings);
+        settings.rate_limiting.backend = RateLimitBackend::Redis;
+        settings.redis.url = redis.clone();
+    };
+    let first = TestApp::with_settings(shared).await;
+    let second = TestApp::with_settings(shared).await;
+
+    let ip = "192.0.2.44";
+    assert_eq!(header(&health(&first, ip).await, "ratelimit-remaining"), 2);
+    assert_eq!(header(&health(&second, ip).await, "ratelimit-remaining"), 1);
+    assert_eq!(header(&health(&first, ip).await, "ratelimit-remaining"), 0);
+
+    for app in [&first, &second] {
+        let response = health(app, ip).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(header(&response, "retry-after") >= 1);
+    }
+}
@@ -0,0 +1,77 @@
+// Stored reports: the risk model behind each score, key rotation and the
+// background re-encryption pass
+
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+use uuid::Uuid;
+
+use super::support::{TestApp, PASSWORD};
+use crate::config::{EncryptionKey, Settings};
+use crate::database::{Database, Reencrypted};
+
+// The app's settings with a new current key and the old one retired
+pub fn rotated(settings: &Settings) -> Settings {
+    let mut rotated = settings.clone();
+    rotated.security.retired_encryption_keys = vec![EncryptionKey {
+        id: settings.security.encryption_key_id.clone(),
+        key: settings.security.encryption_key.clone(),
+    }];
+    rotated.security.encryption_key_id = "rotated".to_string();
+    rotated.security.encryption_key = "rotated-test-key-0123456789abcd!".to_string();
+    rotated
+}
+
+#[tokio::test]
+async fn reencryption_skips_rows_it_cannot_open() {
+    let app = TestApp::new().await;
+    let db = &app.state.db;
+    let user_id = Uuid::new_v4();
+
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let report = db.create_security_report(user_id, "breach_check", "digest", r#"{"ok":true}"#, None, None).await.unwrap();
+        ids.push(report.id);
+    }
+    // One row under a key nobody has any more, one with a mangled ciphertext
+    let corrupt = format!("enc:{}:not base64!", app.state.settings.security.encryption_key_id);
+    for (id, value) in [(ids[1], "enc:lost:AAAAAAAAAAAAAAAAAAAAAAAA"), (ids[2], corrupt.as_str())] {
+        sqlx::query("UPDATE security_reports SET results = $1 WHERE id = $2")
+            .bind(value)
+            .bind(id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+    }
+
+    let rotated = rotated(&app.state.settings);
+    let db = Database::new(&rotated).await.unwrap();
+    let outcome = db.reencrypt_reports(2).await.unwrap();
+    assert_eq!(outcome, Reencrypted { reencrypted: 1, skipped: 2 });
+
+    let report = db.get_report_by_id(user_id, ids[0]).await.unwrap().unwrap();
+    assert_eq!(report.results, r#"{"ok":true}"#);
+}
+
+#[tokio::test]
+async fn risk_scores_are_stored_with_their_model_version() {
+    let app = TestApp::with_settings(|settings| {
+        settings.risk.model_version = "weights-2099-01".to_string();
+    }).await;
+    app.register("risk@example.com").await;
+    let (_, login) = app.login("risk@example.com", PASSWORD).await;
+    let token = login["access_token"].as_str().unwrap();
+
+    let (status, body) = app.request(Method::POST, "/v1/security/risk-score", Some(token), Some(json!({
+        "email": "risk@example.com",
+        "password": "password123",
+    }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["risk_model"], "weights-2099-01");
+
+    let (status, body) = app.request(Method::GET, "/v1/reports", Some(token), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let report = &body["reports"][0];
+    assert_eq!(report["report_type"], "risk_assessment");
+    assert_eq!(report["risk_model"], "weights-2099-01");
+    assert!(report["risk_score"].as_u64().unwrap() > 0, "{}", body);
+}
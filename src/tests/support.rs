@@ -0,0 +1,441 @@
+// Test harness: the real router over a fresh AppState, plus a minimal RESP
+// server that stands in for Redis
+
+use axum::http::{Method, StatusCode};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+
+use crate::config::{HashPepper, Settings};
+use crate::state::AppState;
+
+pub const PASSWORD: &str = "Correct-Horse-42";
+
+pub struct TestApp {
+    base_url: String,
+    client: reqwest::Client,
+    pub state: AppState,
+    db_path: PathBuf,
+    outbox: PathBuf,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        Self::with_settings(|_| {}).await
+    }
+
+    // Adjust the test settings before the app starts
+    pub async fn with_settings(configure: impl FnOnce(&mut Settings)) -> Self {
+        Self::with_setup(configure, |_| {}).await
+    }
+
+    // Adjust the settings, then the state built from them (e.g. to swap in a
+    // fixed clock), before the app starts
+    pub async fn with_setup(
+        configure_settings: impl FnOnce(&mut Settings),
+        configure_state: impl FnOnce(&mut AppState),
+    ) -> Self {
+        let db_path = std::env::temp_dir().join(format!("guardr-test-{}.db", Uuid::new_v4()));
+        let outbox = std::env::temp_dir().join(format!("guardr-test-outbox-{}", Uuid::new_v4()));
+
+        let mut settings = Settings::default();
+        settings.database.sqlite_url = format!("sqlite:{}?mode=rwc", db_path.display());
+        settings.redis.url = spawn_fake_redis().await;
+        settings.email.outbox_dir = outbox.display().to_string();
+        settings.auth.jwt_secret = "integration-test-jwt-secret-0123456789".to_string();
+        settings.security.encryption_key = "integration-test-key-0123456789!".to_string();
+        settings.security.hash_peppers = vec![HashPepper {
+            id: "test".to_string(),
+            secret: "integration-test-pepper-0123456789".to_string(),
+        }];
+        configure_settings(&mut settings);
+        settings.validate().expect("test settings are valid");
+
+        let mut state = AppState::new(settings).await.expect("app state");
+        configure_state(&mut state);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let router = crate::build_app_router(state.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>()).await
+        });
+
+        Self {
+            base_url,
+            client: reqwest::Client::new(),
+            state,
+            db_path,
+            outbox,
+        }
+    }
+
+    // Send a request through the full middleware stack; the body comes back
+    // as JSON, or Null when empty
+    pub async fn request(&self, method: Method, uri: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        let mut request = self.client.request(method, format!("{}{}", self.base_url, uri));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        send(request, body).await
+    }
+
+    // A bare request, for tests that set their own headers or read the
+    // response's
+    pub fn http(&self, method: Method, uri: &str) -> reqwest::RequestBuilder {
+        self.client.request(method, format!("{}{}", self.base_url, uri))
+    }
+
+    // Same as `request`, authenticated with an API key instead of a bearer token
+    pub async fn request_with_key(&self, method: Method, uri: &str, api_key: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let request = self.client.request(method, format!("{}{}", self.base_url, uri))
+            .header(crate::auth::API_KEY_HEADER, api_key);
+        send(request, body).await
+    }
+
+    // Register an account; returns the registration response
+    pub async fn register(&self, email: &str) -> Value {
+        let (status, body) = self.request(Method::POST, "/v1/auth/register", None, Some(serde_json::json!({
+            "email": email,
+            "password": PASSWORD,
+            "confirm_password": PASSWORD,
+            "name": "Test User",
+        }))).await;
+        assert_eq!(status, StatusCode::OK, "register failed: {}", body);
+        body
+    }
+
+    // Mark an account's email verified without going through the link
+    pub async fn verify_email(&self, email: &str) {
+        sqlx::query("UPDATE users SET email_verified = 1 WHERE email = $1")
+            .bind(email)
+            .execute(&self.state.db.pool)
+            .await
+            .unwrap();
+    }
+
+    // Messages the file mailer has written, oldest first. Some are sent in
+    // the background, so this waits up to a second for at least `expected`.
+    pub async fn outbox(&self, expected: usize) -> Vec<String> {
+        for _ in 0..50 {
+            let mut names = std::fs::read_dir(&self.outbox)
+                .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            if names.len() >= expected {
+                names.sort();
+                return names.iter().map(|path| std::fs::read_to_string(path).unwrap()).collect();
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("expected {} emails in {}", expected, self.outbox.display());
+    }
+
+    pub async fn login(&self, email: &str, password: &str) -> (StatusCode, Value) {
+        self.request(Method::POST, "/v1/auth/login", None, Some(serde_json::json!({
+            "email": email,
+            "password": password,
+        }))).await
+    }
+}
+
+async fn send(mut request: reqwest::RequestBuilder, body: Option<Value>) -> (StatusCode, Value) {
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+
+    let response = request.send().await.unwrap();
+    let status = response.status();
+    let bytes = response.bytes().await.unwrap();
+    let json = if bytes.is_empty() { Value::Null } else { serde_json::from_slice(&bytes).unwrap() };
+    (status, json)
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.db_path.clone().into_os_string();
+            path.push(suffix);
+            std::fs::remove_file(path).ok();
+        }
+        std::fs::remove_dir_all(&self.outbox).ok();
+    }
+}
+
+// Serve `router` on a free port and return its base URL, standing in for a
+// third-party API
+pub async fn spawn_mock(router: axum::Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    format!("http://{}", addr)
+}
+
+// Enough of Redis for the token blacklist and the rate limiter: strings and
+// sorted sets with expiry, and MULTI/EXEC
+#[derive(Default)]
+struct Store {
+    entries: HashMap<String, Entry>,
+}
+
+struct Entry {
+    value: Stored,
+    expires: Option<Instant>,
+}
+
+enum Stored {
+    Text(Vec<u8>),
+    // (score, member), kept sorted
+    SortedSet(Vec<(f64, Vec<u8>)>),
+}
+
+enum Reply {
+    Status(&'static str),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Reply>),
+    Error(String),
+}
+
+impl Reply {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Reply::Status(status) => out.extend_from_slice(format!("+{}\r\n", status).as_bytes()),
+            Reply::Integer(n) => out.extend_from_slice(format!(":{}\r\n", n).as_bytes()),
+            Reply::Bulk(None) => out.extend_from_slice(b"$-1\r\n"),
+            Reply::Bulk(Some(value)) => {
+                out.extend_from_slice(format!("${}\r\n", value.len()).as_bytes());
+                out.extend_from_slice(value);
+                out.extend_from_slice(b"\r\n");
+            }
+            Reply::Array(items) => {
+                out.extend_from_slice(format!("*{}\r\n", items.len()).as_bytes());
+                for item in items {
+                    item.encode(out);
+                }
+            }
+            Reply::Error(message) => out.extend_from_slice(format!("-ERR {}\r\n", message).as_bytes()),
+        }
+    }
+}
+
+// Listen on a free port and return the redis:// URL
+pub async fn spawn_fake_redis() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let store = Arc::new(Mutex::new(Store::default()));
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream, store.clone()));
+        }
+    });
+
+    format!("redis://{}", addr)
+}
+
+async fn serve(stream: TcpStream, store: Arc<Mutex<Store>>) -> std::io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    // Commands queued since MULTI
+    let mut transaction: Option<Vec<Vec<Vec<u8>>>> = None;
+
+    while let Some(args) = read_command(&mut reader).await? {
+        let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+        let reply = match (name.as_str(), transaction.as_mut()) {
+            ("MULTI", _) => {
+                transaction = Some(Vec::new());
+                Reply::Status("OK")
+            }
+            ("EXEC", Some(_)) => {
+                let queued = transaction.take().unwrap();
+                let mut store = store.lock().unwrap();
+                Reply::Array(queued.iter().map(|args| store.execute(args)).collect())
+            }
+            (_, Some(queued)) => {
+                queued.push(args);
+                Reply::Status("QUEUED")
+            }
+            _ => store.lock().unwrap().execute(&args),
+        };
+
+        let mut out = Vec::new();
+        reply.encode(&mut out);
+        write.write_all(&out).await?;
+    }
+    Ok(())
+}
+
+// One RESP array of bulk strings, or None at EOF
+async fn read_command<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> std::io::Result<Option<Vec<Vec<u8>>>> {
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed RESP");
+
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    let count: usize = line.trim_end().strip_prefix('*').and_then(|n| n.parse().ok()).ok_or_else(invalid)?;
+
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        line.clear();
+        reader.read_line(&mut line).await?;
+        let len: usize = line.trim_end().strip_prefix('$').and_then(|n| n.parse().ok()).ok_or_else(invalid)?;
+        let mut arg = vec![0; len + 2];
+        reader.read_exact(&mut arg).await?;
+        arg.truncate(len);
+        args.push(arg);
+    }
+    Ok(Some(args))
+}
+
+// A score bound: "-inf", "+inf", "5" (inclusive) or "(5" (exclusive)
+fn in_bound(score: f64, bound: &str, lower: bool) -> bool {
+    let (exclusive, value) = match bound.strip_prefix('(') {
+        Some(value) => (true, value),
+        None => (false, bound),
+    };
+    let value: f64 = match value {
+        "-inf" => f64::NEG_INFINITY,
+        "+inf" | "inf" => f64::INFINITY,
+        value => value.parse().unwrap(),
+    };
+    match (lower, exclusive) {
+        (true, false) => score >= value,
+        (true, true) => score > value,
+        (false, false) => score <= value,
+        (false, true) => score < value,
+    }
+}
+
+impl Store {
+    fn execute(&mut self, args: &[Vec<u8>]) -> Reply {
+        let now = Instant::now();
+        // Expired keys, and sorted sets emptied by the last command, are gone
+        self.entries.retain(|_, entry| {
+            let empty = matches!(&entry.value, Stored::SortedSet(set) if set.is_empty());
+            !empty && entry.expires.is_none_or(|at| at > now)
+        });
+
+        let text = |i: usize| String::from_utf8_lossy(&args[i]).into_owned();
+        let key = || text(1);
+
+        match text(0).to_uppercase().as_str() {
+            "PING" => Reply::Status("PONG"),
+            // Connection setup the client may send
+            "CLIENT" | "SELECT" => Reply::Status("OK"),
+            // SET key value [NX] [EX seconds | PX milliseconds]
+            "SET" => {
+                let options = (3..args.len()).map(|i| text(i).to_uppercase()).collect::<Vec<_>>();
+                if options.iter().any(|o| o == "NX") && self.entries.contains_key(&key()) {
+                    return Reply::Bulk(None);
+                }
+                let ttl = |unit: &str| options.iter().position(|o| o == unit).map(|i| options[i + 1].parse::<u64>().unwrap());
+                let expires = ttl("EX").map(Duration::from_secs)
+                    .or_else(|| ttl("PX").map(Duration::from_millis))
+                    .map(|ttl| now + ttl);
+                self.entries.insert(key(), Entry { value: Stored::Text(args[2].clone()), expires });
+                Reply::Status("OK")
+            }
+            "SETEX" => {
+                let seconds: u64 = text(2).parse().unwrap();
+                let expires = Some(now + Duration::from_secs(seconds));
+                self.entries.insert(key(), Entry { value: Stored::Text(args[3].clone()), expires });
+                Reply::Status("OK")
+            }
+            "GET" => match self.entries.get(&key()).map(|entry| &entry.value) {
+                Some(Stored::Text(value)) => Reply::Bulk(Some(value.clone())),
+                _ => Reply::Bulk(None),
+            },
+            "EXISTS" => Reply::Integer((1..args.len()).filter(|&i| self.entries.contains_key(&text(i))).count() as i64),
+            "DEL" => Reply::Integer((1..args.len()).filter(|&i| self.entries.remove(&text(i)).is_some()).count() as i64),
+            "EXPIRE" | "PEXPIRE" => match self.entries.get_mut(&key()) {
+                Some(entry) => {
+                    let amount: u64 = text(2).parse().unwrap();
+                    let ttl = if text(0).eq_ignore_ascii_case("EXPIRE") {
+                        Duration::from_secs(amount)
+                    } else {
+                        Duration::from_millis(amount)
+                    };
+                    entry.expires = Some(now + ttl);
+                    Reply::Integer(1)
+                }
+                None => Reply::Integer(0),
+            },
+            "PTTL" => match self.entries.get(&key()) {
+                Some(Entry { expires: Some(at), .. }) => Reply::Integer(at.duration_since(now).as_millis() as i64),
+                Some(_) => Reply::Integer(-1),
+                None => Reply::Integer(-2),
+            },
+            "INCR" | "INCRBY" | "DECRBY" => {
+                let by: i64 = if args.len() > 2 { text(2).parse().unwrap() } else { 1 };
+                let by = if text(0).eq_ignore_ascii_case("DECRBY") { -by } else { by };
+                let entry = self.entries.entry(key()).or_insert(Entry { value: Stored::Text(b"0".to_vec()), expires: None });
+                match &mut entry.value {
+                    Stored::Text(value) => {
+                        let total = String::from_utf8_lossy(value).parse::<i64>().unwrap() + by;
+                        *value = total.to_string().into_bytes();
+                        Reply::Integer(total)
+                    }
+                    Stored::SortedSet(_) => Reply::Error("WRONGTYPE".to_string()),
+                }
+            }
+            "ZADD" => {
+                let score: f64 = text(2).parse().unwrap();
+                let set = self.sorted_set(key());
+                set.retain(|(_, member)| *member != args[3]);
+                set.push((score, args[3].clone()));
+                set.sort_by(|a, b| a.0.total_cmp(&b.0));
+                Reply::Integer(1)
+            }
+            "ZREM" => {
+                let set = self.sorted_set(key());
+                let before = set.len();
+                set.retain(|(_, member)| !args[2..].contains(member));
+                Reply::Integer((before - set.len()) as i64)
+            }
+            "ZCARD" => Reply::Integer(self.sorted_set(key()).len() as i64),
+            "ZCOUNT" => {
+                let (min, max) = (text(2), text(3));
+                let count = self.sorted_set(key()).iter()
+                    .filter(|(score, _)| in_bound(*score, &min, true) && in_bound(*score, &max, false))
+                    .count();
+                Reply::Integer(count as i64)
+            }
+            "ZREMRANGEBYSCORE" => {
+                let (min, max) = (text(2), text(3));
+                let set = self.sorted_set(key());
+                let before = set.len();
+                set.retain(|(score, _)| !(in_bound(*score, &min, true) && in_bound(*score, &max, false)));
+                Reply::Integer((before - set.len()) as i64)
+            }
+            "ZRANGE" => {
+                let (start, stop): (usize, usize) = (text(2).parse().unwrap(), text(3).parse().unwrap());
+                let with_scores = args.len() > 4;
+                let set = self.sorted_set(key());
+                let mut items = Vec::new();
+                for (score, member) in set.iter().skip(start).take(stop + 1 - start) {
+                    items.push(Reply::Bulk(Some(member.clone())));
+                    if with_scores {
+                        items.push(Reply::Bulk(Some(score.to_string().into_bytes())));
+                    }
+                }
+                Reply::Array(items)
+            }
+            other => Reply::Error(format!("unknown command '{}'", other)),
+        }
+    }
+
+    // The sorted set at `key`, created empty if missing
+    fn sorted_set(&mut self, key: String) -> &mut Vec<(f64, Vec<u8>)> {
+        let entry = self.entries.entry(key).or_insert(Entry { value: Stored::SortedSet(Vec::new()), expires: None });
+        match &mut entry.value {
+            Stored::SortedSet(set) => set,
+            Stored::Text(_) => panic!("WRONGTYPE: not a sorted set"),
+        }
+    }
+}
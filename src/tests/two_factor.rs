@@ -0,0 +1,261 @@
+// TOTP two-factor authentication: enrollment, the login challenge, replay
+// protection and recovery codes, against a fixed clock
+
+use axum::http::{Method, StatusCode};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+use super::support::{TestApp, PASSWORD};
+use crate::config::Settings;
+use crate::totp::{self, Clock, Totp, RECOVERY_CODE_COUNT};
+
+struct FixedClock(Mutex<DateTime<Utc>>);
+
+impl FixedClock {
+    fn advance(&self, seconds: i64) {
+        *self.0.lock().unwrap() += Duration::seconds(seconds);
+    }
+
+    fn step(&self) -> i64 {
+        self.now().timestamp().div_euclid(30)
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+}
+
+fn settings(settings: &mut Settings) {
+    settings.security.max_login_attempts = 3;
+    // Leave the request rate limiter out of it
+    settings.rate_limiting.burst_size = 100;
+}
+
+async fn two_factor_app() -> (TestApp, Arc<FixedClock>) {
+    let clock = Arc::new(FixedClock(Mutex::new(Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap())));
+    let totp_clock = clock.clone();
+    let app = TestApp::with_setup(settings, move |state| {
+        state.totp = Arc::new(Totp::new(&state.settings.auth.totp_issuer, totp_clock));
+    }).await;
+    (app, clock)
+}
+
+fn code_at(secret: &str, step: i64) -> String {
+    format!("{:06}", totp::code_at(secret, step).unwrap())
+}
+
+fn field(body: &Value, name: &str) -> String {
+    body[name].as_str().unwrap_or_else(|| panic!("no {} in {}", name, body)).to_string()
+}
+
+struct Enrolled {
+    access: String,
+    secret: String,
+    recovery_codes: Vec<String>,
+}
+
+// Register, log in and turn on two-factor; the clock is left one step past
+// the code used to enable it
+async fn enroll(app: &TestApp, clock: &FixedClock, email: &str) -> Enrolled {
+    app.register(email).await;
+    let (_, session) = app.login(email, PASSWORD).await;
+    let access = field(&session, "access_token");
+
+    let (status, setup) = app.request(Method::POST, "/v1/user/2fa/setup", Some(&access), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", setup);
+    let secret = field(&setup, "secret");
+
+    let (status, enabled) = app.request(Method::POST, "/v1/user/2fa/enable", Some(&access),
+        Some(json!({ "code": code_at(&secret, clock.step()) }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", enabled);
+    let recovery_codes: Vec<String> = serde_json::from_value(enabled["recovery_codes"].clone()).unwrap();
+
+    clock.advance(30);
+    Enrolled { access, secret, recovery_codes }
+}
+
+async fn challenge(app: &TestApp, email: &str) -> String {
+    let (status, body) = app.login(email, PASSWORD).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["two_factor_required"], true, "{}", body);
+    assert!(body.get("access_token").is_none());
+    field(&body, "challenge_token")
+}
+
+async fn verify(app: &TestApp, challenge: &str, code: &str) -> (StatusCode, Value) {
+    app.request(Method::POST, "/v1/auth/2fa/verify", None, Some(json!({
+        "challenge_token": challenge,
+        "code": code,
+    }))).await
+}
+
+#[tokio::test]
+async fn enrollment_then_login_needs_a_fresh_code() {
+    let (app, clock) = two_factor_app().await;
+    app.register("ana@example.com").await;
+    let (_, session) = app.login("ana@example.com", PASSWORD).await;
+    let access = field(&session, "access_token");
+
+    let (_, setup) = app.request(Method::POST, "/v1/user/2fa/setup", Some(&access), None).await;
+    let secret = field(&setup, "secret");
+    let uri = field(&setup, "provisioning_uri");
+    assert!(uri.starts_with("otpauth://totp/Guardr:ana@example.com?"), "{}", uri);
+    assert!(uri.contains(&format!("secret={}", secret)), "{}", uri);
+
+    // Nothing changes until a code from the new secret is confirmed
+    let (_, body) = app.login("ana@example.com", PASSWORD).await;
+    assert!(body.get("access_token").is_some(), "{}", body);
+
+    // One step of drift either way is allowed, two is not
+    let (status, _) = app.request(Method::POST, "/v1/user/2fa/enable", Some(&access),
+        Some(json!({ "code": code_at(&secret, clock.step() - 2) }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = app.request(Method::POST, "/v1/user/2fa/enable", Some(&access),
+        Some(json!({ "code": code_at(&secret, clock.step() - 1) }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["recovery_codes"].as_array().unwrap().len(), RECOVERY_CODE_COUNT);
+
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(&access), None).await;
+    assert_eq!(profile["two_factor_enabled"], true);
+
+    // The step used to enable can't be used again
+    let token = challenge(&app, "ana@example.com").await;
+    let (status, _) = verify(&app, &token, &code_at(&secret, clock.step() - 1)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let code = code_at(&secret, clock.step());
+    let (status, body) = verify(&app, &token, &code).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, _) = app.request(Method::GET, "/v1/user/profile", Some(&field(&body, "access_token")), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Neither the challenge nor the code works twice
+    let (status, _) = verify(&app, &token, &code).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let token = challenge(&app, "ana@example.com").await;
+    let (status, _) = verify(&app, &token, &code).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    clock.advance(30);
+    let (status, _) = verify(&app, &token, &code_at(&secret, clock.step())).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn recovery_codes_work_once() {
+    let (app, clock) = two_factor_app().await;
+    let enrolled = enroll(&app, &clock, "ben@example.com").await;
+
+    // Case and dashes don't matter
+    let spoken = enrolled.recovery_codes[0].replace('-', "").to_uppercase();
+    let token = challenge(&app, "ben@example.com").await;
+    let (status, body) = verify(&app, &token, &spoken).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let access = field(&body, "access_token");
+
+    let token = challenge(&app, "ben@example.com").await;
+    let (status, _) = verify(&app, &token, &enrolled.recovery_codes[0]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(&access), None).await;
+    let used = profile["security_events"].as_array().unwrap().iter()
+        .find(|e| e["event_type"] == "recovery_code_used")
+        .unwrap_or_else(|| panic!("no recovery_code_used in {}", profile))
+        .clone();
+    assert_eq!(used["details"]["remaining"], RECOVERY_CODE_COUNT as i64 - 1);
+
+    // Regenerating replaces the old codes, used or not
+    let (status, body) = app.request(Method::POST, "/v1/user/2fa/recovery-codes", Some(&access),
+        Some(json!({ "code": code_at(&enrolled.secret, clock.step()) }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let fresh: Vec<String> = serde_json::from_value(body["recovery_codes"].clone()).unwrap();
+    assert_eq!(fresh.len(), RECOVERY_CODE_COUNT);
+
+    let (status, _) = verify(&app, &token, &enrolled.recovery_codes[1]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = verify(&app, &token, &fresh[0]).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn challenge_is_not_a_session() {
+    let (app, clock) = two_factor_app().await;
+    enroll(&app, &clock, "cai@example.com").await;
+    let token = challenge(&app, "cai@example.com").await;
+
+    let (status, _) = app.request(Method::GET, "/v1/user/profile", Some(&token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app.request(Method::POST, "/v1/auth/refresh", None,
+        Some(json!({ "refresh_token": token }))).await;
+    assert!(!status.is_success(), "{}", status);
+
+    // Nor is a session a challenge
+    let (_, session) = app.request(Method::POST, "/v1/auth/register", None, Some(json!({
+        "email": "dan@example.com",
+        "password": PASSWORD,
+        "confirm_password": PASSWORD,
+    }))).await;
+    let (status, _) = verify(&app, &field(&session, "access_token"), "000000").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn wrong_codes_lock_the_account() {
+    let (app, clock) = two_factor_app().await;
+    let enrolled = enroll(&app, &clock, "dee@example.com").await;
+    let token = challenge(&app, "dee@example.com").await;
+
+    let wrong = code_at(&enrolled.secret, clock.step() + 5);
+    for _ in 0..3 {
+        let (status, _) = verify(&app, &token, &wrong).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    let (status, body) = verify(&app, &token, &code_at(&enrolled.secret, clock.step())).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS, "{}", body);
+    assert_eq!(body["error"]["type"], "LOGIN_LOCKED");
+}
+
+#[tokio::test]
+async fn disabling_needs_password_and_code() {
+    let (app, clock) = two_factor_app().await;
+    let enrolled = enroll(&app, &clock, "eve@example.com").await;
+    let code = code_at(&enrolled.secret, clock.step());
+
+    let (status, _) = app.request(Method::POST, "/v1/user/2fa/disable", Some(&enrolled.access),
+        Some(json!({ "password": "Wrong-Password-1", "code": code }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = app.request(Method::POST, "/v1/user/2fa/disable", Some(&enrolled.access),
+        Some(json!({ "password": PASSWORD, "code": code }))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = app.login("eve@example.com", PASSWORD).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("access_token").is_some(), "{}", body);
+    let (_, profile) = app.request(Method::GET, "/v1/user/profile", Some(&field(&body, "access_token")), None).await;
+    assert_eq!(profile["two_factor_enabled"], false);
+}
+
+#[tokio::test]
+async fn key_rotation_reencrypts_secrets() {
+    let app = TestApp::new().await;
+    app.register("ravi@example.com").await;
+    let user = app.state.db.get_user_by_email("ravi@example.com").await.unwrap().unwrap();
+    assert!(app.state.db.start_two_factor_enrollment(user.id, "JBSWY3DPEHPK3PXP").await.unwrap());
+
+    // Rotate: the old key is retired, then the startup pass runs
+    let mut rotated = super::reports::rotated(&app.state.settings);
+    let db = crate::database::Database::new(&rotated).await.unwrap();
+    assert_eq!(db.reencrypt_two_factor_secrets(1).await.unwrap().reencrypted, 1);
+    assert_eq!(db.reencrypt_two_factor_secrets(1).await.unwrap().reencrypted, 0);
+
+    // Once it has, the retired key can go
+    rotated.security.retired_encryption_keys.clear();
+    let db = crate::database::Database::new(&rotated).await.unwrap();
+    let two_factor = db.get_two_factor(user.id).await.unwrap().unwrap();
+    assert_eq!(two_factor.secret, "JBSWY3DPEHPK3PXP");
+}
@@ -33,6 +33,7 @@ type DemoResult = {
   name?: string;
   risk_level?: RiskLevel;
   risk_score?: number;
+  safety_score?: number;
   person_verification?: string;
   recommendations?: string[];
   safety_tips?: SafetyTip[];